@@ -0,0 +1,92 @@
+//! Audit log configuration
+//!
+//! Read from `~/.claude/hooks/audit-logger/config.toml`, then the project's
+//! `.claude/audit-logger.toml`.
+//!
+//! ```toml
+//! log_dir = "~/.claude/logs"
+//! max_bytes = 10485760
+//! max_files = 5
+//! include_data = false
+//! ```
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuditConfig {
+    pub enabled: bool,
+    /// Directory holding `audit.jsonl` and its rotated copies
+    pub log_dir: PathBuf,
+    /// Rotate once the log reaches this size
+    pub max_bytes: u64,
+    /// Rotated copies kept (`audit.1.jsonl` is the newest)
+    pub max_files: usize,
+    /// Record the event's full tool data, not just the summary fields
+    pub include_data: bool,
+}
+
+impl Default for AuditConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            log_dir: dirs::home_dir()
+                .unwrap_or_else(|| PathBuf::from("."))
+                .join(".claude")
+                .join("logs"),
+            max_bytes: 10 * 1024 * 1024,
+            max_files: 5,
+            include_data: false,
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigLayer {
+    enabled: Option<bool>,
+    log_dir: Option<String>,
+    max_bytes: Option<u64>,
+    max_files: Option<usize>,
+    include_data: Option<bool>,
+}
+
+impl AuditConfig {
+    /// Load the global config, then layer the project's on top
+    pub fn load(project_dir: Option<&Path>) -> Result<Self> {
+        let global = dirs::home_dir().map(|home| home.join(".claude/hooks/audit-logger/config.toml"));
+        let project = project_dir.map(|dir| dir.join(".claude").join("audit-logger.toml"));
+        
+        let mut config = Self::default();
+        for path in global.into_iter().chain(project) {
+            if path.is_file() {
+                let content = std::fs::read_to_string(&path)
+                    .with_context(|| format!("Failed to read {}", path.display()))?;
+                config.merge_toml(&content)
+                    .with_context(|| format!("Failed to parse {}", path.display()))?;
+            }
+        }
+        Ok(config)
+    }
+    
+    /// Layer one config file's contents on top of this config
+    pub fn merge_toml(&mut self, content: &str) -> Result<()> {
+        let layer: ConfigLayer = toml::from_str(content)?;
+        self.enabled = layer.enabled.unwrap_or(self.enabled);
+        if let Some(log_dir) = layer.log_dir {
+            self.log_dir = expand_home(&log_dir);
+        }
+        self.max_bytes = layer.max_bytes.unwrap_or(self.max_bytes);
+        self.max_files = layer.max_files.unwrap_or(self.max_files);
+        self.include_data = layer.include_data.unwrap_or(self.include_data);
+        Ok(())
+    }
+}
+
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), dirs::home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest),
+        _ => PathBuf::from(path),
+    }
+}
@@ -0,0 +1,57 @@
+//! Audit Logger Hook
+//!
+//! Registered for every event, it appends one JSON line per event (session,
+//! event type, tool, command or file, decision, timestamp) to
+//! `~/.claude/logs/audit.jsonl`, rotating the file once it grows past the
+//! configured size. It never blocks anything.
+//!
+//! The binary is a thin wrapper around [`handle`], which hook-mux also
+//! calls in-process.
+
+use anyhow::Result;
+use claude_hooks_sdk::{HookInput, HookOutput};
+
+mod config;
+mod log;
+#[cfg(test)]
+mod tests;
+
+use config::AuditConfig;
+use log::{AuditLog, AuditRecord};
+
+/// Name used in error messages
+pub const NAME: &str = "Audit logger";
+
+/// Record one hook event, given the raw JSON input; always approves
+pub fn handle(input: &str) -> HookOutput {
+    claude_hooks_sdk::handle(NAME, input, hook)
+}
+
+/// Record one parsed hook event; always approves
+pub fn hook(input: HookInput) -> Result<HookOutput> {
+    // Auditing must never get in the way of the session
+    if let Err(e) = record(&input) {
+        eprintln!("Hook error: {}", e);
+    }
+    Ok(HookOutput::approve())
+}
+
+fn record(input: &HookInput) -> Result<()> {
+    let config = AuditConfig::load(Some(input.project_dir()))?;
+    if !config.enabled {
+        return Ok(());
+    }
+    
+    let mut record = AuditRecord::new(
+        &input.session.id,
+        &input.session.project_dir,
+        &input.event.event_type,
+        &input.event.data,
+        config.include_data,
+    );
+    // The official schema names the tool outside its input
+    if let Some(tool) = &input.event.tool_name {
+        record.tool = Some(tool.clone());
+    }
+    AuditLog::new(&config).append(&record)
+}
@@ -0,0 +1,122 @@
+//! Audit records and the rotating log they are appended to
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::config::AuditConfig;
+
+pub use claude_hooks_sdk::rfc3339;
+
+/// One line of the audit log
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AuditRecord {
+    /// RFC 3339, UTC
+    pub timestamp: String,
+    pub ts_ms: u64,
+    pub session_id: String,
+    pub project_dir: String,
+    pub event: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub command: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file_path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub decision: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<serde_json::Value>,
+}
+
+impl AuditRecord {
+    pub fn new(session_id: &str, project_dir: &str, event: &str, data: &serde_json::Value, include_data: bool) -> Self {
+        let field = |names: &[&str]| {
+            names.iter().find_map(|name| data.get(*name)?.as_str().map(str::to_string))
+        };
+        let command = field(&["command"]);
+        // Only Bash carries a bare command when the tool isn't named
+        let tool = field(&["tool", "tool_name", "toolName"]).or_else(|| command.as_ref().map(|_| "Bash".to_string()));
+        let ts_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_millis() as u64);
+        
+        Self {
+            timestamp: rfc3339(ts_ms),
+            ts_ms,
+            session_id: session_id.to_string(),
+            project_dir: project_dir.to_string(),
+            event: event.to_string(),
+            tool,
+            command,
+            file_path: field(&["file_path", "filePath", "path"]),
+            decision: field(&["decision", "permissionDecision"]),
+            data: include_data.then(|| data.clone()),
+        }
+    }
+}
+
+pub struct AuditLog {
+    dir: PathBuf,
+    max_bytes: u64,
+    max_files: usize,
+}
+
+impl AuditLog {
+    pub fn new(config: &AuditConfig) -> Self {
+        Self {
+            dir: config.log_dir.clone(),
+            max_bytes: config.max_bytes,
+            max_files: config.max_files,
+        }
+    }
+    
+    pub fn path(&self) -> PathBuf {
+        self.dir.join("audit.jsonl")
+    }
+    
+    /// `audit.N.jsonl`
+    pub fn rotated_path(&self, index: usize) -> PathBuf {
+        self.dir.join(format!("audit.{}.jsonl", index))
+    }
+    
+    /// Append `record` as one line, rotating first if the log is full
+    pub fn append(&self, record: &AuditRecord) -> Result<()> {
+        std::fs::create_dir_all(&self.dir)
+            .with_context(|| format!("Failed to create {}", self.dir.display()))?;
+        let path = self.path();
+        if std::fs::metadata(&path).is_ok_and(|meta| meta.len() >= self.max_bytes) {
+            self.rotate()?;
+        }
+        
+        // One write per record keeps concurrent appends from interleaving
+        let mut line = serde_json::to_string(record)?;
+        line.push('\n');
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .with_context(|| format!("Failed to open {}", path.display()))?;
+        file.write_all(line.as_bytes())
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+    
+    /// Shift `audit.jsonl` → `audit.1.jsonl` → ... dropping the oldest
+    fn rotate(&self) -> Result<()> {
+        if self.max_files == 0 {
+            return std::fs::remove_file(self.path()).context("Failed to truncate audit log");
+        }
+        let _ = std::fs::remove_file(self.rotated_path(self.max_files));
+        for index in (1..self.max_files).rev() {
+            let from = self.rotated_path(index);
+            if from.exists() {
+                std::fs::rename(&from, self.rotated_path(index + 1))
+                    .with_context(|| format!("Failed to rotate {}", from.display()))?;
+            }
+        }
+        std::fs::rename(self.path(), self.rotated_path(1)).context("Failed to rotate audit log")
+    }
+}
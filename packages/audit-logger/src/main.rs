@@ -0,0 +1,5 @@
+//! Audit Logger Hook binary; the logging lives in the library
+
+fn main() {
+    claude_hooks_sdk::run(audit_logger::NAME, audit_logger::hook);
+}
@@ -0,0 +1,54 @@
+//! Tests for audit records and log rotation
+
+use crate::config::AuditConfig;
+use crate::log::{rfc3339, AuditLog, AuditRecord};
+
+#[test]
+fn test_record_fields() {
+    let data = serde_json::json!({ "command": "cargo test", "description": "run tests" });
+    let record = AuditRecord::new("s1", "/work/project", "PreToolUse", &data, false);
+    
+    assert_eq!(record.tool.as_deref(), Some("Bash"));
+    assert_eq!(record.command.as_deref(), Some("cargo test"));
+    assert_eq!(record.data, None);
+    
+    let data = serde_json::json!({ "tool_name": "Write", "file_path": "src/lib.rs" });
+    let record = AuditRecord::new("s1", "/work/project", "PostToolUse", &data, true);
+    assert_eq!(record.tool.as_deref(), Some("Write"));
+    assert_eq!(record.file_path.as_deref(), Some("src/lib.rs"));
+    assert_eq!(record.data, Some(data));
+    
+    let line = serde_json::to_value(&record).unwrap();
+    assert_eq!(line["event"], "PostToolUse");
+    assert!(line.get("command").is_none());
+}
+
+#[test]
+fn test_timestamps() {
+    assert_eq!(rfc3339(0), "1970-01-01T00:00:00.000Z");
+    assert_eq!(rfc3339(951_782_400_123), "2000-02-29T00:00:00.123Z");
+    assert_eq!(rfc3339(1_735_689_599_999), "2024-12-31T23:59:59.999Z");
+}
+
+#[test]
+fn test_log_rotation() {
+    let dir = std::env::temp_dir().join(format!("audit-logger-{}", std::process::id()));
+    let mut config = AuditConfig::default();
+    config.merge_toml(&format!("log_dir = {:?}\nmax_bytes = 1\nmax_files = 2", dir.display().to_string())).unwrap();
+    let log = AuditLog::new(&config);
+    
+    for session in ["a", "b", "c", "d"] {
+        log.append(&AuditRecord::new(session, "/p", "Stop", &serde_json::Value::Null, false)).unwrap();
+    }
+    
+    let session_of = |path: std::path::PathBuf| -> String {
+        let line: serde_json::Value = serde_json::from_str(std::fs::read_to_string(path).unwrap().trim()).unwrap();
+        line["session_id"].as_str().unwrap().to_string()
+    };
+    assert_eq!(session_of(log.path()), "d");
+    assert_eq!(session_of(log.rotated_path(1)), "c");
+    assert_eq!(session_of(log.rotated_path(2)), "b");
+    assert!(!log.rotated_path(3).exists());
+    
+    std::fs::remove_dir_all(&dir).unwrap();
+}
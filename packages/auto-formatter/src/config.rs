@@ -0,0 +1,134 @@
+//! Formatter configuration
+//!
+//! Read from `~/.claude/hooks/auto-formatter/config.toml`, then the
+//! project's `.claude/auto-formatter.toml`. Formatters are keyed by name; a
+//! later file replaces the fields it sets.
+//!
+//! ```toml
+//! timeout_ms = 5000
+//!
+//! [formatters.prettier]
+//! enabled = false
+//!
+//! [formatters.taplo]
+//! extensions = ["toml"]
+//! command = ["taplo", "fmt", "{file}"]
+//! ```
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Formatter {
+    pub enabled: bool,
+    /// File extensions handled, without the dot
+    pub extensions: Vec<String>,
+    /// Program and arguments; `{file}` is replaced with the file's path
+    pub command: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FormatterConfig {
+    /// Turn the hook off entirely
+    pub enabled: bool,
+    /// How long one formatter run may take
+    pub timeout_ms: u64,
+    pub formatters: BTreeMap<String, Formatter>,
+}
+
+impl Default for FormatterConfig {
+    fn default() -> Self {
+        let formatter = |extensions: &[&str], command: &[&str]| Formatter {
+            enabled: true,
+            extensions: extensions.iter().map(|s| s.to_string()).collect(),
+            command: command.iter().map(|s| s.to_string()).collect(),
+        };
+        
+        let mut formatters = BTreeMap::new();
+        formatters.insert("rustfmt".to_string(), formatter(&["rs"], &["rustfmt", "--edition", "2021", "{file}"]));
+        formatters.insert("prettier".to_string(), formatter(
+            &["js", "jsx", "mjs", "cjs", "ts", "tsx", "json", "css", "scss", "md", "yaml", "yml", "html"],
+            &["prettier", "--write", "{file}"],
+        ));
+        formatters.insert("black".to_string(), formatter(&["py"], &["black", "--quiet", "{file}"]));
+        formatters.insert("gofmt".to_string(), formatter(&["go"], &["gofmt", "-w", "{file}"]));
+        
+        Self {
+            enabled: true,
+            timeout_ms: 10_000,
+            formatters,
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigLayer {
+    enabled: Option<bool>,
+    timeout_ms: Option<u64>,
+    #[serde(default)]
+    formatters: BTreeMap<String, FormatterLayer>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FormatterLayer {
+    enabled: Option<bool>,
+    extensions: Option<Vec<String>>,
+    command: Option<Vec<String>>,
+}
+
+impl FormatterConfig {
+    /// Load the global config, then layer the project's on top
+    pub fn load(project_dir: Option<&Path>) -> Result<Self> {
+        let global = dirs::home_dir().map(|home| home.join(".claude/hooks/auto-formatter/config.toml"));
+        let project = project_dir.map(|dir| dir.join(".claude").join("auto-formatter.toml"));
+        
+        let mut config = Self::default();
+        for path in global.into_iter().chain(project) {
+            if path.is_file() {
+                let content = std::fs::read_to_string(&path)
+                    .with_context(|| format!("Failed to read {}", path.display()))?;
+                config.merge_toml(&content)
+                    .with_context(|| format!("Failed to parse {}", path.display()))?;
+            }
+        }
+        Ok(config)
+    }
+    
+    /// Layer one config file's contents on top of this config
+    pub fn merge_toml(&mut self, content: &str) -> Result<()> {
+        let layer: ConfigLayer = toml::from_str(content)?;
+        self.enabled = layer.enabled.unwrap_or(self.enabled);
+        self.timeout_ms = layer.timeout_ms.unwrap_or(self.timeout_ms);
+        
+        for (name, update) in layer.formatters {
+            let existing = self.formatters.get(&name).cloned();
+            let command = update.command
+                .or_else(|| existing.as_ref().map(|f| f.command.clone()))
+                .with_context(|| format!("Formatter '{}' needs a command", name))?;
+            let formatter = Formatter {
+                enabled: update.enabled.or(existing.as_ref().map(|f| f.enabled)).unwrap_or(true),
+                extensions: update.extensions
+                    .or_else(|| existing.as_ref().map(|f| f.extensions.clone()))
+                    .unwrap_or_default(),
+                command,
+            };
+            self.formatters.insert(name, formatter);
+        }
+        Ok(())
+    }
+    
+    /// The enabled formatter for `file`, by extension
+    pub fn formatter_for(&self, file: &Path) -> Option<(&str, &Formatter)> {
+        let extension = file.extension()?.to_str()?;
+        self.formatters
+            .iter()
+            .find(|(_, formatter)| {
+                formatter.enabled && formatter.extensions.iter().any(|e| e.eq_ignore_ascii_case(extension))
+            })
+            .map(|(name, formatter)| (name.as_str(), formatter))
+    }
+}
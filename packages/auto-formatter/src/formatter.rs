@@ -0,0 +1,84 @@
+//! Running a formatter and telling whether it changed the file
+
+use anyhow::{Context, Result};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+use crate::config::FormatterConfig;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FormatOutcome {
+    Changed { formatter: String },
+    Unchanged { formatter: String },
+    Failed { formatter: String, error: String },
+}
+
+/// Format `file` with its configured formatter. `None` when there is no
+/// formatter for it or the formatter isn't installed.
+pub fn format_file(config: &FormatterConfig, file: &Path) -> Result<Option<FormatOutcome>> {
+    if !config.enabled || !file.is_file() {
+        return Ok(None);
+    }
+    let Some((name, formatter)) = config.formatter_for(file) else {
+        return Ok(None);
+    };
+    let Some((program, args)) = formatter.command.split_first() else {
+        return Ok(None);
+    };
+    if which::which(program).is_err() {
+        return Ok(None);
+    }
+    
+    let before = std::fs::read(file).with_context(|| format!("Failed to read {}", file.display()))?;
+    let file_arg = file.display().to_string();
+    let mut command = Command::new(program);
+    command
+        .args(args.iter().map(|arg| arg.replace("{file}", &file_arg)))
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped());
+    
+    let formatter = name.to_string();
+    let outcome = match run_with_timeout(command, Duration::from_millis(config.timeout_ms))? {
+        Some((true, _)) => {
+            let after = std::fs::read(file).with_context(|| format!("Failed to read {}", file.display()))?;
+            if after == before {
+                FormatOutcome::Unchanged { formatter }
+            } else {
+                FormatOutcome::Changed { formatter }
+            }
+        }
+        Some((false, stderr)) => FormatOutcome::Failed {
+            formatter,
+            error: stderr.lines().next().unwrap_or("exited with an error").to_string(),
+        },
+        None => FormatOutcome::Failed {
+            formatter,
+            error: format!("timed out after {} ms", config.timeout_ms),
+        },
+    };
+    Ok(Some(outcome))
+}
+
+/// Run to completion, returning success and stderr, or `None` (after
+/// killing it) if it outlives `timeout`
+fn run_with_timeout(mut command: Command, timeout: Duration) -> Result<Option<(bool, String)>> {
+    let mut child = command.spawn().context("Failed to start formatter")?;
+    let started = Instant::now();
+    loop {
+        if let Some(status) = child.try_wait()? {
+            let mut stderr = String::new();
+            if let Some(mut pipe) = child.stderr.take() {
+                std::io::Read::read_to_string(&mut pipe, &mut stderr)?;
+            }
+            return Ok(Some((status.success(), stderr)));
+        }
+        if started.elapsed() >= timeout {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Ok(None);
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+}
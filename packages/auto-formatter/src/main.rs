@@ -0,0 +1,71 @@
+//! Auto Formatter PostToolUse Hook
+//!
+//! After Write, Edit, or MultiEdit touches a file with a recognized
+//! extension, runs the matching formatter on it:
+//! - `.rs` → rustfmt
+//! - `.js`, `.ts`, `.json`, `.css`, `.md`, ... → prettier
+//! - `.py` → black
+//! - `.go` → gofmt
+//!
+//! Claude is told whether the file changed, so it re-reads before its next
+//! edit instead of working from a stale copy.
+
+use anyhow::Result;
+use claude_hooks_sdk::{HookInput, HookOutput};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+mod config;
+mod formatter;
+#[cfg(test)]
+mod tests;
+
+use config::FormatterConfig;
+use formatter::FormatOutcome;
+
+/// Tool data of a file-editing tool
+#[derive(Debug, Default, Deserialize)]
+struct EditToolData {
+    #[serde(default)]
+    file_path: Option<String>,
+}
+
+fn main() {
+    claude_hooks_sdk::run("Auto formatter", run);
+}
+
+fn run(input: HookInput) -> Result<HookOutput> {
+    if input.event.event_type != "PostToolUse" {
+        return Ok(HookOutput::approve());
+    }
+    let tool_data: EditToolData = input.event.tool_data().unwrap_or_default();
+    let Some(file_path) = tool_data.file_path else {
+        return Ok(HookOutput::approve());
+    };
+    
+    let project_dir = input.project_dir();
+    let config = FormatterConfig::load(Some(project_dir))?;
+    let file = project_dir.join(&file_path);
+    
+    let outcome = formatter::format_file(&config, &file)?;
+    Ok(outcome.map_or_else(HookOutput::approve, |outcome| {
+        HookOutput::additional_context(describe(&outcome, &PathBuf::from(&file_path)))
+    }))
+}
+
+/// Context for Claude about what the formatter did
+fn describe(outcome: &FormatOutcome, file: &Path) -> String {
+    match outcome {
+        FormatOutcome::Changed { formatter } => format!(
+            "auto-formatter: {} reformatted {}. Re-read the file before editing it again.",
+            formatter,
+            file.display(),
+        ),
+        FormatOutcome::Unchanged { formatter } => {
+            format!("auto-formatter: {} is already formatted ({}).", file.display(), formatter)
+        }
+        FormatOutcome::Failed { formatter, error } => {
+            format!("auto-formatter: {} failed on {}: {}", formatter, file.display(), error)
+        }
+    }
+}
@@ -0,0 +1,80 @@
+//! Tests for formatter selection and change detection
+
+use std::path::Path;
+
+use crate::config::FormatterConfig;
+use crate::formatter::{format_file, FormatOutcome};
+
+/// A config whose only formatter is a shell snippet, so tests don't depend
+/// on installed formatters
+fn shell_formatter(script: &str) -> FormatterConfig {
+    let mut config = FormatterConfig::default();
+    config.formatters.clear();
+    config.merge_toml(&format!(
+        "[formatters.fake]\nextensions = [\"txt\"]\ncommand = [\"sh\", \"-c\", {:?}, \"sh\", \"{{file}}\"]\n",
+        script,
+    )).unwrap();
+    config
+}
+
+#[test]
+fn test_formatter_selection() {
+    let config = FormatterConfig::default();
+    assert_eq!(config.formatter_for(Path::new("src/main.rs")).unwrap().0, "rustfmt");
+    assert_eq!(config.formatter_for(Path::new("web/App.TSX")).unwrap().0, "prettier");
+    assert_eq!(config.formatter_for(Path::new("tool.py")).unwrap().0, "black");
+    assert!(config.formatter_for(Path::new("Makefile")).is_none());
+}
+
+#[test]
+fn test_project_layer_overrides() {
+    let mut config = FormatterConfig::default();
+    config.merge_toml(r#"
+        timeout_ms = 500
+
+        [formatters.prettier]
+        enabled = false
+
+        [formatters.black]
+        command = ["ruff", "format", "{file}"]
+    "#).unwrap();
+    
+    assert_eq!(config.timeout_ms, 500);
+    assert!(config.formatter_for(Path::new("index.ts")).is_none());
+    let (_, black) = config.formatter_for(Path::new("tool.py")).unwrap();
+    assert_eq!(black.command[0], "ruff");
+    assert_eq!(black.extensions, vec!["py"]);
+    
+    // A new formatter must say how to run it
+    assert!(config.merge_toml("[formatters.taplo]\nextensions = [\"toml\"]").is_err());
+}
+
+#[test]
+fn test_change_detection() {
+    let dir = std::env::temp_dir().join(format!("auto-formatter-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let file = dir.join("notes.txt");
+    
+    std::fs::write(&file, "messy").unwrap();
+    let outcome = format_file(&shell_formatter("printf tidy > \"$1\""), &file).unwrap();
+    assert_eq!(outcome, Some(FormatOutcome::Changed { formatter: "fake".to_string() }));
+    assert_eq!(std::fs::read_to_string(&file).unwrap(), "tidy");
+    
+    let outcome = format_file(&shell_formatter("true"), &file).unwrap();
+    assert_eq!(outcome, Some(FormatOutcome::Unchanged { formatter: "fake".to_string() }));
+    
+    let outcome = format_file(&shell_formatter("echo 'syntax error' >&2; exit 1"), &file).unwrap();
+    assert_eq!(outcome, Some(FormatOutcome::Failed {
+        formatter: "fake".to_string(),
+        error: "syntax error".to_string(),
+    }));
+    
+    let mut slow = shell_formatter("sleep 5");
+    slow.timeout_ms = 50;
+    assert!(matches!(format_file(&slow, &file).unwrap(), Some(FormatOutcome::Failed { .. })));
+    
+    // Files without a formatter are left alone
+    assert_eq!(format_file(&shell_formatter("true"), &dir.join("missing.txt")).unwrap(), None);
+    
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,346 @@
+//! Claude Code hook SDK
+//!
+//! The hook protocol (as specified in HOOKS_DOCUMENTATION.md) and the
+//! plumbing every hook binary in this workspace needs: reading the event
+//! from stdin, parsing it with simd-json, and printing the response. A hook
+//! that fails approves the tool call with a message rather than blocking
+//! the session.
+//!
+//! Both input formats are accepted: the `session`/`event` envelope, and the
+//! flat schema current Claude Code sends (`session_id`, `hook_event_name`,
+//! `tool_input`, ...; see [`official`]). Hooks only ever see a
+//! [`HookInput`], and the response is printed in the shape matching the
+//! input. A hook binary is then little more than:
+//!
+//! ```no_run
+//! use claude_hooks_sdk::{HookInput, HookOutput};
+//!
+//! fn main() {
+//!     claude_hooks_sdk::run("Example", |input: HookInput| {
+//!         if input.event.event_type != "PreToolUse" {
+//!             return Ok(HookOutput::approve());
+//!         }
+//!         Ok(HookOutput::additional_context("checked by example"))
+//!     });
+//! }
+//! ```
+
+use anyhow::{Context, Result};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use simd_json::prelude::ValueAsMutContainer;
+use std::fmt;
+use std::io::{self, Read};
+use std::path::Path;
+
+pub mod official;
+pub mod shell;
+#[cfg(test)]
+mod tests;
+
+/// Hook input format
+#[derive(Debug, Clone, Deserialize)]
+pub struct HookInput {
+    pub session: Session,
+    pub event: Event,
+    /// Which format the input arrived in
+    #[serde(skip)]
+    pub schema: Schema,
+}
+
+/// The input format, which decides the response format too
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Schema {
+    /// `{"session": {...}, "event": {"type": ..., "data": ...}}`
+    #[default]
+    Legacy,
+    /// `{"session_id": ..., "hook_event_name": ..., "tool_input": ...}`
+    Official,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Session {
+    #[serde(default)]
+    pub id: String,
+    #[serde(rename = "projectDir")]
+    pub project_dir: String,
+    #[serde(default)]
+    pub context: serde_json::Map<String, serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Event {
+    #[serde(rename = "type")]
+    pub event_type: String,
+    #[serde(default)]
+    pub data: serde_json::Value,
+    /// The tool being called, when the input names it
+    #[serde(default)]
+    pub tool_name: Option<String>,
+    /// What the tool returned (PostToolUse in the official schema)
+    #[serde(default)]
+    pub tool_response: Option<serde_json::Value>,
+}
+
+impl HookInput {
+    /// Parse the raw input in either schema
+    pub fn parse(input: &str) -> Result<Self> {
+        Self::from_slice(&mut input.as_bytes().to_vec())
+    }
+    
+    /// Parse the raw input in either schema, in place: simd-json unescapes
+    /// strings inside `bytes` and borrows them from there, so only the
+    /// fields the event actually uses are copied out
+    pub fn from_slice(bytes: &mut [u8]) -> Result<Self> {
+        let mut value = simd_json::to_borrowed_value(bytes).context("Failed to parse JSON input")?;
+        
+        if let Some(object) = value.as_object_mut().filter(|object| object.contains_key("hook_event_name")) {
+            official::drop_unused(object);
+            let official: official::OfficialInput = simd_json::serde::from_borrowed_value(value)
+                .context("Failed to parse JSON input")?;
+            return Ok(official.into());
+        }
+        simd_json::serde::from_borrowed_value(value).context("Failed to parse JSON input")
+    }
+    
+    pub fn project_dir(&self) -> &Path {
+        Path::new(&self.session.project_dir)
+    }
+}
+
+impl Event {
+    /// The event data as a tool's input, or `None` if it has another shape
+    pub fn tool_data<T: DeserializeOwned>(&self) -> Option<T> {
+        serde_json::from_value(self.data.clone()).ok()
+    }
+}
+
+/// Hook output format
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HookOutput {
+    pub decision: Decision,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context: Option<serde_json::Value>,
+    /// A note for the user that never reaches Claude's context
+    #[serde(rename = "systemMessage", default, skip_serializing_if = "Option::is_none")]
+    pub system_message: Option<String>,
+    /// Keep the hook's stdout out of the transcript
+    #[serde(rename = "suppressOutput", default, skip_serializing_if = "std::ops::Not::not")]
+    pub suppress_output: bool,
+}
+
+/// Ordered by severity, so combining decisions is `max`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Decision {
+    Approve,
+    /// Leave it to the user to confirm
+    Ask,
+    Block,
+}
+
+impl HookOutput {
+    pub fn approve() -> Self {
+        Self {
+            decision: Decision::Approve,
+            message: None,
+            context: None,
+            system_message: None,
+            suppress_output: false,
+        }
+    }
+    
+    pub fn block(message: impl Into<String>) -> Self {
+        Self {
+            decision: Decision::Block,
+            message: Some(message.into()),
+            context: None,
+            system_message: None,
+            suppress_output: false,
+        }
+    }
+    
+    /// Escalate to the user, who sees `message` when asked to confirm
+    pub fn ask(message: impl Into<String>) -> Self {
+        Self {
+            decision: Decision::Ask,
+            message: Some(message.into()),
+            context: None,
+            system_message: None,
+            suppress_output: false,
+        }
+    }
+    
+    /// Approve, handing Claude some extra context
+    pub fn additional_context(context: impl Into<String>) -> Self {
+        Self::approve().with_context(serde_json::json!({ "additional_context": context.into() }))
+    }
+    
+    /// Approve, reporting that the hook named `hook` failed
+    pub fn error(hook: &str, error: impl fmt::Display) -> Self {
+        Self::approve().with_message(format!("{} hook error: {}", hook, error))
+    }
+    
+    pub fn with_message(mut self, message: impl Into<String>) -> Self {
+        self.message = Some(message.into());
+        self
+    }
+    
+    pub fn with_context(mut self, context: serde_json::Value) -> Self {
+        self.context = Some(context);
+        self
+    }
+    
+    /// Show `message` to the user without adding it to Claude's context
+    pub fn with_system_message(mut self, message: impl Into<String>) -> Self {
+        self.system_message = Some(message.into());
+        self
+    }
+    
+    pub fn with_suppressed_output(mut self) -> Self {
+        self.suppress_output = true;
+        self
+    }
+    
+    pub fn is_block(&self) -> bool {
+        self.decision == Decision::Block
+    }
+    
+    /// The JSON to print in response to `input`, or `None` to print
+    /// nothing
+    pub fn render(&self, input: &HookInput) -> Option<serde_json::Value> {
+        match input.schema {
+            Schema::Legacy => serde_json::to_value(self).ok(),
+            Schema::Official => official::render(self, &input.event),
+        }
+    }
+}
+
+/// Largest event read from stdin unless `CLAUDE_HOOK_MAX_INPUT_BYTES`
+/// says otherwise
+pub const DEFAULT_MAX_INPUT_BYTES: u64 = 8 * 1024 * 1024;
+
+/// The event on stdin was larger than the configured limit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InputTooLarge {
+    pub limit: u64,
+}
+
+impl fmt::Display for InputTooLarge {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Hook input exceeds {} bytes; approving without inspecting it", self.limit)
+    }
+}
+
+impl std::error::Error for InputTooLarge {}
+
+/// The input size limit: `CLAUDE_HOOK_MAX_INPUT_BYTES`, or
+/// [`DEFAULT_MAX_INPUT_BYTES`]
+pub fn max_input_bytes() -> u64 {
+    std::env::var("CLAUDE_HOOK_MAX_INPUT_BYTES")
+        .ok()
+        .and_then(|value| value.trim().parse().ok())
+        .unwrap_or(DEFAULT_MAX_INPUT_BYTES)
+}
+
+/// Read the whole event from stdin, up to [`max_input_bytes`]
+pub fn read_stdin() -> Result<String> {
+    read_bounded(io::stdin().lock(), max_input_bytes())
+}
+
+/// Read the whole event from stdin as bytes, ready for
+/// [`HookInput::from_slice`]
+pub fn read_stdin_bytes() -> Result<Vec<u8>> {
+    read_bounded_bytes(io::stdin().lock(), max_input_bytes())
+}
+
+/// Read `reader` to the end, failing with [`InputTooLarge`] as soon as more
+/// than `limit` bytes arrive rather than buffering all of them
+pub fn read_bounded(reader: impl Read, limit: u64) -> Result<String> {
+    String::from_utf8(read_bounded_bytes(reader, limit)?).context("Failed to read from stdin")
+}
+
+/// [`read_bounded`] without the UTF-8 check, which simd-json does anyway
+pub fn read_bounded_bytes(reader: impl Read, limit: u64) -> Result<Vec<u8>> {
+    let mut input = Vec::new();
+    reader.take(limit.saturating_add(1)).read_to_end(&mut input)
+        .context("Failed to read from stdin")?;
+    if input.len() as u64 > limit {
+        return Err(InputTooLarge { limit }.into());
+    }
+    Ok(input)
+}
+
+/// Parse `input` and run `hook` on it; errors are reported on stderr and
+/// turned into an approval, so a broken hook never takes the shell away
+pub fn handle<F>(name: &str, input: &str, hook: F) -> HookOutput
+where
+    F: FnOnce(HookInput) -> Result<HookOutput>,
+{
+    HookInput::parse(input).and_then(hook).unwrap_or_else(|e| {
+        eprintln!("Hook error: {}", e);
+        HookOutput::error(name, e)
+    })
+}
+
+/// Run `hook` as the process: read stdin, handle the event, print the
+/// response
+pub fn run<F>(name: &str, hook: F)
+where
+    F: FnOnce(HookInput) -> Result<HookOutput>,
+{
+    let parsed = read_stdin_bytes().and_then(|mut input| HookInput::from_slice(&mut input));
+    let rendered = match parsed {
+        Ok(input) => {
+            let output = hook(input.clone()).unwrap_or_else(|e| {
+                eprintln!("Hook error: {}", e);
+                HookOutput::error(name, e)
+            });
+            output.render(&input)
+        }
+        // Too big to look at, which is no reason to hold up the tool call
+        Err(e) if e.is::<InputTooLarge>() => {
+            eprintln!("{}", e);
+            serde_json::to_value(HookOutput::approve()).ok()
+        }
+        // The format is unknown, so answer in the original one
+        Err(e) => {
+            eprintln!("Hook error: {}", e);
+            serde_json::to_value(HookOutput::error(name, e)).ok()
+        }
+    };
+    
+    if let Some(json) = rendered {
+        println!("{}", json);
+    }
+}
+
+/// Format milliseconds since the epoch as `YYYY-MM-DDTHH:MM:SS.mmmZ`
+pub fn rfc3339(ts_ms: u64) -> String {
+    let secs = ts_ms / 1000;
+    let (days, rem) = (secs / 86_400, secs % 86_400);
+    
+    // Civil-from-days, after Howard Hinnant's date algorithms
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60,
+        ts_ms % 1000,
+    )
+}
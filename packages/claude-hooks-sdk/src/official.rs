@@ -0,0 +1,155 @@
+//! The flat hook schema current Claude Code sends
+//!
+//! ```json
+//! {"session_id": "...", "transcript_path": "...", "cwd": "/work/project",
+//!  "hook_event_name": "PreToolUse", "tool_name": "Bash",
+//!  "tool_input": {"command": "grep -r foo ."}}
+//! ```
+//!
+//! It is mapped onto [`HookInput`]: `cwd` becomes the project directory,
+//! `tool_input` (or, for events without a tool, the event's own fields)
+//! becomes the event data, and `transcript_path` and `permission_mode` go
+//! into the session context. Responses use `hookSpecificOutput`,
+//! `decision`/`reason`, and `systemMessage` as the event calls for; a
+//! PreToolUse hook changes the tool input by returning `modified_command`
+//! or an `updated_input` object in its context.
+
+use serde::Deserialize;
+use simd_json::prelude::*;
+
+use crate::{Decision, Event, HookInput, HookOutput, Schema, Session};
+
+#[derive(Debug, Deserialize)]
+pub struct OfficialInput {
+    #[serde(default)]
+    pub session_id: String,
+    #[serde(default)]
+    pub transcript_path: Option<String>,
+    #[serde(default)]
+    pub cwd: String,
+    #[serde(default)]
+    pub permission_mode: Option<String>,
+    pub hook_event_name: String,
+    #[serde(default)]
+    pub tool_name: Option<String>,
+    #[serde(default)]
+    pub tool_input: Option<serde_json::Value>,
+    #[serde(default)]
+    pub tool_response: Option<serde_json::Value>,
+    /// Event-specific fields (`prompt`, `message`, `source`, ...)
+    #[serde(flatten)]
+    pub rest: serde_json::Map<String, serde_json::Value>,
+}
+
+impl From<OfficialInput> for HookInput {
+    fn from(input: OfficialInput) -> Self {
+        let mut context = serde_json::Map::new();
+        if let Some(path) = input.transcript_path {
+            context.insert("transcript_path".to_string(), path.into());
+        }
+        if let Some(mode) = input.permission_mode {
+            context.insert("permission_mode".to_string(), mode.into());
+        }
+        
+        HookInput {
+            session: Session {
+                id: input.session_id,
+                project_dir: input.cwd,
+                context,
+            },
+            event: Event {
+                event_type: input.hook_event_name,
+                data: input.tool_input.unwrap_or(serde_json::Value::Object(input.rest)),
+                tool_name: input.tool_name,
+                tool_response: input.tool_response,
+            },
+            schema: Schema::Official,
+        }
+    }
+}
+
+/// Fields of [`OfficialInput`] other than the event-specific ones
+const FIELDS: &[&str] = &[
+    "session_id",
+    "transcript_path",
+    "cwd",
+    "permission_mode",
+    "hook_event_name",
+    "tool_name",
+    "tool_input",
+    "tool_response",
+];
+
+/// Drop the fields the event won't use before anything is copied out of
+/// the input buffer: the tool's response outside PostToolUse, and the
+/// event-specific fields when `tool_input` stands in for them
+pub(crate) fn drop_unused(object: &mut simd_json::borrowed::Object) {
+    if object.get("hook_event_name").and_then(|name| name.as_str()) != Some("PostToolUse") {
+        object.remove("tool_response");
+    }
+    if object.get("tool_input").is_some_and(|input| !input.is_null()) {
+        object.retain(|key, _| FIELDS.contains(&key.as_ref()));
+    }
+}
+
+/// `output` in the official response shape for `event`
+pub fn render(output: &HookOutput, event: &Event) -> Option<serde_json::Value> {
+    let name = event.event_type.as_str();
+    let mut response = serde_json::Map::new();
+    let mut specific = serde_json::Map::new();
+    
+    match (output.decision, name) {
+        (Decision::Block | Decision::Ask, "PreToolUse") => {
+            let permission = if output.decision == Decision::Block { "deny" } else { "ask" };
+            specific.insert("permissionDecision".to_string(), permission.into());
+            if let Some(message) = &output.message {
+                specific.insert("permissionDecisionReason".to_string(), message.clone().into());
+            }
+        }
+        (Decision::Block, _) => {
+            response.insert("decision".to_string(), "block".into());
+            if let Some(message) = &output.message {
+                response.insert("reason".to_string(), message.clone().into());
+            }
+        }
+        // Shown to the user, not Claude; only tool calls can be confirmed
+        (Decision::Approve | Decision::Ask, _) => {
+            if let Some(message) = &output.message {
+                response.insert("systemMessage".to_string(), message.clone().into());
+            }
+        }
+    }
+    if let Some(note) = &output.system_message {
+        let joined = match response.get("systemMessage").and_then(|message| message.as_str()) {
+            Some(message) => format!("{}\n{}", message, note),
+            None => note.clone(),
+        };
+        response.insert("systemMessage".to_string(), joined.into());
+    }
+    if output.suppress_output {
+        response.insert("suppressOutput".to_string(), true.into());
+    }
+    
+    let context = output.context.as_ref();
+    if let Some(additional) = context.and_then(|context| context["additional_context"].as_str()) {
+        specific.insert("additionalContext".to_string(), additional.into());
+    }
+    // A rewritten command, or any other `updated_input` fields, replace
+    // those in the original tool input
+    let command = context.and_then(|context| context["modified_command"].as_str());
+    let fields = context.and_then(|context| context["updated_input"].as_object());
+    if name == "PreToolUse" && (command.is_some() || fields.is_some()) {
+        let mut updated = event.data.as_object().cloned().unwrap_or_default();
+        if let Some(command) = command {
+            updated.insert("command".to_string(), command.into());
+        }
+        updated.extend(fields.into_iter().flatten().map(|(key, value)| (key.clone(), value.clone())));
+        specific.insert("updatedInput".to_string(), updated.into());
+    }
+    
+    if !specific.is_empty() {
+        specific.insert("hookEventName".to_string(), name.into());
+        response.insert("hookSpecificOutput".to_string(), specific.into());
+    }
+    (!response.is_empty()).then_some(serde_json::Value::Object(response))
+}
@@ -0,0 +1,119 @@
+//! Splitting a Bash command into simple commands
+//!
+//! Quotes and escapes are honoured; `;`, `&&`, `||`, `|`, `&`, and newlines
+//! separate commands. Redirection targets are kept apart from the words.
+
+/// One simple command: its words and the files it redirects to and from
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct SimpleCommand {
+    pub words: Vec<String>,
+    /// Targets of `>`, `>>`, `>|`, and `&>`
+    pub outputs: Vec<String>,
+    /// Sources of `<`
+    pub inputs: Vec<String>,
+}
+
+#[derive(Clone, Copy)]
+enum Redirect {
+    Output,
+    Input,
+}
+
+/// The words of each simple command in `command`, without redirections
+pub fn simple_commands(command: &str) -> Vec<Vec<String>> {
+    parse(command)
+        .into_iter()
+        .map(|command| command.words)
+        .filter(|words| !words.is_empty())
+        .collect()
+}
+
+/// Each simple command in `command`, with its redirections
+pub fn parse(command: &str) -> Vec<SimpleCommand> {
+    let mut commands = vec![SimpleCommand::default()];
+    let mut word = String::new();
+    let mut in_word = false;
+    let mut redirect = None;
+    let mut chars = command.chars().peekable();
+    
+    let finish = |commands: &mut Vec<SimpleCommand>, word: &mut String, in_word: &mut bool, redirect: &mut Option<Redirect>| {
+        if *in_word {
+            let word = std::mem::take(word);
+            let current = commands.last_mut().unwrap();
+            match redirect.take() {
+                Some(Redirect::Output) => current.outputs.push(word),
+                Some(Redirect::Input) => current.inputs.push(word),
+                None => current.words.push(word),
+            }
+            *in_word = false;
+        }
+    };
+    
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' => {
+                in_word = true;
+                for c in chars.by_ref() {
+                    if c == '\'' {
+                        break;
+                    }
+                    word.push(c);
+                }
+            }
+            '"' => {
+                in_word = true;
+                while let Some(c) = chars.next() {
+                    match c {
+                        '"' => break,
+                        '\\' => word.extend(chars.next()),
+                        c => word.push(c),
+                    }
+                }
+            }
+            '\\' => {
+                in_word = true;
+                word.extend(chars.next());
+            }
+            ';' | '|' | '&' | '\n' => {
+                finish(&mut commands, &mut word, &mut in_word, &mut redirect);
+                redirect = None;
+                // `&>` redirects both streams
+                if c == '&' && chars.peek() == Some(&'>') {
+                    continue;
+                }
+                if chars.peek() == Some(&c) {
+                    chars.next();
+                }
+                commands.push(SimpleCommand::default());
+            }
+            '>' | '<' => {
+                // A file descriptor number belongs to the operator (`2>`)
+                if in_word && word.chars().all(|c| c.is_ascii_digit()) {
+                    word.clear();
+                    in_word = false;
+                }
+                finish(&mut commands, &mut word, &mut in_word, &mut redirect);
+                if chars.peek() == Some(&'>') || chars.peek() == Some(&'|') {
+                    chars.next();
+                }
+                // `>&2` duplicates a descriptor rather than naming a file
+                if chars.peek() == Some(&'&') {
+                    chars.next();
+                    while chars.peek().is_some_and(|c| c.is_ascii_digit() || *c == '-') {
+                        chars.next();
+                    }
+                    continue;
+                }
+                redirect = Some(if c == '>' { Redirect::Output } else { Redirect::Input });
+            }
+            c if c.is_whitespace() => finish(&mut commands, &mut word, &mut in_word, &mut redirect),
+            c => {
+                in_word = true;
+                word.push(c);
+            }
+        }
+    }
+    finish(&mut commands, &mut word, &mut in_word, &mut redirect);
+    commands.retain(|command| *command != SimpleCommand::default());
+    commands
+}
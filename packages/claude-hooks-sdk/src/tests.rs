@@ -0,0 +1,155 @@
+//! Tests for protocol parsing and error handling
+
+use serde_json::json;
+use std::io::Read;
+
+use crate::{handle, read_bounded, HookInput, HookOutput, InputTooLarge, Schema};
+
+#[test]
+fn test_parse_input() {
+    let input = HookInput::parse(r#"{
+        "session": { "id": "s1", "projectDir": "/work", "context": { "flag": true } },
+        "event": { "type": "PreToolUse", "data": { "command": "ls" } }
+    }"#).unwrap();
+    assert_eq!(input.session.id, "s1");
+    assert_eq!(input.project_dir(), std::path::Path::new("/work"));
+    assert_eq!(input.session.context["flag"], true);
+    
+    #[derive(serde::Deserialize)]
+    struct Bash {
+        command: String,
+    }
+    assert_eq!(input.event.tool_data::<Bash>().unwrap().command, "ls");
+    
+    // `id`, `context`, and `data` are optional
+    let minimal = HookInput::parse(r#"{"session":{"projectDir":"."},"event":{"type":"Stop"}}"#).unwrap();
+    assert!(minimal.event.data.is_null());
+    assert!(HookInput::parse("not json").is_err());
+}
+
+#[test]
+fn test_output_shapes() {
+    assert_eq!(serde_json::to_value(HookOutput::approve()).unwrap(), json!({ "decision": "approve" }));
+    assert_eq!(
+        serde_json::to_value(HookOutput::block("no")).unwrap(),
+        json!({ "decision": "block", "message": "no" }),
+    );
+    assert_eq!(
+        serde_json::to_value(HookOutput::additional_context("hint")).unwrap(),
+        json!({ "decision": "approve", "context": { "additional_context": "hint" } }),
+    );
+}
+
+#[test]
+fn test_errors_approve() {
+    let output = handle("Example", "{}", |_| Ok(HookOutput::block("unreachable")));
+    assert!(!output.is_block());
+    assert!(output.message.unwrap().starts_with("Example hook error: Failed to parse JSON input"));
+    
+    let input = r#"{"session":{"projectDir":"."},"event":{"type":"Stop"}}"#;
+    let output = handle("Example", input, |_| anyhow::bail!("boom"));
+    assert_eq!(output, HookOutput::error("Example", "boom"));
+}
+
+#[test]
+fn test_official_schema() {
+    let input = HookInput::parse(r#"{
+        "session_id": "abc", "transcript_path": "/t.jsonl", "cwd": "/work",
+        "hook_event_name": "PreToolUse", "tool_name": "Bash",
+        "tool_input": { "command": "grep foo", "description": "search" }
+    }"#).unwrap();
+    assert_eq!(input.schema, Schema::Official);
+    assert_eq!(input.session.id, "abc");
+    assert_eq!(input.project_dir(), std::path::Path::new("/work"));
+    assert_eq!(input.session.context["transcript_path"], "/t.jsonl");
+    assert_eq!(input.event.event_type, "PreToolUse");
+    assert_eq!(input.event.tool_name.as_deref(), Some("Bash"));
+    assert_eq!(input.event.data["command"], "grep foo");
+    
+    // Rewrites keep the rest of the tool input
+    let rewrite = HookOutput::approve()
+        .with_message("Using rg")
+        .with_context(json!({ "modified_command": "rg foo", "original_command": "grep foo" }));
+    assert_eq!(rewrite.render(&input).unwrap(), json!({
+        "systemMessage": "Using rg",
+        "hookSpecificOutput": {
+            "hookEventName": "PreToolUse",
+            "updatedInput": { "command": "rg foo", "description": "search" },
+        },
+    }));
+    let updated = HookOutput::approve().with_context(json!({ "updated_input": { "timeout": 1000 } }));
+    assert_eq!(
+        updated.render(&input).unwrap()["hookSpecificOutput"]["updatedInput"],
+        json!({ "command": "grep foo", "description": "search", "timeout": 1000 }),
+    );
+    assert_eq!(
+        HookOutput::block("no").render(&input).unwrap()["hookSpecificOutput"]["permissionDecision"],
+        "deny",
+    );
+    assert_eq!(HookOutput::approve().render(&input), None);
+    assert_eq!(
+        HookOutput::approve().with_system_message("using rg").with_suppressed_output().render(&input).unwrap(),
+        json!({ "systemMessage": "using rg", "suppressOutput": true }),
+    );
+    assert_eq!(
+        HookOutput::ask("sure?").render(&input).unwrap()["hookSpecificOutput"],
+        json!({ "hookEventName": "PreToolUse", "permissionDecision": "ask", "permissionDecisionReason": "sure?" }),
+    );
+    
+    // Events without a tool keep their own fields as data
+    let prompt = HookInput::parse(r#"{"session_id":"abc","cwd":"/work","hook_event_name":"UserPromptSubmit","prompt":"hi"}"#).unwrap();
+    assert_eq!(prompt.event.data["prompt"], "hi");
+    assert_eq!(
+        HookOutput::additional_context("facts").render(&prompt).unwrap(),
+        json!({ "hookSpecificOutput": { "hookEventName": "UserPromptSubmit", "additionalContext": "facts" } }),
+    );
+    assert_eq!(
+        HookOutput::block("wait").render(&HookInput { schema: Schema::Official, ..prompt }).unwrap(),
+        json!({ "decision": "block", "reason": "wait" }),
+    );
+}
+
+#[test]
+fn test_parse_in_place() {
+    let mut bytes = br#"{"session_id":"s","cwd":"/w","hook_event_name":"PostToolUse","tool_name":"Bash",
+        "tool_input":{"command":"echo \"a\tb\""},"tool_response":{"exit_code":1}}"#.to_vec();
+    let input = HookInput::from_slice(&mut bytes).unwrap();
+    assert_eq!(input.event.data["command"], "echo \"a\tb\"");
+    assert_eq!(input.event.tool_response.unwrap()["exit_code"], 1);
+    
+    // Only PostToolUse needs the tool's response
+    let input = HookInput::parse(r#"{"cwd":"/w","hook_event_name":"PreToolUse","tool_input":{},"tool_response":{"a":1}}"#).unwrap();
+    assert!(input.event.tool_response.is_none());
+    assert!(HookInput::from_slice(&mut b"{\"cwd\": \xff}".to_vec()).is_err());
+}
+
+#[test]
+fn test_bounded_input() {
+    let event = r#"{"session":{"projectDir":"."},"event":{"type":"Stop"}}"#;
+    assert_eq!(read_bounded(event.as_bytes(), event.len() as u64).unwrap(), event);
+    
+    // Reading stops one byte past the limit, however much is left
+    let mut oversized = event.as_bytes().chain(std::io::repeat(b' '));
+    let error = read_bounded(&mut oversized, 1024).unwrap_err();
+    assert_eq!(error.downcast_ref::<InputTooLarge>(), Some(&InputTooLarge { limit: 1024 }));
+    
+    // A payload cut off mid-event is a parse error, which approves
+    let truncated = read_bounded(&event.as_bytes()[..20], 1024).unwrap();
+    let output = handle("Example", &truncated, |_| Ok(HookOutput::block("unreachable")));
+    assert!(!output.is_block());
+    assert!(output.message.unwrap().starts_with("Example hook error: Failed to parse JSON input"));
+}
+
+#[test]
+fn test_shell_parse() {
+    use crate::shell::{parse, simple_commands};
+    
+    let parsed = parse("echo 'a > b' > out.txt 2>&1; sort < in.txt &> sorted | tee -a log");
+    assert_eq!(parsed.len(), 3);
+    assert_eq!(parsed[0].words, ["echo", "a > b"]);
+    assert_eq!(parsed[0].outputs, ["out.txt"]);
+    assert_eq!(parsed[1].words, ["sort"]);
+    assert_eq!(parsed[1].inputs, ["in.txt"]);
+    assert_eq!(parsed[1].outputs, ["sorted"]);
+    assert_eq!(simple_commands("git status && cargo test 2> err"), [vec!["git", "status"], vec!["cargo", "test"]]);
+}
@@ -0,0 +1,116 @@
+//! The command-replacer engine as a WebAssembly module
+//!
+//! Built with `cargo build --release -p command-replacer-wasm --target
+//! wasm32-wasip1`, it runs inside Node hooks or browser dashboards (with a
+//! WASI shim) without a binary per platform. There is no PATH to search, so
+//! the module imports `command_replacer.tool_available(ptr, len) -> i32`
+//! and asks the host whether each tool, named in UTF-8 in linear memory,
+//! is installed.
+//!
+//! To rewrite a command the host copies a JSON request into a buffer from
+//! `cr_alloc`, calls `cr_rewrite(ptr, len)`, and reads the JSON response
+//! from the pointer (high 32 bits) and length (low 32 bits) it returns.
+//! Both buffers are released with `cr_free(ptr, len)`. That packing needs
+//! 32-bit pointers, so `cr_rewrite` is only exported from wasm32; native
+//! callers use [`rewrite_json`].
+//!
+//! ```json
+//! {"command": "grep -rn TODO src/", "config": {}, "context": {"git_repo": true}}
+//! {"rewritten": "rg -n TODO src/", "reason": null, "construct": null}
+//! ```
+//!
+//! `config` and `context` are optional: the built-in config and an empty
+//! context are used without them. A request that fails answers
+//! `{"error": "..."}`.
+
+use anyhow::{Context, Result};
+use command_replacer::{Config, ReplacementEngine, RuleContext};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Deserialize)]
+struct Request {
+    command: String,
+    #[serde(default)]
+    config: Option<Config>,
+    #[serde(default)]
+    context: RuleContext,
+}
+
+/// What the engine made of one command
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Response {
+    pub rewritten: Option<String>,
+    /// Why the command was left alone, when the engine said
+    pub reason: Option<String>,
+    /// Short name of what stopped the rewrite, e.g. `tool-missing`
+    pub construct: Option<String>,
+}
+
+/// Answer one JSON request, asking `tool_available` which tools are installed
+pub fn rewrite_json(request: &str, tool_available: impl Fn(&str) -> bool + Send + Sync + 'static) -> String {
+    match rewrite(request, tool_available) {
+        Ok(response) => serde_json::to_string(&response).unwrap_or_default(),
+        Err(e) => serde_json::json!({ "error": format!("{:#}", e) }).to_string(),
+    }
+}
+
+fn rewrite(request: &str, tool_available: impl Fn(&str) -> bool + Send + Sync + 'static) -> Result<Response> {
+    let request: Request = serde_json::from_str(request).context("Invalid request")?;
+    let engine = ReplacementEngine::for_context(request.config.unwrap_or_default(), request.context)?
+        .with_tool_probe(tool_available);
+    
+    let rewritten = engine.replace_command(&request.command)?;
+    let fallback = engine.last_fallback_reason().filter(|_| rewritten.is_none());
+    Ok(Response {
+        rewritten,
+        reason: fallback.as_ref().map(|reason| reason.detail.clone()),
+        construct: fallback.map(|reason| reason.construct),
+    })
+}
+
+/// A buffer of `len` bytes for the host to write a request into
+#[no_mangle]
+pub extern "C" fn cr_alloc(len: usize) -> *mut u8 {
+    Box::into_raw(vec![0u8; len].into_boxed_slice()) as *mut u8
+}
+
+/// Release a buffer from [`cr_alloc`] or a response from `cr_rewrite`
+///
+/// # Safety
+///
+/// `ptr` and `len` must describe exactly one such buffer, not yet released.
+#[no_mangle]
+pub unsafe extern "C" fn cr_free(ptr: *mut u8, len: usize) {
+    drop(Box::from_raw(std::ptr::slice_from_raw_parts_mut(ptr, len)));
+}
+
+/// Rewrite the command in the JSON request at `ptr`; returns the response's
+/// pointer and length packed into one value
+///
+/// # Safety
+///
+/// `ptr` must point to `len` readable bytes.
+#[cfg(all(target_family = "wasm", target_pointer_width = "32"))]
+#[no_mangle]
+pub unsafe extern "C" fn cr_rewrite(ptr: *const u8, len: usize) -> u64 {
+    let request = String::from_utf8_lossy(std::slice::from_raw_parts(ptr, len));
+    let response = rewrite_json(&request, host_tool_available).into_bytes().into_boxed_slice();
+    let len = response.len() as u64;
+    let ptr = Box::into_raw(response) as *mut u8 as usize as u64;
+    (ptr << 32) | len
+}
+
+#[cfg(all(target_family = "wasm", target_pointer_width = "32"))]
+#[link(wasm_import_module = "command_replacer")]
+extern "C" {
+    fn tool_available(name: *const u8, len: usize) -> i32;
+}
+
+/// Ask the host whether `tool` is installed
+#[cfg(all(target_family = "wasm", target_pointer_width = "32"))]
+fn host_tool_available(tool: &str) -> bool {
+    unsafe { tool_available(tool.as_ptr(), tool.len()) != 0 }
+}
+
+#[cfg(test)]
+mod tests;
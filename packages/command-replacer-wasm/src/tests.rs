@@ -0,0 +1,17 @@
+use crate::rewrite_json;
+
+#[test]
+fn test_rewrite_with_host_probe() {
+    let installed = |tools: &'static [&'static str]| move |tool: &str| tools.contains(&tool);
+    
+    let response: serde_json::Value = serde_json::from_str(&rewrite_json(r#"{"command": "grep -rn TODO src/"}"#, installed(&["rg"]))).unwrap();
+    assert!(response["rewritten"].as_str().unwrap().starts_with("rg "));
+    assert_eq!(response["reason"], serde_json::Value::Null);
+    
+    let response: serde_json::Value = serde_json::from_str(&rewrite_json(r#"{"command": "grep -rn TODO src/", "context": {"git_repo": true}}"#, installed(&[]))).unwrap();
+    assert_eq!(response["rewritten"], serde_json::Value::Null);
+    assert_eq!(response["construct"], "tool-missing");
+    
+    let response: serde_json::Value = serde_json::from_str(&rewrite_json("{", installed(&[]))).unwrap();
+    assert!(response["error"].as_str().unwrap().starts_with("Invalid request"));
+}
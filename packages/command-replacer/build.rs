@@ -0,0 +1,21 @@
+//! Embed the rule specs in `rules.d/` as the built-in specs, so adding a
+//! replacement only takes a new file there
+
+use std::path::{Path, PathBuf};
+
+fn main() {
+    println!("cargo:rerun-if-changed=rules.d");
+    let dir = Path::new(&std::env::var("CARGO_MANIFEST_DIR").unwrap()).join("rules.d");
+    let mut specs: Vec<PathBuf> = std::fs::read_dir(&dir)
+        .map(|entries| entries.filter_map(|entry| Some(entry.ok()?.path())).collect())
+        .unwrap_or_default();
+    specs.retain(|path| path.extension().is_some_and(|ext| ext == "toml"));
+    specs.sort();
+    
+    let entries: String = specs
+        .iter()
+        .map(|path| format!("    ({:?}, include_str!({:?})),\n", path.file_name().unwrap().to_string_lossy(), path.display()))
+        .collect();
+    let out = Path::new(&std::env::var("OUT_DIR").unwrap()).join("rule_specs.rs");
+    std::fs::write(out, format!("&[\n{}]\n", entries)).unwrap();
+}
@@ -0,0 +1,116 @@
+//! Long-running command advice
+//!
+//! Full builds, image pulls, dependency installs, and whole test suites
+//! regularly outlast the Bash tool's timeout. For those, Claude is told how
+//! to avoid losing the run: split it up, background it, or parallelize it.
+//! With `settings.long_running.auto_timeout` the command is also wrapped in
+//! `timeout` so it fails cleanly instead of being killed mid-write.
+
+/// Why a command is expected to take a while
+#[derive(Debug, Clone, PartialEq)]
+pub struct Advice {
+    /// The command as the user would recognize it (`cargo build`, `make`)
+    pub program: String,
+    pub kind: &'static str,
+    /// Flag that runs the work in parallel, if the tool doesn't already
+    pub parallelism: Option<&'static str>,
+}
+
+impl Advice {
+    pub fn message(&self) -> String {
+        let mut message = format!(
+            "`{}` ({}) may run past the Bash tool's timeout. Consider splitting it into smaller steps or running it in the background (`run_in_background`, or `nohup ... > build.log 2>&1 &`)",
+            self.program, self.kind,
+        );
+        if let Some(flag) = self.parallelism {
+            message.push_str(&format!(", or adding `{}` to parallelize it", flag));
+        }
+        message.push('.');
+        message
+    }
+}
+
+/// The first command in `command` likely to run for minutes, unless the
+/// user already backgrounded or bounded it
+pub fn detect(command: &str) -> Option<Advice> {
+    let trimmed = command.trim();
+    if trimmed.ends_with('&') && !trimmed.ends_with("&&") {
+        return None;
+    }
+    trimmed
+        .split(['\n', ';', '|', '&'])
+        .filter_map(shlex::split)
+        .find_map(|words| classify(strip_wrappers(&words)))
+}
+
+/// `timeout <secs> <command>`, through `bash -c` when the command is more
+/// than a single simple command, and `env` when it starts by setting
+/// variables
+pub fn with_timeout(command: &str, secs: u64) -> String {
+    let compound = command.contains(['\n', ';', '|', '&', '>', '<', '$', '`']);
+    if compound {
+        let quoted = shlex::try_quote(command).map(|quoted| quoted.into_owned()).unwrap_or_else(|_| command.to_string());
+        format!("timeout {} bash -c {}", secs, quoted)
+    } else if command.split_whitespace().next().is_some_and(is_assignment) {
+        format!("timeout {} env {}", secs, command)
+    } else {
+        format!("timeout {} {}", secs, command)
+    }
+}
+
+/// Whether `word` is a `NAME=value` prefix rather than a program
+fn is_assignment(word: &str) -> bool {
+    word.split_once('=').is_some_and(|(name, _)| {
+        name.chars().next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+    })
+}
+
+fn strip_wrappers(words: &[String]) -> &[String] {
+    let mut words = words;
+    while let Some(first) = words.first() {
+        if matches!(first.as_str(), "sudo" | "env" | "time" | "nice") || (first.contains('=') && !first.starts_with('-')) {
+            words = &words[1..];
+        } else {
+            break;
+        }
+    }
+    words
+}
+
+fn classify(words: &[String]) -> Option<Advice> {
+    let (program, args) = words.split_first()?;
+    let has = |flags: &[&str]| args.iter().any(|arg| flags.iter().any(|flag| arg == flag || arg.starts_with(&format!("{}=", flag))));
+    let subcommand = args.iter().find(|arg| !arg.starts_with('-')).map(String::as_str);
+    let advice = |name: String, kind, parallelism| Some(Advice { program: name, kind, parallelism });
+    
+    match (program.rsplit('/').next().unwrap_or(program), subcommand) {
+        // Selecting one package keeps cargo's work small
+        ("cargo", Some(sub @ ("build" | "test" | "bench" | "install" | "doc"))) if !has(&["-p", "--package"]) || has(&["--workspace"]) => {
+            advice(format!("cargo {}", sub), "full workspace build", None)
+        }
+        ("make" | "gmake", target) if !target.is_some_and(|t| matches!(t, "clean" | "help" | "install")) && !args.iter().any(|arg| arg.starts_with("-j")) => {
+            advice("make".to_string(), "full build", Some("-j$(nproc)"))
+        }
+        ("docker" | "podman", Some(sub @ ("pull" | "build"))) => advice(format!("{} {}", program, sub), "image download or build", None),
+        ("docker" | "podman", Some("compose")) if args.iter().any(|arg| matches!(arg.as_str(), "pull" | "build" | "up")) => {
+            advice(format!("{} compose", program), "image download or build", None)
+        }
+        ("npm" | "pnpm" | "yarn" | "bun", Some(sub @ ("install" | "ci" | "i"))) => advice(format!("{} {}", program, sub), "dependency install", None),
+        ("pip" | "pip3", Some("install")) if has(&["-r", "--requirement"]) => advice(format!("{} install -r", program), "dependency install", None),
+        ("mvn" | "./mvnw", Some("install" | "package" | "verify" | "test")) if !has(&["-T", "--threads"]) => {
+            advice(program.to_string(), "full build", Some("-T 1C"))
+        }
+        ("gradle" | "./gradlew", Some("build" | "test" | "check")) if !has(&["--parallel"]) => advice(program.to_string(), "full build", Some("--parallel")),
+        // Without a file or node id, pytest runs the whole suite
+        ("pytest", _) if !args.iter().any(|arg| !arg.starts_with('-')) && !has(&["-n", "--numprocesses"]) => {
+            advice("pytest".to_string(), "whole test suite", Some("-n auto"))
+        }
+        ("go", Some(sub @ ("build" | "test"))) if args.iter().any(|arg| arg.ends_with("/...")) => {
+            advice(format!("go {} ./...", sub), "every package", None)
+        }
+        ("cmake", _) if has(&["--build"]) && !has(&["-j", "--parallel"]) => advice("cmake --build".to_string(), "full build", Some("--parallel")),
+        ("ctest", _) if !has(&["-j", "--parallel"]) => advice("ctest".to_string(), "whole test suite", Some("-j$(nproc)")),
+        _ => None,
+    }
+}
@@ -0,0 +1,63 @@
+//! `--batch`: serve many events from one process
+//!
+//! Events arrive as newline-delimited JSON on stdin and each gets exactly
+//! one response line on stdout, flushed as soon as it's written, so a test
+//! harness or a multiplexer can keep a single hook process running. A
+//! protocol that prints nothing answers `{}`; the exit-code protocol, which
+//! has no JSON of its own, answers `{"exit_code": ..., "stderr": ...}`.
+
+use anyhow::{Context, Result};
+use std::io::{BufRead, Write};
+
+use crate::config::OutputProtocol;
+use crate::output::{Emission, HookResponse, PassReason};
+use crate::Handled;
+
+/// Answer every event read from `reader` until it ends; `protocol` overrides
+/// the one each event's config picks
+pub fn serve(mut reader: impl BufRead, mut writer: impl Write, protocol: Option<OutputProtocol>, max_line_bytes: u64) -> Result<()> {
+    let mut line = Vec::new();
+    loop {
+        line.clear();
+        if reader.read_until(b'\n', &mut line).context("Failed to read from stdin")? == 0 {
+            return Ok(());
+        }
+        if line.iter().all(u8::is_ascii_whitespace) {
+            continue;
+        }
+        
+        let handled = if line.len() as u64 > max_line_bytes {
+            Handled {
+                response: HookResponse::Passthrough(PassReason::InputTooLarge),
+                protocol: crate::config::output_protocol_from_env(std::env::vars()).unwrap_or_default(),
+                timings: None,
+            }
+        } else {
+            crate::handle_slice(&mut line)
+        };
+        let protocol = protocol.unwrap_or(handled.protocol);
+        let emission = handled.response.render_timed(protocol, handled.timings.as_ref()).unwrap_or_else(|e| Emission {
+            stdout: None,
+            stderr: Some(format!("Hook error: {}", e)),
+            exit_code: 0,
+        });
+        if let Some(stderr) = emission.stderr.as_ref().filter(|_| protocol != OutputProtocol::ExitCode) {
+            eprintln!("{}", stderr);
+        }
+        writeln!(writer, "{}", response_line(&emission, protocol)).context("Failed to write to stdout")?;
+        writer.flush().context("Failed to write to stdout")?;
+    }
+}
+
+/// The single line answering one event
+pub fn response_line(emission: &Emission, protocol: OutputProtocol) -> String {
+    match (protocol, &emission.stdout) {
+        (OutputProtocol::ExitCode, _) => serde_json::json!({
+            "exit_code": emission.exit_code,
+            "stderr": emission.stderr,
+        })
+        .to_string(),
+        (_, Some(stdout)) => stdout.clone(),
+        (_, None) => "{}".to_string(),
+    }
+}
@@ -0,0 +1,186 @@
+//! `command-replacer bench`: time the engine over a corpus of commands
+//!
+//! Each command is rewritten in samples of many calls after a warm-up, so
+//! the tool probes and regex compilation are paid before timing starts.
+//! `--hyperfine` additionally times whole hook processes end to end.
+
+use anyhow::{bail, Context, Result};
+use std::fmt;
+use std::path::Path;
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+use crate::config::Config;
+use crate::replacements::ReplacementEngine;
+use crate::tool_cache::ToolCache;
+
+/// Representative commands: plain rewrites, flag translation, pipelines,
+/// declines, and commands nothing applies to
+pub const CORPUS: &[&str] = &[
+    "grep -rn TODO src/",
+    "grep -i 'error' app.log | head -20",
+    "grep -E 'fn (main|run)' -r . --include='*.rs'",
+    "grep -v '^#' config.ini",
+    "find . -name '*.rs' -type f",
+    "find src -type d -name tests",
+    "find . -name '*.tmp' -mtime +7 -delete",
+    "cat README.md",
+    "cat -n src/lib.rs | head -50",
+    "ls -la",
+    "ls -lhS target/",
+    "sed -i 's/foo/bar/g' file.txt",
+    "sed -n '1,10p' file.txt",
+    "ps aux | grep node",
+    "cd packages && grep -rl serde . | xargs wc -l",
+    "cargo test --workspace",
+    "git status --short",
+];
+
+/// Timing statistics over the samples of one benchmark, per call
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stats {
+    pub mean: Duration,
+    pub median: Duration,
+    pub min: Duration,
+    pub max: Duration,
+    pub std_dev: Duration,
+}
+
+impl Stats {
+    /// Summarize per-call sample times
+    pub fn from_samples(samples: &[Duration]) -> Option<Self> {
+        if samples.is_empty() {
+            return None;
+        }
+        let mut sorted = samples.to_vec();
+        sorted.sort();
+        
+        let secs: Vec<f64> = sorted.iter().map(Duration::as_secs_f64).collect();
+        let mean = secs.iter().sum::<f64>() / secs.len() as f64;
+        let variance = secs.iter().map(|s| (s - mean).powi(2)).sum::<f64>() / secs.len() as f64;
+        let middle = sorted.len() / 2;
+        let median = if sorted.len().is_multiple_of(2) {
+            (sorted[middle - 1] + sorted[middle]) / 2
+        } else {
+            sorted[middle]
+        };
+        
+        Some(Self {
+            mean: Duration::from_secs_f64(mean),
+            median,
+            min: sorted[0],
+            max: sorted[sorted.len() - 1],
+            std_dev: Duration::from_secs_f64(variance.sqrt()),
+        })
+    }
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:>10} {:>10} {:>10} {:>10} {:>10}",
+            micros(self.mean),
+            micros(self.median),
+            micros(self.min),
+            micros(self.max),
+            micros(self.std_dev),
+        )
+    }
+}
+
+fn micros(duration: Duration) -> String {
+    format!("{:.2}µs", duration.as_secs_f64() * 1e6)
+}
+
+/// Time `engine` on each command: `samples` samples of `iterations` calls
+pub fn run_corpus(engine: &ReplacementEngine, commands: &[&str], samples: usize, iterations: u32) -> Result<Vec<(String, Stats)>> {
+    let iterations = iterations.max(1);
+    let mut results = Vec::new();
+    for command in commands {
+        // Warm up: probes, caches, and lazily compiled patterns
+        for _ in 0..iterations.min(10) {
+            engine.replace_command(command)?;
+        }
+        
+        let mut times = Vec::with_capacity(samples);
+        for _ in 0..samples {
+            let started = Instant::now();
+            for _ in 0..iterations {
+                std::hint::black_box(engine.replace_command(std::hint::black_box(command))?);
+            }
+            times.push(started.elapsed() / iterations);
+        }
+        if let Some(stats) = Stats::from_samples(&times) {
+            results.push((command.to_string(), stats));
+        }
+    }
+    Ok(results)
+}
+
+/// `bench`: print per-command statistics for the engine and, with
+/// `hyperfine`, the end-to-end time of one hook process
+pub fn bench(project: Option<&Path>, samples: usize, iterations: u32, hyperfine: bool) -> Result<i32> {
+    let mut config = Config::load_for_project(project)?;
+    // Time the engine itself, not lookups of the decisions it already made
+    config.settings.rewrite_cache.enabled = false;
+    let tool_cache = if config.settings.cache_tool_checks {
+        ToolCache::load(&ToolCache::default_path())
+    } else {
+        ToolCache::default()
+    };
+    let engine = ReplacementEngine::new(config)?.with_tool_cache(tool_cache);
+    
+    let results = run_corpus(&engine, CORPUS, samples, iterations)?;
+    let width = results.iter().map(|(command, _)| command.chars().count()).max().unwrap_or(0);
+    println!("{:<width$} {:>10} {:>10} {:>10} {:>10} {:>10}", "command", "mean", "median", "min", "max", "std dev");
+    for (command, stats) in &results {
+        println!("{:<width$} {}", command, stats);
+    }
+    let means: Vec<Duration> = results.iter().map(|(_, stats)| stats.mean).collect();
+    if let Some(overall) = Stats::from_samples(&means) {
+        println!("{:<width$} {}", "(per-command means)", overall);
+    }
+    println!("\n{} commands, {} samples of {} calls each", results.len(), samples, iterations.max(1));
+    
+    if hyperfine {
+        end_to_end(project)?;
+    }
+    Ok(0)
+}
+
+/// Run hyperfine on this binary, fed a PreToolUse event for the first
+/// corpus command
+fn end_to_end(project: Option<&Path>) -> Result<()> {
+    if crate::tool_cache::find_program("hyperfine").is_none() {
+        bail!("hyperfine is not installed");
+    }
+    let exe = std::env::current_exe().context("Failed to locate the command-replacer binary")?;
+    let exe = shlex::try_quote(&exe.to_string_lossy()).context("Unquotable binary path")?.into_owned();
+    let cwd = match project {
+        Some(dir) => dir.to_path_buf(),
+        None => std::env::current_dir().context("Failed to read the current directory")?,
+    };
+    let event = serde_json::json!({
+        "session_id": "bench",
+        "cwd": cwd,
+        "hook_event_name": "PreToolUse",
+        "tool_name": "Bash",
+        "tool_input": { "command": CORPUS[0] },
+    });
+    let input = std::env::temp_dir().join(format!("command-replacer-bench.{}.json", std::process::id()));
+    std::fs::write(&input, event.to_string())
+        .with_context(|| format!("Failed to write {}", input.display()))?;
+    
+    let status = Command::new("hyperfine")
+        .args(["--shell=none", "--warmup", "3", "--input"])
+        .arg(&input)
+        .arg(&exe)
+        .status();
+    let _ = std::fs::remove_file(&input);
+    let status = status.context("Failed to run hyperfine")?;
+    if !status.success() {
+        bail!("hyperfine exited with {}", status);
+    }
+    Ok(())
+}
@@ -0,0 +1,47 @@
+//! Self-enforced latency budget
+//!
+//! A slow NFS home directory or a cold PATH lookup must never stall a tool
+//! call, so PreToolUse checks the clock between phases (config load, project
+//! detection, rewriting) and gives up with a plain approve once
+//! `settings.latency_budget_ms` is spent.
+
+use std::time::{Duration, Instant};
+
+use crate::replacements::FallbackReason;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Budget {
+    started: Instant,
+    limit: Option<Duration>,
+}
+
+impl Budget {
+    /// A budget of `limit_ms` counted from `started`; `None` never runs out
+    pub fn new(started: Instant, limit_ms: Option<u64>) -> Self {
+        Self {
+            started,
+            limit: limit_ms.map(Duration::from_millis),
+        }
+    }
+    
+    /// When the budget runs out, if it ever does
+    pub fn deadline(&self) -> Option<Instant> {
+        self.limit.map(|limit| self.started + limit)
+    }
+    
+    /// Why `command` was left alone, if the budget ran out by the end of
+    /// `phase`
+    pub fn check(&self, command: &str, phase: &str) -> Option<FallbackReason> {
+        let limit = self.limit?;
+        let elapsed = self.started.elapsed();
+        (elapsed >= limit).then(|| {
+            let detail = format!(
+                "{}ms latency budget spent after {} ({}ms elapsed)",
+                limit.as_millis(),
+                phase,
+                elapsed.as_millis(),
+            );
+            FallbackReason::new(command, "latency-budget", detail)
+        })
+    }
+}
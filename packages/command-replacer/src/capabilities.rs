@@ -0,0 +1,57 @@
+//! What an installed replacement tool can do, beyond being installed
+//!
+//! Decided from the tool's `--version` output, which is probed once per
+//! process (or read from the SessionStart tool cache), so a replacer can
+//! pick the translation the installed build supports.
+
+use crate::replacements::{parse_version, version_at_least};
+
+/// Flags eza added that exa never had
+pub const EZA_ONLY_FLAGS: &[&str] = &[
+    "--absolute",
+    "--git-repos",
+    "--git-repos-no-status",
+    "--hyperlink",
+    "--no-quotes",
+    "--no-symlinks",
+    "--show-symlinks",
+    "--smart-group",
+    "-X",
+    "--dereference",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Capability {
+    /// ripgrep built with PCRE2, so `grep -P` can become `rg --pcre2`
+    RgPcre2,
+    /// fd 7.1+, which filters by `--size`
+    FdSize,
+    /// eza rather than exa, so flags exa never had can be passed through
+    EzaFlags,
+    /// The GNU build of sed, grep, or find, rather than the BSD one macOS
+    /// and the BSDs ship (or that prints nothing for `--version`)
+    Gnu,
+}
+
+impl Capability {
+    /// Whether a tool printing `version_output` for `--version` has this
+    /// capability; the output also has to come from the right tool
+    pub fn detect(self, version_output: &str) -> bool {
+        let output = version_output.trim_start();
+        match self {
+            // `features:+pcre2` on ripgrep 14, `+pcre2` or `PCRE2 ... is
+            // available` on older builds
+            Capability::RgPcre2 => {
+                output.starts_with("ripgrep")
+                    && (output.contains("+pcre2") || output.lines().any(|line| line.starts_with("PCRE2") && line.contains("is available")))
+            }
+            Capability::FdSize => {
+                output.starts_with("fd ") && parse_version(output).is_some_and(|version| version_at_least(&version, &[7, 1]))
+            }
+            Capability::EzaFlags => output.starts_with("eza"),
+            // `sed (GNU sed) 4.9`, `find (GNU findutils) 4.9.0`; macOS grep
+            // says `(BSD grep, GNU compatible)`
+            Capability::Gnu => output.lines().next().is_some_and(|line| line.contains("(GNU ")),
+        }
+    }
+}
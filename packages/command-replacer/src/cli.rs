@@ -0,0 +1,708 @@
+//! Command-line interface for maintenance subcommands
+//!
+//! Invoked without a subcommand, the binary runs as the PreToolUse hook and
+//! reads the event from stdin.
+
+use anyhow::{bail, Context, Result};
+use clap::{Parser, Subcommand, ValueEnum};
+use regex::Regex;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use crate::config::{Config, OutputProtocol};
+use crate::install;
+use crate::tool_install;
+use crate::show::EffectiveConfig;
+use crate::validate;
+
+#[derive(Debug, Parser)]
+#[command(name = "command-replacer", version, about = "Replace shell commands with faster modern alternatives")]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+    
+    /// Response shape to emit when running as the hook, overriding the
+    /// config and CLAUDE_CMDREPL_OUTPUT_PROTOCOL
+    #[arg(long, value_name = "PROTOCOL")]
+    pub protocol: Option<OutputProtocol>,
+    
+    /// Read newline-delimited events from stdin until it closes, answering
+    /// each with one line
+    #[arg(long)]
+    pub batch: bool,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Check a config file against the schema (defaults to the global config)
+    ValidateConfig {
+        path: Option<PathBuf>,
+    },
+    /// Print the config JSON Schema
+    Schema,
+    /// Print the effective merged config, annotated with each setting's source
+    ShowConfig {
+        /// Project directory whose config layers to include
+        #[arg(long, value_name = "DIR")]
+        project: Option<PathBuf>,
+        #[arg(long, value_enum, default_value_t = OutputFormat::Toml)]
+        format: OutputFormat,
+    },
+    /// Share the config with other tools (windows-tools)
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
+    },
+    /// Write a commented starter config
+    Init {
+        /// Scaffold `<DIR>/.claude/command-replacer.toml` instead of the global config
+        #[arg(long, value_name = "DIR")]
+        project: Option<PathBuf>,
+        /// Overwrite an existing file
+        #[arg(long)]
+        force: bool,
+        /// Print the config instead of writing it
+        #[arg(long)]
+        print: bool,
+    },
+    /// Check tools, config, patterns, and hook registration
+    Doctor {
+        /// Project directory whose config and settings to check as well
+        #[arg(long, value_name = "DIR")]
+        project: Option<PathBuf>,
+        /// Install missing tools with the platform's package manager
+        #[arg(long)]
+        fix: bool,
+    },
+    /// Register the hook in Claude's settings.json
+    Install {
+        /// Register in `<DIR>/.claude/settings.json` instead of the user's settings
+        #[arg(long, value_name = "DIR")]
+        project: Option<PathBuf>,
+        /// Binary the hook should run (defaults to this one)
+        #[arg(long, value_name = "PATH")]
+        binary: Option<PathBuf>,
+    },
+    /// Remove the hook from Claude's settings.json
+    Uninstall {
+        /// Deregister from `<DIR>/.claude/settings.json` instead of the user's settings
+        #[arg(long, value_name = "DIR")]
+        project: Option<PathBuf>,
+        /// Also delete the global config, caches, session state, and logs
+        #[arg(long)]
+        purge: bool,
+    },
+    /// Replace this binary with the latest release after verifying it
+    #[cfg(feature = "native")]
+    SelfUpdate {
+        /// Only report whether an update is available
+        #[arg(long)]
+        check: bool,
+        /// Release feed to read (a GitHub "latest release" API URL)
+        #[arg(long, value_name = "URL", default_value = crate::self_update::DEFAULT_FEED)]
+        feed: String,
+        /// Minisign public key the release must be signed with, in place
+        /// of the built-in release key
+        #[arg(long, value_name = "KEY")]
+        public_key: Option<String>,
+        /// Install a release without a valid signature, trusting its
+        /// checksum alone
+        #[arg(long, conflicts_with = "public_key")]
+        allow_unsigned: bool,
+    },
+    /// Print the counters kept with `settings.metrics`
+    Metrics {
+        /// Print them as an OTLP/JSON export request instead of Prometheus text
+        #[arg(long)]
+        otlp: bool,
+    },
+    /// Print, as JSON, how the engine parses a command, which rules and
+    /// built-ins it considers, and why it rewrites or keeps the command
+    Explain {
+        command: String,
+        /// Project directory whose config and context to explain with
+        /// (defaults to the current directory)
+        #[arg(long, value_name = "DIR")]
+        project: Option<PathBuf>,
+    },
+    /// Time the rewrite engine over a bundled corpus of commands
+    Bench {
+        /// Project directory whose config to benchmark with
+        #[arg(long, value_name = "DIR")]
+        project: Option<PathBuf>,
+        /// Samples taken per command
+        #[arg(long, default_value_t = 50)]
+        samples: usize,
+        /// Calls timed together in each sample
+        #[arg(long, default_value_t = 100)]
+        iterations: u32,
+        /// Also time whole hook processes with hyperfine
+        #[arg(long)]
+        hyperfine: bool,
+    },
+    /// Serve a JSON API (POST /rewrite, GET /stats, GET /health) over HTTP
+    #[cfg(feature = "native")]
+    Serve {
+        /// Loopback address to listen on, e.g. 127.0.0.1:7878
+        #[arg(long, value_name = "ADDR")]
+        http: std::net::SocketAddr,
+        /// Project directory whose config and context to rewrite with
+        /// (defaults to the current directory)
+        #[arg(long, value_name = "DIR")]
+        project: Option<PathBuf>,
+    },
+    /// Serve `optimize_command` and `check_tool` as MCP tools over stdio
+    McpServe {
+        /// Project directory whose config and context to rewrite with
+        /// (defaults to the current directory)
+        #[arg(long, value_name = "DIR")]
+        project: Option<PathBuf>,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ConfigCommand {
+    /// Print the tool paths, replacements, and policies other tools share,
+    /// from the effective config
+    Export {
+        /// Project directory whose config layers to include
+        #[arg(long, value_name = "DIR")]
+        project: Option<PathBuf>,
+        #[arg(long, value_enum, default_value_t = OutputFormat::Json)]
+        format: OutputFormat,
+    },
+    /// Print the JSON Schema of `config export`'s output
+    Schema,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum OutputFormat {
+    Toml,
+    Json,
+}
+
+impl Command {
+    /// Run the subcommand, returning the process exit code
+    pub fn run(self) -> i32 {
+        let result = match self {
+            Command::ValidateConfig { path } => Ok(validate_config(path)),
+            Command::Schema => {
+                let schema = validate::config_schema();
+                println!("{}", serde_json::to_string_pretty(&schema).unwrap_or_default());
+                Ok(0)
+            }
+            Command::ShowConfig { project, format } => show_config(project.as_deref(), format),
+            Command::Config { command: ConfigCommand::Export { project, format } } => export_config(project.as_deref(), format),
+            Command::Config { command: ConfigCommand::Schema } => {
+                println!("{}", serde_json::to_string_pretty(&crate::shared::shared_schema()).unwrap_or_default());
+                Ok(0)
+            }
+            Command::Init { project, force, print } => init(project.as_deref(), force, print),
+            Command::Doctor { project, fix } => Ok(doctor(project.as_deref(), fix)),
+            Command::Install { project, binary } => install(project.as_deref(), binary),
+            Command::Uninstall { project, purge } => uninstall(project.as_deref(), purge),
+            #[cfg(feature = "native")]
+            Command::SelfUpdate { check, feed, public_key, allow_unsigned } => self_update(check, &feed, public_key.as_deref(), allow_unsigned),
+            Command::Metrics { otlp } => {
+                let metrics = crate::metrics::Metrics::load(&Config::metrics_path());
+                match otlp {
+                    true => println!("{}", serde_json::to_string_pretty(&metrics.to_otlp()).unwrap_or_default()),
+                    false => print!("{}", metrics.to_prometheus()),
+                }
+                Ok(0)
+            }
+            Command::Explain { command, project } => explain(&command, project),
+            Command::Bench { project, samples, iterations, hyperfine } => {
+                crate::bench::bench(project.as_deref(), samples, iterations, hyperfine)
+            }
+            #[cfg(feature = "native")]
+            Command::Serve { http, project } => serve_http(http, project),
+            Command::McpServe { project } => mcp_serve(project),
+        };
+        
+        result.unwrap_or_else(|e| {
+            eprintln!("{:#}", e);
+            2
+        })
+    }
+}
+
+/// `validate-config [PATH]`: check a config file against the schema,
+/// defaulting to the global config. Returns the process exit code.
+fn validate_config(path: Option<PathBuf>) -> i32 {
+    let Some(path) = path.or_else(Config::global_config_path) else {
+        eprintln!("No config file found; pass a path to validate");
+        return 2;
+    };
+    
+    match validate::validate_config_file(&path) {
+        Ok(issues) if issues.is_empty() => {
+            println!("{}: OK", path.display());
+            0
+        }
+        Ok(issues) => {
+            for issue in &issues {
+                println!("{}:{}", path.display(), issue);
+            }
+            1
+        }
+        Err(e) => {
+            eprintln!("{:#}", e);
+            2
+        }
+    }
+}
+
+/// `show-config`: print the config the hook would use from here
+fn show_config(project: Option<&Path>, format: OutputFormat) -> Result<i32> {
+    let cwd = std::env::current_dir().context("Failed to read the current directory")?;
+    let effective = EffectiveConfig::resolve(project, &cwd, std::env::vars())?;
+    
+    match format {
+        OutputFormat::Toml => print!("{}", effective.to_annotated_toml()?),
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&effective.to_json()?)?),
+    }
+    Ok(0)
+}
+
+/// `config export`: print the part of the effective config windows-tools reads
+fn export_config(project: Option<&Path>, format: OutputFormat) -> Result<i32> {
+    let config = Config::load_for_project(project)?;
+    let shared = crate::shared::SharedConfig::from_config(&config, std::env::consts::OS);
+    match format {
+        OutputFormat::Toml => print!("{}", toml::to_string_pretty(&shared)?),
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&shared)?),
+    }
+    Ok(0)
+}
+
+/// `explain COMMAND`: print every decision behind the hook's answer as JSON
+fn explain(command: &str, project: Option<PathBuf>) -> Result<i32> {
+    let explanation = crate::service::explain(&project_or_cwd(project)?, command)?;
+    println!("{}", serde_json::to_string_pretty(&explanation)?);
+    Ok(0)
+}
+
+/// `serve --http`: answer API requests until interrupted
+#[cfg(feature = "native")]
+fn serve_http(addr: std::net::SocketAddr, project: Option<PathBuf>) -> Result<i32> {
+    let server = crate::http::HttpServer::bind(addr, &project_or_cwd(project)?)?;
+    eprintln!("Listening on http://{}", server.local_addr().unwrap_or(addr));
+    server.run()?;
+    Ok(0)
+}
+
+/// `mcp-serve`: answer MCP requests on stdin until it closes
+fn mcp_serve(project: Option<PathBuf>) -> Result<i32> {
+    crate::mcp::serve(std::io::stdin().lock(), std::io::stdout().lock(), &project_or_cwd(project)?)?;
+    Ok(0)
+}
+
+fn project_or_cwd(project: Option<PathBuf>) -> Result<PathBuf> {
+    match project {
+        Some(dir) => Ok(dir),
+        None => std::env::current_dir().context("Failed to read the current directory"),
+    }
+}
+
+/// `init`: scaffold a commented config file
+fn init(project: Option<&Path>, force: bool, print: bool) -> Result<i32> {
+    let content = match project {
+        Some(_) => scaffold_project_config(),
+        None => scaffold_global_config()?,
+    };
+    if print {
+        print!("{}", content);
+        return Ok(0);
+    }
+    
+    let path = match project {
+        Some(dir) => dir.join(".claude").join("command-replacer.toml"),
+        None => Config::config_path(),
+    };
+    if path.exists() && !force {
+        bail!("{} already exists; pass --force to overwrite", path.display());
+    }
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    std::fs::write(&path, content)
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    
+    println!("Wrote {}", path.display());
+    Ok(0)
+}
+
+/// `install`: register the hook for every event it handles
+fn install(project: Option<&Path>, binary: Option<PathBuf>) -> Result<i32> {
+    let binary = match binary {
+        Some(path) => path,
+        None => std::env::current_exe().context("Failed to locate the running binary")?,
+    };
+    let binary = binary.canonicalize().with_context(|| format!("Failed to resolve {}", binary.display()))?;
+    let command = install::hook_command(&binary)?;
+    let path = install::settings_path(project)?;
+    let existed = path.exists();
+    
+    let changes = install::install_into(&path, &command)?;
+    if changes.is_empty() {
+        println!("{} already runs {}", path.display(), command);
+        return Ok(0);
+    }
+    for (event, change) in &changes {
+        let verb = match change {
+            install::Change::Added => "Registered",
+            install::Change::Updated => "Repointed",
+        };
+        println!("{} {} -> {}", verb, event, command);
+    }
+    if existed {
+        println!("Backed up the previous settings to {}", install::backup_path(&path).display());
+    }
+    println!("Wrote {}", path.display());
+    Ok(0)
+}
+
+/// `uninstall`: deregister the hook, and with `purge` delete its files
+fn uninstall(project: Option<&Path>, purge: bool) -> Result<i32> {
+    let path = install::settings_path(project)?;
+    let removed = install::uninstall_from(&path)?;
+    if removed.is_empty() {
+        println!("{} does not run command-replacer", path.display());
+    } else {
+        for (event, count) in &removed {
+            println!("Removed {} {} hook{}", count, event, if *count == 1 { "" } else { "s" });
+        }
+        println!("Backed up the previous settings to {}", install::backup_path(&path).display());
+        println!("Wrote {}", path.display());
+    }
+    
+    if purge {
+        // The binary may have been installed next to its config
+        let running = std::env::current_exe().ok().and_then(|exe| exe.canonicalize().ok());
+        for path in install::purge_dir(&Config::config_dir(), running.as_deref())? {
+            println!("Deleted {}", path.display());
+        }
+    }
+    Ok(0)
+}
+
+/// `self-update`: install the latest release over the running binary
+#[cfg(feature = "native")]
+fn self_update(check: bool, feed: &str, public_key: Option<&str>, allow_unsigned: bool) -> Result<i32> {
+    use crate::self_update::Outcome;
+    
+    // Checking downloads nothing, so needs no key
+    let public_key = if check { None } else { crate::self_update::signing_key(public_key, allow_unsigned)? };
+    let exe = std::env::current_exe().context("Failed to locate the running binary")?;
+    let exe = exe.canonicalize().with_context(|| format!("Failed to resolve {}", exe.display()))?;
+    match crate::self_update::self_update(feed, &exe, public_key, check)? {
+        Outcome::UpToDate { tag } => println!("Already up to date ({})", tag),
+        Outcome::Available { tag } => {
+            println!("{} is available; run `command-replacer self-update` to install it", tag);
+            return Ok(1);
+        }
+        Outcome::Updated { tag, path } => println!("Updated {} to {}", path.display(), tag),
+    }
+    Ok(0)
+}
+
+/// Global config: the defaults, preceded by commented examples of the
+/// optional sections
+pub fn scaffold_global_config() -> Result<String> {
+    let defaults = toml::to_string_pretty(&Config::default())
+        .context("Failed to serialize default config")?;
+    Ok(format!("{}\n{}", GLOBAL_HEADER, defaults))
+}
+
+/// Project overlay: everything commented out, since any key set here
+/// overrides the global config
+pub fn scaffold_project_config() -> String {
+    format!("version = {}\n\n{}", crate::migrate::CONFIG_VERSION, PROJECT_TEMPLATE)
+}
+
+const GLOBAL_HEADER: &str = r#"# command-replacer configuration
+#
+# Projects can override any of this in .claude/command-replacer.toml, and
+# CLAUDE_CMDREPL_* environment variables override both.
+#
+# Optional sections (uncomment to use):
+#
+# [settings]
+# profile = "balanced"           # conservative | balanced | aggressive
+#
+# [[rules]]                      # declarative tool swap
+# command = "du"
+# template = "dust {flags} {paths}"
+# positionals = ["paths"]
+# flags = { "-h" = "", "-s" = "-d 0" }
+#
+# [[rewrites]]                   # regex rewrite of the whole command
+# pattern = '^make (\w+)$'
+# replacement = 'just $1'
+# stage = "before"               # before | after the structured engine
+#
+# [overrides."**/vendor/**"]     # policy inside matching directories
+# replacements.grep.enabled = false
+"#;
+
+const PROJECT_TEMPLATE: &str = r#"# Project overrides for command-replacer; keys set here are merged over the
+# global config (~/.claude/hooks/command-replacer/config.toml).
+
+# [settings]
+# compatibility_mode = true
+# fallback_patterns = ['\bxargs\b']
+
+# [replacements.sed]
+# enabled = false
+
+# [overrides."**/legacy/**"]
+# replacements.grep.enabled = false
+"#;
+
+/// Outcome of a single doctor check
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Health {
+    Ok,
+    Warn,
+    Fail,
+}
+
+#[derive(Debug, Clone)]
+pub struct Check {
+    pub health: Health,
+    pub label: String,
+    pub detail: String,
+}
+
+impl Check {
+    fn new(health: Health, label: impl Into<String>, detail: impl Into<String>) -> Self {
+        Self { health, label: label.into(), detail: detail.into() }
+    }
+}
+
+impl fmt::Display for Check {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let marker = match self.health {
+            Health::Ok => "ok  ",
+            Health::Warn => "warn",
+            Health::Fail => "FAIL",
+        };
+        write!(f, "[{}] {}", marker, self.label)?;
+        if !self.detail.is_empty() {
+            write!(f, ": {}", self.detail)?;
+        }
+        Ok(())
+    }
+}
+
+/// `doctor`: print a health report; exits non-zero if any check failed
+fn doctor(project: Option<&Path>, fix: bool) -> i32 {
+    let mut checks = config_file_checks(project);
+    
+    match Config::load_for_project(project) {
+        Ok(config) => {
+            checks.extend(pattern_checks(&config));
+            if fix {
+                install_missing_tools(&config);
+            }
+            checks.extend(tool_checks(&config));
+            #[cfg(feature = "scripting")]
+            checks.extend(script_checks(&config));
+            #[cfg(feature = "wasm-plugins")]
+            checks.extend(wasm_plugin_checks(&config));
+        }
+        Err(e) => checks.push(Check::new(Health::Fail, "config", format!("{:#}", e))),
+    }
+    checks.push(hook_registration_check(&settings_paths(project)));
+    
+    for check in &checks {
+        println!("{}", check);
+    }
+    
+    let failures = checks.iter().filter(|c| c.health == Health::Fail).count();
+    let warnings = checks.iter().filter(|c| c.health == Health::Warn).count();
+    println!("\n{} checks, {} failed, {} warnings", checks.len(), failures, warnings);
+    
+    if failures > 0 { 1 } else { 0 }
+}
+
+/// Schema validation of the global and project config files
+fn config_file_checks(project: Option<&Path>) -> Vec<Check> {
+    let mut paths = Vec::new();
+    match Config::global_config_path() {
+        Some(path) => paths.push(path),
+        None => return vec![Check::new(Health::Warn, "global config", "not found; run `command-replacer init`")],
+    }
+    paths.extend(project.and_then(Config::project_config_path));
+    
+    paths
+        .into_iter()
+        .map(|path| {
+            let label = format!("config {}", path.display());
+            match validate::validate_config_file(&path) {
+                Ok(issues) if issues.is_empty() => Check::new(Health::Ok, label, "valid"),
+                Ok(issues) => {
+                    let detail = issues.iter().map(ToString::to_string).collect::<Vec<_>>().join("; ");
+                    Check::new(Health::Fail, label, detail)
+                }
+                Err(e) => Check::new(Health::Fail, label, format!("{:#}", e)),
+            }
+        })
+        .collect()
+}
+
+/// Every regex in the config must compile, or the hook errors at runtime
+pub fn pattern_checks(config: &Config) -> Vec<Check> {
+    let patterns = config.settings.fallback_patterns.iter().map(|p| ("fallback pattern", p))
+        .chain(config.replacements.values().flat_map(|r| r.fallback_patterns.iter().map(|p| ("replacement fallback pattern", p))))
+        .chain(config.rules.iter().flat_map(|r| r.fallback_patterns.iter().map(|p| ("rule fallback pattern", p))))
+        .chain(config.rewrites.iter().map(|r| ("rewrite pattern", &r.pattern)));
+    
+    patterns
+        .map(|(kind, pattern)| match Regex::new(pattern) {
+            Ok(_) => Check::new(Health::Ok, format!("{} `{}`", kind, pattern), ""),
+            Err(e) => Check::new(Health::Fail, format!("{} `{}`", kind, pattern), e.to_string()),
+        })
+        .collect()
+}
+
+/// Replacement tools for enabled commands; a missing tool only means the
+/// original command runs unchanged, so it is a warning
+fn tool_checks(config: &Config) -> Vec<Check> {
+    let mut commands: Vec<_> = config.replacements.iter().filter(|(_, r)| r.enabled).collect();
+    commands.sort_by_key(|(command, _)| command.as_str());
+    
+    commands
+        .into_iter()
+        .map(|(command, replacement)| {
+            let label = format!("{} -> {}", command, replacement.replacement);
+            if let Some(pinned) = config.tools.get(&replacement.replacement) {
+                return match Path::new(pinned).is_file() {
+                    true => Check::new(Health::Ok, label, format!("{} (pinned)", pinned)),
+                    false => Check::new(Health::Fail, label, format!("pinned path {} does not exist", pinned)),
+                };
+            }
+            match crate::tool_cache::find_program(&replacement.replacement) {
+                Some(path) => Check::new(Health::Ok, label, path.display().to_string()),
+                None => match tool_install::install_command(&replacement.replacement) {
+                    Some(install) => Check::new(
+                        Health::Warn,
+                        label,
+                        format!("not installed; commands pass through unchanged. Install with `{}` (or `doctor --fix`)", install),
+                    ),
+                    None => Check::new(Health::Warn, label, "not installed; commands pass through unchanged"),
+                },
+            }
+        })
+        .collect()
+}
+
+/// Every plugin script must compile, or the plugin is silently skipped
+#[cfg(feature = "scripting")]
+fn script_checks(config: &Config) -> Vec<Check> {
+    config.plugins
+        .iter()
+        .filter_map(|plugin| Some((plugin, plugin.script.as_deref()?)))
+        .map(|(plugin, script)| match crate::scripts::check(script) {
+            Ok(()) => Check::new(Health::Ok, format!("plugin script {}", plugin.name), script.display().to_string()),
+            Err(e) => Check::new(Health::Fail, format!("plugin script {}", plugin.name), format!("{:#}", e)),
+        })
+        .collect()
+}
+
+/// Modules in the plugins directory, each of which must compile
+#[cfg(feature = "wasm-plugins")]
+fn wasm_plugin_checks(config: &Config) -> Vec<Check> {
+    let runtime = match crate::wasm_plugins::WasmRuntime::load(&Config::plugins_dir(), &Config::cache_dir(), &config.plugins) {
+        Ok(runtime) => runtime,
+        Err(e) => return vec![Check::new(Health::Fail, "wasm plugins", format!("{:#}", e))],
+    };
+    let loaded = runtime.plugins
+        .iter()
+        .map(|plugin| Check::new(Health::Ok, format!("wasm plugin {}", plugin.config.name), plugin.path.display().to_string()));
+    let failed = runtime.failed
+        .iter()
+        .map(|(path, error)| Check::new(Health::Fail, format!("wasm plugin {}", path.display()), error.clone()));
+    loaded.chain(failed).collect()
+}
+
+/// `doctor --fix`: install each missing, unpinned replacement tool
+fn install_missing_tools(config: &Config) {
+    let mut tools: Vec<&str> = config.replacements.values()
+        .filter(|replacement| replacement.enabled && !config.tools.contains_key(&replacement.replacement))
+        .map(|replacement| replacement.replacement.as_str())
+        .filter(|tool| crate::tool_cache::find_program(tool).is_none())
+        .collect();
+    tools.sort_unstable();
+    tools.dedup();
+    
+    for tool in tools {
+        match tool_install::install_command(tool) {
+            Some(install) => {
+                println!("Installing {}: {}", tool, install);
+                if let Err(e) = install.run() {
+                    eprintln!("{:#}", e);
+                }
+            }
+            None => println!("No known package manager here installs {}", tool),
+        }
+    }
+}
+
+/// Claude settings files that may register the hook
+fn settings_paths(project: Option<&Path>) -> Vec<PathBuf> {
+    let mut paths: Vec<PathBuf> = dirs::home_dir()
+        .map(|home| home.join(".claude").join("settings.json"))
+        .into_iter()
+        .collect();
+    if let Some(project) = project {
+        let claude = project.join(".claude");
+        paths.push(claude.join("settings.json"));
+        paths.push(claude.join("settings.local.json"));
+    }
+    paths
+}
+
+fn hook_registration_check(paths: &[PathBuf]) -> Check {
+    for path in paths {
+        let Ok(content) = std::fs::read_to_string(path) else {
+            continue;
+        };
+        match serde_json::from_str::<serde_json::Value>(&content) {
+            Ok(settings) if hook_registered(&settings) => {
+                return Check::new(Health::Ok, "hook registration", path.display().to_string());
+            }
+            Ok(_) => {}
+            Err(e) => return Check::new(Health::Fail, "hook registration", format!("{}: {}", path.display(), e)),
+        }
+    }
+    Check::new(Health::Fail, "hook registration", "no PreToolUse hook runs command-replacer")
+}
+
+/// Whether settings register command-replacer as a PreToolUse hook for Bash
+pub fn hook_registered(settings: &serde_json::Value) -> bool {
+    let Some(entries) = settings.pointer("/hooks/PreToolUse").and_then(|v| v.as_array()) else {
+        return false;
+    };
+    
+    entries.iter().any(|entry| {
+        let matcher = entry.get("matcher").and_then(|m| m.as_str()).unwrap_or("");
+        let matches_bash = matcher.is_empty()
+            || matcher == "*"
+            || Regex::new(&format!("^(?:{})$", matcher)).is_ok_and(|re| re.is_match("Bash"));
+        
+        matches_bash
+            && entry
+                .get("hooks")
+                .and_then(|hooks| hooks.as_array())
+                .is_some_and(|hooks| {
+                    hooks.iter().any(|hook| {
+                        hook.get("command")
+                            .and_then(|c| c.as_str())
+                            .is_some_and(|command| command.contains("command-replacer"))
+                    })
+                })
+    })
+}
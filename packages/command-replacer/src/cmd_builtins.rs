@@ -0,0 +1,296 @@
+//! cmd.exe builtins: `dir` → fd, `type` → bat, `findstr` → rg, `where` → which
+//!
+//! cmd spells switches `/s`, in either case, and lets several share one
+//! word (`/s/b`, `/sin`); values follow a colon (`/a:-d`, `/c:"two words"`).
+//! Only findstr also takes `-s`: `dir -l` is GNU dir, an ls clone, so a dash
+//! switch leaves dir alone. These are enabled by default only on Windows,
+//! where `type` still needs care because Git Bash's `type` looks up commands.
+
+use crate::replacements::FallbackReason;
+
+/// The builtins this translates
+pub const COMMANDS: [&str; 4] = ["dir", "type", "findstr", "where"];
+
+/// New arguments (unquoted, after the tool name) for a cmd builtin's `args`
+pub fn translate(cmd: &str, args: &[String]) -> Result<Vec<String>, FallbackReason> {
+    match cmd {
+        "dir" => dir(args),
+        "type" => type_(args),
+        "findstr" => findstr(args),
+        "where" => where_(args),
+        _ => Err(FallbackReason::new(cmd, "option", format!("{} is not a cmd builtin", cmd))),
+    }
+}
+
+/// One switch: its letter, lowercased, and anything after it (`/a:-d` is
+/// `('a', "-d")`)
+type Switch = (char, String);
+
+/// The switches of a `/s/b` word, or `None` for an operand
+fn slash_switches(arg: &str) -> Option<Vec<Switch>> {
+    let rest = arg.strip_prefix('/')?;
+    let switches = rest
+        .split('/')
+        .map(|part| {
+            let mut chars = part.chars();
+            let letter = chars.next().unwrap_or('/').to_ascii_lowercase();
+            let value = chars.as_str();
+            (letter, value.strip_prefix(':').unwrap_or(value).to_string())
+        })
+        .collect();
+    Some(switches)
+}
+
+fn unsupported(cmd: &str, switch: &str, tool: &str) -> FallbackReason {
+    FallbackReason::new(cmd, "option", format!("{} {} has no {} equivalent", cmd, switch, tool))
+}
+
+pub fn has_wildcard(word: &str) -> bool {
+    word.contains(['*', '?'])
+}
+
+/// `src/*.cs` as `("src", "*.cs")`; a word without a directory has an empty one
+pub fn split_dir(word: &str) -> (&str, &str) {
+    match word.rfind(['/', '\\']) {
+        Some(0) => (&word[..1], &word[1..]),
+        Some(end) => (&word[..end], &word[end + 1..]),
+        None => ("", word),
+    }
+}
+
+/// `dir /b [/s] [/a[:][-]d] [PATH]`
+///
+/// Only bare listings (`/b`) have an fd equivalent; `/s` prints full paths.
+fn dir(args: &[String]) -> Result<Vec<String>, FallbackReason> {
+    let (mut bare, mut recursive, mut all) = (false, false, false);
+    let mut file_type = None;
+    let mut operands = Vec::new();
+    for arg in args {
+        if arg.starts_with('-') {
+            return Err(FallbackReason::new("dir", "option", format!("dir {} is GNU dir's syntax, not cmd's", arg)));
+        }
+        let Some(switches) = slash_switches(arg) else {
+            operands.push(arg.as_str());
+            continue;
+        };
+        for (letter, value) in switches {
+            match (letter, value.to_ascii_lowercase().as_str()) {
+                ('b', "") => bare = true,
+                ('s', "") => recursive = true,
+                ('a', "") => all = true,
+                ('a', "d") => file_type = Some("directory"),
+                ('a', "-d") => file_type = Some("file"),
+                _ => return Err(unsupported("dir", arg, "fd")),
+            }
+        }
+    }
+    if !bare {
+        return Err(FallbackReason::new("dir", "output-format", "dir without /b prints headers, sizes, and dates"));
+    }
+    let [operand] = operands[..] else {
+        if operands.len() > 1 {
+            return Err(FallbackReason::new("dir", "option", "dir with several paths lists each in turn"));
+        }
+        return Ok(dir_args(recursive, all, file_type, "", ""));
+    };
+    
+    let (base, glob) = match split_dir(operand) {
+        (base, name) if has_wildcard(name) => (base, name),
+        (_, "" | "." | "..") => (operand, ""),
+        _ => return Err(FallbackReason::new("dir", "ambiguous", format!("dir {} lists a directory or finds a file, depending on what it is", operand))),
+    };
+    Ok(dir_args(recursive, all, file_type, base, glob))
+}
+
+fn dir_args(recursive: bool, all: bool, file_type: Option<&str>, base: &str, glob: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    // dir /s prints full paths, plain dir /b bare names
+    if recursive {
+        args.push("--absolute-path".to_string());
+    } else {
+        args.extend(["--max-depth".to_string(), "1".to_string()]);
+    }
+    // dir reads no ignore files, and /a includes hidden files
+    if all {
+        args.push("-H".to_string());
+    }
+    args.push("-I".to_string());
+    if let Some(file_type) = file_type {
+        args.extend(["--type".to_string(), file_type.to_string()]);
+    }
+    if !base.is_empty() {
+        args.extend(["--base-directory".to_string(), base.to_string()]);
+    }
+    // `*.*` matches names without a dot too
+    if !glob.is_empty() && glob != "*" && glob != "*.*" {
+        // Windows file names match case-insensitively
+        args.extend(["--ignore-case".to_string(), "--glob".to_string(), glob.to_string()]);
+    }
+    args
+}
+
+/// `type FILE...`
+fn type_(args: &[String]) -> Result<Vec<String>, FallbackReason> {
+    if args.is_empty() {
+        return Err(FallbackReason::new("type", "option", "type without a file"));
+    }
+    for arg in args {
+        // `/?`, but not `/etc/hosts`
+        if arg.starts_with('/') && !arg[1..].contains('/') {
+            return Err(unsupported("type", arg, "bat"));
+        }
+        if has_wildcard(arg) {
+            return Err(FallbackReason::new("type", "glob", format!("type expands {} itself and prints each file's name", arg)));
+        }
+        // `type git` is the shell's command lookup, not a file
+        if !arg.contains(['.', '/', '\\']) {
+            return Err(FallbackReason::new("type", "ambiguous", format!("type {} looks like a command lookup", arg)));
+        }
+    }
+    let mut new_args = vec!["--style=plain".to_string()];
+    new_args.extend(args.iter().cloned());
+    Ok(new_args)
+}
+
+/// The switches of a findstr word, which may start with `/` or `-` and run
+/// several together (`/sin`, `/c:text`), or `None` for an operand
+fn findstr_switches(arg: &str) -> Option<Vec<Switch>> {
+    let rest = arg.strip_prefix('/').or_else(|| arg.strip_prefix('-'))?;
+    let mut switches = Vec::new();
+    for (i, letter) in rest.char_indices() {
+        let after = i + letter.len_utf8();
+        if let Some(value) = rest[after..].strip_prefix(':') {
+            switches.push((letter.to_ascii_lowercase(), value.to_string()));
+            break;
+        }
+        switches.push((letter.to_ascii_lowercase(), String::new()));
+    }
+    (!switches.is_empty()).then_some(switches)
+}
+
+/// `findstr [/s] [/i] [/n] [/v] [/m] [/x] [/l | /r] [/c:TEXT | TEXTS] FILE...`
+fn findstr(args: &[String]) -> Result<Vec<String>, FallbackReason> {
+    let mut flags = Vec::new();
+    let (mut recursive, mut literal, mut regex, mut quoted) = (false, false, false, false);
+    let mut search: Option<Vec<String>> = None;
+    let mut files = Vec::new();
+    for arg in args {
+        // Switches come first, though `/c:TEXT` may be followed by more
+        let switches = if files.is_empty() && (search.is_none() || quoted) { findstr_switches(arg) } else { None };
+        let Some(switches) = switches else {
+            match search {
+                None => search = Some(arg.split_whitespace().map(str::to_string).collect()),
+                Some(_) => files.push(arg.as_str()),
+            }
+            continue;
+        };
+        for (letter, value) in switches {
+            match letter {
+                's' => recursive = true,
+                'i' => flags.push("--ignore-case"),
+                'n' => flags.push("--line-number"),
+                'v' => flags.push("--invert-match"),
+                'm' => flags.push("--files-with-matches"),
+                'x' => flags.push("--line-regexp"),
+                'l' => literal = true,
+                'r' => regex = true,
+                // rg skips binary files anyway
+                'p' => {}
+                'c' if search.is_none() && !value.is_empty() => {
+                    search = Some(vec![value]);
+                    quoted = true;
+                }
+                _ => return Err(unsupported("findstr", arg, "rg")),
+            }
+        }
+    }
+    let Some(search) = search.filter(|search| !search.is_empty()) else {
+        return Err(FallbackReason::new("findstr", "option", "findstr without a search string"));
+    };
+    if files.is_empty() {
+        return Err(FallbackReason::new("findstr", "stdin", "findstr without files reads stdin, rg would search the directory"));
+    }
+    
+    // findstr reads no ignore files
+    let mut new_args: Vec<String> = ["--no-ignore", "--hidden"].into_iter().chain(flags).map(str::to_string).collect();
+    // /c: strings are literal unless /r says otherwise, the rest regexes unless /l
+    let literal = literal || (quoted && !regex);
+    if literal {
+        new_args.push("--fixed-strings".to_string());
+    }
+    for pattern in &search {
+        let pattern = if literal { pattern.clone() } else { findstr_regex(pattern)? };
+        new_args.extend(["-e".to_string(), pattern]);
+    }
+    new_args.extend(findstr_files(&files, recursive)?);
+    Ok(new_args)
+}
+
+/// rg's `--max-depth`/`--iglob`/path arguments for findstr's file operands
+fn findstr_files(files: &[&str], recursive: bool) -> Result<Vec<String>, FallbackReason> {
+    if !recursive && !files.iter().any(|file| has_wildcard(file)) {
+        return Ok(files.iter().map(|file| file.to_string()).collect());
+    }
+    // Globs, and with /s every name, match in each directory of one tree
+    let (base, _) = split_dir(files[0]);
+    let mut args = Vec::new();
+    if !recursive {
+        args.extend(["--max-depth".to_string(), "1".to_string()]);
+    }
+    for file in files {
+        let (dir, name) = split_dir(file);
+        if dir != base || (!recursive && !has_wildcard(name)) {
+            return Err(FallbackReason::new("findstr", "option", "findstr files from different directories, or globs mixed with plain files"));
+        }
+        args.extend(["--iglob".to_string(), name.to_string()]);
+    }
+    if !base.is_empty() {
+        args.push(base.to_string());
+    }
+    Ok(args)
+}
+
+/// A findstr regex in rg's syntax: findstr only knows `. * ^ $ [...] \< \>`,
+/// so rg's other metacharacters are literal
+fn findstr_regex(pattern: &str) -> Result<String, FallbackReason> {
+    let mut regex = String::new();
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('<' | '>') => regex.push_str(r"\b"),
+                Some(escaped) if "\\.*^$[]".contains(escaped) => {
+                    regex.push('\\');
+                    regex.push(escaped);
+                }
+                Some(escaped) => regex.push_str(&regex::escape(&escaped.to_string())),
+                None => return Err(FallbackReason::new("findstr", "regex", "findstr pattern ends in a lone backslash")),
+            },
+            '+' | '?' | '(' | ')' | '{' | '}' | '|' => {
+                regex.push('\\');
+                regex.push(c);
+            }
+            _ => regex.push(c),
+        }
+    }
+    Ok(regex)
+}
+
+/// `where NAME...`
+fn where_(args: &[String]) -> Result<Vec<String>, FallbackReason> {
+    if args.is_empty() {
+        return Err(FallbackReason::new("where", "option", "where without a name"));
+    }
+    for arg in args {
+        if arg.starts_with('/') {
+            return Err(unsupported("where", arg, "which"));
+        }
+        if has_wildcard(arg) || arg.contains(':') {
+            return Err(FallbackReason::new("where", "glob", format!("where {} searches with a pattern", arg)));
+        }
+    }
+    // where lists every match on PATH
+    let mut new_args = vec!["-a".to_string()];
+    new_args.extend(args.iter().cloned());
+    Ok(new_args)
+}
@@ -1,14 +1,33 @@
 //! Configuration management for command replacements
 
 use anyhow::{Context, Result};
+use once_cell::sync::Lazy;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::path::PathBuf;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+use crate::migrate::{self, CONFIG_VERSION};
+use crate::remote;
+
+/// Parsed file layers shared by every load in this process
+static LAYER_CACHE: Lazy<ConfigCache> = Lazy::new(ConfigCache::default);
 
 /// Configuration for command replacements
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[schemars(deny_unknown_fields)]
 pub struct Config {
-    /// Tool paths (for validation)
+    /// Config layout version, used to migrate older files on load
+    #[serde(default)]
+    pub version: u32,
+    
+    /// Files merged underneath this one, in order (relative to this file)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub include: Vec<String>,
+    
+    /// Pinned tool paths (e.g. `rg = "C:/tools/rg.exe"`), used instead of a PATH lookup
     #[serde(default)]
     pub tools: HashMap<String, String>,
     
@@ -19,9 +38,207 @@ pub struct Config {
     /// Global settings
     #[serde(default)]
     pub settings: GlobalSettings,
+    
+    /// Declarative replacement rules, interpreted without recompiling
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rules: Vec<RuleConfig>,
+    
+    /// Regex rewrites applied to the whole command string
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rewrites: Vec<RewriteConfig>,
+    
+    /// External replacers, asked in declaration order before the built-in engine
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub plugins: Vec<PluginConfig>,
+    
+    /// Partial configs applied when working inside matching directories,
+    /// keyed by a path glob relative to the project (e.g. `"**/legacy/**"`)
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    #[schemars(with = "BTreeMap<String, serde_json::Value>")]
+    pub overrides: BTreeMap<String, toml::Value>,
+    
+    /// Team policy fetched from a URL and merged underneath this file
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub remote_config: Option<RemoteConfig>,
+    
+    /// Overrides applied on Windows, e.g. `[windows.replacements.ls]`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(with = "Option<serde_json::Value>")]
+    pub windows: Option<toml::Value>,
+    
+    /// Overrides applied on macOS
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(with = "Option<serde_json::Value>")]
+    pub macos: Option<toml::Value>,
+    
+    /// Overrides applied on Linux (e.g. `batcat` on Debian)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(with = "Option<serde_json::Value>")]
+    pub linux: Option<toml::Value>,
+    
+    /// Files this config was merged from, lowest precedence first
+    #[serde(skip)]
+    #[schemars(skip)]
+    pub sources: Vec<PathBuf>,
+}
+
+/// A declarative replacement rule (`[[rules]]`), e.g.
+///
+/// ```toml
+/// [[rules]]
+/// command = "du"
+/// template = "dust {flags} {paths}"
+/// positionals = ["paths"]
+/// flags = { "-h" = "", "-s" = "-d 0" }
+/// fallback_flags = ["--files0-from"]
+/// ```
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[schemars(deny_unknown_fields)]
+pub struct RuleConfig {
+    /// Command this rule rewrites
+    pub command: String,
+    
+    /// Replacement command line; `{flags}`, `{args}`, and the names listed in
+    /// `positionals` are substituted. The first word is the replacement tool.
+    pub template: String,
+    
+    /// Whether this rule is enabled
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    
+    /// Priority against other rules and the built-in for the same command
+    /// (higher wins); defaults above every built-in
+    #[serde(default = "default_rule_priority")]
+    pub priority: u8,
+    
+    /// Names for positional arguments in order; the last absorbs any extras
+    #[serde(default)]
+    pub positionals: Vec<String>,
+    
+    /// Flag translations (original -> replacement, empty string drops the flag)
+    #[serde(default)]
+    pub flags: HashMap<String, String>,
+    
+    /// Original flags that take a separate value argument
+    #[serde(default)]
+    pub value_flags: Vec<String>,
+    
+    /// Pass flags missing from `flags` through unchanged instead of falling back
+    #[serde(default)]
+    pub passthrough_flags: bool,
+    
+    /// Flags that force a fallback to the original command
+    #[serde(default)]
+    pub fallback_flags: Vec<String>,
+    
+    /// Regexes over the whole command that force a fallback
+    #[serde(default)]
+    pub fallback_patterns: Vec<String>,
+    
+    /// Only apply when the detected context matches
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub when: Option<WhenCondition>,
+}
+
+/// A whole-command regex rewrite (`[[rewrites]]`). `replacement` may use
+/// capture groups (`$1`, `${name}`).
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[schemars(deny_unknown_fields)]
+pub struct RewriteConfig {
+    /// Regex matched against the full command string
+    pub pattern: String,
+    
+    /// Replacement text, with capture group references
+    pub replacement: String,
+    
+    /// Whether to rewrite before or after the structured engine runs
+    #[serde(default)]
+    pub stage: RewriteStage,
+    
+    /// Whether this rewrite is enabled
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+}
+
+/// Remote team config (`[remote_config]`): an `https://` URL serving a
+/// config file, or a git URL plus the file's path in the repository
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
+#[schemars(deny_unknown_fields)]
+pub struct RemoteConfig {
+    /// HTTPS endpoint or git repository URL
+    pub url: String,
+    
+    /// File within a git repository (default `command-replacer.toml`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    
+    /// Seconds before the cached copy is refreshed
+    #[serde(default = "default_remote_ttl")]
+    pub ttl_secs: u64,
+    
+    /// Network timeout for HTTPS fetches and git clones (ms)
+    #[serde(default = "default_remote_timeout")]
+    pub timeout_ms: u64,
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+impl RemoteConfig {
+    /// Path of the config file inside a git repository
+    pub fn repo_path(&self) -> &str {
+        self.path.as_deref().unwrap_or("command-replacer.toml")
+    }
+}
+
+/// An external replacer (`[[plugins]]`): any executable that reads
+/// `{"command": ..., "context": {...}}` as JSON on stdin and answers
+/// `{"rewritten": ...}`, `{"fallback_reason": ...}`, or `{}` on stdout,
+/// or a rhai `script`. An entry with neither instead configures the
+/// `.wasm` plugin of the same name in the plugins directory.
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[schemars(deny_unknown_fields)]
+pub struct PluginConfig {
+    /// Name used in fallback reasons and diagnostics
+    pub name: String,
+    
+    /// Program and arguments to run (not through a shell)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub run: Vec<String>,
+    
+    /// rhai script defining `fn rewrite(cmd, ctx)`, relative to the config
+    /// directory unless absolute
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub script: Option<PathBuf>,
+    
+    /// Programs whose commands the plugin is offered (e.g. `["kubectl"]`);
+    /// empty offers it every command
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub commands: Vec<String>,
+    
+    /// Time the plugin gets to answer before it is killed and skipped (ms)
+    #[serde(default = "default_plugin_timeout")]
+    pub timeout_ms: u64,
+    
+    /// Memory a `.wasm` plugin may grow to (MiB)
+    #[serde(default = "default_plugin_memory")]
+    pub memory_mb: u64,
+    
+    /// Whether this plugin is enabled
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+}
+
+/// When a `[[rewrites]]` entry is applied
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum RewriteStage {
+    /// On the original command; the structured engine sees the result
+    #[default]
+    Before,
+    /// On the structured engine's output
+    After,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[schemars(deny_unknown_fields)]
 pub struct ReplacementConfig {
     /// Whether this replacement is enabled
     #[serde(default = "default_true")]
@@ -45,9 +262,42 @@ pub struct ReplacementConfig {
     /// Whether to use fallback if replacement tool not available
     #[serde(default = "default_true")]
     pub use_fallback: bool,
+    
+    /// Only apply when the detected context matches
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub when: Option<WhenCondition>,
+    
+    /// Regexes over the whole command that make this replacement fall back,
+    /// checked alongside the global `settings.fallback_patterns`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fallback_patterns: Vec<String>,
+    
+    /// Oldest `replacement --version` this rewrite is valid for (e.g. `"13"`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_version: Option<String>,
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+/// Context a replacement is conditional on, e.g.
+/// `when = { git_repo = true, ci = false, project_type = "rust" }`.
+/// Unset fields match anything.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize, JsonSchema)]
+#[schemars(deny_unknown_fields)]
+pub struct WhenCondition {
+    /// Inside (or outside) a git repository
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub git_repo: Option<bool>,
+    
+    /// Running (or not) under CI
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ci: Option<bool>,
+    
+    /// Project type detected from marker files (`rust`, `node`, `python`, ...)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub project_type: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[schemars(deny_unknown_fields)]
 pub struct GlobalSettings {
     /// Enable debug logging
     #[serde(default)]
@@ -69,13 +319,414 @@ pub struct GlobalSettings {
     #[serde(default = "default_true")]
     pub semantic_analysis: bool,
     
-    /// Regex patterns for command contexts that require fallback
+    /// Regex patterns for command contexts that require fallback, whatever
+    /// the command
     #[serde(default)]
     pub fallback_patterns: Vec<String>,
+    
+    /// Commands (exact or glob, e.g. `make *`) that are never rewritten
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub never_replace: Vec<String>,
+    
+    /// If non-empty, only commands matching one of these are rewritten
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub only_replace: Vec<String>,
+    
+    /// Commands (exact or glob, e.g. `curl *`) the user is asked to confirm,
+    /// rewritten or not, instead of being approved silently
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ask: Vec<String>,
+    
+    /// How rewrites are reported to the user in the hook message
+    #[serde(default)]
+    pub message: MessageMode,
+    
+    /// Message template; `{original}`, `{replacement}`, `{original_tool}`,
+    /// and `{replacement_tool}` are substituted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message_template: Option<String>,
+    
+    /// Response shape emitted to Claude Code; `legacy-decision` brings back
+    /// the old context-only output
+    #[serde(default)]
+    pub output_protocol: OutputProtocol,
+    
+    /// On UserPromptSubmit, tell Claude which fast tools are installed so it
+    /// uses them directly
+    #[serde(default)]
+    pub prompt_context: bool,
+    
+    /// When a replacement's tool is missing, tell the user (once per
+    /// session) the command that would install it
+    #[serde(default)]
+    pub install_hints: bool,
+    
+    /// Honor everything in the project's `.claude/command-replacer.*` and
+    /// the remote team config. Off by default, since cloning a repo would
+    /// otherwise be enough to choose what the hook runs. Without it, those
+    /// layers can only turn replacements off and add fallbacks or commands
+    /// to skip.
+    /// Only read from the global config.
+    #[serde(default)]
+    pub trust_shared_config: bool,
+    
+    /// Advice for commands likely to outlast the Bash tool's timeout
+    #[serde(default)]
+    pub long_running: LongRunningSettings,
+    
+    /// Advice for Read calls on files too large to read whole
+    #[serde(default)]
+    pub read: ReadSettings,
+    
+    /// Defaults filled into Grep and Glob calls
+    #[serde(default)]
+    pub search: SearchSettings,
+    
+    /// Memoized decisions for repeated commands
+    #[serde(default)]
+    pub rewrite_cache: RewriteCacheSettings,
+    
+    /// Counters exported for fleet monitoring
+    #[serde(default)]
+    pub metrics: MetricsSettings,
+    
+    /// Wall-clock budget for one hook call (ms). It is checked between
+    /// phases, and once spent the tool call is approved unchanged.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub latency_budget_ms: Option<u64>,
+    
+    /// Events handled at all; anything else is passed through untouched
+    #[serde(default, rename = "match")]
+    pub event_match: MatchSettings,
+    
+    /// Pre-baked bundle of the settings above; overrides them when set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<Profile>,
+}
+
+/// `[settings.long_running]`
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
+#[schemars(deny_unknown_fields)]
+pub struct LongRunningSettings {
+    /// Suggest splitting, backgrounding, or parallelizing full builds,
+    /// image pulls, and whole test suites
+    #[serde(default = "default_true")]
+    pub advise: bool,
+    
+    /// Wrap those commands in `timeout <seconds>`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auto_timeout: Option<u64>,
+}
+
+impl Default for LongRunningSettings {
+    fn default() -> Self {
+        Self {
+            advise: true,
+            auto_timeout: None,
+        }
+    }
+}
+
+/// `[settings.read]`
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
+#[schemars(deny_unknown_fields)]
+pub struct ReadSettings {
+    /// Suggest `offset`/`limit` or an `rg` extraction for large files
+    #[serde(default = "default_true")]
+    pub advise: bool,
+    
+    /// Files larger than this (bytes) get the advice
+    #[serde(default = "default_read_max_bytes")]
+    pub max_bytes: u64,
+}
+
+impl Default for ReadSettings {
+    fn default() -> Self {
+        Self {
+            advise: true,
+            max_bytes: default_read_max_bytes(),
+        }
+    }
+}
+
+/// `[settings.search]`
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
+#[schemars(deny_unknown_fields)]
+pub struct SearchSettings {
+    /// Scope searches without a `path` to the monorepo package being worked in
+    #[serde(default = "default_true")]
+    pub package_scope: bool,
+    
+    /// Limit Grep calls without a `type` or `glob` to a single-language
+    /// project's source files
+    #[serde(default)]
+    pub project_type: bool,
+}
+
+impl Default for SearchSettings {
+    fn default() -> Self {
+        Self {
+            package_scope: true,
+            project_type: false,
+        }
+    }
+}
+
+/// `[settings.rewrite_cache]`
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
+#[schemars(deny_unknown_fields)]
+pub struct RewriteCacheSettings {
+    /// Remember the decision for each command, so a repeat skips parsing
+    /// and translation
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    
+    /// Commands remembered; the least recently used is forgotten first
+    #[serde(default = "default_rewrite_cache_capacity")]
+    pub capacity: usize,
+    
+    /// Keep the cache on disk, so it outlives the hook process
+    #[serde(default)]
+    pub persist: bool,
+}
+
+impl Default for RewriteCacheSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            capacity: default_rewrite_cache_capacity(),
+            persist: false,
+        }
+    }
+}
+
+/// `[settings.metrics]`: counters kept across hook calls and exported to
+/// Prometheus (via node_exporter's textfile collector) or an OTLP collector
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize, JsonSchema)]
+#[schemars(deny_unknown_fields)]
+pub struct MetricsSettings {
+    /// Count events, rewrites, fallbacks, and probe latencies
+    #[serde(default)]
+    pub enabled: bool,
+    
+    /// Prometheus text file rewritten after every call, e.g.
+    /// `/var/lib/node_exporter/textfile/command_replacer.prom`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub textfile: Option<PathBuf>,
+    
+    /// OTLP/HTTP metrics endpoint the counters are pushed to on Stop, e.g.
+    /// `http://localhost:4318/v1/metrics`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub otlp_endpoint: Option<String>,
+}
+
+/// `[settings.match]`, checked before anything else runs, so the hook can
+/// be registered for every event and tool in settings.json and no-op cheaply
+/// on the ones it doesn't care about. Empty lists match anything.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
+#[schemars(deny_unknown_fields)]
+pub struct MatchSettings {
+    /// Hook event names, e.g. `["PreToolUse", "SessionStart"]`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub events: Vec<String>,
+    
+    /// Tool names, when the event names one
+    #[serde(default = "default_match_tools")]
+    pub tools: Vec<String>,
+    
+    /// Regexes, one of which the command must match, when the event carries one
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub commands: Vec<String>,
+}
+
+impl Default for MatchSettings {
+    fn default() -> Self {
+        Self {
+            events: Vec::new(),
+            tools: default_match_tools(),
+            commands: Vec::new(),
+        }
+    }
+}
+
+impl MatchSettings {
+    /// Whether an event with this name, tool, and command should be handled
+    pub fn matches(&self, event: &str, tool: Option<&str>, command: Option<&str>) -> Result<bool> {
+        if !self.events.is_empty() && !self.events.iter().any(|name| name == event) {
+            return Ok(false);
+        }
+        if let Some(tool) = tool.filter(|_| !self.tools.is_empty()) {
+            if !self.tools.iter().any(|name| name == tool) {
+                return Ok(false);
+            }
+        }
+        if let Some(command) = command.filter(|_| !self.commands.is_empty()) {
+            for pattern in &self.commands {
+                let regex = regex::Regex::new(pattern)
+                    .with_context(|| format!("Invalid match pattern: {}", pattern))?;
+                if regex.is_match(command) {
+                    return Ok(true);
+                }
+            }
+            return Ok(false);
+        }
+        Ok(true)
+    }
+}
+
+/// Built-in configuration profiles
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Profile {
+    /// Never surprise me: exact-behavior mode, only grep and find rewritten
+    Conservative,
+    /// The built-in defaults
+    Balanced,
+    /// Maximize speed: every rewrite enabled, only per-flag safety checks
+    Aggressive,
+}
+
+impl Profile {
+    /// Overwrite the settings this profile bundles
+    pub fn apply(self, config: &mut Config) {
+        let defaults = GlobalSettings::default();
+        let settings = &mut config.settings;
+        
+        match self {
+            Profile::Conservative => {
+                settings.compatibility_mode = Some(true);
+                settings.semantic_analysis = true;
+                settings.fallback_patterns = defaults.fallback_patterns;
+                settings.fallback_patterns.extend([
+                    r"\bxargs\b".to_string(),    // Argument splitting depends on exact output
+                    r"sed.*\s-i".to_string(),    // In-place edits
+                ]);
+            }
+            Profile::Balanced => {
+                settings.compatibility_mode = defaults.compatibility_mode;
+                settings.semantic_analysis = defaults.semantic_analysis;
+                settings.fallback_patterns = defaults.fallback_patterns;
+            }
+            Profile::Aggressive => {
+                settings.compatibility_mode = Some(false);
+                settings.semantic_analysis = false;
+                settings.fallback_patterns = Vec::new();
+            }
+        }
+        
+        for (command, replacement) in config.replacements.iter_mut() {
+            replacement.enabled = match self {
+                // bat/eza/procs change output formats; sd edits files
+                Profile::Conservative => matches!(command.as_str(), "grep" | "find"),
+                Profile::Balanced | Profile::Aggressive => true,
+            };
+        }
+    }
+}
+
+impl std::str::FromStr for Profile {
+    type Err = anyhow::Error;
+    
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "conservative" => Ok(Profile::Conservative),
+            "balanced" => Ok(Profile::Balanced),
+            "aggressive" => Ok(Profile::Aggressive),
+            _ => anyhow::bail!("Unknown profile '{}' (expected conservative, balanced, or aggressive)", s),
+        }
+    }
+}
+
+/// How a rewrite is surfaced in `HookOutput.message`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum MessageMode {
+    /// No message; rewrites are invisible
+    #[default]
+    Silent,
+    /// Name the tool swap, e.g. "Using `rg` instead of `grep`"
+    Announce,
+    /// Show the original and rewritten commands in full
+    Full,
+}
+
+impl std::str::FromStr for MessageMode {
+    type Err = anyhow::Error;
+    
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "silent" => Ok(MessageMode::Silent),
+            "announce" => Ok(MessageMode::Announce),
+            "full" => Ok(MessageMode::Full),
+            _ => anyhow::bail!("Unknown message mode '{}' (expected silent, announce, or full)", s),
+        }
+    }
+}
+
+/// Hook response schema revision to speak
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum OutputProtocol {
+    /// `{"decision": "approve", "context": {...}}`; rewrites are only
+    /// reported in `context.modified_command`, which Claude Code ignores
+    LegacyDecision,
+    /// `{"hookSpecificOutput": {"hookEventName": "PreToolUse", ...}}`, with
+    /// rewrites applied through `updatedInput`
+    #[default]
+    HookSpecificOutput,
+    /// No JSON; the exit status carries the decision
+    ExitCode,
+}
+
+impl std::str::FromStr for OutputProtocol {
+    type Err = anyhow::Error;
+    
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "legacy-decision" => Ok(OutputProtocol::LegacyDecision),
+            "hook-specific-output" => Ok(OutputProtocol::HookSpecificOutput),
+            "exit-code" => Ok(OutputProtocol::ExitCode),
+            _ => anyhow::bail!(
+                "Unknown output protocol '{}' (expected legacy-decision, hook-specific-output, or exit-code)",
+                s
+            ),
+        }
+    }
+}
+
+impl GlobalSettings {
+    /// User-facing message for a rewrite, or `None` in silent mode
+    pub fn replacement_message(&self, original: &str, replacement: &str) -> Option<String> {
+        let template = match (self.message, &self.message_template) {
+            (MessageMode::Silent, _) => return None,
+            (_, Some(template)) => template.as_str(),
+            (MessageMode::Announce, None) => "Using `{replacement_tool}` instead of `{original_tool}`",
+            (MessageMode::Full, None) => "Replaced `{original}` with `{replacement}`",
+        };
+        let first_word = |command: &str| command.split_whitespace().next().unwrap_or("").to_string();
+        
+        Some(
+            template
+                .replace("{original_tool}", &first_word(original))
+                .replace("{replacement_tool}", &first_word(replacement))
+                .replace("{original}", original)
+                .replace("{replacement}", replacement),
+        )
+    }
 }
 
 impl Default for Config {
     fn default() -> Self {
+        Self::defaults_for(std::env::consts::OS)
+    }
+}
+
+impl Config {
+    /// The built-in defaults for `os` (a `std::env::consts::OS` value): the
+    /// cmd builtins only on Windows, where eza is rarely installed and `ps`
+    /// is MSYS's
+    pub fn defaults_for(os: &str) -> Self {
+        let windows = os == "windows";
         let mut replacements = HashMap::new();
         
         // grep → rg
@@ -99,6 +750,11 @@ impl Default for Config {
             flag_mappings: HashMap::new(),
             priority: 10,
             use_fallback: true,
+            when: None,
+            fallback_patterns: vec![
+                r"grep.*--null-data".to_string(), // Binary data handling
+            ],
+            min_version: None,
         });
         
         // find → fd
@@ -123,6 +779,11 @@ impl Default for Config {
             },
             priority: 10,
             use_fallback: true,
+            when: None,
+            fallback_patterns: vec![
+                r"find.*-perm".to_string(), // Permission-based find
+            ],
+            min_version: None,
         });
         
         // cat → bat
@@ -140,11 +801,14 @@ impl Default for Config {
             },
             priority: 5, // Lower priority, bat changes output format
             use_fallback: true,
+            when: None,
+            fallback_patterns: Vec::new(),
+            min_version: None,
         });
         
         // ls → eza/exa
         replacements.insert("ls".to_string(), ReplacementConfig {
-            enabled: true,
+            enabled: !windows,
             replacement: "eza".to_string(), // Try eza first, fallback to exa
             preserve_flags: vec![
                 "-l".to_string(),
@@ -160,6 +824,9 @@ impl Default for Config {
             flag_mappings: HashMap::new(),
             priority: 8,
             use_fallback: true,
+            when: None,
+            fallback_patterns: Vec::new(),
+            min_version: None,
         });
         
         // sed → sd
@@ -170,6 +837,9 @@ impl Default for Config {
             flag_mappings: HashMap::new(),
             priority: 6,
             use_fallback: true,
+            when: None,
+            fallback_patterns: Vec::new(),
+            min_version: None,
         });
         
         // ps → procs
@@ -182,15 +852,66 @@ impl Default for Config {
                 "-x".to_string(),
                 "-f".to_string(),
             ],
-            flag_mappings: HashMap::new(),
+            flag_mappings: {
+                let mut map = HashMap::new();
+                // MSYS ps -W adds Windows processes, which procs always lists
+                if windows {
+                    map.insert("-W".to_string(), "".to_string());
+                }
+                map
+            },
             priority: 7,
             use_fallback: true,
+            when: None,
+            fallback_patterns: Vec::new(),
+            min_version: None,
         });
         
+        // cmd.exe builtins, which only mean the same thing on Windows
+        for (command, replacement, priority) in [("dir", "fd", 10), ("type", "bat", 5), ("findstr", "rg", 10), ("where", "which", 4)] {
+            replacements.insert(command.to_string(), ReplacementConfig {
+                enabled: windows,
+                replacement: replacement.to_string(),
+                preserve_flags: Vec::new(),
+                flag_mappings: HashMap::new(),
+                priority,
+                use_fallback: true,
+                when: None,
+                fallback_patterns: Vec::new(),
+                min_version: None,
+            });
+        }
+        
+        // PowerShell cmdlets, only ever seen inside `powershell -Command`
+        for (command, replacement, priority) in [("Get-ChildItem", "fd", 10), ("Get-Content", "bat", 5), ("Select-String", "rg", 10)] {
+            replacements.insert(command.to_string(), ReplacementConfig {
+                enabled: true,
+                replacement: replacement.to_string(),
+                preserve_flags: Vec::new(),
+                flag_mappings: HashMap::new(),
+                priority,
+                use_fallback: true,
+                when: None,
+                fallback_patterns: Vec::new(),
+                min_version: None,
+            });
+        }
+        
         Self {
+            version: CONFIG_VERSION,
+            include: Vec::new(),
             tools: HashMap::new(),
             replacements,
             settings: GlobalSettings::default(),
+            rules: Vec::new(),
+            rewrites: Vec::new(),
+            plugins: Vec::new(),
+            overrides: BTreeMap::new(),
+            remote_config: None,
+            windows: None,
+            macos: None,
+            linux: None,
+            sources: Vec::new(),
         }
     }
 }
@@ -203,66 +924,781 @@ impl Default for GlobalSettings {
             cache_tool_checks: true,
             compatibility_mode: None, // Auto-detect based on context
             semantic_analysis: true,
-            fallback_patterns: vec![
-                // Patterns that commonly require exact grep behavior
-                r"grep.*-P".to_string(),      // Perl regex
-                r"grep.*--null-data".to_string(), // Binary data handling
-                r"find.*-exec".to_string(),   // Find with exec actions
-                r"find.*-size".to_string(),   // Size-based find
-                r"find.*-perm".to_string(),   // Permission-based find
-            ],
+            // Command-specific patterns live on each replacement
+            fallback_patterns: Vec::new(),
+            never_replace: Vec::new(),
+            ask: Vec::new(),
+            only_replace: Vec::new(),
+            message: MessageMode::default(),
+            message_template: None,
+            output_protocol: OutputProtocol::default(),
+            prompt_context: false,
+            install_hints: false,
+            trust_shared_config: false,
+            long_running: LongRunningSettings::default(),
+            read: ReadSettings::default(),
+            search: SearchSettings::default(),
+            rewrite_cache: RewriteCacheSettings::default(),
+            metrics: MetricsSettings::default(),
+            latency_budget_ms: None,
+            event_match: MatchSettings::default(),
+            profile: None,
         }
     }
 }
 
 impl Config {
-    /// Load configuration from file, or return default if not found
-    pub fn load() -> Result<Self> {
-        let config_path = Self::config_path();
+    /// Load the effective configuration: global file, then project file,
+    /// then `CLAUDE_CMDREPL_*` environment overrides
+    ///
+    /// File layers are cached by modification time, so long-lived processes
+    /// pick up edits immediately without re-parsing on every call. When no
+    /// config file exists the defaults are used without touching the cache.
+    pub fn load_for_project(project_dir: Option<&Path>) -> Result<Self> {
+        Self::load_layers(project_dir)?.resolve(project_dir)
+    }
+    
+    /// The global and project files merged, before anything that depends
+    /// on the call (directory overrides, profile, environment)
+    pub fn load_layers(project_dir: Option<&Path>) -> Result<Self> {
+        let global = Self::global_config_path();
+        let project = project_dir.and_then(Self::project_config_path);
+        match (global, project) {
+            (None, None) => Self::default().with_rule_specs(project_dir),
+            (global, project) => {
+                let mut sources = vec![global.unwrap_or_else(Self::config_path)];
+                sources.extend(project);
+                LAYER_CACHE.get_or_load(&sources, || Self::load_file_layers(project_dir)?.with_rule_specs(project_dir))
+            }
+        }
+    }
+    
+    /// Append the rules of the built-in, global, and project `rules.d` spec
+    /// files, after the config's own `[[rules]]`. A spec file replaces any
+    /// earlier one with the same name, so an empty `du.toml` in the global
+    /// `rules.d` turns the built-in du spec off.
+    pub fn with_rule_specs(mut self, project_dir: Option<&Path>) -> Result<Self> {
+        let mut specs: Vec<(String, Vec<RuleConfig>)> = Vec::new();
+        let mut add = |name: String, rules: Vec<RuleConfig>| match specs.iter_mut().find(|(existing, _)| *existing == name) {
+            Some(spec) => spec.1 = rules,
+            None => specs.push((name, rules)),
+        };
+        for (name, content) in BUILTIN_RULE_SPECS {
+            add(name.to_string(), parse_rule_spec(content).with_context(|| format!("Invalid built-in rule spec {}", name))?);
+        }
         
-        if config_path.exists() {
-            let content = std::fs::read_to_string(&config_path)
-                .context("Failed to read config file")?;
-            
-            let config: Config = toml::from_str(&content)
-                .context("Failed to parse config file")?;
-            
-            Ok(config)
-        } else {
-            // Create default config file
-            let default_config = Self::default();
-            default_config.save()?;
-            Ok(default_config)
+        let mut dirs = vec![Self::rules_dir()];
+        dirs.extend(project_dir.map(Self::project_rules_dir));
+        for dir in dirs.into_iter().filter(|dir| dir.is_dir()) {
+            let mut paths: Vec<PathBuf> = std::fs::read_dir(&dir)
+                .with_context(|| format!("Failed to read {}", dir.display()))?
+                .filter_map(|entry| Some(entry.ok()?.path()))
+                .filter(|path| path.extension().is_some_and(|ext| ext == "toml"))
+                .collect();
+            paths.sort();
+            // Stamping the directory notices specs being added or removed
+            self.sources.push(dir);
+            for path in paths {
+                let content = std::fs::read_to_string(&path)
+                    .with_context(|| format!("Failed to read {}", path.display()))?;
+                let rules = parse_rule_spec(&content).with_context(|| format!("Invalid rule spec {}", path.display()))?;
+                add(path.file_name().unwrap_or_default().to_string_lossy().into_owned(), rules);
+                self.sources.push(path);
+            }
+        }
+        self.rules.extend(specs.into_iter().flat_map(|(_, rules)| rules));
+        Ok(self)
+    }
+    
+    /// Apply directory overrides, the profile, and environment overrides to
+    /// config loaded by [`Self::load_layers`]
+    pub fn resolve(self, project_dir: Option<&Path>) -> Result<Self> {
+        let mut config = self;
+        if !config.overrides.is_empty() {
+            if let Ok(cwd) = std::env::current_dir() {
+                config = config.with_directory_overrides(&cwd, project_dir)?;
+            }
+        }
+        config.apply_profile();
+        config.apply_env_overrides(std::env::vars())?;
+        Ok(config)
+    }
+    
+    /// Stamps telling whether [`Self::load_layers`] would still return this
+    /// config: its files, plus every file name a new layer could take
+    pub fn layer_stamps(&self, project_dir: Option<&Path>) -> Vec<FileStamp> {
+        let mut paths = config_file_candidates(&Self::config_dir(), "config");
+        if let Some(project_dir) = project_dir {
+            paths.extend(config_file_candidates(&project_dir.join(".claude"), "command-replacer"));
+        }
+        for source in &self.sources {
+            if !paths.contains(source) {
+                paths.push(source.clone());
+            }
+        }
+        stamp_files(&paths)
+    }
+    
+    /// Load the global configuration and deep-merge the project's
+    /// `.claude/command-replacer.toml` over it, if one exists
+    pub fn load_file_layers(project_dir: Option<&Path>) -> Result<Self> {
+        let global = Self::load()?;
+        
+        let Some(project_path) = project_dir.and_then(Self::project_config_path) else {
+            return Ok(global);
+        };
+        global.with_project_file(&project_path)
+    }
+    
+    /// Deep-merge the project config at `path` over this one. Unless this
+    /// config trusts shared config, the project can only turn replacements
+    /// and checks off or add commands to skip; it can't change what runs or
+    /// where files are written.
+    pub fn with_project_file(self, path: &Path) -> Result<Self> {
+        // Project files are often checked in, so migrate them in memory only
+        let mut overlay = read_config_value(path)?;
+        migrate::migrate(&mut overlay)
+            .with_context(|| format!("Failed to migrate config {}", path.display()))?;
+        let mut merged = toml::Value::try_from(&self)
+            .context("Failed to serialize global config")?;
+        if !self.settings.trust_shared_config {
+            strip_untrusted(&mut overlay, &merged);
         }
+        let mut sources = self.sources.clone();
+        let overlay = expand_includes(path, overlay, &mut Vec::new(), &mut sources)?;
+        
+        merge_toml(&mut merged, overlay);
+        
+        let mut config: Config = merged.try_into()
+            .with_context(|| format!("Invalid settings in project config {}", path.display()))?;
+        config.sources = sources;
+        Ok(config)
     }
     
-    /// Save configuration to file
-    pub fn save(&self) -> Result<()> {
-        let config_path = Self::config_path();
+    /// Merge every `[overrides."<glob>"]` table whose glob matches `dir`.
+    ///
+    /// Globs are matched against the path relative to the project directory
+    /// (or the absolute path outside it). More specific (longer) globs are
+    /// applied last so they win over broad ones.
+    pub fn with_directory_overrides(self, dir: &Path, project_dir: Option<&Path>) -> Result<Self> {
+        if self.overrides.is_empty() {
+            return Ok(self);
+        }
         
-        // Ensure parent directory exists
-        if let Some(parent) = config_path.parent() {
-            std::fs::create_dir_all(parent)
-                .context("Failed to create config directory")?;
+        let relative = project_dir
+            .and_then(|project| dir.strip_prefix(project).ok())
+            .unwrap_or(dir);
+        // A trailing separator lets `legacy/**` match the `legacy` directory itself
+        let candidates = [relative.to_path_buf(), relative.join("")];
+        
+        let mut matching = Vec::new();
+        for pattern in self.overrides.keys() {
+            let glob = globset::GlobBuilder::new(pattern)
+                .literal_separator(true)
+                .build()
+                .with_context(|| format!("Invalid override glob: {}", pattern))?
+                .compile_matcher();
+            if candidates.iter().any(|candidate| glob.is_match(candidate)) {
+                matching.push(pattern.clone());
+            }
+        }
+        if matching.is_empty() {
+            return Ok(self);
         }
+        matching.sort_by_key(|pattern| pattern.len());
         
-        let content = toml::to_string_pretty(self)
-            .context("Failed to serialize config")?;
+        let mut merged = toml::Value::try_from(&self).context("Failed to serialize config")?;
+        for pattern in &matching {
+            let mut overlay = self.overrides[pattern].clone();
+            if let Some(table) = overlay.as_table_mut() {
+                table.remove("overrides");
+            }
+            merge_toml(&mut merged, overlay);
+        }
         
-        std::fs::write(&config_path, content)
-            .context("Failed to write config file")?;
+        merged.try_into()
+            .with_context(|| format!("Invalid settings in directory overrides {}", matching.join(", ")))
+    }
+    
+    /// Apply the selected profile, if any, over the individual settings
+    pub fn apply_profile(&mut self) {
+        if let Some(profile) = self.settings.profile {
+            profile.apply(self);
+        }
+    }
+    
+    /// Apply `CLAUDE_CMDREPL_*` overrides so behavior can be tweaked per shell or
+    /// CI job without editing TOML. Unrelated variables are ignored.
+    ///
+    /// Supported: `PROFILE`, `DEBUG`, `COMPATIBILITY_MODE`, `SEMANTIC_ANALYSIS`,
+    /// `CACHE_TOOL_CHECKS`, `TOOL_CHECK_TIMEOUT`, `LATENCY_BUDGET_MS`, `MESSAGE`, `OUTPUT_PROTOCOL`, and the comma-separated
+    /// command lists `DISABLE` / `ENABLE` (e.g. `CLAUDE_CMDREPL_DISABLE=grep,sed`).
+    /// `CLAUDE_CMDREPL_DISABLE=1` disables the hook entirely.
+    /// A profile is applied first so the individual overrides refine it.
+    pub fn apply_env_overrides<I>(&mut self, vars: I) -> Result<()>
+    where
+        I: IntoIterator<Item = (String, String)>,
+    {
+        let vars: Vec<(String, String)> = vars.into_iter().collect();
+        let profile_key = format!("{}PROFILE", ENV_PREFIX);
+        if let Some((_, value)) = vars.iter().find(|(key, _)| *key == profile_key) {
+            self.settings.profile = Some(value.trim().parse()?);
+            self.apply_profile();
+        }
         
+        for (key, value) in vars {
+            let Some(name) = key.strip_prefix(ENV_PREFIX) else {
+                continue;
+            };
+            let value = value.trim();
+            
+            match name {
+                "DEBUG" => self.settings.debug = parse_env_bool(&key, value)?,
+                "COMPATIBILITY_MODE" => self.settings.compatibility_mode = Some(parse_env_bool(&key, value)?),
+                "SEMANTIC_ANALYSIS" => self.settings.semantic_analysis = parse_env_bool(&key, value)?,
+                "CACHE_TOOL_CHECKS" => self.settings.cache_tool_checks = parse_env_bool(&key, value)?,
+                "MESSAGE" => self.settings.message = value.parse()?,
+                "OUTPUT_PROTOCOL" => self.settings.output_protocol = value.parse()?,
+                "PROMPT_CONTEXT" => self.settings.prompt_context = parse_env_bool(&key, value)?,
+                "INSTALL_HINTS" => self.settings.install_hints = parse_env_bool(&key, value)?,
+                "TOOL_CHECK_TIMEOUT" => {
+                    self.settings.tool_check_timeout = value.parse()
+                        .with_context(|| format!("{} must be a number of milliseconds, got '{}'", key, value))?;
+                }
+                "LATENCY_BUDGET_MS" => {
+                    self.settings.latency_budget_ms = Some(value.parse()
+                        .with_context(|| format!("{} must be a number of milliseconds, got '{}'", key, value))?);
+                }
+                // Handled before config is loaded; see `kill_switch_engaged`
+                "DISABLE" if is_kill_switch(value) => {}
+                "DISABLE" | "ENABLE" => {
+                    let enabled = name == "ENABLE";
+                    for command in value.split(',').map(str::trim).filter(|c| !c.is_empty()) {
+                        if let Some(replacement) = self.replacements.get_mut(command) {
+                            replacement.enabled = enabled;
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
         Ok(())
     }
     
-    /// Get the configuration file path
-    fn config_path() -> PathBuf {
+    /// Load the global configuration, or the built-in defaults if there is
+    /// no config file. Never writes a default file (`init` scaffolds one).
+    ///
+    /// `config.toml`, `config.json`, and `config.yaml` are tried in that order.
+    /// Files from an older layout are migrated in place, keeping a backup.
+    pub fn load() -> Result<Self> {
+        Self::load_from_dir(&Self::config_dir())
+    }
+    
+    /// Load the global configuration from `dir`
+    pub fn load_from_dir(dir: &Path) -> Result<Self> {
+        let Some(config_path) = find_config_file(dir, "config") else {
+            return Ok(Self::default());
+        };
+        
+        let format = ConfigFormat::from_path(&config_path)
+            .with_context(|| format!("Unrecognized config format: {}", config_path.display()))?;
+        let original = read_config_value(&config_path)?;
+        // A read-only home still gets the migrated layout, just not on disk
+        let value = match migrate::migrate_file(&config_path, format, original.clone()) {
+            Ok(value) => value,
+            Err(_) => {
+                let mut value = original;
+                migrate::migrate(&mut value)?;
+                value
+            }
+        };
+        let mut sources = Vec::new();
+        let mut value = expand_includes(&config_path, value, &mut Vec::new(), &mut sources)?;
+        
+        // Only the keys the user actually wrote override the team policy
+        if let Some(remote) = value.get("remote_config") {
+            let remote: RemoteConfig = remote.clone().try_into()
+                .with_context(|| format!("Invalid remote_config in {}", config_path.display()))?;
+            let cache_dir = dir.join("cache");
+            let mut base = remote::load_remote(&remote, &cache_dir)?;
+            migrate::migrate(&mut base)
+                .with_context(|| format!("Failed to migrate remote config {}", remote.url))?;
+            if let Some(table) = base.as_table_mut() {
+                table.remove("include");
+                table.remove("remote_config");
+            }
+            let trusted = value
+                .get("settings")
+                .and_then(|settings| settings.get("trust_shared_config"))
+                .and_then(toml::Value::as_bool)
+                .unwrap_or(false);
+            if !trusted {
+                strip_untrusted(&mut base, &value);
+            }
+            apply_platform_section(&mut base, std::env::consts::OS);
+            merge_toml(&mut base, value);
+            value = base;
+            sources.insert(0, remote::cache_path(&remote, &cache_dir));
+        }
+        let mut config: Config = value
+            .try_into()
+            .with_context(|| format!("Invalid settings in {}", config_path.display()))?;
+        config.sources = sources;
+        
+        Ok(config)
+    }
+    
+    /// Find the project-local configuration file
+    /// (`.claude/command-replacer.{toml,json,yaml}`), if any
+    pub fn project_config_path(project_dir: &Path) -> Option<PathBuf> {
+        find_config_file(&project_dir.join(".claude"), "command-replacer")
+    }
+    
+    /// Find the global configuration file, if one exists
+    pub fn global_config_path() -> Option<PathBuf> {
+        find_config_file(&Self::config_dir(), "config")
+    }
+    
+    /// Get the directory holding the global configuration
+    pub fn config_dir() -> PathBuf {
         dirs::home_dir()
             .unwrap_or_else(|| PathBuf::from("."))
             .join(".claude")
             .join("hooks")
             .join("command-replacer")
-            .join("config.toml")
+    }
+    
+    /// Rule spec files (`*.toml`) added to every config
+    pub fn rules_dir() -> PathBuf {
+        Self::config_dir().join("rules.d")
+    }
+    
+    /// A project's own rule spec files
+    pub fn project_rules_dir(project_dir: &Path) -> PathBuf {
+        project_dir.join(".claude").join("command-replacer").join("rules.d")
+    }
+    
+    /// Get the path new configuration files are written to
+    pub fn config_path() -> PathBuf {
+        Self::config_dir().join("config.toml")
+    }
+    
+    /// Directory for caches the hook can rebuild at any time
+    pub fn cache_dir() -> PathBuf {
+        Self::config_dir().join("cache")
+    }
+    
+    /// Per-session replacement totals, appended on Stop
+    pub fn history_path() -> PathBuf {
+        Self::config_dir().join("history.jsonl")
+    }
+    
+    /// `.wasm` plugins, loaded with the `wasm-plugins` feature
+    pub fn plugins_dir() -> PathBuf {
+        Self::config_dir().join("plugins")
+    }
+    
+    /// Rewrite decisions kept across processes with `rewrite_cache.persist`
+    pub fn rewrite_cache_path() -> PathBuf {
+        Self::cache_dir().join("rewrites.json")
+    }
+    
+    /// Counters behind `settings.metrics`
+    pub fn metrics_path() -> PathBuf {
+        Self::cache_dir().join("metrics.json")
+    }
+    
+    /// Per-call phase timings, appended in debug mode
+    pub fn debug_log_path() -> PathBuf {
+        Self::config_dir().join("debug.log")
+    }
+    
+    /// Directory holding per-session state, such as pending rewrites
+    pub fn state_dir() -> PathBuf {
+        Self::config_dir().join("sessions")
+    }
+}
+
+/// Formats a configuration file may be written in, in discovery priority order
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigFormat {
+    Toml,
+    Json,
+    Yaml,
+}
+
+impl ConfigFormat {
+    pub const ALL: [ConfigFormat; 3] = [ConfigFormat::Toml, ConfigFormat::Json, ConfigFormat::Yaml];
+    
+    /// File extensions recognized for this format
+    pub fn extensions(self) -> &'static [&'static str] {
+        match self {
+            ConfigFormat::Toml => &["toml"],
+            ConfigFormat::Json => &["json"],
+            ConfigFormat::Yaml => &["yaml", "yml"],
+        }
+    }
+    
+    /// Detect the format from a file extension
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        Self::ALL.into_iter().find(|format| format.extensions().contains(&extension.as_str()))
+    }
+    
+    /// Parse file content into a format-neutral value tree
+    pub fn parse(self, content: &str) -> Result<toml::Value> {
+        match self {
+            ConfigFormat::Toml => Ok(toml::from_str(content)?),
+            ConfigFormat::Json => {
+                let value: serde_json::Value = serde_json::from_str(content)?;
+                Ok(toml::Value::try_from(value)?)
+            }
+            ConfigFormat::Yaml => {
+                let value: serde_json::Value = serde_yaml::from_str(content)?;
+                Ok(toml::Value::try_from(value)?)
+            }
+        }
+    }
+    
+    /// Render a value tree back into this format
+    pub fn serialize(self, value: &toml::Value) -> Result<String> {
+        match self {
+            ConfigFormat::Toml => Ok(toml::to_string_pretty(value)?),
+            ConfigFormat::Json => Ok(serde_json::to_string_pretty(value)? + "\n"),
+            ConfigFormat::Yaml => Ok(serde_yaml::to_string(value)?),
+        }
+    }
+}
+
+/// Specs shipped in this crate's `rules.d/`, by file name (see build.rs)
+const BUILTIN_RULE_SPECS: &[(&str, &str)] = include!(concat!(env!("OUT_DIR"), "/rule_specs.rs"));
+
+/// The rules of one spec file: a single rule at the top level, or a
+/// `[[rules]]` array. An empty file has none.
+pub fn parse_rule_spec(content: &str) -> Result<Vec<RuleConfig>> {
+    let spec: toml::Table = toml::from_str(content)?;
+    if spec.is_empty() {
+        return Ok(Vec::new());
+    }
+    match spec.get("rules") {
+        Some(rules) if spec.len() == 1 => Ok(rules.clone().try_into()?),
+        _ => Ok(vec![toml::Value::Table(spec).try_into()?]),
+    }
+}
+
+/// Find `<dir>/<stem>.<ext>`, trying TOML, then JSON, then YAML
+pub fn find_config_file(dir: &Path, stem: &str) -> Option<PathBuf> {
+    config_file_candidates(dir, stem).into_iter().find(|path| path.is_file())
+}
+
+/// Every path [`find_config_file`] checks, in order
+fn config_file_candidates(dir: &Path, stem: &str) -> Vec<PathBuf> {
+    ConfigFormat::ALL
+        .iter()
+        .flat_map(|format| format.extensions())
+        .map(|extension| dir.join(format!("{}.{}", stem, extension)))
+        .collect()
+}
+
+/// Read and parse a configuration file in whichever format its extension names
+pub fn read_config_value(path: &Path) -> Result<toml::Value> {
+    let format = ConfigFormat::from_path(path)
+        .with_context(|| format!("Unrecognized config format: {}", path.display()))?;
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read config file {}", path.display()))?;
+    format.parse(&content)
+        .with_context(|| format!("Failed to parse config file {}", path.display()))
+}
+
+/// Read a config file, migrating it in memory and resolving its `include`
+/// list. Every file read is appended to `sources` in merge order.
+pub fn read_config_with_includes(path: &Path, sources: &mut Vec<PathBuf>) -> Result<toml::Value> {
+    let mut value = read_config_value(path)?;
+    migrate::migrate(&mut value)
+        .with_context(|| format!("Failed to migrate config {}", path.display()))?;
+    expand_includes(path, value, &mut Vec::new(), sources)
+}
+
+/// Top-level keys honored from shared config without
+/// `settings.trust_shared_config`. Everything else (rules, rewrites,
+/// plugins, tool paths, includes) chooses what runs, so it is dropped.
+const SHARED_KEYS: [&str; 7] = ["version", "replacements", "settings", "overrides", "windows", "macos", "linux"];
+
+/// Keys of a `[replacements.<command>]` table shared config may set; the
+/// replacement tool and its flags stay as the global config has them
+const SHARED_REPLACEMENT_KEYS: [&str; 3] = ["enabled", "use_fallback", "fallback_patterns"];
+
+/// Settings shared config may set: switches, and lists that only turn
+/// rewrites off or ask first. Paths, endpoints, and anything that changes
+/// the command (like `long_running.auto_timeout`) are dropped.
+const SHARED_SETTINGS: [&str; 16] = [
+    "debug",
+    "cache_tool_checks",
+    "compatibility_mode",
+    "semantic_analysis",
+    "fallback_patterns",
+    "never_replace",
+    "ask",
+    "message",
+    "prompt_context",
+    "install_hints",
+    "long_running.advise",
+    "read.advise",
+    "search.package_scope",
+    "search.project_type",
+    "rewrite_cache.enabled",
+    "metrics.enabled",
+];
+
+/// Switches shared config may only flip toward rewriting less, with the
+/// value it may set them to
+const SHARED_SWITCHES: [(&str, bool); 2] = [("semantic_analysis", true), ("compatibility_mode", true)];
+const SHARED_REPLACEMENT_SWITCHES: [(&str, bool); 2] = [("enabled", false), ("use_fallback", true)];
+
+/// Lists shared config adds to rather than replaces
+const SHARED_LISTS: [&str; 3] = ["fallback_patterns", "never_replace", "ask"];
+const SHARED_REPLACEMENT_LISTS: [&str; 1] = ["fallback_patterns"];
+
+/// Reduce a project or remote config, including its platform sections and
+/// directory overrides, to the keys listed above, so that merged over (or
+/// under) `trusted` it can only make the hook rewrite less. Replacements
+/// `trusted` doesn't define are dropped, since shared config can't name
+/// their tool.
+fn strip_untrusted(value: &mut toml::Value, trusted: &toml::Value) {
+    let Some(table) = value.as_table_mut() else {
+        return;
+    };
+    table.retain(|key, _| SHARED_KEYS.contains(&key));
+    if let Some(settings) = table.get_mut("settings") {
+        retain_paths(settings, &SHARED_SETTINGS);
+        restrict_shared(settings, trusted.get("settings"), &SHARED_SWITCHES, &SHARED_LISTS);
+    }
+    if let Some(replacements) = table.get_mut("replacements").and_then(toml::Value::as_table_mut) {
+        let known = trusted.get("replacements").and_then(toml::Value::as_table);
+        replacements.retain(|name, _| known.is_some_and(|known| known.contains_key(name)));
+        for (name, replacement) in replacements.iter_mut() {
+            retain_paths(replacement, &SHARED_REPLACEMENT_KEYS);
+            let trusted = known.and_then(|known| known.get(name));
+            restrict_shared(replacement, trusted, &SHARED_REPLACEMENT_SWITCHES, &SHARED_REPLACEMENT_LISTS);
+        }
+    }
+    for section in PLATFORM_SECTIONS {
+        if let Some(section) = table.get_mut(section) {
+            strip_untrusted(section, trusted);
+        }
+    }
+    if let Some(overrides) = table.get_mut("overrides").and_then(toml::Value::as_table_mut) {
+        overrides.iter_mut().for_each(|(_, layer)| strip_untrusted(layer, trusted));
+    }
+}
+
+/// Drop `switches` set the wrong way, and put the `trusted` entries of
+/// `lists` in front of the shared ones so merging can't remove them
+fn restrict_shared(value: &mut toml::Value, trusted: Option<&toml::Value>, switches: &[(&str, bool)], lists: &[&str]) {
+    let Some(table) = value.as_table_mut() else {
+        return;
+    };
+    table.retain(|key, value| {
+        switches.iter().all(|(switch, allowed)| *switch != key || value.as_bool() == Some(*allowed))
+    });
+    for list in lists {
+        let kept = trusted.and_then(|trusted| trusted.get(*list)).and_then(toml::Value::as_array);
+        if let (Some(toml::Value::Array(shared)), Some(kept)) = (table.get_mut(*list), kept) {
+            let added = std::mem::replace(shared, kept.clone());
+            shared.extend(added);
+        }
+    }
+}
+
+/// Keep only the dotted `paths` of a table, e.g. `read.advise`
+fn retain_paths(value: &mut toml::Value, paths: &[&str]) {
+    let Some(table) = value.as_table_mut() else {
+        return;
+    };
+    table.retain(|key, value| {
+        let nested: Vec<&str> = paths
+            .iter()
+            .filter_map(|path| path.strip_prefix(key)?.strip_prefix('.'))
+            .collect();
+        if nested.is_empty() {
+            return paths.contains(&key);
+        }
+        retain_paths(value, &nested);
+        true
+    });
+}
+
+/// Per-OS section names, matching `std::env::consts::OS`
+pub const PLATFORM_SECTIONS: [&str; 3] = ["windows", "macos", "linux"];
+
+/// Merge the `[<os>]` section of a single file over the rest of it and drop
+/// every platform section, so a file's own OS tweaks beat its base settings
+/// but not the files layered above it
+pub fn apply_platform_section(value: &mut toml::Value, os: &str) {
+    let Some(table) = value.as_table_mut() else {
+        return;
+    };
+    let mut sections: Vec<(String, toml::Value)> = PLATFORM_SECTIONS
+        .iter()
+        .filter_map(|name| table.remove(*name).map(|section| (name.to_string(), section)))
+        .collect();
+    
+    if let Some(index) = sections.iter().position(|(name, _)| name == os) {
+        let (_, mut section) = sections.swap_remove(index);
+        if let Some(section_table) = section.as_table_mut() {
+            section_table.retain(|key, _| !PLATFORM_SECTIONS.contains(&key) && key != "include");
+        }
+        merge_toml(value, section);
+    }
+}
+
+/// Merge `value`'s includes underneath it: each included file in listed
+/// order (its own includes first, depth-first), then `value` itself on top.
+/// `stack` holds the files being expanded, to reject include cycles.
+fn expand_includes(
+    path: &Path,
+    mut value: toml::Value,
+    stack: &mut Vec<PathBuf>,
+    sources: &mut Vec<PathBuf>,
+) -> Result<toml::Value> {
+    let includes = match value.as_table_mut().and_then(|table| table.remove("include")) {
+        None => Vec::new(),
+        Some(toml::Value::Array(items)) => items
+            .into_iter()
+            .map(|item| match item {
+                toml::Value::String(include) => Ok(include),
+                other => anyhow::bail!("include entries must be strings, found {} in {}", other, path.display()),
+            })
+            .collect::<Result<Vec<_>>>()?,
+        Some(other) => anyhow::bail!("include must be a list of paths, found {} in {}", other, path.display()),
+    };
+    
+    let identity = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    stack.push(identity);
+    
+    let mut merged = toml::Value::Table(toml::Table::new());
+    let base_dir = path.parent().unwrap_or(Path::new("."));
+    for include in &includes {
+        let include_path = base_dir.join(include);
+        let include_identity = include_path.canonicalize().unwrap_or_else(|_| include_path.clone());
+        if stack.contains(&include_identity) {
+            anyhow::bail!("Config include cycle: {} includes {}", path.display(), include_path.display());
+        }
+        
+        let mut included = read_config_value(&include_path)
+            .with_context(|| format!("Failed to include {} from {}", include, path.display()))?;
+        migrate::migrate(&mut included)
+            .with_context(|| format!("Failed to migrate config {}", include_path.display()))?;
+        let included = expand_includes(&include_path, included, stack, sources)?;
+        merge_toml(&mut merged, included);
+    }
+    
+    stack.pop();
+    apply_platform_section(&mut value, std::env::consts::OS);
+    merge_toml(&mut merged, value);
+    sources.push(path.to_path_buf());
+    Ok(merged)
+}
+
+/// Identity of a config file's contents: path, modification time, and size
+/// (size catches same-second edits on filesystems with coarse mtimes)
+pub type FileStamp = (PathBuf, Option<SystemTime>, Option<u64>);
+
+/// Take stamps for a set of config files; missing files stamp as `None`
+pub fn stamp_files(paths: &[PathBuf]) -> Vec<FileStamp> {
+    paths
+        .iter()
+        .map(|path| {
+            let metadata = std::fs::metadata(path).ok();
+            let modified = metadata.as_ref().and_then(|m| m.modified().ok());
+            (path.clone(), modified, metadata.map(|m| m.len()))
+        })
+        .collect()
+}
+
+/// Cache of a parsed config keyed by the stamps of the files it came from
+#[derive(Default)]
+pub struct ConfigCache {
+    entry: Mutex<Option<(Vec<FileStamp>, Config)>>,
+}
+
+impl ConfigCache {
+    /// Return the cached config if none of `sources` (nor any file they
+    /// included) changed, else reload it
+    pub fn get_or_load<F>(&self, sources: &[PathBuf], load: F) -> Result<Config>
+    where
+        F: FnOnce() -> Result<Config>,
+    {
+        let mut entry = self.entry.lock().unwrap();
+        
+        if let Some((cached_stamps, config)) = entry.as_ref() {
+            let paths: Vec<PathBuf> = cached_stamps.iter().map(|(path, _, _)| path.clone()).collect();
+            if paths.starts_with(sources) && stamp_files(&paths) == *cached_stamps {
+                return Ok(config.clone());
+            }
+        }
+        
+        let config = load()?;
+        let mut paths = sources.to_vec();
+        paths.extend(config.sources.iter().filter(|path| !sources.contains(path)).cloned());
+        *entry = Some((stamp_files(&paths), config.clone()));
+        Ok(config)
+    }
+}
+
+/// Prefix shared by all environment-variable overrides
+pub const ENV_PREFIX: &str = "CLAUDE_CMDREPL_";
+
+/// `CLAUDE_CMDREPL_OUTPUT_PROTOCOL`, for responses sent before (or without)
+/// loading the config
+pub fn output_protocol_from_env<I>(vars: I) -> Option<OutputProtocol>
+where
+    I: IntoIterator<Item = (String, String)>,
+{
+    let key = format!("{}OUTPUT_PROTOCOL", ENV_PREFIX);
+    vars.into_iter()
+        .find(|(name, _)| *name == key)
+        .and_then(|(_, value)| value.trim().parse().ok())
+}
+
+/// Whether `CLAUDE_CMDREPL_DISABLE` turns the whole hook off (`1`, `true`,
+/// `yes`, `on`, or `all`) rather than naming commands to disable
+pub fn kill_switch_engaged<I>(vars: I) -> bool
+where
+    I: IntoIterator<Item = (String, String)>,
+{
+    let key = format!("{}DISABLE", ENV_PREFIX);
+    vars.into_iter().any(|(name, value)| name == key && is_kill_switch(&value))
+}
+
+fn is_kill_switch(value: &str) -> bool {
+    matches!(value.trim().to_ascii_lowercase().as_str(), "1" | "true" | "yes" | "on" | "all")
+}
+
+/// Parse a boolean environment value (`1/0`, `true/false`, `yes/no`, `on/off`)
+fn parse_env_bool(key: &str, value: &str) -> Result<bool> {
+    match value.to_ascii_lowercase().as_str() {
+        "1" | "true" | "yes" | "on" => Ok(true),
+        "0" | "false" | "no" | "off" => Ok(false),
+        _ => anyhow::bail!("{} must be a boolean (1/0, true/false), got '{}'", key, value),
+    }
+}
+
+/// Recursively merge `overlay` into `base`: tables merge key by key, while
+/// arrays and scalars from the overlay replace the base value outright
+pub fn merge_toml(base: &mut toml::Value, overlay: toml::Value) {
+    match (base, overlay) {
+        (toml::Value::Table(base_table), toml::Value::Table(overlay_table)) => {
+            for (key, value) in overlay_table {
+                match base_table.get_mut(&key) {
+                    Some(existing) => merge_toml(existing, value),
+                    None => {
+                        base_table.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
     }
 }
 
@@ -274,6 +1710,37 @@ fn default_priority() -> u8 {
     5
 }
 
+fn default_rule_priority() -> u8 {
+    50
+}
+
 fn default_timeout() -> u64 {
     1000
-}
\ No newline at end of file
+}
+
+fn default_remote_ttl() -> u64 {
+    3600
+}
+
+fn default_plugin_timeout() -> u64 {
+    200
+}
+
+fn default_plugin_memory() -> u64 {
+    16
+}
+
+fn default_remote_timeout() -> u64 {
+    2000
+}
+fn default_match_tools() -> Vec<String> {
+    ["Bash", "Read", "Grep", "Glob"].map(String::from).to_vec()
+}
+
+fn default_read_max_bytes() -> u64 {
+    256 * 1024
+}
+
+fn default_rewrite_cache_capacity() -> usize {
+    256
+}
@@ -0,0 +1,96 @@
+//! Detected environment that `when` clauses on replacements are matched against
+
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::config::WhenCondition;
+
+/// Environment variables set by common CI providers
+const CI_ENV_VARS: &[&str] = &[
+    "CI",
+    "GITHUB_ACTIONS",
+    "GITLAB_CI",
+    "BUILDKITE",
+    "CIRCLECI",
+    "JENKINS_URL",
+    "TF_BUILD",
+    "TEAMCITY_VERSION",
+];
+
+/// Directories searched for `.git`, the starting one included: enough for
+/// a project nested deep inside a repo, few enough that a slow network
+/// mount isn't walked to its root
+pub const GIT_SEARCH_DEPTH: usize = 16;
+
+/// Marker files identifying a project type, checked in the project root
+const PROJECT_MARKERS: &[(&str, &str)] = &[
+    ("Cargo.toml", "rust"),
+    ("package.json", "node"),
+    ("pyproject.toml", "python"),
+    ("setup.py", "python"),
+    ("requirements.txt", "python"),
+    ("go.mod", "go"),
+    ("pom.xml", "java"),
+    ("build.gradle", "java"),
+    ("build.gradle.kts", "java"),
+    ("Gemfile", "ruby"),
+    ("composer.json", "php"),
+    ("CMakeLists.txt", "cpp"),
+];
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RuleContext {
+    /// Inside a git work tree
+    pub git_repo: bool,
+    /// Running under a CI provider
+    pub ci: bool,
+    /// Project types detected from marker files (a repo can be several)
+    pub project_types: Vec<String>,
+    /// Running inside WSL
+    pub wsl: bool,
+    /// Name of the git work tree's top directory
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub repo: Option<String>,
+}
+
+impl RuleContext {
+    /// Detect the context for commands run in `dir`
+    pub fn detect(dir: &Path) -> Self {
+        let root = git_root(dir);
+        Self {
+            git_repo: root.is_some(),
+            ci: CI_ENV_VARS.iter().any(|var| {
+                std::env::var(var).is_ok_and(|value| !value.is_empty() && value != "0" && value != "false")
+            }),
+            project_types: detect_project_types(dir),
+            wsl: crate::wsl::inside(),
+            repo: root.and_then(Path::file_name).map(|name| name.to_string_lossy().into_owned()),
+        }
+    }
+    
+    /// Whether every condition set in `when` holds
+    pub fn satisfies(&self, when: &WhenCondition) -> bool {
+        when.git_repo.is_none_or(|git_repo| git_repo == self.git_repo)
+            && when.ci.is_none_or(|ci| ci == self.ci)
+            && when.project_type.as_ref().is_none_or(|project_type| {
+                self.project_types.iter().any(|detected| detected.eq_ignore_ascii_case(project_type))
+            })
+    }
+}
+
+/// The nearest directory at or above `dir` holding `.git`, at most
+/// [`GIT_SEARCH_DEPTH`] levels up
+pub fn git_root(dir: &Path) -> Option<&Path> {
+    dir.ancestors().take(GIT_SEARCH_DEPTH).find(|ancestor| ancestor.join(".git").exists())
+}
+
+fn detect_project_types(dir: &Path) -> Vec<String> {
+    let mut types: Vec<String> = Vec::new();
+    for (marker, project_type) in PROJECT_MARKERS {
+        if dir.join(marker).exists() && !types.iter().any(|t| t == project_type) {
+            types.push(project_type.to_string());
+        }
+    }
+    types
+}
@@ -0,0 +1,136 @@
+//! `command-replacer explain`: why the engine did what it did to a command
+//!
+//! The engine notes each decision while it runs in explain mode (see
+//! [`ReplacementEngine::explain`](crate::replacements::ReplacementEngine::explain)),
+//! and this is the JSON shape those notes are reported in: the parsed
+//! command, every stage that changed or decided something, every rule and
+//! built-in considered with what it made of each flag, and the outcome.
+
+use serde::Serialize;
+
+use crate::config::ReplacementConfig;
+use crate::replacements::FallbackReason;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Explanation {
+    pub command: String,
+    pub parsed: ParsedCommand,
+    /// Stages that changed the command or decided its fate, in order
+    pub steps: Vec<Step>,
+    /// Rules and built-ins for the program, highest priority first
+    pub candidates: Vec<CandidateReport>,
+    pub rewritten: Option<String>,
+    /// Why the command was left alone, when it was
+    pub fallback: Option<FallbackReason>,
+}
+
+/// The command split into shell words
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ParsedCommand {
+    pub program: Option<String>,
+    /// Flags and operands up to the first shell operator
+    pub flags: Vec<String>,
+    pub operands: Vec<String>,
+    /// The first shell operator and everything after it (`| sort -h`)
+    pub tail: Vec<String>,
+}
+
+impl ParsedCommand {
+    pub fn new(words: &[String]) -> Self {
+        let Some((program, args)) = words.split_first() else {
+            return Self::default();
+        };
+        let (args, tail) = crate::replacements::split_at_shell_operator(args);
+        let (flags, operands) = flags_and_operands(args);
+        Self { program: Some(program.clone()), flags, operands, tail: tail.to_vec() }
+    }
+}
+
+/// `args` split into flags and operands; everything after `--` is an operand
+fn flags_and_operands(args: &[String]) -> (Vec<String>, Vec<String>) {
+    let mut flags = Vec::new();
+    let mut operands = Vec::new();
+    let mut only_operands = false;
+    for arg in args {
+        if !only_operands && arg == "--" {
+            only_operands = true;
+        } else if !only_operands && arg.starts_with('-') && arg != "-" {
+            flags.push(arg.clone());
+        } else {
+            operands.push(arg.clone());
+        }
+    }
+    (flags, operands)
+}
+
+/// One stage of the pipeline (`never_replace`, `rewrite`, `plugin`, ...)
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Step {
+    pub stage: String,
+    pub detail: String,
+    /// The command after this stage, when it changed it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub command: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CandidateReport {
+    /// `rule` or `builtin`
+    pub kind: &'static str,
+    pub tool: String,
+    pub priority: u8,
+    /// `applied`, `declined`, `skipped`, or `not-reached` (an earlier
+    /// candidate won)
+    pub outcome: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rewritten: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<FallbackReason>,
+    pub flags: Vec<FlagMapping>,
+}
+
+/// What a candidate does with one flag
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FlagMapping {
+    pub flag: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mapped: Option<String>,
+    /// `mapped`, `dropped`, `passthrough`, `fallback`, `unmapped`,
+    /// `missing-value`, or `built-in` (translated by the built-in's own code)
+    pub how: &'static str,
+}
+
+impl FlagMapping {
+    pub fn new(flag: &str, value: Option<&str>, mapped: Option<&str>, how: &'static str) -> Self {
+        Self {
+            flag: flag.to_string(),
+            value: value.map(str::to_string),
+            mapped: mapped.map(str::to_string),
+            how,
+        }
+    }
+}
+
+/// Flags of `args` as a built-in sees them: its `flag_mappings` first,
+/// everything else is up to its translation code
+pub fn builtin_flags(args: &[String], replacement: &ReplacementConfig) -> Vec<FlagMapping> {
+    let (args, _) = crate::replacements::split_at_shell_operator(args);
+    flags_and_operands(args)
+        .0
+        .iter()
+        .map(|flag| match replacement.flag_mappings.get(flag) {
+            Some(mapped) if mapped.is_empty() => FlagMapping::new(flag, None, None, "dropped"),
+            Some(mapped) => FlagMapping::new(flag, None, Some(mapped), "mapped"),
+            None => FlagMapping::new(flag, None, None, "built-in"),
+        })
+        .collect()
+}
+
+/// What the engine noted during one explained call
+#[derive(Debug, Default)]
+pub struct Trace {
+    pub steps: Vec<Step>,
+    pub candidates: Vec<CandidateReport>,
+}
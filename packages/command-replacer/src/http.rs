@@ -0,0 +1,173 @@
+//! `command-replacer serve --http ADDR`: the engine behind a small JSON API
+//!
+//! Editor extensions and windows-tools can ask for rewrites without
+//! spawning a process per command:
+//!
+//! - `POST /rewrite` with `{"command": "..."}` answers
+//!   `{"rewritten": ..., "reason": ..., "construct": ...}`
+//! - `POST /explain` with the same body answers what `command-replacer
+//!   explain` prints
+//! - `GET /stats` counts what was served since the server started
+//! - `GET /health` answers `{"status": "ok", "version": ...}`
+//!
+//! There is no authentication, so only loopback addresses are served.
+
+use anyhow::{anyhow, bail, Result};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::io::Read;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use crate::service;
+
+#[derive(Deserialize)]
+struct RewriteRequest {
+    command: String,
+}
+
+/// Counters behind `GET /stats`
+#[derive(Debug, Default)]
+struct Stats {
+    requests: u64,
+    rewrites: u64,
+    unchanged: u64,
+    errors: u64,
+    /// Commands left alone, by what stopped the rewrite
+    declined: BTreeMap<String, u64>,
+    rewrite_time: Duration,
+}
+
+pub struct HttpServer {
+    server: Server,
+    project_dir: PathBuf,
+    started: Instant,
+    stats: Stats,
+}
+
+impl HttpServer {
+    /// Listen on `addr`, rewriting for sessions in `project_dir`
+    pub fn bind(addr: SocketAddr, project_dir: &Path) -> Result<Self> {
+        if !addr.ip().is_loopback() {
+            bail!("Refusing to serve on {}: the API has no authentication, so only loopback addresses are allowed", addr);
+        }
+        let server = Server::http(addr).map_err(|e| anyhow!("Failed to listen on {}: {}", addr, e))?;
+        Ok(Self {
+            server,
+            project_dir: project_dir.to_path_buf(),
+            started: Instant::now(),
+            stats: Stats::default(),
+        })
+    }
+    
+    /// Where the server ended up listening, e.g. after binding port 0
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        self.server.server_addr().to_ip()
+    }
+    
+    /// Answer requests until the listener fails
+    pub fn run(mut self) -> Result<()> {
+        loop {
+            let request = self.server.recv()?;
+            self.respond(request);
+        }
+    }
+    
+    fn respond(&mut self, mut request: Request) {
+        let (status, body) = self.route(&mut request);
+        let content_type = Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..]).expect("static header is valid");
+        let response = Response::from_string(body.to_string())
+            .with_status_code(status)
+            .with_header(content_type);
+        // A client that hung up doesn't need its answer
+        let _ = request.respond(response);
+    }
+    
+    fn route(&mut self, request: &mut Request) -> (u16, Value) {
+        self.stats.requests += 1;
+        let path = request.url().split('?').next().unwrap_or_default().to_string();
+        match (request.method(), path.as_str()) {
+            (Method::Get, "/health") => (200, json!({ "status": "ok", "version": env!("CARGO_PKG_VERSION") })),
+            (Method::Get, "/stats") => (200, self.stats_json()),
+            (Method::Post, "/rewrite") => self.rewrite(request),
+            (Method::Post, "/explain") => match read_command(request) {
+                Ok(command) => match service::explain(&self.project_dir, &command) {
+                    Ok(explanation) => (200, json!(explanation)),
+                    Err(e) => (500, error(&format!("{:#}", e))),
+                },
+                Err(response) => response,
+            },
+            (_, "/health" | "/stats" | "/rewrite" | "/explain") => (405, error("Method not allowed")),
+            _ => (404, error("Not found")),
+        }
+    }
+    
+    /// `POST /rewrite`
+    fn rewrite(&mut self, request: &mut Request) -> (u16, Value) {
+        let command = match read_command(request) {
+            Ok(command) => command,
+            Err(response) => return response,
+        };
+        
+        let started = Instant::now();
+        let optimized = service::optimize(&self.project_dir, &command);
+        self.stats.rewrite_time += started.elapsed();
+        match optimized {
+            Ok(optimized) => {
+                match (&optimized.rewritten, &optimized.fallback) {
+                    (Some(_), _) => self.stats.rewrites += 1,
+                    (None, Some(reason)) => *self.stats.declined.entry(reason.construct.clone()).or_default() += 1,
+                    (None, None) => self.stats.unchanged += 1,
+                }
+                (200, optimized.to_json())
+            }
+            Err(e) => {
+                self.stats.errors += 1;
+                (500, error(&format!("{:#}", e)))
+            }
+        }
+    }
+    
+    fn stats_json(&self) -> Value {
+        let answered = self.stats.rewrites + self.stats.unchanged + self.stats.errors + self.stats.declined.values().sum::<u64>();
+        let mean_ms = match answered {
+            0 => 0.0,
+            n => self.stats.rewrite_time.as_secs_f64() * 1e3 / n as f64,
+        };
+        json!({
+            "uptime_secs": self.started.elapsed().as_secs(),
+            "requests": self.stats.requests,
+            "rewrites": self.stats.rewrites,
+            "unchanged": self.stats.unchanged,
+            "declined": self.stats.declined,
+            "errors": self.stats.errors,
+            "mean_rewrite_ms": (mean_ms * 1e3).round() / 1e3,
+        })
+    }
+}
+
+/// The command in a `{"command": "..."}` body, or the response refusing it
+fn read_command(request: &mut Request) -> Result<String, (u16, Value)> {
+    let max_bytes = claude_hooks_sdk::max_input_bytes();
+    if request.body_length().is_some_and(|length| length as u64 > max_bytes) {
+        return Err((413, error("Request body too large")));
+    }
+    let mut body = String::new();
+    if request.as_reader().take(max_bytes + 1).read_to_string(&mut body).is_err() {
+        return Err((400, error("Request body is not UTF-8")));
+    }
+    if body.len() as u64 > max_bytes {
+        return Err((413, error("Request body too large")));
+    }
+    match serde_json::from_str::<RewriteRequest>(&body) {
+        Ok(rewrite_request) => Ok(rewrite_request.command),
+        Err(e) => Err((400, error(&format!("Expected {{\"command\": \"...\"}}: {}", e)))),
+    }
+}
+
+fn error(message: &str) -> Value {
+    json!({ "error": message })
+}
@@ -0,0 +1,239 @@
+//! `command-replacer install` / `uninstall`: (de)register the hook in
+//! Claude's settings.json
+//!
+//! Each event the binary handles gets one entry running it, added next to
+//! whatever hooks are already there. Installing again only adds what is
+//! missing, and points entries at the binary if it has moved. Uninstalling
+//! removes every hook running command-replacer and leaves the rest alone.
+//! Either way the file is backed up before it is changed and replaced
+//! atomically.
+
+use anyhow::{bail, Context, Result};
+use serde_json::{json, Map, Value};
+use std::path::{Path, PathBuf};
+
+/// Events the hook handles, with the tool matcher each is registered under
+pub const HOOK_EVENTS: &[(&str, Option<&str>)] = &[
+    ("PreToolUse", Some("Bash|Read|Grep|Glob")),
+    ("PostToolUse", Some("Bash")),
+    ("SessionStart", None),
+    ("UserPromptSubmit", None),
+    ("Stop", None),
+    ("SubagentStop", None),
+];
+
+/// What installing did to one event's registration
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Change {
+    Added,
+    /// An entry ran command-replacer from another path
+    Updated,
+}
+
+/// The settings file `install` writes: the project's when one is given,
+/// otherwise the user's
+pub fn settings_path(project: Option<&Path>) -> Result<PathBuf> {
+    match project {
+        Some(dir) => Ok(dir.join(".claude").join("settings.json")),
+        None => dirs::home_dir()
+            .map(|home| home.join(".claude").join("settings.json"))
+            .context("Failed to locate the home directory"),
+    }
+}
+
+/// The command line settings should run: `binary`, quoted when it needs it
+pub fn hook_command(binary: &Path) -> Result<String> {
+    if !binary.is_file() {
+        bail!("{} is not a file", binary.display());
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = binary.metadata().with_context(|| format!("Failed to read {}", binary.display()))?.permissions().mode();
+        if mode & 0o111 == 0 {
+            bail!("{} is not executable", binary.display());
+        }
+    }
+    let path = binary.to_str().with_context(|| format!("{} is not valid UTF-8", binary.display()))?;
+    Ok(if path.contains(char::is_whitespace) { format!("\"{}\"", path) } else { path.to_string() })
+}
+
+/// Register `command` for every hook event in `settings`, returning what changed
+pub fn register(settings: &mut Value, command: &str) -> Result<Vec<(&'static str, Change)>> {
+    let Some(root) = settings.as_object_mut() else {
+        bail!("settings are not a JSON object");
+    };
+    let Some(hooks) = root.entry("hooks").or_insert_with(|| json!({})).as_object_mut() else {
+        bail!("`hooks` is not a JSON object");
+    };
+    
+    let mut changes = Vec::new();
+    for &(event, matcher) in HOOK_EVENTS {
+        let Some(entries) = hooks.entry(event).or_insert_with(|| json!([])).as_array_mut() else {
+            bail!("`hooks.{}` is not an array", event);
+        };
+        let mut found = false;
+        let mut updated = false;
+        for hook in entries.iter_mut().filter_map(|entry| entry.get_mut("hooks")?.as_array_mut()).flatten() {
+            let Some(existing) = hook.get("command").and_then(Value::as_str).filter(|c| runs_replacer(c)) else {
+                continue;
+            };
+            found = true;
+            let repointed = repoint(existing, command);
+            if repointed != existing {
+                hook["command"] = repointed.into();
+                updated = true;
+            }
+        }
+        if updated {
+            changes.push((event, Change::Updated));
+        } else if !found {
+            let mut entry = Map::new();
+            if let Some(matcher) = matcher {
+                entry.insert("matcher".to_string(), matcher.into());
+            }
+            entry.insert("hooks".to_string(), json!([{ "type": "command", "command": command }]));
+            entries.push(entry.into());
+            changes.push((event, Change::Added));
+        }
+    }
+    Ok(changes)
+}
+
+/// Register `command` in the settings file at `path`, creating it if needed.
+/// Nothing is written when it is already registered; otherwise an existing
+/// file is first copied to `<path>.bak`.
+pub fn install_into(path: &Path, command: &str) -> Result<Vec<(&'static str, Change)>> {
+    let mut settings = read_settings(path)?;
+    let changes = register(&mut settings, command)?;
+    if !changes.is_empty() {
+        write_settings(path, &settings)?;
+    }
+    Ok(changes)
+}
+
+/// Remove every hook running command-replacer from `settings`, returning
+/// how many were removed per event. Entries and events left without hooks
+/// go too.
+pub fn deregister(settings: &mut Value) -> Vec<(String, usize)> {
+    let Some(hooks) = settings.get_mut("hooks").and_then(Value::as_object_mut) else {
+        return Vec::new();
+    };
+    
+    let mut removed = Vec::new();
+    for (event, entries) in hooks.iter_mut() {
+        let Some(entries) = entries.as_array_mut() else {
+            continue;
+        };
+        let mut count = 0;
+        for entry in entries.iter_mut() {
+            if let Some(commands) = entry.get_mut("hooks").and_then(Value::as_array_mut) {
+                let before = commands.len();
+                commands.retain(|hook| !hook.get("command").and_then(Value::as_str).is_some_and(runs_replacer));
+                count += before - commands.len();
+            }
+        }
+        if count > 0 {
+            entries.retain(|entry| entry.get("hooks").and_then(Value::as_array).is_none_or(|hooks| !hooks.is_empty()));
+            removed.push((event.clone(), count));
+        }
+    }
+    hooks.retain(|event, entries| {
+        !removed.iter().any(|(removed, _)| removed == event) || entries.as_array().is_none_or(|entries| !entries.is_empty())
+    });
+    if hooks.is_empty() && !removed.is_empty() {
+        if let Some(root) = settings.as_object_mut() {
+            root.remove("hooks");
+        }
+    }
+    removed
+}
+
+/// Deregister the hook from the settings file at `path`, which is only
+/// written (after a backup) when something was removed
+pub fn uninstall_from(path: &Path) -> Result<Vec<(String, usize)>> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let mut settings = read_settings(path)?;
+    let removed = deregister(&mut settings);
+    if !removed.is_empty() {
+        write_settings(path, &settings)?;
+    }
+    Ok(removed)
+}
+
+/// Delete everything in `dir` except `keep`, returning what was deleted.
+/// The directory itself goes too once it's empty.
+pub fn purge_dir(dir: &Path, keep: Option<&Path>) -> Result<Vec<PathBuf>> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {}", dir.display())),
+    };
+    let mut removed = Vec::new();
+    for entry in entries {
+        let path = entry.with_context(|| format!("Failed to read {}", dir.display()))?.path();
+        if keep.is_some_and(|keep| keep.starts_with(&path)) {
+            continue;
+        }
+        if path.is_dir() {
+            std::fs::remove_dir_all(&path)
+        } else {
+            std::fs::remove_file(&path)
+        }
+        .with_context(|| format!("Failed to delete {}", path.display()))?;
+        removed.push(path);
+    }
+    if std::fs::remove_dir(dir).is_ok() {
+        removed.push(dir.to_path_buf());
+    }
+    Ok(removed)
+}
+
+/// `existing` running `command`'s binary instead, keeping its arguments
+fn repoint(existing: &str, command: &str) -> String {
+    let arguments = match existing.strip_prefix('"') {
+        Some(quoted) => quoted.split_once('"').map_or("", |(_, rest)| rest),
+        None => existing.find(char::is_whitespace).map_or("", |at| &existing[at..]),
+    };
+    format!("{}{}", command, arguments)
+}
+
+/// Whether a hook command line runs this binary
+fn runs_replacer(command: &str) -> bool {
+    command.contains("command-replacer")
+}
+
+/// Settings at `path`; a missing file is an empty object
+fn read_settings(path: &Path) -> Result<Value> {
+    match std::fs::read_to_string(path) {
+        Ok(content) if content.trim().is_empty() => Ok(json!({})),
+        Ok(content) => serde_json::from_str(&content).with_context(|| format!("Failed to parse {}", path.display())),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(json!({})),
+        Err(e) => Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    }
+}
+
+/// Back up the current file, then replace it atomically
+fn write_settings(path: &Path, settings: &Value) -> Result<()> {
+    if path.exists() {
+        let backup = backup_path(path);
+        std::fs::copy(path, &backup)
+            .with_context(|| format!("Failed to back up {} to {}", path.display(), backup.display()))?;
+    } else if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    let temp = path.with_extension(format!("tmp.{}", std::process::id()));
+    std::fs::write(&temp, format!("{}\n", serde_json::to_string_pretty(settings)?))
+        .with_context(|| format!("Failed to write {}", temp.display()))?;
+    std::fs::rename(&temp, path)
+        .with_context(|| format!("Failed to replace {}", path.display()))
+}
+
+pub fn backup_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".bak");
+    path.with_file_name(name)
+}
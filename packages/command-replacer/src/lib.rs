@@ -0,0 +1,585 @@
+//! Command Replacer PreToolUse Hook
+//! 
+//! A high-performance hook that replaces common commands with faster alternatives:
+//! - grep → rg (ripgrep)
+//! - find → fd 
+//! - cat → bat (if available, fallback to cat)
+//! - ls → eza/exa (if available, fallback to ls)
+//! - sed → sd (if available, fallback to sed)
+//! - ps → procs (if available, fallback to ps)
+//! - cmd.exe's dir, type, findstr, and where → fd, bat, rg, and which (Windows)
+//! - PowerShell's Get-ChildItem, Get-Content, and Select-String → fd, bat, and rg
+//!   (inside `powershell -Command`)
+//!
+//! Commands likely to outlast the Bash tool's timeout get advice on
+//! splitting or backgrounding them, and can be wrapped in `timeout`. Read
+//! calls on very large files get advice on reading a slice instead, and
+//! Grep and Glob calls are scoped to the active package of a monorepo.
+//!
+//! SessionStart prewarms the tool cache and project context, UserPromptSubmit
+//! can advertise the installed tools, and PostToolUse events are matched back to earlier rewrites so a failed
+//! replacement can be reported along with the original command. Stop and
+//! SubagentStop summarize the session's rewrites.
+//!
+//! The binary is a thin wrapper around [`handle`], which hook-mux also
+//! calls in-process; with `--batch` it answers a stream of events (see
+//! [`batch`]).
+//!
+//! # Library use
+//!
+//! The engine also works without the hook protocol, for other hooks and
+//! tools that want the same rewrites:
+//!
+//! ```
+//! use command_replacer::{Config, ReplacementEngine};
+//!
+//! let engine = ReplacementEngine::new(Config::default())?;
+//! match engine.replace_command("grep -rn TODO src/")? {
+//!     Some(rewritten) => println!("run `{}` instead", rewritten),
+//!     None => {
+//!         if let Some(reason) = engine.last_fallback_reason() {
+//!             println!("kept as is: {}", reason.detail);
+//!         }
+//!     }
+//! }
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+//!
+//! The items re-exported at the crate root, with [`handle`] and [`hook`],
+//! are the stable API and only change incompatibly with a major version.
+//! The public modules exist for the binary and hook-mux; anything in them
+//! not re-exported here may change in any release.
+
+use anyhow::{Context, Result};
+use claude_hooks_sdk::{Event, HookInput, Session};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+mod advisor;
+pub mod batch;
+mod bench;
+mod budget;
+mod capabilities;
+pub mod cli;
+mod cmd_builtins;
+pub mod config;
+mod context;
+mod explain;
+#[cfg(feature = "native")]
+mod http;
+mod install;
+mod mcp;
+mod metrics;
+mod migrate;
+pub mod output;
+mod plugins;
+mod powershell;
+mod reads;
+mod remote;
+mod replacements;
+mod rewrite_cache;
+mod rules;
+mod scripts;
+mod search;
+#[cfg(feature = "native")]
+mod self_update;
+mod service;
+mod session;
+mod shared;
+mod shell_wrappers;
+mod show;
+mod snapshot;
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod tests;
+pub mod timings;
+mod tool_cache;
+mod tool_install;
+mod tracking;
+mod validate;
+#[cfg(feature = "wasm-plugins")]
+mod wasm_plugins;
+mod wsl;
+
+use budget::Budget;
+use config::{MetricsSettings, OutputProtocol};
+use snapshot::SessionSnapshot;
+use timings::Timings;
+use tracking::Tracker;
+
+pub use capabilities::Capability;
+pub use config::{Config, GlobalSettings, ReplacementConfig, RewriteConfig, RewriteStage, RuleConfig, WhenCondition};
+pub use context::RuleContext;
+pub use output::{HookResponse, PassReason};
+pub use replacements::{FallbackReason, ReplacementEngine};
+pub use tool_cache::{ToolCache, ToolEntry};
+
+/// Whether the session asked for the hook to stand down
+/// (`"disable_command_replacer": true` in its context)
+fn disables_replacer(session: &Session) -> bool {
+    session.context.get("disable_command_replacer").and_then(|v| v.as_bool()).unwrap_or(false)
+}
+
+/// Tool data for Bash commands
+#[derive(Debug, Deserialize)]
+struct BashToolData {
+    command: String,
+    #[serde(default)]
+    #[allow(dead_code)]
+    description: Option<String>,
+}
+
+/// Tool data for a Read call
+#[derive(Debug, Deserialize)]
+struct ReadToolData {
+    file_path: String,
+    #[serde(default)]
+    offset: Option<u64>,
+    #[serde(default)]
+    limit: Option<u64>,
+}
+
+/// Tool data for a finished Bash command
+#[derive(Debug, Deserialize)]
+struct PostToolData {
+    command: String,
+    #[serde(default, alias = "exitCode")]
+    exit_code: Option<i32>,
+    /// Interrupted or reported as an error
+    #[serde(default)]
+    errored: bool,
+}
+
+impl PostToolData {
+    /// The tool data of a PostToolUse event, with the outcome taken from the
+    /// official schema's `tool_response` when there is one.
+    ///
+    /// That response carries no exit status for Bash, so a call counts as
+    /// failed when it was `interrupted` or came back in the error shape (a
+    /// bare string, or an `error` or `is_error` field), with the status read
+    /// from its "Exit code N" when present. Output on stderr alone is not a
+    /// failure: plenty of tools log there when they succeed.
+    fn from_event(data: serde_json::Value, response: Option<&serde_json::Value>) -> Result<Self> {
+        let mut tool_data: Self = serde_json::from_value(data).context("Failed to parse tool data")?;
+        let Some(response) = response else {
+            return Ok(tool_data);
+        };
+        
+        let code = response.get("exit_code").or_else(|| response.get("exitCode")).and_then(serde_json::Value::as_i64);
+        let error = response.as_str().or_else(|| response.get("error")?.as_str());
+        let flagged = |field: &str| response.get(field).and_then(serde_json::Value::as_bool) == Some(true);
+        tool_data.exit_code = tool_data.exit_code
+            .or(code.map(|code| code as i32))
+            .or_else(|| error.and_then(error_exit_code));
+        tool_data.errored |= error.is_some() || flagged("is_error") || flagged("interrupted");
+        Ok(tool_data)
+    }
+}
+
+/// The status in an error response's "Exit code N"
+fn error_exit_code(error: &str) -> Option<i32> {
+    let (_, rest) = error.split_once("Exit code ")?;
+    let digits: String = rest.chars().take_while(|c| c.is_ascii_digit()).collect();
+    digits.parse().ok()
+}
+
+/// One handled hook event
+#[derive(Debug)]
+pub struct Handled {
+    pub response: HookResponse,
+    /// The protocol the response should be emitted in
+    pub protocol: OutputProtocol,
+    /// Where the time went, when `settings.debug` is on
+    pub timings: Option<Timings>,
+}
+
+/// Handle one hook event, given the raw JSON input, returning the response
+/// and the protocol it should be emitted in
+pub fn handle(input: &str) -> (HookResponse, OutputProtocol) {
+    let handled = parsed(Instant::now(), HookInput::parse(input));
+    (handled.response, handled.protocol)
+}
+
+/// [`handle`] for raw stdin bytes, parsed in place without a copy
+pub fn handle_slice(input: &mut [u8]) -> Handled {
+    parsed(Instant::now(), HookInput::from_slice(input))
+}
+
+fn parsed(started: Instant, input: Result<HookInput>) -> Handled {
+    match input {
+        Ok(input) => hook_at(started, input),
+        Err(e) => Handled { response: HookResponse::Error(e.to_string()), protocol: protocol_from_env(), timings: None },
+    }
+}
+
+/// Handle one parsed hook event
+pub fn hook(input: HookInput) -> (HookResponse, OutputProtocol) {
+    let handled = hook_at(Instant::now(), input);
+    (handled.response, handled.protocol)
+}
+
+/// Handle an event whose call began at `started`; in debug mode the phase
+/// timings are logged as well as returned
+fn hook_at(started: Instant, input: HookInput) -> Handled {
+    let mut call = Call {
+        protocol: protocol_from_env(),
+        timings: Timings::new(started),
+        debug: false,
+        metrics: None,
+    };
+    call.timings.mark("parse");
+    let event = input.event.event_type.clone();
+    let response = run(input, &mut call).unwrap_or_else(|e| HookResponse::Error(e.to_string()));
+    
+    if let Some(settings) = &call.metrics {
+        // Monitoring only; never worth failing the call over
+        let _ = metrics::update(settings, &event, &response, call.timings.phase("probes"));
+    }
+    let timings = call.debug.then_some(call.timings);
+    if let Some(timings) = &timings {
+        // Diagnostics only; never worth failing the call over
+        let _ = timings.log(&Config::debug_log_path(), &event, &response.reason_code());
+    }
+    Handled { response, protocol: call.protocol, timings }
+}
+
+/// What [`run`] learns about the call besides its response
+struct Call {
+    /// Updated once the config is loaded
+    protocol: OutputProtocol,
+    timings: Timings,
+    /// `settings.debug`, once the config is loaded
+    debug: bool,
+    /// `settings.metrics`, once the config is loaded and if enabled
+    metrics: Option<MetricsSettings>,
+}
+
+fn protocol_from_env() -> OutputProtocol {
+    config::output_protocol_from_env(std::env::vars()).unwrap_or_default()
+}
+
+/// Handle one hook event
+fn run(hook_input: HookInput, call: &mut Call) -> Result<HookResponse> {
+    // Session-level kill switch, checked before any config is read
+    if config::kill_switch_engaged(std::env::vars()) || disables_replacer(&hook_input.session) {
+        return Ok(HookResponse::Passthrough(PassReason::Disabled));
+    }
+    
+    // Settle whether this event is ours before touching the filesystem
+    let event = &hook_input.event;
+    let tool = tool_name(event);
+    if !handles(&event.event_type, tool.as_deref()) {
+        return Ok(HookResponse::Passthrough(PassReason::NotMatched));
+    }
+    let command = event.data.get("command").and_then(|command| command.as_str());
+    // SessionStart takes the snapshot the rest of the session reads
+    let project_dir = hook_input.project_dir();
+    let snapshot = Some(&hook_input.session.id)
+        .filter(|id| !id.is_empty() && event.event_type != "SessionStart")
+        .and_then(|id| SessionSnapshot::load(&SessionSnapshot::path(&Config::state_dir(), id), project_dir));
+    let config = match &snapshot {
+        Some(snapshot) => snapshot.layers.clone().resolve(Some(project_dir)),
+        None => Config::load_for_project(Some(project_dir)),
+    }
+    .unwrap_or_default();
+    call.protocol = config.settings.output_protocol;
+    call.debug = config.settings.debug;
+    call.metrics = Some(&config.settings.metrics).filter(|metrics| metrics.enabled).cloned();
+    call.timings.mark("config");
+    if !config.settings.event_match.matches(&event.event_type, tool.as_deref(), command)? {
+        return Ok(HookResponse::Passthrough(PassReason::NotMatched));
+    }
+    let budget = Budget::new(call.timings.started(), config.settings.latency_budget_ms);
+
+    match hook_input.event.event_type.as_str() {
+        "PreToolUse" => match tool.as_deref() {
+            Some("Bash") => pre_tool_use(config, hook_input.session, hook_input.event.data, snapshot, budget, &mut call.timings),
+            Some("Read") => pre_read(&config, hook_input.session, hook_input.event.data),
+            Some(tool @ ("Grep" | "Glob")) => pre_search(&config, tool, hook_input.session, hook_input.event.data),
+            _ => Ok(HookResponse::Passthrough(PassReason::NotMatched)),
+        },
+        "SessionStart" => session_start(&config, hook_input.session),
+        "UserPromptSubmit" => user_prompt_submit(config, hook_input.session),
+        "PostToolUse" => {
+            // The official schema reports the outcome separately from the input
+            let tool_data = PostToolData::from_event(hook_input.event.data, hook_input.event.tool_response.as_ref())?;
+            post_tool_use(hook_input.session, tool_data)
+        }
+        "Stop" | "SubagentStop" => stop(&config, hook_input.session),
+        _ => Ok(HookResponse::Passthrough(PassReason::NotMatched)),
+    }
+}
+
+/// Whether [`run`] does anything for this event and tool
+fn handles(event_type: &str, tool: Option<&str>) -> bool {
+    match event_type {
+        "PreToolUse" => matches!(tool, Some("Bash" | "Read" | "Grep" | "Glob")),
+        "PostToolUse" => tool == Some("Bash"),
+        "SessionStart" | "UserPromptSubmit" | "Stop" | "SubagentStop" => true,
+        _ => false,
+    }
+}
+
+/// The tool an event is about: named in the official schema, otherwise
+/// inferred from the tool input's shape
+fn tool_name(event: &Event) -> Option<String> {
+    if let Some(tool) = event.tool_name.as_deref().or_else(|| event.data.get("tool_name")?.as_str()) {
+        return Some(tool.to_string());
+    }
+    let has = |field: &str| event.data.get(field).is_some();
+    if has("command") {
+        Some("Bash".to_string())
+    } else if has("file_path") && !["content", "old_string", "edits"].iter().any(|field| has(field)) {
+        Some("Read".to_string())
+    } else {
+        None
+    }
+}
+
+/// Where the session's commands run: its project directory, or the hook's
+/// own working directory when the event names none
+fn project_dir(session: &Session) -> Result<PathBuf> {
+    let dir = Path::new(&session.project_dir);
+    if dir.is_dir() {
+        return Ok(dir.to_path_buf());
+    }
+    std::env::current_dir().context("Failed to read the current directory")
+}
+
+/// Rewrite the Bash command about to run
+fn pre_tool_use(
+    config: Config,
+    session: Session,
+    data: serde_json::Value,
+    snapshot: Option<SessionSnapshot>,
+    budget: Budget,
+    timings: &mut Timings,
+) -> Result<HookResponse> {
+    // Extract bash command data
+    let tool_data = BashToolData::deserialize(&data)
+        .context("Failed to parse tool data")?;
+
+    let settings = config.settings.clone();
+    // Out of time: approve as-is, saying why only in debug mode
+    let over_budget = |reason: FallbackReason| {
+        if settings.debug {
+            HookResponse::Fallback(reason)
+        } else {
+            HookResponse::Passthrough(PassReason::Declined(reason.construct))
+        }
+    };
+    if let Some(reason) = budget.check(&tool_data.command, "config load") {
+        return Ok(over_budget(reason));
+    }
+    
+    // Initialize replacement engine, reusing whatever SessionStart probed
+    let (context, tool_cache) = match snapshot {
+        Some(snapshot) => (snapshot.context, Some(snapshot.tools)),
+        None => (session::context_for(&Config::state_dir(), &session.id, &project_dir(&session)?), None),
+    };
+    let tool_cache = match tool_cache {
+        _ if !settings.cache_tool_checks => ToolCache::default(),
+        Some(tool_cache) => tool_cache,
+        None => ToolCache::load(&ToolCache::default_path()),
+    };
+    let engine = ReplacementEngine::for_context(config, context)?
+        .with_tool_cache(tool_cache)
+        .with_deadline(budget.deadline());
+    timings.mark("detect");
+    if let Some(reason) = budget.check(&tool_data.command, "project detection") {
+        return Ok(over_budget(reason));
+    }
+
+    // Apply command replacements
+    let ask = replacements::matching_command_pattern(&settings.ask, &tool_data.command)?
+        .map(|pattern| format!("`{}` needs confirmation (matched `{}` in settings.ask)", tool_data.command, pattern));
+    let replaced = engine.replace_command(&tool_data.command)?;
+    let advice = advisor::detect(&tool_data.command)
+        .filter(|_| settings.long_running.advise || settings.long_running.auto_timeout.is_some())
+        .map(|advice| advice.message());
+    let timeout = settings.long_running.auto_timeout
+        .filter(|_| advice.is_some() && tool_cache::find_program("timeout").is_some());
+    timings.mark("rewrite");
+    timings.split_off("probes", engine.probe_time());
+    if let Some(reason) = budget.check(&tool_data.command, "rewriting") {
+        return Ok(over_budget(reason));
+    }
+    
+    let message = replaced.as_ref().and_then(|new_command| settings.replacement_message(&tool_data.command, new_command));
+    let new_command = match timeout {
+        Some(secs) => Some(advisor::with_timeout(replaced.as_deref().unwrap_or(&tool_data.command), secs)),
+        None => replaced,
+    };
+    
+    let response = match (new_command, advice) {
+        (Some(new_command), advice) => {
+            // Tracking is best-effort; it must never block the rewrite
+            let _ = Tracker::new(Config::state_dir()).record_rewrite(&session.id, &tool_data.command, &new_command);
+            let message = match (message, advice) {
+                (Some(message), Some(advice)) => Some(format!("{}. {}", message, advice)),
+                (message, advice) => message.or(advice),
+            };
+            HookResponse::Rewrite {
+                message,
+                original: tool_data.command.clone(),
+                command: new_command,
+                tool_input: data.clone(),
+            }
+        }
+        // Claude decides how to run it, so the advice goes to Claude
+        (None, Some(advice)) => HookResponse::Context {
+            event: "PreToolUse".to_string(),
+            context: advice,
+        },
+        (None, None) => {
+            let reason = engine.last_fallback_reason();
+            if let Some(reason) = &reason {
+                let _ = Tracker::new(Config::state_dir()).record_fallback(&session.id, &reason.command, &reason.construct);
+            }
+            let install = reason.as_ref()
+                .filter(|reason| settings.install_hints && reason.construct == "tool-missing")
+                .and_then(|reason| engine.replacement_tool(&reason.command))
+                .and_then(tool_install::install_command)
+                .filter(|install| tool_install::first_hint(&Config::state_dir(), &session.id, &install.tool));
+            match (reason, install) {
+                (Some(reason), _) if settings.debug => HookResponse::Fallback(reason),
+                (Some(reason), Some(install)) => HookResponse::Notice(format!(
+                    "{} is not installed, so `{}` commands run unchanged. Install it with `{}` to have them rewritten.",
+                    install.tool, reason.command, install,
+                )),
+                (Some(reason), None) => HookResponse::Passthrough(PassReason::Declined(reason.construct)),
+                // No replacement needed, allow as-is
+                (None, _) => HookResponse::Passthrough(PassReason::NoChange),
+            }
+        }
+    };
+    
+    // Listed commands go to the user, carrying any rewrite along
+    let response = match (ask, response) {
+        (None, response) => response,
+        (Some(reason), HookResponse::Rewrite { command, message, .. }) => HookResponse::Ask {
+            reason: match message {
+                Some(message) => format!("{}. {}", reason, message),
+                None => reason,
+            },
+            original: tool_data.command,
+            command: Some(command),
+            tool_input: data,
+        },
+        (Some(reason), _) => HookResponse::Ask {
+            reason,
+            original: tool_data.command,
+            command: None,
+            tool_input: data,
+        },
+    };
+
+    Ok(response)
+}
+
+/// Steer a Read of a very large file towards a slice or an extraction
+fn pre_read(config: &Config, session: Session, data: serde_json::Value) -> Result<HookResponse> {
+    let tool_data = ReadToolData::deserialize(&data)
+        .context("Failed to parse tool data")?;
+    
+    let project_dir = Path::new(&session.project_dir);
+    // Already reading a slice
+    if tool_data.offset.is_some() || tool_data.limit.is_some() {
+        return Ok(HookResponse::Passthrough(PassReason::NoChange));
+    }
+    
+    let response = match reads::advice(Path::new(&tool_data.file_path), project_dir, &config.settings.read) {
+        Some(advice) => HookResponse::Context {
+            event: "PreToolUse".to_string(),
+            context: advice,
+        },
+        None => HookResponse::Passthrough(PassReason::NoChange),
+    };
+    Ok(response)
+}
+
+/// Fill defaults into a Grep or Glob call
+fn pre_search(config: &Config, tool: &str, session: Session, data: serde_json::Value) -> Result<HookResponse> {
+    let cwd = Path::new(&session.project_dir);
+    let root = search::repo_root(cwd);
+    let context = session::context_for(&Config::state_dir(), &session.id, &root);
+    let response = match search::tune(tool, &data, cwd, &root, &context, &config.settings.search) {
+        Some((tool_input, context)) => HookResponse::UpdateInput { tool_input, context },
+        None => HookResponse::Passthrough(PassReason::NoChange),
+    };
+    Ok(response)
+}
+
+/// Probe tools and detect the project up front, and snapshot them with the
+/// merged config, so PreToolUse calls don't have to
+fn session_start(config: &Config, session: Session) -> Result<HookResponse> {
+    let project_dir = Path::new(&session.project_dir);
+    let state_dir = Config::state_dir();
+    let tools = session::prewarm(
+        config,
+        &session.id,
+        project_dir,
+        &state_dir,
+        &ToolCache::default_path(),
+    )?;
+    if !session.id.is_empty() {
+        let layers = Config::load_layers(Some(project_dir))?;
+        let context = session::context_for(&state_dir, &session.id, project_dir);
+        SessionSnapshot::new(project_dir, layers, context, tools)
+            .save(&SessionSnapshot::path(&state_dir, &session.id))?;
+    }
+    
+    Ok(HookResponse::Passthrough(PassReason::NoChange))
+}
+
+/// Describe the installed fast tools, when `settings.prompt_context` is on
+fn user_prompt_submit(config: Config, session: Session) -> Result<HookResponse> {
+    if !config.settings.prompt_context {
+        return Ok(HookResponse::Passthrough(PassReason::NoChange));
+    }
+    
+    let context = session::context_for(&Config::state_dir(), &session.id, &project_dir(&session)?);
+    let engine = ReplacementEngine::for_context(config, context)?
+        .with_tool_cache(ToolCache::load(&ToolCache::default_path()));
+    
+    let response = match session::tooling_context(&engine.available_replacements()?) {
+        Some(context) => HookResponse::Context {
+            event: "UserPromptSubmit".to_string(),
+            context,
+        },
+        None => HookResponse::Passthrough(PassReason::NoChange),
+    };
+    
+    Ok(response)
+}
+
+/// Match a finished command to the rewrite that produced it and, if the
+/// replacement failed, suggest retrying with the original
+fn post_tool_use(session: Session, tool_data: PostToolData) -> Result<HookResponse> {
+    let outcome = Tracker::new(Config::state_dir())
+        .record_outcome(&session.id, &tool_data.command, tool_data.exit_code, tool_data.errored)?;
+    let response = match outcome.filter(|outcome| outcome.failed()) {
+        Some(outcome) => HookResponse::Context {
+            event: "PostToolUse".to_string(),
+            context: outcome.retry_context(),
+        },
+        None => HookResponse::Passthrough(PassReason::NoChange),
+    };
+    
+    Ok(response)
+}
+
+/// Summarize the session's rewrites, flush the new totals to the history
+/// store, and fold journaled rewrite decisions into the rewrite cache
+fn stop(config: &Config, session: Session) -> Result<HookResponse> {
+    let tracker = Tracker::new(Config::state_dir());
+    tracker.flush(&session.id, &Config::history_path())?;
+    replacements::flush_rewrite_cache(&config.settings.rewrite_cache)?;
+    let response = match tracker.stats(&session.id)?.summary() {
+        Some(summary) => HookResponse::Notice(summary),
+        None => HookResponse::Passthrough(PassReason::NoChange),
+    };
+    
+    Ok(response)
+}
@@ -0,0 +1,151 @@
+//! `command-replacer mcp-serve`: the engine as Model Context Protocol tools
+//!
+//! Speaks JSON-RPC 2.0 over stdio, one message per line, so Claude can ask
+//! the engine directly (`optimize_command`, `check_tool`) instead of only
+//! meeting it through the hook.
+
+use anyhow::{Context, Result};
+use serde_json::{json, Value};
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+
+use crate::service;
+
+/// Protocol revisions this server speaks, newest first
+const PROTOCOL_VERSIONS: &[&str] = &["2025-06-18", "2025-03-26", "2024-11-05"];
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+/// Answer requests read from `reader` until it ends
+pub fn serve(reader: impl BufRead, mut writer: impl Write, project_dir: &Path) -> Result<()> {
+    let server = McpServer::new(project_dir);
+    for line in reader.lines() {
+        let line = line.context("Failed to read from stdin")?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = match serde_json::from_str::<Value>(&line) {
+            Ok(message) => server.handle(&message),
+            Err(e) => Some(error(Value::Null, PARSE_ERROR, &e.to_string())),
+        };
+        if let Some(response) = response {
+            writeln!(writer, "{}", response).context("Failed to write to stdout")?;
+            writer.flush().context("Failed to write to stdout")?;
+        }
+    }
+    Ok(())
+}
+
+pub struct McpServer {
+    project_dir: PathBuf,
+}
+
+impl McpServer {
+    /// Serve rewrites for commands run in `project_dir`
+    pub fn new(project_dir: &Path) -> Self {
+        Self { project_dir: project_dir.to_path_buf() }
+    }
+    
+    /// The response to one message; notifications get none
+    pub fn handle(&self, message: &Value) -> Option<Value> {
+        let id = message.get("id").cloned();
+        let Some(method) = message.get("method").and_then(Value::as_str) else {
+            // Replies to requests we never send need no answer either
+            if message.get("result").is_some() || message.get("error").is_some() {
+                return None;
+            }
+            return Some(error(id.unwrap_or(Value::Null), INVALID_REQUEST, "Missing method"));
+        };
+        let id = id?;
+        let params = message.get("params").cloned().unwrap_or(Value::Null);
+        
+        let result = match method {
+            "initialize" => Ok(initialize(&params)),
+            "ping" => Ok(json!({})),
+            "tools/list" => Ok(json!({ "tools": tool_definitions() })),
+            "tools/call" => self.call_tool(&params),
+            _ => Err((METHOD_NOT_FOUND, format!("Unknown method: {}", method))),
+        };
+        Some(match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err((code, message)) => error(id, code, &message),
+        })
+    }
+    
+    /// `tools/call`: run one tool; failures inside the tool are reported in
+    /// its result, as MCP asks
+    fn call_tool(&self, params: &Value) -> Result<Value, (i64, String)> {
+        let name = params.get("name").and_then(Value::as_str).unwrap_or_default();
+        let arguments = params.get("arguments").cloned().unwrap_or(Value::Null);
+        let argument = |key: &str| {
+            arguments.get(key)
+                .and_then(Value::as_str)
+                .map(str::to_string)
+                .ok_or((INVALID_PARAMS, format!("{} needs a string `{}` argument", name, key)))
+        };
+        
+        let outcome = match name {
+            "optimize_command" => service::optimize(&self.project_dir, &argument("command")?).map(|optimized| optimized.to_json()),
+            "check_tool" => service::check_tool(&self.project_dir, &argument("name")?),
+            _ => return Err((INVALID_PARAMS, format!("Unknown tool: {}", name))),
+        };
+        Ok(match outcome {
+            Ok(structured) => json!({
+                "content": [{ "type": "text", "text": structured.to_string() }],
+                "structuredContent": structured,
+                "isError": false,
+            }),
+            Err(e) => json!({
+                "content": [{ "type": "text", "text": format!("{:#}", e) }],
+                "isError": true,
+            }),
+        })
+    }
+}
+
+/// `initialize`: agree on the client's protocol revision when we speak it
+fn initialize(params: &Value) -> Value {
+    let requested = params.get("protocolVersion").and_then(Value::as_str);
+    let version = requested
+        .filter(|requested| PROTOCOL_VERSIONS.contains(requested))
+        .unwrap_or(PROTOCOL_VERSIONS[0]);
+    json!({
+        "protocolVersion": version,
+        "capabilities": { "tools": {} },
+        "serverInfo": { "name": "command-replacer", "version": env!("CARGO_PKG_VERSION") },
+    })
+}
+
+fn tool_definitions() -> Value {
+    json!([
+        {
+            "name": "optimize_command",
+            "description": "Rewrite a shell command to use faster modern tools (rg, fd, bat, eza, ...) the way the command-replacer hook would. Returns the rewritten command, or null with the reason it was left alone.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "command": { "type": "string", "description": "Shell command to optimize" },
+                },
+                "required": ["command"],
+            },
+        },
+        {
+            "name": "check_tool",
+            "description": "Check whether a command-line tool is installed, where, and at which version.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "name": { "type": "string", "description": "Tool name, e.g. rg" },
+                },
+                "required": ["name"],
+            },
+        },
+    ])
+}
+
+fn error(id: Value, code: i64, message: &str) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+}
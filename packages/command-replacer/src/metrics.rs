@@ -0,0 +1,227 @@
+//! Opt-in counters for watching the hook across a fleet of machines
+//!
+//! Every hook call is its own process, so the counters live in a small JSON
+//! file that each call updates after it has responded. From there they are
+//! exported as a Prometheus text file for node_exporter's textfile
+//! collector, and/or pushed as cumulative OTLP metrics on Stop. Updates are
+//! best-effort: two calls finishing at the same instant may lose a count,
+//! which monitoring tolerates better than a slower hook.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::config::{Config, MetricsSettings};
+use crate::output::{HookResponse, PassReason};
+
+/// Upper bounds (seconds) of the probe latency histogram buckets
+pub const PROBE_BUCKETS: &[f64] = &[0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0];
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Metrics {
+    /// When counting began, for cumulative OTLP sums
+    pub started_unix_nanos: u64,
+    /// Calls by hook event name
+    pub events: BTreeMap<String, u64>,
+    pub rewrites: u64,
+    /// Declined rewrites by construct (`tool-missing`, `address-range`, ...)
+    pub fallbacks: BTreeMap<String, u64>,
+    pub errors: u64,
+    /// Per-bucket (not cumulative) counts of calls that probed tools, with
+    /// one more bucket for anything slower than the last bound
+    pub probe_buckets: Vec<u64>,
+    pub probe_seconds_sum: f64,
+}
+
+impl Metrics {
+    /// Counters at `path`; a missing or unreadable file starts from zero
+    pub fn load(path: &Path) -> Self {
+        let mut metrics: Self = std::fs::read_to_string(path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        if metrics.started_unix_nanos == 0 {
+            metrics.started_unix_nanos = unix_nanos();
+        }
+        metrics.probe_buckets.resize(PROBE_BUCKETS.len() + 1, 0);
+        metrics
+    }
+    
+    pub fn save(&self, path: &Path) -> Result<()> {
+        write_atomically(path, &serde_json::to_string(self)?)
+    }
+    
+    /// Count one call; `probes` is the time it spent probing tools
+    pub fn record(&mut self, event: &str, response: &HookResponse, probes: Duration) {
+        *self.events.entry(event.to_string()).or_default() += 1;
+        match response {
+            HookResponse::Rewrite { .. } => self.rewrites += 1,
+            HookResponse::Fallback(reason) => *self.fallbacks.entry(reason.construct.clone()).or_default() += 1,
+            HookResponse::Passthrough(PassReason::Declined(construct)) => *self.fallbacks.entry(construct.clone()).or_default() += 1,
+            HookResponse::Error(_) => self.errors += 1,
+            _ => {}
+        }
+        if !probes.is_zero() {
+            let seconds = probes.as_secs_f64();
+            let bucket = PROBE_BUCKETS.iter().position(|bound| seconds <= *bound).unwrap_or(PROBE_BUCKETS.len());
+            self.probe_buckets[bucket] += 1;
+            self.probe_seconds_sum += seconds;
+        }
+    }
+    
+    fn probe_count(&self) -> u64 {
+        self.probe_buckets.iter().sum()
+    }
+    
+    /// Prometheus text exposition format
+    pub fn to_prometheus(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "# HELP command_replacer_events_total Hook events handled, by event.");
+        let _ = writeln!(out, "# TYPE command_replacer_events_total counter");
+        for (event, count) in &self.events {
+            let _ = writeln!(out, "command_replacer_events_total{{event=\"{}\"}} {}", escape_label(event), count);
+        }
+        let _ = writeln!(out, "# HELP command_replacer_rewrites_total Commands rewritten.");
+        let _ = writeln!(out, "# TYPE command_replacer_rewrites_total counter");
+        let _ = writeln!(out, "command_replacer_rewrites_total {}", self.rewrites);
+        let _ = writeln!(out, "# HELP command_replacer_fallbacks_total Rewrites declined, by reason.");
+        let _ = writeln!(out, "# TYPE command_replacer_fallbacks_total counter");
+        for (reason, count) in &self.fallbacks {
+            let _ = writeln!(out, "command_replacer_fallbacks_total{{reason=\"{}\"}} {}", escape_label(reason), count);
+        }
+        let _ = writeln!(out, "# HELP command_replacer_errors_total Hook calls that failed.");
+        let _ = writeln!(out, "# TYPE command_replacer_errors_total counter");
+        let _ = writeln!(out, "command_replacer_errors_total {}", self.errors);
+        let _ = writeln!(out, "# HELP command_replacer_probe_duration_seconds Time a call spent probing tools.");
+        let _ = writeln!(out, "# TYPE command_replacer_probe_duration_seconds histogram");
+        let mut cumulative = 0;
+        for (bound, count) in PROBE_BUCKETS.iter().zip(&self.probe_buckets) {
+            cumulative += count;
+            let _ = writeln!(out, "command_replacer_probe_duration_seconds_bucket{{le=\"{}\"}} {}", bound, cumulative);
+        }
+        let _ = writeln!(out, "command_replacer_probe_duration_seconds_bucket{{le=\"+Inf\"}} {}", self.probe_count());
+        let _ = writeln!(out, "command_replacer_probe_duration_seconds_sum {}", self.probe_seconds_sum);
+        let _ = writeln!(out, "command_replacer_probe_duration_seconds_count {}", self.probe_count());
+        out
+    }
+    
+    /// OTLP/JSON `ExportMetricsServiceRequest` with cumulative temporality
+    pub fn to_otlp(&self) -> serde_json::Value {
+        let start = self.started_unix_nanos.to_string();
+        let now = unix_nanos().to_string();
+        let sum = |name: &str, description: &str, points: Vec<(Option<(&str, &str)>, u64)>| {
+            let points: Vec<_> = points
+                .into_iter()
+                .map(|(attribute, value)| json!({
+                    "attributes": attribute.map(|(key, value)| vec![json!({ "key": key, "value": { "stringValue": value } })]).unwrap_or_default(),
+                    "startTimeUnixNano": start,
+                    "timeUnixNano": now,
+                    "asInt": value.to_string(),
+                }))
+                .collect();
+            json!({
+                "name": name,
+                "description": description,
+                "sum": { "aggregationTemporality": 2, "isMonotonic": true, "dataPoints": points },
+            })
+        };
+        
+        let metrics = vec![
+            sum("command_replacer.events", "Hook events handled, by event", self.events.iter().map(|(event, count)| (Some(("event", event.as_str())), *count)).collect()),
+            sum("command_replacer.rewrites", "Commands rewritten", vec![(None, self.rewrites)]),
+            sum("command_replacer.fallbacks", "Rewrites declined, by reason", self.fallbacks.iter().map(|(reason, count)| (Some(("reason", reason.as_str())), *count)).collect()),
+            sum("command_replacer.errors", "Hook calls that failed", vec![(None, self.errors)]),
+            json!({
+                "name": "command_replacer.probe.duration",
+                "description": "Time a call spent probing tools",
+                "unit": "s",
+                "histogram": {
+                    "aggregationTemporality": 2,
+                    "dataPoints": [{
+                        "startTimeUnixNano": start,
+                        "timeUnixNano": now,
+                        "count": self.probe_count().to_string(),
+                        "sum": self.probe_seconds_sum,
+                        "bucketCounts": self.probe_buckets.iter().map(u64::to_string).collect::<Vec<_>>(),
+                        "explicitBounds": PROBE_BUCKETS,
+                    }],
+                },
+            }),
+        ];
+        let host = std::env::var("HOSTNAME").or_else(|_| std::env::var("COMPUTERNAME")).unwrap_or_default();
+        json!({
+            "resourceMetrics": [{
+                "resource": { "attributes": [
+                    { "key": "service.name", "value": { "stringValue": "command-replacer" } },
+                    { "key": "host.name", "value": { "stringValue": host } },
+                ]},
+                "scopeMetrics": [{
+                    "scope": { "name": "command-replacer", "version": env!("CARGO_PKG_VERSION") },
+                    "metrics": metrics,
+                }],
+            }],
+        })
+    }
+}
+
+/// Count one call and export the counters as `settings` asks
+pub fn update(settings: &MetricsSettings, event: &str, response: &HookResponse, probes: Duration) -> Result<()> {
+    let path = Config::metrics_path();
+    let mut metrics = Metrics::load(&path);
+    metrics.record(event, response, probes);
+    metrics.save(&path)?;
+    
+    if let Some(textfile) = &settings.textfile {
+        write_atomically(textfile, &metrics.to_prometheus())?;
+    }
+    // Once per turn is plenty for a collector, and keeps the network off
+    // the PreToolUse path
+    if let Some(endpoint) = settings.otlp_endpoint.as_deref().filter(|_| event == "Stop") {
+        push_otlp(&metrics, endpoint)?;
+    }
+    Ok(())
+}
+
+/// Push the counters to an OTLP/HTTP collector
+#[cfg(feature = "native")]
+pub fn push_otlp(metrics: &Metrics, endpoint: &str) -> Result<()> {
+    ureq::AgentBuilder::new()
+        .timeout(Duration::from_secs(1))
+        .build()
+        .post(endpoint)
+        .set("Content-Type", "application/json")
+        .send_string(&metrics.to_otlp().to_string())
+        .with_context(|| format!("Failed to push metrics to {}", endpoint))?;
+    Ok(())
+}
+
+#[cfg(not(feature = "native"))]
+pub fn push_otlp(_metrics: &Metrics, endpoint: &str) -> Result<()> {
+    anyhow::bail!("Pushing metrics to {} needs a build with the `native` feature", endpoint)
+}
+
+/// Write `content` next to `path` and rename it into place, so collectors
+/// never read a half-written file
+pub fn write_atomically(path: &Path, content: &str) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    let temp = path.with_extension(format!("tmp.{}", std::process::id()));
+    std::fs::write(&temp, content)
+        .with_context(|| format!("Failed to write {}", temp.display()))?;
+    std::fs::rename(&temp, path)
+        .with_context(|| format!("Failed to replace {}", path.display()))
+}
+
+fn escape_label(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+fn unix_nanos() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_nanos() as u64).unwrap_or(0)
+}
@@ -0,0 +1,97 @@
+//! Config layout versioning and migration
+//!
+//! Every structural change to `Config` bumps `CONFIG_VERSION` and adds a step
+//! here, so configs written by older releases keep loading instead of
+//! silently falling back to defaults.
+
+use anyhow::{bail, Context, Result};
+use std::path::{Path, PathBuf};
+
+use crate::config::ConfigFormat;
+
+/// Layout version written by this release
+pub const CONFIG_VERSION: u32 = 1;
+
+/// Settings that lived at the top level before they moved under `[settings]`
+const LEGACY_TOP_LEVEL_SETTINGS: &[&str] = &[
+    "compatibility_mode",
+    "semantic_analysis",
+    "fallback_patterns",
+    "debug",
+    "cache_tool_checks",
+    "tool_check_timeout",
+];
+
+/// Layout version of a parsed config; unversioned files are version 0
+pub fn config_version(value: &toml::Value) -> Result<u32> {
+    match value.get("version") {
+        None => Ok(0),
+        Some(toml::Value::Integer(version)) => u32::try_from(*version)
+            .with_context(|| format!("Invalid config version {}", version)),
+        Some(other) => bail!("Config version must be an integer, found {}", other),
+    }
+}
+
+/// Upgrade a parsed config to `CONFIG_VERSION` in place.
+///
+/// Returns whether anything changed. Configs from a newer release are an
+/// error rather than being loaded with unknown semantics.
+pub fn migrate(value: &mut toml::Value) -> Result<bool> {
+    let from = config_version(value)?;
+    if from > CONFIG_VERSION {
+        bail!(
+            "Config version {} is newer than this command-replacer supports ({}); upgrade the binary",
+            from,
+            CONFIG_VERSION
+        );
+    }
+    if from == CONFIG_VERSION {
+        return Ok(false);
+    }
+    
+    let Some(table) = value.as_table_mut() else {
+        bail!("Config root must be a table");
+    };
+    
+    // 0 -> 1: hoist top-level settings into [settings]
+    if from < 1 {
+        for key in LEGACY_TOP_LEVEL_SETTINGS {
+            let Some(setting) = table.remove(*key) else {
+                continue;
+            };
+            let settings = table
+                .entry("settings")
+                .or_insert_with(|| toml::Value::Table(toml::Table::new()));
+            if let Some(settings) = settings.as_table_mut() {
+                // An explicit [settings] entry wins over the legacy location
+                settings.entry(key.to_string()).or_insert(setting);
+            }
+        }
+    }
+    
+    table.insert("version".to_string(), toml::Value::Integer(CONFIG_VERSION.into()));
+    Ok(true)
+}
+
+/// Migrate a config file on disk, keeping the original next to it as
+/// `<name>.v<old>.bak`. Returns the migrated value.
+pub fn migrate_file(path: &Path, format: ConfigFormat, mut value: toml::Value) -> Result<toml::Value> {
+    let from = config_version(&value)?;
+    if !migrate(&mut value)? {
+        return Ok(value);
+    }
+    
+    let backup = backup_path(path, from);
+    std::fs::copy(path, &backup)
+        .with_context(|| format!("Failed to back up {} to {}", path.display(), backup.display()))?;
+    std::fs::write(path, format.serialize(&value)?)
+        .with_context(|| format!("Failed to write migrated config {}", path.display()))?;
+    
+    Ok(value)
+}
+
+fn backup_path(path: &Path, version: u32) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".v{}.bak", version));
+    path.with_file_name(name)
+}
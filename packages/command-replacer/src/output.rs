@@ -0,0 +1,315 @@
+//! Hook responses and the wire formats they are emitted in
+//!
+//! The engine decides *what* happened ([`HookResponse`]); the configured
+//! [`OutputProtocol`] decides how that is communicated to Claude Code.
+
+use anyhow::Result;
+use claude_hooks_sdk::{Decision, HookOutput};
+
+use crate::config::OutputProtocol;
+use crate::replacements::FallbackReason;
+use crate::timings::Timings;
+
+/// Outcome of handling one hook event
+#[derive(Debug, Clone, PartialEq)]
+pub enum HookResponse {
+    /// Nothing to do; let the tool call proceed unchanged
+    Passthrough(PassReason),
+    /// The command was rewritten
+    Rewrite {
+        original: String,
+        command: String,
+        message: Option<String>,
+        /// The Bash tool input the command came from, so the rewrite keeps
+        /// its description, timeout, and so on
+        tool_input: serde_json::Value,
+    },
+    /// Defaults were filled into a non-Bash tool's input
+    UpdateInput {
+        /// The complete updated input
+        tool_input: serde_json::Value,
+        /// What changed, for Claude
+        context: String,
+    },
+    /// The user has to confirm the command, which may also be rewritten
+    Ask {
+        reason: String,
+        original: String,
+        /// The rewrite, if there is one
+        command: Option<String>,
+        tool_input: serde_json::Value,
+    },
+    /// A rewrite was declined; only reported in debug mode
+    Fallback(FallbackReason),
+    /// Extra context for Claude about the named hook event
+    Context {
+        event: String,
+        context: String,
+    },
+    /// A message for the user rather than for Claude
+    Notice(String),
+    /// The hook failed; the tool call still proceeds
+    Error(String),
+}
+
+/// Why a call was let through unchanged
+#[derive(Debug, Clone, PartialEq)]
+pub enum PassReason {
+    /// Nothing to rewrite or add
+    NoChange,
+    /// Switched off by the kill switch or the session
+    Disabled,
+    /// Outside `settings.match`, or an event or tool this hook ignores
+    NotMatched,
+    /// A rewrite was declined; holds the [`FallbackReason::construct`]
+    Declined(String),
+    /// The event was too large to inspect
+    InputTooLarge,
+}
+
+/// What to write and how to exit
+#[derive(Debug, Clone, PartialEq)]
+pub struct Emission {
+    pub stdout: Option<String>,
+    pub stderr: Option<String>,
+    pub exit_code: i32,
+}
+
+impl HookResponse {
+    /// Machine-readable summary of why the engine responded this way, e.g.
+    /// `REWRITTEN`, `FALLBACK_PCRE`, or `TOOL_MISSING`
+    pub fn reason_code(&self) -> String {
+        match self {
+            HookResponse::Passthrough(PassReason::NoChange) => "NO_CHANGE".to_string(),
+            HookResponse::Passthrough(PassReason::Disabled) => "DISABLED".to_string(),
+            HookResponse::Passthrough(PassReason::NotMatched) => "NOT_MATCHED".to_string(),
+            HookResponse::Passthrough(PassReason::Declined(construct)) => fallback_code(construct),
+            HookResponse::Passthrough(PassReason::InputTooLarge) => "INPUT_TOO_LARGE".to_string(),
+            HookResponse::Rewrite { .. } => "REWRITTEN".to_string(),
+            HookResponse::UpdateInput { .. } => "INPUT_UPDATED".to_string(),
+            HookResponse::Ask { .. } => "CONFIRMATION_REQUIRED".to_string(),
+            HookResponse::Fallback(reason) => fallback_code(&reason.construct),
+            HookResponse::Context { .. } => "CONTEXT".to_string(),
+            HookResponse::Notice(_) => "NOTICE".to_string(),
+            HookResponse::Error(_) => "ERROR".to_string(),
+        }
+    }
+    
+    /// Render this response in the given protocol
+    pub fn render(&self, protocol: OutputProtocol) -> Result<Emission> {
+        self.render_timed(protocol, None)
+    }
+    
+    /// [`render`](Self::render), adding `timings` to the context where the
+    /// protocol has one (the legacy decision)
+    pub fn render_timed(&self, protocol: OutputProtocol, timings: Option<&Timings>) -> Result<Emission> {
+        match protocol {
+            OutputProtocol::LegacyDecision => self.render_legacy(timings),
+            OutputProtocol::HookSpecificOutput => self.render_hook_specific(),
+            OutputProtocol::ExitCode => Ok(self.render_exit_code()),
+        }
+    }
+    
+    /// Print the rendered response and return the process exit code
+    pub fn emit(&self, protocol: OutputProtocol, timings: Option<&Timings>) -> i32 {
+        let emission = self.render_timed(protocol, timings).unwrap_or_else(|e| Emission {
+            stdout: None,
+            stderr: Some(format!("Hook error: {}", e)),
+            exit_code: 0,
+        });
+        if let Some(stderr) = &emission.stderr {
+            eprintln!("{}", stderr);
+        }
+        if let Some(stdout) = &emission.stdout {
+            println!("{}", stdout);
+        }
+        emission.exit_code
+    }
+    
+    /// `{"decision": "approve", "message": ..., "context": {...}}`, with a
+    /// `reason_code` in every context
+    fn render_legacy(&self, timings: Option<&Timings>) -> Result<Emission> {
+        let mut output = match self {
+            HookResponse::Passthrough(_) => HookOutput {
+                decision: Decision::Approve,
+                message: None,
+                context: None,
+                ..HookOutput::approve()
+            },
+            HookResponse::Rewrite { original, command, message, .. } => HookOutput {
+                decision: Decision::Approve,
+                message: message.clone(),
+                context: Some(serde_json::json!({
+                    "modified_command": command,
+                    "original_command": original,
+                })),
+                ..HookOutput::approve()
+            },
+            // Tell the user which construct cost them the rewrite
+            HookResponse::Fallback(reason) => HookOutput {
+                decision: Decision::Approve,
+                message: None,
+                context: Some(serde_json::json!({
+                    "fallback_reason": reason,
+                })),
+                ..HookOutput::approve()
+            },
+            HookResponse::Context { context, .. } => HookOutput {
+                decision: Decision::Approve,
+                message: None,
+                context: Some(serde_json::json!({
+                    "additional_context": context,
+                })),
+                ..HookOutput::approve()
+            },
+            HookResponse::UpdateInput { tool_input, context } => HookOutput {
+                decision: Decision::Approve,
+                message: None,
+                context: Some(serde_json::json!({
+                    "updated_input": tool_input,
+                    "additional_context": context,
+                })),
+                ..HookOutput::approve()
+            },
+            HookResponse::Ask { reason, original, command, .. } => HookOutput {
+                decision: Decision::Ask,
+                message: Some(reason.clone()),
+                context: command.as_ref().map(|command| serde_json::json!({
+                    "modified_command": command,
+                    "original_command": original,
+                })),
+                ..HookOutput::approve()
+            },
+            HookResponse::Notice(notice) => HookOutput {
+                decision: Decision::Approve,
+                message: Some(notice.clone()),
+                context: None,
+                ..HookOutput::approve()
+            },
+            // On error, allow the operation to continue
+            HookResponse::Error(error) => HookOutput {
+                decision: Decision::Approve,
+                message: Some(format!("Command replacer hook error: {}", error)),
+                context: None,
+                ..HookOutput::approve()
+            },
+        };
+        let context = output.context.get_or_insert_with(|| serde_json::json!({}));
+        context["reason_code"] = self.reason_code().into();
+        if let Some(timings) = timings {
+            context["timings"] = timings.to_json();
+        }
+        
+        Ok(Emission {
+            stdout: Some(serde_json::to_string(&output)?),
+            stderr: matches!(self, HookResponse::Error(_)).then(|| format!("Hook error: {}", self.error_text())),
+            exit_code: 0,
+        })
+    }
+    
+    /// `{"hookSpecificOutput": {"hookEventName": "PreToolUse", ...}}`.
+    /// Passthrough prints nothing, leaving the normal permission flow alone.
+    /// A rewrite replaces the tool input through `updatedInput`, so the new
+    /// command is the one that actually runs, and its message becomes a
+    /// `systemMessage` the user sees but Claude doesn't.
+    fn render_hook_specific(&self) -> Result<Emission> {
+        let mut note = None;
+        let specific = match self {
+            HookResponse::Passthrough(_) => return Ok(Emission { stdout: None, stderr: None, exit_code: 0 }),
+            HookResponse::Error(_) => {
+                return Ok(Emission {
+                    stdout: None,
+                    stderr: Some(format!("Hook error: {}", self.error_text())),
+                    exit_code: 0,
+                });
+            }
+            HookResponse::Rewrite { command, message, tool_input, .. } => {
+                let mut updated = tool_input.as_object().cloned().unwrap_or_default();
+                updated.insert("command".to_string(), command.clone().into());
+                note = message.clone();
+                serde_json::json!({
+                    "hookEventName": "PreToolUse",
+                    "updatedInput": updated,
+                })
+            }
+            HookResponse::UpdateInput { tool_input, context } => serde_json::json!({
+                "hookEventName": "PreToolUse",
+                "updatedInput": tool_input,
+                "additionalContext": context,
+            }),
+            HookResponse::Ask { reason, command, tool_input, .. } => {
+                let mut specific = serde_json::json!({
+                    "hookEventName": "PreToolUse",
+                    "permissionDecision": "ask",
+                    "permissionDecisionReason": reason,
+                });
+                if let Some(command) = command {
+                    let mut updated = tool_input.as_object().cloned().unwrap_or_default();
+                    updated.insert("command".to_string(), command.clone().into());
+                    specific["updatedInput"] = updated.into();
+                }
+                specific
+            }
+            HookResponse::Fallback(reason) => serde_json::json!({
+                "hookEventName": "PreToolUse",
+                "permissionDecisionReason": format!("command-replacer kept the original command: {}", reason.detail),
+            }),
+            HookResponse::Context { event, context } => serde_json::json!({
+                "hookEventName": event,
+                "additionalContext": context,
+            }),
+            // Not event-specific; shown to the user as-is
+            HookResponse::Notice(notice) => {
+                return Ok(Emission {
+                    stdout: Some(serde_json::to_string(&serde_json::json!({ "systemMessage": notice }))?),
+                    stderr: None,
+                    exit_code: 0,
+                });
+            }
+        };
+        
+        let mut output = serde_json::json!({ "hookSpecificOutput": specific });
+        if let Some(note) = note {
+            output["systemMessage"] = note.into();
+            output["suppressOutput"] = true.into();
+        }
+        Ok(Emission {
+            stdout: Some(serde_json::to_string(&output)?),
+            stderr: None,
+            exit_code: 0,
+        })
+    }
+    
+    /// Exit status only: 0 lets the call proceed, with any explanation on
+    /// stderr. The contract has no way to ask, so an ask blocks (2) and
+    /// leaves the reason on stderr for Claude to relay.
+    fn render_exit_code(&self) -> Emission {
+        let stderr = match self {
+            HookResponse::Passthrough(_) => None,
+            HookResponse::Rewrite { message, .. } => message.clone(),
+            HookResponse::Ask { reason, .. } => Some(reason.clone()),
+            HookResponse::UpdateInput { context, .. } => Some(context.clone()),
+            HookResponse::Fallback(reason) => Some(format!("command-replacer kept the original command: {}", reason.detail)),
+            HookResponse::Context { context, .. } => Some(context.clone()),
+            HookResponse::Notice(notice) => Some(notice.clone()),
+            HookResponse::Error(_) => Some(format!("Hook error: {}", self.error_text())),
+        };
+        let exit_code = if matches!(self, HookResponse::Ask { .. }) { 2 } else { 0 };
+        Emission { stdout: None, stderr, exit_code }
+    }
+    
+    fn error_text(&self) -> &str {
+        match self {
+            HookResponse::Error(error) => error,
+            _ => "",
+        }
+    }
+}
+
+/// `FALLBACK_<CONSTRUCT>`, or `TOOL_MISSING`
+fn fallback_code(construct: &str) -> String {
+    match construct {
+        "tool-missing" => "TOOL_MISSING".to_string(),
+        _ => format!("FALLBACK_{}", construct.to_ascii_uppercase().replace('-', "_")),
+    }
+}
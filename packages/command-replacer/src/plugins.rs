@@ -0,0 +1,122 @@
+//! External process plugins (`[[plugins]]`)
+//!
+//! A plugin is any executable: it gets `{"command": ..., "context": {...}}`
+//! on stdin and answers on stdout with `{"rewritten": "..."}` to replace
+//! the command, `{"fallback_reason": "..."}` to keep it as it is, or `{}`
+//! to leave it to the next plugin and the built-in engine. A plugin that
+//! fails, times out, or answers nonsense is skipped as if it had answered
+//! `{}`, so a broken plugin can slow the hook down by its timeout at most.
+//! Plugins come from the global config only, unless it sets
+//! `settings.trust_shared_config` (see [`crate::config`]).
+
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use std::io::{Read, Write};
+use std::process::{Command, Stdio};
+use std::sync::mpsc;
+use std::time::Duration;
+
+use crate::config::PluginConfig;
+use crate::context::RuleContext;
+use crate::scripts;
+
+/// Largest answer read from a plugin
+const MAX_RESPONSE_BYTES: u64 = 64 * 1024;
+
+/// What a plugin made of a command
+#[derive(Debug, Clone, PartialEq)]
+pub enum Answer {
+    Rewrite(String),
+    /// Keep the command unchanged, for this reason
+    Decline(String),
+    /// No opinion
+    Pass,
+}
+
+#[derive(Debug, Deserialize)]
+struct Response {
+    #[serde(default)]
+    rewritten: Option<String>,
+    #[serde(default)]
+    fallback_reason: Option<String>,
+}
+
+/// Whether `plugin` wants to see commands running `program`
+pub fn offered(plugin: &PluginConfig, program: &str) -> bool {
+    plugin.enabled && (plugin.commands.is_empty() || plugin.commands.iter().any(|command| command == program))
+}
+
+/// Whether `plugin` is a program or a script, rather than settings for a
+/// `.wasm` plugin
+pub fn external(plugin: &PluginConfig) -> bool {
+    !plugin.run.is_empty() || plugin.script.is_some()
+}
+
+/// Ask `plugin` about `command`
+pub fn ask(plugin: &PluginConfig, command: &str, context: &RuleContext) -> Result<Answer> {
+    if let Some(script) = &plugin.script {
+        return scripts::ask(plugin, script, command, context);
+    }
+    let Some((program, args)) = plugin.run.split_first() else {
+        bail!("Plugin {} has nothing to run", plugin.name);
+    };
+    let request = request(command, context);
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .with_context(|| format!("Failed to start plugin {}", plugin.name))?;
+    
+    // Write and read on another thread, so a plugin that never reads or
+    // never answers still can't hold the hook past its timeout
+    let mut stdin = child.stdin.take();
+    let mut stdout = child.stdout.take();
+    let (sender, receiver) = mpsc::channel();
+    std::thread::spawn(move || {
+        // A plugin may answer without reading the request at all
+        let written = stdin
+            .take()
+            .map(|mut stdin| stdin.write_all(request.to_string().as_bytes()))
+            .filter(|written| !matches!(written, Err(e) if e.kind() == std::io::ErrorKind::BrokenPipe));
+        let mut output = Vec::new();
+        let read = stdout.as_mut().map(|stdout| stdout.take(MAX_RESPONSE_BYTES).read_to_end(&mut output));
+        let _ = sender.send(match (written, read) {
+            (Some(Err(e)), _) | (_, Some(Err(e))) => Err(e),
+            _ => Ok(output),
+        });
+    });
+    let output = match receiver.recv_timeout(Duration::from_millis(plugin.timeout_ms)) {
+        Ok(output) => output.with_context(|| format!("Failed to talk to plugin {}", plugin.name))?,
+        Err(_) => {
+            let _ = child.kill();
+            let _ = child.wait();
+            bail!("Plugin {} did not answer within {}ms", plugin.name, plugin.timeout_ms);
+        }
+    };
+    let status = child.wait().with_context(|| format!("Failed to wait for plugin {}", plugin.name))?;
+    if !status.success() {
+        bail!("Plugin {} failed ({})", plugin.name, status);
+    }
+    answer(plugin, command, &output)
+}
+
+/// The JSON request a plugin gets for `command`
+pub fn request(command: &str, context: &RuleContext) -> serde_json::Value {
+    serde_json::json!({ "command": command, "context": context })
+}
+
+/// Read a plugin's `output` about `command`; nothing at all means no opinion
+pub fn answer(plugin: &PluginConfig, command: &str, output: &[u8]) -> Result<Answer> {
+    if output.iter().all(u8::is_ascii_whitespace) {
+        return Ok(Answer::Pass);
+    }
+    let response: Response = serde_json::from_slice(output)
+        .with_context(|| format!("Plugin {} answered with invalid JSON", plugin.name))?;
+    Ok(match (response.rewritten, response.fallback_reason) {
+        (Some(rewritten), _) if rewritten != command => Answer::Rewrite(rewritten),
+        (_, Some(reason)) => Answer::Decline(reason),
+        _ => Answer::Pass,
+    })
+}
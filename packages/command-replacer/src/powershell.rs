@@ -0,0 +1,367 @@
+//! PowerShell cmdlets: `Get-ChildItem -Name` → fd, `Get-Content` → bat,
+//! `Select-String` → rg
+//!
+//! Only reached through a `powershell -Command` wrapper. Cmdlets and their
+//! parameters match in any case, aliases included (`gci`, `gc`, `sls`, and
+//! the `ls`/`cat` lookalikes), and parameters may be shortened to any
+//! unambiguous prefix. A payload that is a script rather than one call
+//! (pipelines, variables, subexpressions, arrays) is left to PowerShell, as
+//! is any call whose output is objects rather than text.
+
+use crate::cmd_builtins::{has_wildcard, split_dir};
+use crate::replacements::FallbackReason;
+
+/// The cmdlets this translates, as spelled in `[replacements]`
+pub const CMDLETS: [&str; 3] = ["Get-ChildItem", "Get-Content", "Select-String"];
+
+/// The cmdlet `name` stands for, if it is one of [`CMDLETS`] or an alias
+pub fn cmdlet(name: &str) -> Option<&'static str> {
+    match name.to_ascii_lowercase().as_str() {
+        "get-childitem" | "gci" | "ls" | "dir" => Some("Get-ChildItem"),
+        "get-content" | "gc" | "cat" | "type" => Some("Get-Content"),
+        "select-string" | "sls" => Some("Select-String"),
+        _ => None,
+    }
+}
+
+fn script(detail: String) -> FallbackReason {
+    FallbackReason::new("powershell", "script", detail)
+}
+
+/// `payload` split into words, unquoted, or why it is more than one call
+/// with literal arguments
+pub fn split_words(payload: &str) -> Result<Vec<String>, FallbackReason> {
+    let mut words = Vec::new();
+    let mut chars = payload.chars().peekable();
+    loop {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        if chars.peek().is_none() {
+            return Ok(words);
+        }
+        
+        let mut word = String::new();
+        while let Some(c) = chars.next_if(|c| !c.is_whitespace()) {
+            match c {
+                // Single quotes are literal, with '' for a quote
+                '\'' => loop {
+                    match chars.next() {
+                        Some('\'') if chars.next_if_eq(&'\'').is_some() => word.push('\''),
+                        Some('\'') => break,
+                        Some(c) => word.push(c),
+                        None => return Err(script("unterminated quote".to_string())),
+                    }
+                },
+                '"' => loop {
+                    match chars.next() {
+                        Some('"') if chars.next_if_eq(&'"').is_some() => word.push('"'),
+                        Some('"') => break,
+                        Some('$' | '`') => return Err(script(format!("\"{}...\" expands variables or escapes", word))),
+                        Some(c) => word.push(c),
+                        None => return Err(script("unterminated quote".to_string())),
+                    }
+                },
+                '$' | '`' | '(' | ')' | '{' | '}' | ',' | ';' | '|' | '&' | '<' | '>' => {
+                    return Err(script(format!("`{}` makes the payload a script", c)));
+                }
+                '@' | '#' if word.is_empty() => return Err(script(format!("`{}` makes the payload a script", c))),
+                _ => word.push(c),
+            }
+        }
+        words.push(word);
+    }
+}
+
+/// `word` as PowerShell passes it to a program: bare when it is plain,
+/// otherwise single-quoted
+pub fn quote(word: &str) -> String {
+    if !word.is_empty() && word.chars().all(|c| c.is_ascii_alphanumeric() || "-_./\\:=*?+".contains(c)) {
+        return word.to_string();
+    }
+    format!("'{}'", word.replace('\'', "''"))
+}
+
+/// New arguments (unquoted, after the tool name) for a cmdlet's `args`
+pub fn translate(cmdlet: &str, args: &[String]) -> Result<Vec<String>, FallbackReason> {
+    match cmdlet {
+        "Get-ChildItem" => get_child_item(args),
+        "Get-Content" => get_content(args),
+        "Select-String" => select_string(args),
+        _ => Err(FallbackReason::new(cmdlet, "option", format!("{} is not a cmdlet this translates", cmdlet))),
+    }
+}
+
+/// One cmdlet's parameters: their full names, lowercased, and aliases
+struct Parameters {
+    cmdlet: &'static str,
+    names: &'static [&'static str],
+    aliases: &'static [(&'static str, &'static str)],
+}
+
+impl Parameters {
+    /// The parameter `-name` means: an alias, the full name, or a prefix of
+    /// exactly one name
+    fn resolve(&self, name: &str) -> Result<&'static str, FallbackReason> {
+        let lower = name.to_ascii_lowercase();
+        if let Some((_, full)) = self.aliases.iter().find(|(alias, _)| *alias == lower) {
+            return Ok(full);
+        }
+        if let Some(full) = self.names.iter().find(|full| **full == lower) {
+            return Ok(full);
+        }
+        match self.names.iter().filter(|full| full.starts_with(&lower)).collect::<Vec<_>>()[..] {
+            [full] => Ok(full),
+            [] => Err(FallbackReason::new(self.cmdlet, "option", format!("{} -{} has no equivalent", self.cmdlet, name))),
+            _ => Err(FallbackReason::new(self.cmdlet, "ambiguous", format!("{} -{} could be several parameters", self.cmdlet, name))),
+        }
+    }
+}
+
+/// `args` as `(parameter, value)` pairs, with `None` for positional
+/// arguments. Parameters in `valued` take a value, after a colon or as the
+/// next word; the rest are switches.
+fn parse_args(
+    parameters: &Parameters,
+    valued: &[&str],
+    args: &[String],
+) -> Result<Vec<(Option<&'static str>, String)>, FallbackReason> {
+    let mut parsed = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let Some(rest) = arg.strip_prefix('-').filter(|rest| !rest.is_empty()) else {
+            parsed.push((None, arg.clone()));
+            continue;
+        };
+        let (name, inline) = match rest.split_once(':') {
+            Some((name, value)) => (name, Some(value.to_string())),
+            None => (rest, None),
+        };
+        let parameter = parameters.resolve(name)?;
+        let value = match inline {
+            _ if !valued.contains(&parameter) => String::new(),
+            Some(value) => value,
+            None => args.next().cloned().ok_or_else(|| {
+                FallbackReason::new(parameters.cmdlet, "option", format!("{} -{} without a value", parameters.cmdlet, name))
+            })?,
+        };
+        parsed.push((Some(parameter), value));
+    }
+    Ok(parsed)
+}
+
+fn unsupported(cmdlet: &str, parameter: &str, tool: &str) -> FallbackReason {
+    FallbackReason::new(cmdlet, "option", format!("{} -{} has no {} equivalent", cmdlet, parameter, tool))
+}
+
+/// `Get-ChildItem -Name [-Path] PATH [-Filter] GLOB [-Recurse | -Depth N]
+/// [-File | -Directory] [-Force]`
+///
+/// Only `-Name` prints plain names; without it the output is a table.
+fn get_child_item(args: &[String]) -> Result<Vec<String>, FallbackReason> {
+    const PARAMETERS: Parameters = Parameters {
+        cmdlet: "Get-ChildItem",
+        names: &["path", "literalpath", "filter", "include", "exclude", "recurse", "depth", "force", "name", "attributes", "directory", "file", "hidden", "readonly", "system", "followsymlink"],
+        aliases: &[("ad", "directory"), ("af", "file"), ("s", "recurse"), ("lp", "literalpath")],
+    };
+    let parsed = parse_args(&PARAMETERS, &["path", "literalpath", "filter", "depth"], args)?;
+    let (mut names, mut recursive, mut hidden) = (false, false, false);
+    let (mut depth, mut file_type) = (None, None);
+    let (mut path, mut literal, mut filter) = (None, false, None);
+    let mut positional = Vec::new();
+    for (parameter, value) in parsed {
+        match parameter {
+            Some("name") => names = true,
+            Some("recurse") => recursive = true,
+            Some("force") => hidden = true,
+            Some("file") => file_type = Some("file"),
+            Some("directory") => file_type = Some("directory"),
+            Some("depth") => {
+                let levels: u32 = value.parse().map_err(|_| FallbackReason::new("Get-ChildItem", "option", format!("Get-ChildItem -Depth {}", value)))?;
+                depth = Some(levels);
+            }
+            Some("path") => path = Some(value),
+            Some("literalpath") => {
+                path = Some(value);
+                literal = true;
+            }
+            Some("filter") => filter = Some(value),
+            Some(parameter) => return Err(unsupported("Get-ChildItem", parameter, "fd")),
+            None => positional.push(value),
+        }
+    }
+    // Path comes first, then Filter
+    let mut positional = positional.into_iter();
+    path = path.or_else(|| positional.next());
+    filter = filter.or_else(|| positional.next());
+    if positional.next().is_some() {
+        return Err(FallbackReason::new("Get-ChildItem", "option", "Get-ChildItem with more than a path and a filter"));
+    }
+    if !names {
+        return Err(FallbackReason::new("Get-ChildItem", "output-format", "Get-ChildItem without -Name prints modes, dates, and sizes"));
+    }
+    
+    let path = path.unwrap_or_default();
+    let (base, glob) = match split_dir(&path) {
+        _ if path.is_empty() => ("", ""),
+        (base, name) if !literal && has_wildcard(name) => (base, name),
+        (_, "" | "." | "..") => (path.as_str(), ""),
+        _ => return Err(FallbackReason::new("Get-ChildItem", "ambiguous", format!("Get-ChildItem {} lists a directory or names a file, depending on what it is", path))),
+    };
+    let glob = match (glob, filter.as_deref()) {
+        ("", filter) => filter.unwrap_or_default(),
+        (glob, None) => glob,
+        (_, Some(_)) => return Err(FallbackReason::new("Get-ChildItem", "glob", "Get-ChildItem with both a wildcard path and -Filter")),
+    };
+    
+    let mut new_args = Vec::new();
+    // -Depth counts levels below the first; it implies -Recurse
+    match (depth, recursive) {
+        (Some(levels), _) => new_args.extend(["--max-depth".to_string(), (levels + 1).to_string()]),
+        (None, false) => new_args.extend(["--max-depth".to_string(), "1".to_string()]),
+        (None, true) => {}
+    }
+    // -Force includes hidden files; ignore files mean nothing to PowerShell
+    if hidden {
+        new_args.push("-H".to_string());
+    }
+    new_args.push("-I".to_string());
+    if let Some(file_type) = file_type {
+        new_args.extend(["--type".to_string(), file_type.to_string()]);
+    }
+    if !base.is_empty() {
+        new_args.extend(["--base-directory".to_string(), base.to_string()]);
+    }
+    if !glob.is_empty() && glob != "*" && glob != "*.*" {
+        new_args.extend(["--ignore-case".to_string(), "--glob".to_string(), glob.to_string()]);
+    }
+    Ok(new_args)
+}
+
+/// `Get-Content [-Path] FILE... [-TotalCount N] [-Raw]`
+fn get_content(args: &[String]) -> Result<Vec<String>, FallbackReason> {
+    const PARAMETERS: Parameters = Parameters {
+        cmdlet: "Get-Content",
+        names: &["path", "literalpath", "totalcount", "tail", "raw", "readcount", "encoding", "delimiter", "wait", "stream", "asbytestream"],
+        aliases: &[("head", "totalcount"), ("first", "totalcount"), ("last", "tail"), ("lp", "literalpath")],
+    };
+    let parsed = parse_args(&PARAMETERS, &["path", "literalpath", "totalcount", "tail", "readcount", "encoding", "delimiter", "stream"], args)?;
+    let mut files = Vec::new();
+    let mut count = None;
+    for (parameter, value) in parsed {
+        match parameter {
+            // Lines are printed the same either way
+            Some("raw" | "readcount") => {}
+            Some("totalcount") => match value.parse::<u64>() {
+                Ok(lines) if lines > 0 => count = Some(lines),
+                _ => return Err(FallbackReason::new("Get-Content", "option", format!("Get-Content -TotalCount {}", value))),
+            },
+            Some("literalpath") => files.push(value),
+            Some("path") | None => {
+                if has_wildcard(&value) {
+                    return Err(FallbackReason::new("Get-Content", "glob", format!("Get-Content expands {} itself", value)));
+                }
+                files.push(value);
+            }
+            Some(parameter) => return Err(unsupported("Get-Content", parameter, "bat")),
+        }
+    }
+    if files.is_empty() {
+        return Err(FallbackReason::new("Get-Content", "option", "Get-Content without a path"));
+    }
+    
+    let mut new_args = vec!["--style=plain".to_string()];
+    if let Some(lines) = count {
+        new_args.extend(["--line-range".to_string(), format!(":{}", lines)]);
+    }
+    new_args.extend(files);
+    Ok(new_args)
+}
+
+/// `Select-String [-Pattern] TEXT [-Path] FILE [-SimpleMatch] [-CaseSensitive]
+/// [-NotMatch] [-List] [-Context N] [-Raw]`
+fn select_string(args: &[String]) -> Result<Vec<String>, FallbackReason> {
+    const PARAMETERS: Parameters = Parameters {
+        cmdlet: "Select-String",
+        names: &["pattern", "path", "literalpath", "simplematch", "casesensitive", "notmatch", "list", "allmatches", "context", "raw", "quiet", "include", "exclude", "encoding", "noemphasis", "culture", "inputobject"],
+        aliases: &[("lp", "literalpath")],
+    };
+    let parsed = parse_args(&PARAMETERS, &["pattern", "path", "literalpath", "context", "include", "exclude", "encoding", "culture", "inputobject"], args)?;
+    let mut flags = Vec::new();
+    let (mut simple, mut case_sensitive, mut raw) = (false, false, false);
+    let (mut pattern, mut path, mut literal) = (None, None, false);
+    let mut positional = Vec::new();
+    for (parameter, value) in parsed {
+        match parameter {
+            Some("simplematch") => simple = true,
+            Some("casesensitive") => case_sensitive = true,
+            Some("raw") => raw = true,
+            Some("notmatch") => flags.push("--invert-match".to_string()),
+            // Only the first match in each file
+            Some("list") => flags.extend(["--max-count".to_string(), "1".to_string()]),
+            // Only change the match objects, not the lines printed
+            Some("allmatches" | "noemphasis") => {}
+            Some("context") => match value.parse::<u32>() {
+                Ok(lines) => flags.extend(["--context".to_string(), lines.to_string()]),
+                Err(_) => return Err(FallbackReason::new("Select-String", "option", format!("Select-String -Context {}", value))),
+            },
+            Some("pattern") => pattern = Some(value),
+            Some("path") => path = Some(value),
+            Some("literalpath") => {
+                path = Some(value);
+                literal = true;
+            }
+            Some(parameter) => return Err(unsupported("Select-String", parameter, "rg")),
+            None => positional.push(value),
+        }
+    }
+    // Pattern comes first, then Path
+    let mut positional = positional.into_iter();
+    pattern = pattern.or_else(|| positional.next());
+    path = path.or_else(|| positional.next());
+    if positional.next().is_some() {
+        return Err(FallbackReason::new("Select-String", "option", "Select-String with more than a pattern and a path"));
+    }
+    let Some(pattern) = pattern else {
+        return Err(FallbackReason::new("Select-String", "option", "Select-String without a pattern"));
+    };
+    let Some(path) = path else {
+        return Err(FallbackReason::new("Select-String", "stdin", "Select-String without -Path reads the pipeline, rg would search the directory"));
+    };
+    if !simple && dotnet_only_regex(&pattern) {
+        return Err(FallbackReason::new("Select-String", "regex", format!("{} uses .NET regex features rg lacks", pattern)));
+    }
+    
+    // Select-String reads no ignore files, and matches case-insensitively
+    let mut new_args: Vec<String> = vec!["--no-ignore".to_string(), "--hidden".to_string()];
+    if !case_sensitive {
+        new_args.push("--ignore-case".to_string());
+    }
+    new_args.extend(flags);
+    if simple {
+        new_args.push("--fixed-strings".to_string());
+    }
+    // -Raw prints the matching lines alone, otherwise `file:line:text`
+    if raw {
+        new_args.extend(["--no-filename".to_string(), "--no-line-number".to_string()]);
+    } else {
+        new_args.extend(["--with-filename".to_string(), "--line-number".to_string()]);
+    }
+    new_args.extend(["-e".to_string(), pattern]);
+    // A wildcard matches in one directory, without descending
+    match split_dir(&path) {
+        (base, name) if !literal && has_wildcard(name) => {
+            new_args.extend(["--max-depth".to_string(), "1".to_string(), "--iglob".to_string(), name.to_string()]);
+            if !base.is_empty() {
+                new_args.push(base.to_string());
+            }
+        }
+        _ => new_args.push(path),
+    }
+    Ok(new_args)
+}
+
+/// Whether `pattern` needs lookaround or backreferences, which rg's default
+/// engine doesn't have
+fn dotnet_only_regex(pattern: &str) -> bool {
+    ["(?=", "(?!", "(?<=", "(?<!", r"\k<"].iter().any(|construct| pattern.contains(construct))
+        || pattern.as_bytes().windows(2).any(|pair| pair[0] == b'\\' && (b'1'..=b'9').contains(&pair[1]))
+}
@@ -0,0 +1,56 @@
+//! Large file advice for the Read tool
+//!
+//! Reading a multi-megabyte log or generated file whole floods Claude's
+//! context. When a Read without `offset`/`limit` targets a file over
+//! `settings.read.max_bytes`, Claude is pointed at reading a slice or
+//! extracting the relevant lines instead.
+
+use std::path::Path;
+
+use crate::config::ReadSettings;
+
+/// Files the Read tool renders rather than pulling in as text
+const RENDERED_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "gif", "webp", "bmp", "pdf", "ipynb"];
+
+/// Advice for reading `path` (relative paths resolve against `project_dir`),
+/// or `None` if reading it whole is fine
+pub fn advice(path: &Path, project_dir: &Path, settings: &ReadSettings) -> Option<String> {
+    if !settings.advise {
+        return None;
+    }
+    let rendered = path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| RENDERED_EXTENSIONS.contains(&extension.to_ascii_lowercase().as_str()));
+    if rendered {
+        return None;
+    }
+    
+    let size = std::fs::metadata(project_dir.join(path)).ok().filter(|metadata| metadata.is_file())?.len();
+    if size <= settings.max_bytes {
+        return None;
+    }
+    let search = if crate::tool_cache::find_program("rg").is_some() { "rg -n" } else { "grep -n" };
+    Some(format!(
+        "`{}` is {}; reading it whole would flood the context. Read a slice with `offset`/`limit` (e.g. `limit: 200`), or pull out the relevant lines with `{} '<pattern>' {}`.",
+        path.display(),
+        human_size(size),
+        search,
+        shlex::try_quote(&path.to_string_lossy()).map(|quoted| quoted.into_owned()).unwrap_or_else(|_| path.display().to_string()),
+    ))
+}
+
+/// `1.5 MB`, `300 KB`
+pub fn human_size(bytes: u64) -> String {
+    const UNITS: &[&str] = &["bytes", "KB", "MB", "GB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    match unit {
+        0 => format!("{} bytes", bytes),
+        _ if size < 10.0 => format!("{:.1} {}", size, UNITS[unit]),
+        _ => format!("{:.0} {}", size, UNITS[unit]),
+    }
+}
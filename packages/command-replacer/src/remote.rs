@@ -0,0 +1,163 @@
+//! Team configuration fetched from an HTTPS endpoint or git repository
+//!
+//! The fetched policy is cached on disk and only refetched once its TTL has
+//! passed. If a refresh fails, the stale copy keeps being used so a network
+//! outage never takes the hook down.
+
+use anyhow::{bail, Context, Result};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::config::{ConfigFormat, RemoteConfig};
+
+/// Where the cached copy of a remote config lives
+pub fn cache_path(remote: &RemoteConfig, cache_dir: &Path) -> PathBuf {
+    let mut hasher = DefaultHasher::new();
+    remote.url.hash(&mut hasher);
+    remote.path.hash(&mut hasher);
+    let extension = remote_format(remote).extensions()[0];
+    cache_dir.join(format!("remote-{:016x}.{}", hasher.finish(), extension))
+}
+
+/// Return the remote config, refreshing the cached copy when it is older
+/// than the TTL
+pub fn load_remote(remote: &RemoteConfig, cache_dir: &Path) -> Result<toml::Value> {
+    load_remote_with(remote, cache_dir, fetch)
+}
+
+/// `load_remote` with the fetch step injected
+pub fn load_remote_with<F>(remote: &RemoteConfig, cache_dir: &Path, fetch: F) -> Result<toml::Value>
+where
+    F: FnOnce(&RemoteConfig) -> Result<String>,
+{
+    let path = cache_path(remote, cache_dir);
+    let format = remote_format(remote);
+    
+    let age = std::fs::metadata(&path)
+        .and_then(|meta| meta.modified())
+        .ok()
+        .map(|modified| SystemTime::now().duration_since(modified).unwrap_or_default());
+    let fresh = age.is_some_and(|age| age < Duration::from_secs(remote.ttl_secs));
+    
+    if !fresh {
+        match fetch(remote) {
+            Ok(content) => {
+                // Don't cache something we can't parse
+                format.parse(&content)
+                    .with_context(|| format!("Invalid remote config from {}", remote.url))?;
+                std::fs::create_dir_all(cache_dir)
+                    .with_context(|| format!("Failed to create {}", cache_dir.display()))?;
+                std::fs::write(&path, content)
+                    .with_context(|| format!("Failed to cache remote config at {}", path.display()))?;
+            }
+            Err(e) if age.is_none() => return Err(e),
+            // Keep using the stale copy
+            Err(_) => {}
+        }
+    }
+    
+    let content = std::fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read cached remote config {}", path.display()))?;
+    format.parse(&content)
+        .with_context(|| format!("Invalid cached remote config {}", path.display()))
+}
+
+/// Format of the remote file, from the URL or repository path extension
+fn remote_format(remote: &RemoteConfig) -> ConfigFormat {
+    let name = if is_git_url(&remote.url) { remote.repo_path() } else { remote.url.as_str() };
+    let name = name.split(['?', '#']).next().unwrap_or(name);
+    ConfigFormat::from_path(Path::new(name)).unwrap_or(ConfigFormat::Toml)
+}
+
+fn is_git_url(url: &str) -> bool {
+    url.starts_with("git@") || url.starts_with("git+") || url.starts_with("ssh://") || url.ends_with(".git")
+}
+
+/// Fetch the raw config text
+pub fn fetch(remote: &RemoteConfig) -> Result<String> {
+    if is_git_url(&remote.url) {
+        fetch_git(remote)
+    } else {
+        fetch_https(remote)
+    }
+}
+
+fn fetch_https(remote: &RemoteConfig) -> Result<String> {
+    if !remote.url.starts_with("https://") {
+        bail!("Remote config URL must use https:// or be a git URL: {}", remote.url);
+    }
+    #[cfg(not(feature = "native"))]
+    bail!("Fetching {} needs a build with the `native` feature", remote.url);
+    #[cfg(feature = "native")]
+    ureq::AgentBuilder::new()
+        .timeout(Duration::from_millis(remote.timeout_ms))
+        .build()
+        .get(&remote.url)
+        .call()
+        .with_context(|| format!("Failed to fetch {}", remote.url))?
+        .into_string()
+        .with_context(|| format!("Failed to read response from {}", remote.url))
+}
+
+/// Shallow-clone the repository and read the config file out of it
+fn fetch_git(remote: &RemoteConfig) -> Result<String> {
+    let url = remote.url.strip_prefix("git+").unwrap_or(&remote.url);
+    let checkout = unique_temp_dir()?;
+    
+    let result = (|| {
+        let child = Command::new("git")
+            .args(["clone", "--quiet", "--depth", "1", "--", url])
+            .arg(checkout.join("repo"))
+            .env("GIT_TERMINAL_PROMPT", "0")
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .context("Failed to run git")?;
+        let status = wait_with_timeout(child, Duration::from_millis(remote.timeout_ms))
+            .with_context(|| format!("git clone of {} did not finish", url))?;
+        if !status.success() {
+            bail!("git clone of {} failed ({})", url, status);
+        }
+        std::fs::read_to_string(checkout.join("repo").join(remote.repo_path()))
+            .with_context(|| format!("{} not found in {}", remote.repo_path(), url))
+    })();
+    
+    let _ = std::fs::remove_dir_all(&checkout);
+    result
+}
+
+/// A fresh directory under the system temp dir that no other run shares
+fn unique_temp_dir() -> Result<PathBuf> {
+    static COUNTER: AtomicU32 = AtomicU32::new(0);
+    let nanos = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default().subsec_nanos();
+    loop {
+        let name = format!("cmdrepl-remote-{}-{}-{}", std::process::id(), nanos, COUNTER.fetch_add(1, Ordering::Relaxed));
+        let dir = std::env::temp_dir().join(name);
+        match std::fs::create_dir(&dir) {
+            Ok(()) => return Ok(dir),
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e).with_context(|| format!("Failed to create {}", dir.display())),
+        }
+    }
+}
+
+/// Wait for `child`, killing it once `timeout` has passed
+pub fn wait_with_timeout(mut child: Child, timeout: Duration) -> Result<ExitStatus> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(status);
+        }
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            bail!("Timed out after {}ms", timeout.as_millis());
+        }
+        std::thread::sleep(Duration::from_millis(20));
+    }
+}
@@ -2,59 +2,410 @@
 
 use anyhow::{Context, Result};
 use once_cell::sync::Lazy;
-use regex::Regex;
-use std::collections::HashMap;
-use std::sync::Mutex;
+use regex::{Regex, RegexSet};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
-use which::which;
 
-use crate::config::{Config, ReplacementConfig};
+use crate::config::{Config, PluginConfig, ReplacementConfig, RewriteCacheSettings, RewriteStage, RuleConfig, WhenCondition};
+use crate::context::RuleContext;
+use crate::explain::{self, CandidateReport, Explanation, ParsedCommand, Step, Trace};
+use crate::rewrite_cache::{self, Decision, RewriteCache};
+use crate::plugins::{self, Answer};
+use crate::rules;
+use crate::capabilities::{Capability, EZA_ONLY_FLAGS};
+use crate::cmd_builtins;
+use crate::powershell;
+use crate::shell_wrappers::{self, Shell};
+use crate::wsl;
+use crate::tool_cache::{find_program, off_path_location, probe_concurrently, probe_version_text, ToolCache};
 
 /// Tool availability cache
 static TOOL_CACHE: Lazy<Mutex<HashMap<String, (bool, Instant)>>> = 
     Lazy::new(|| Mutex::new(HashMap::new()));
 
+/// `--version` output per tool, probed at most once per process
+static TOOL_VERSIONS: Lazy<Mutex<HashMap<String, Option<String>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Decisions for recently rewritten commands; loaded from disk on first use
+/// when `rewrite_cache.persist` is set
+static REWRITE_CACHE: Lazy<Mutex<Option<RewriteCache>>> =
+    Lazy::new(|| Mutex::new(None));
+
+/// Fold the decisions hook processes have journaled into the persisted
+/// rewrite cache, e.g. when a session stops
+pub fn flush_rewrite_cache(settings: &RewriteCacheSettings) -> Result<()> {
+    if !settings.persist {
+        return Ok(());
+    }
+    let path = Config::rewrite_cache_path();
+    let mut cache = REWRITE_CACHE.lock().unwrap();
+    cache.get_or_insert_with(|| RewriteCache::load(&path, settings.capacity)).compact(&path)
+}
+
+/// Embedder-supplied answer to "is this tool installed?"
+type ToolProbe = Arc<dyn Fn(&str) -> bool + Send + Sync>;
+
 /// Replacement engine handles command transformations
 pub struct ReplacementEngine {
     config: Config,
     compatibility_mode: bool,
     pub is_git_repo: bool,
+    /// Detected environment for `when` conditions
+    context: RuleContext,
+    /// Forced tool availability answers that bypass `which()`
+    tool_overrides: HashMap<String, bool>,
+    /// Forced `--version` answers that bypass running the tool
+    version_overrides: HashMap<String, String>,
+    /// Asked instead of searching PATH, when set
+    tool_probe: Option<ToolProbe>,
+    /// Asked instead of `wsl.exe which` for commands wrapped in `wsl.exe`
+    wsl_probe: Option<ToolProbe>,
+    /// Tools probed at SessionStart
+    tool_cache: ToolCache,
+    /// `settings.fallback_patterns`, compiled once
+    fallback_patterns: FallbackPatterns,
+    /// Each replacement's own `fallback_patterns`, by command
+    replacement_fallbacks: HashMap<String, FallbackPatterns>,
+    /// Why the most recent `replace_command` call declined to rewrite, if known
+    last_fallback: Mutex<Option<FallbackReason>>,
+    /// Unquoted glob words of the command currently being rewritten
+    unquoted_globs: Mutex<HashSet<String>>,
+    /// System whose shell conventions commands follow (`std::env::consts::OS`)
+    os: String,
+    /// Time spent looking tools up on PATH and running `--version`
+    probe_time: Mutex<Duration>,
+    /// PATH lookups done ahead of time by [`Self::prefetch_tools`]; a tool
+    /// that couldn't be checked before the deadline counts as missing
+    prefetched: Mutex<HashMap<String, bool>>,
+    /// When concurrent probes stop being waited for
+    deadline: Option<Instant>,
+    /// Whether a prefetch gave up on a tool at the deadline, which makes
+    /// this call's decisions unfit to remember
+    probes_timed_out: AtomicBool,
+    /// Whether a plugin or script was asked about the last command; they
+    /// can look at the clock, environment, and filesystem, so their
+    /// answers are never remembered
+    plugin_asked: AtomicBool,
+    /// Fingerprint of everything a rewrite decision depends on
+    fingerprint: once_cell::sync::OnceCell<u64>,
+    /// Decisions noted while [`Self::explain`] runs
+    trace: Mutex<Option<Trace>>,
+    /// `.wasm` plugins, loaded the first time a command needs them
+    #[cfg(feature = "wasm-plugins")]
+    wasm_plugins: once_cell::sync::OnceCell<Option<crate::wasm_plugins::WasmRuntime>>,
+}
+
+/// Structured explanation for a declined rewrite
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FallbackReason {
+    /// Original command the translation was attempted for
+    pub command: String,
+    /// Construct that has no faithful equivalent (e.g. `address-range`)
+    pub construct: String,
+    /// Human-readable explanation
+    pub detail: String,
+}
+
+impl FallbackReason {
+    pub fn new(command: &str, construct: &str, detail: impl Into<String>) -> Self {
+        Self {
+            command: command.to_string(),
+            construct: construct.to_string(),
+            detail: detail.into(),
+        }
+    }
+}
+
+/// A rule or built-in replacement competing to rewrite a command
+enum Candidate<'a> {
+    Rule(&'a RuleConfig),
+    Builtin(&'a ReplacementConfig),
+}
+
+impl Candidate<'_> {
+    fn priority(&self) -> u8 {
+        match self {
+            Candidate::Rule(rule) => rule.priority,
+            Candidate::Builtin(replacement) => replacement.priority,
+        }
+    }
 }
 
 impl ReplacementEngine {
+    /// Engine for commands run in the current directory
     pub fn new(config: Config) -> Result<Self> {
+        let context = RuleContext::detect(&std::env::current_dir()?);
+        Self::for_context(config, context)
+    }
+    
+    /// Engine for commands run where `context` was detected
+    pub fn for_context(config: Config, context: RuleContext) -> Result<Self> {
         let compatibility_mode = config.settings.compatibility_mode.unwrap_or(false);
-        let is_git_repo = Self::detect_git_repo()?;
+        let fallback_patterns = FallbackPatterns::new(&config.settings.fallback_patterns)
+            .context("Invalid settings.fallback_patterns")?;
+        let replacement_fallbacks = config.replacements.iter()
+            .map(|(cmd, replacement)| {
+                let patterns = FallbackPatterns::new(&replacement.fallback_patterns)
+                    .with_context(|| format!("Invalid fallback_patterns for {}", cmd))?;
+                Ok((cmd.clone(), patterns))
+            })
+            .collect::<Result<_>>()?;
         
         Ok(Self { 
             config,
             compatibility_mode,
-            is_git_repo,
+            is_git_repo: context.git_repo,
+            context,
+            tool_overrides: HashMap::new(),
+            version_overrides: HashMap::new(),
+            tool_probe: None,
+            wsl_probe: None,
+            tool_cache: ToolCache::default(),
+            fallback_patterns,
+            replacement_fallbacks,
+            last_fallback: Mutex::new(None),
+            unquoted_globs: Mutex::new(HashSet::new()),
+            os: std::env::consts::OS.to_string(),
+            probe_time: Mutex::new(Duration::ZERO),
+            prefetched: Mutex::new(HashMap::new()),
+            deadline: None,
+            probes_timed_out: AtomicBool::new(false),
+            plugin_asked: AtomicBool::new(false),
+            fingerprint: once_cell::sync::OnceCell::new(),
+            trace: Mutex::new(None),
+            #[cfg(feature = "wasm-plugins")]
+            wasm_plugins: once_cell::sync::OnceCell::new(),
         })
     }
     
-    /// Detect if we're in a git repository
-    fn detect_git_repo() -> Result<bool> {
-        // Check for .git directory in current or parent directories
-        let mut current_dir = std::env::current_dir()?;
-        loop {
-            if current_dir.join(".git").exists() {
-                return Ok(true);
-            }
-            if let Some(parent) = current_dir.parent() {
-                current_dir = parent.to_path_buf();
+    /// Force the availability answer for a tool, independent of PATH
+    pub fn with_tool_override(mut self, tool: &str, available: bool) -> Self {
+        self.tool_overrides.insert(tool.to_string(), available);
+        self
+    }
+    
+    /// Force the `--version` output for a tool
+    pub fn with_version_override(mut self, tool: &str, version_output: &str) -> Self {
+        self.version_overrides.insert(tool.to_string(), version_output.to_string());
+        self
+    }
+    
+    /// Replace the context detected from the working directory
+    pub fn with_context(mut self, context: RuleContext) -> Self {
+        self.is_git_repo = context.git_repo;
+        self.context = context;
+        self
+    }
+    
+    /// Parse and quote commands as on `os` (a `std::env::consts::OS` value)
+    /// instead of the running system
+    pub fn with_os(mut self, os: &str) -> Self {
+        self.os = os.to_string();
+        self
+    }
+    
+    /// Ask `probe` whether a tool is installed instead of searching PATH,
+    /// e.g. a host callback when running as WebAssembly. Decisions are not
+    /// remembered across calls, since the probe's answers can't be
+    /// fingerprinted.
+    pub fn with_tool_probe(mut self, probe: impl Fn(&str) -> bool + Send + Sync + 'static) -> Self {
+        self.tool_probe = Some(Arc::new(probe));
+        self
+    }
+    
+    /// Ask `probe` which tools are installed inside WSL instead of running
+    /// `wsl.exe which`
+    pub fn with_wsl_probe(mut self, probe: impl Fn(&str) -> bool + Send + Sync + 'static) -> Self {
+        self.wsl_probe = Some(Arc::new(probe));
+        self
+    }
+    
+    /// Stop waiting for tool probes at `deadline`, e.g. when the latency
+    /// budget runs out
+    pub fn with_deadline(mut self, deadline: Option<Instant>) -> Self {
+        self.deadline = deadline;
+        self
+    }
+    
+    /// Answer tool checks from a persisted probe before searching PATH
+    pub fn with_tool_cache(mut self, tool_cache: ToolCache) -> Self {
+        self.tool_cache = tool_cache;
+        self
+    }
+    
+    /// Replace a command if a better alternative is available
+    pub fn replace_command(&self, command: &str) -> Result<Option<String>> {
+        let settings = &self.config.settings.rewrite_cache;
+        // What a `wsl.exe` wrapper becomes depends on tools inside WSL,
+        // which the fingerprint can't see
+        if !settings.enabled || self.tool_probe.is_some() || wsl::wraps(command) {
+            return self.replace_uncached(command);
+        }
+        
+        let key = rewrite_cache::normalize(command);
+        let fingerprint = *self.fingerprint.get_or_init(|| self.decision_fingerprint());
+        if let Some(decision) = self.cached_decisions(|cache| cache.get(fingerprint, &key)) {
+            *self.last_fallback.lock().unwrap() = decision.fallback;
+            return Ok(decision.rewrite);
+        }
+        
+        let rewrite = self.replace_uncached(command)?;
+        if !self.probes_timed_out.load(Ordering::Relaxed) && !self.plugin_asked.load(Ordering::Relaxed) {
+            let decision = Decision { rewrite: rewrite.clone(), fallback: self.last_fallback_reason() };
+            self.cached_decisions(|cache| {
+                cache.insert(fingerprint, &key, decision.clone(), settings.capacity);
+                if settings.persist {
+                    cache.record(&Config::rewrite_cache_path(), fingerprint, &key, decision)
+                } else {
+                    Ok(())
+                }
+            })?;
+        }
+        Ok(rewrite)
+    }
+    
+    /// Run `f` on the process-wide rewrite cache, loading it first if needed
+    fn cached_decisions<R>(&self, f: impl FnOnce(&mut RewriteCache) -> R) -> R {
+        let mut cache = REWRITE_CACHE.lock().unwrap();
+        let cache = cache.get_or_insert_with(|| {
+            if self.config.settings.rewrite_cache.persist {
+                RewriteCache::load(&Config::rewrite_cache_path(), self.config.settings.rewrite_cache.capacity)
             } else {
-                break;
+                RewriteCache::default()
             }
+        });
+        f(cache)
+    }
+    
+    /// Config, detected context, tool answers, and PATH: a change to any of
+    /// them can change what a command becomes
+    fn decision_fingerprint(&self) -> u64 {
+        rewrite_cache::fingerprint(&(
+            &self.config,
+            &self.context,
+            &self.tool_overrides,
+            &self.version_overrides,
+            &self.tool_cache,
+            &self.os,
+            std::env::var_os("PATH").map(|path| path.to_string_lossy().into_owned()),
+            wasm_plugin_files(),
+        ))
+    }
+    
+    /// [`Self::replace_command`] without consulting the rewrite cache
+    fn replace_uncached(&self, command: &str) -> Result<Option<String>> {
+        *self.last_fallback.lock().unwrap() = None;
+        self.plugin_asked.store(false, Ordering::Relaxed);
+        
+        // Allow/deny lists are checked before any parsing or rewriting
+        let settings = &self.config.settings;
+        if let Some(pattern) = matching_command_pattern(&settings.never_replace, command)? {
+            self.note("never_replace", format!("matched `{}`", pattern), None);
+            return self.decline(FallbackReason::new(command, "never-replace", format!("matched `{}`", pattern)));
+        }
+        if !settings.only_replace.is_empty() && matching_command_pattern(&settings.only_replace, command)?.is_none() {
+            self.note("only_replace", "not listed", None);
+            return self.decline(FallbackReason::new(command, "only-replace", "not listed in only_replace"));
+        }
+        
+        // `before` rewrites feed the structured engine; if it declines, the
+        // rewritten command still stands on its own
+        let rewritten = self.apply_rewrites(command, RewriteStage::Before)?;
+        if rewritten != command {
+            self.note("rewrites", "`before` [[rewrites]] applied", Some(&rewritten));
+        }
+        let replaced = match self.ask_plugins(&rewritten) {
+            Some(Answer::Rewrite(new_command)) => Some(new_command),
+            Some(Answer::Decline(reason)) => return self.decline(FallbackReason::new(command, "plugin", reason)),
+            _ => match self.replace_structured(&rewritten)? {
+                Some(new_command) => Some(new_command),
+                None if rewritten != command => Some(rewritten),
+                None => None,
+            },
+        };
+        
+        match replaced {
+            Some(new_command) => {
+                let pinned = pin_tool_paths(&new_command, |word| self.tool_location(word), |path| self.quote(path));
+                if pinned != new_command {
+                    self.note("tools", "tool paths pinned in [tools] or found outside PATH", Some(&pinned));
+                }
+                let new_command = self.apply_rewrites(&pinned, RewriteStage::After)?;
+                if new_command != pinned {
+                    self.note("rewrites", "`after` [[rewrites]] applied", Some(&new_command));
+                }
+                Ok(Some(new_command))
+            }
+            None => Ok(None),
         }
-        Ok(false)
     }
     
-    /// Replace a command if a better alternative is available
-    pub fn replace_command(&self, command: &str) -> Result<Option<String>> {
+    /// The first answer other than "no opinion" from the `[[plugins]]`
+    /// offered this command; plugins that fail are skipped
+    fn ask_plugins(&self, command: &str) -> Option<Answer> {
+        if self.config.plugins.is_empty() && !cfg!(feature = "wasm-plugins") {
+            return None;
+        }
+        let program = self.parse_command(command).ok().and_then(|parts| parts.into_iter().next()).unwrap_or_default();
+        let opinion = |plugin: &PluginConfig, answer: Result<Answer>| {
+            self.plugin_asked.store(true, Ordering::Relaxed);
+            match &answer {
+                Ok(Answer::Rewrite(new_command)) => self.note("plugin", format!("{} rewrote it", plugin.name), Some(new_command)),
+                Ok(Answer::Decline(reason)) => self.note("plugin", format!("{} declined: {}", plugin.name, reason), None),
+                Ok(Answer::Pass) => self.note("plugin", format!("{} had no opinion", plugin.name), None),
+                Err(e) => self.note("plugin", format!("{} was skipped: {:#}", plugin.name, e), None),
+            }
+            match answer {
+                Ok(Answer::Pass) | Err(_) => None,
+                Ok(Answer::Decline(reason)) => Some(Answer::Decline(format!("{}: {}", plugin.name, reason))),
+                Ok(answer) => Some(answer),
+            }
+        };
+        let answer = self.config.plugins
+            .iter()
+            .filter(|plugin| plugins::external(plugin) && plugins::offered(plugin, &program))
+            .find_map(|plugin| opinion(plugin, plugins::ask(plugin, command, &self.context)));
+        
+        #[cfg(feature = "wasm-plugins")]
+        let answer = answer.or_else(|| {
+            let runtime = self.wasm_plugins
+                .get_or_init(|| {
+                    let dir = Config::plugins_dir();
+                    dir.is_dir().then(|| crate::wasm_plugins::WasmRuntime::load(&dir, &Config::cache_dir(), &self.config.plugins).ok()).flatten()
+                })
+                .as_ref()?;
+            runtime.plugins
+                .iter()
+                .filter(|plugin| plugins::offered(&plugin.config, &program))
+                .find_map(|plugin| opinion(&plugin.config, runtime.ask(plugin, command, &self.context)))
+        });
+        answer
+    }
+    
+    /// Apply the enabled `[[rewrites]]` for one stage, in declaration order
+    fn apply_rewrites(&self, command: &str, stage: RewriteStage) -> Result<String> {
+        let mut command = command.to_string();
+        for rewrite in self.config.rewrites.iter().filter(|r| r.enabled && r.stage == stage) {
+            let regex = Regex::new(&rewrite.pattern)
+                .with_context(|| format!("Invalid rewrite pattern: {}", rewrite.pattern))?;
+            command = regex.replace_all(&command, rewrite.replacement.as_str()).into_owned();
+        }
+        Ok(command)
+    }
+    
+    /// Run the structured (tool-aware) replacement engine
+    fn replace_structured(&self, command: &str) -> Result<Option<String>> {
+        *self.unquoted_globs.lock().unwrap() = unquoted_glob_words(command, self.windows());
+        
         // Check if semantic analysis is enabled and command matches fallback patterns
-        if self.config.settings.semantic_analysis && self.matches_fallback_patterns(command)? {
-            return Ok(None);
+        if self.config.settings.semantic_analysis {
+            if let Some(pattern) = self.fallback_patterns.first_match(command) {
+                return self.decline(FallbackReason::new(command, "fallback-pattern", format!("matched `{}`", pattern)));
+            }
         }
         
         // Parse command into parts
@@ -62,48 +413,374 @@ impl ReplacementEngine {
         if parts.is_empty() {
             return Ok(None);
         }
+        let (words, tail) = split_at_shell_operator(&parts);
+        if let Some(wrapper) = wsl::Wrapper::parse(words) {
+            return self.replace_in_wsl(wrapper, tail);
+        }
+        if let Some(wrapper) = shell_wrappers::Wrapper::parse(words) {
+            return self.replace_in_shell(wrapper, tail);
+        }
+        let parts = self.translate_wsl_paths(parts);
         
-        let cmd = &parts[0];
+        let cmd = self.command_key(&parts[0]);
         let args = &parts[1..];
         
-        // Check if we have a replacement for this command
-        if let Some(replacement_config) = self.config.replacements.get(cmd) {
-            if !replacement_config.enabled {
-                return Ok(None);
+        // Every rule and built-in for this command, highest priority first;
+        // the sort is stable, so ties keep declaration order with `[[rules]]`
+        // ahead of the built-in
+        let mut candidates: Vec<Candidate> = self.config.rules.iter()
+            .filter(|rule| rule.command == cmd)
+            .map(Candidate::Rule)
+            .chain(self.config.replacements.get(cmd).filter(|_| !powershell::CMDLETS.contains(&cmd)).map(Candidate::Builtin))
+            .collect();
+        candidates.sort_by_key(|candidate| std::cmp::Reverse(candidate.priority()));
+        self.note_candidates(&candidates, args);
+        
+        // First candidate that produces a rewrite wins
+        for (index, candidate) in candidates.into_iter().enumerate() {
+            let earlier_reason = self.last_fallback.lock().unwrap().take();
+            let rewritten = match candidate {
+                Candidate::Rule(rule) => self.try_rule(command, cmd, args, rule)?,
+                Candidate::Builtin(replacement_config) => self.try_builtin(command, cmd, args, replacement_config)?,
+            };
+            // A candidate that gives no reason leaves the last one standing
+            let mut last_fallback = self.last_fallback.lock().unwrap();
+            let reason = last_fallback.clone();
+            if reason.is_none() {
+                *last_fallback = earlier_reason;
             }
-            
-            // Check if replacement tool is available
-            if self.is_tool_available(&replacement_config.replacement)? {
-                return self.apply_replacement(cmd, args, replacement_config);
-            } else if !replacement_config.use_fallback {
-                // Replacement not available and fallback disabled
-                return Ok(None);
+            drop(last_fallback);
+            self.note_outcome(index, rewritten.as_deref(), reason);
+            if rewritten.is_some() {
+                return Ok(rewritten);
             }
-            
-            // Try alternative tools for some commands
-            if let Some(alternative) = self.get_alternative_tool(cmd)? {
-                let alt_config = ReplacementConfig {
-                    replacement: alternative,
-                    ..replacement_config.clone()
+        }
+        
+        Ok(None)
+    }
+    
+    /// The `[replacements]` or `[[rules]]` command `program` runs: itself on
+    /// Unix, and on Windows, where `GREP` and `grep.exe` run grep too, the
+    /// command it names in any case and with or without `.exe`
+    fn command_key<'a>(&'a self, program: &'a str) -> &'a str {
+        if !self.windows() || self.config.replacements.contains_key(program) {
+            return program;
+        }
+        let name = match program.len().checked_sub(4) {
+            Some(stem) if program.is_char_boundary(stem) && program[stem..].eq_ignore_ascii_case(".exe") => &program[..stem],
+            _ => program,
+        };
+        self.config.replacements.keys()
+            .map(String::as_str)
+            .chain(self.config.rules.iter().map(|rule| rule.command.as_str()))
+            .find(|command| command.eq_ignore_ascii_case(name))
+            .unwrap_or(program)
+    }
+    
+    /// Rewrite the command a `wsl.exe` wrapper runs for the tools inside
+    /// WSL, keeping the wrapper
+    fn replace_in_wsl(&self, wrapper: wsl::Wrapper, tail: &[String]) -> Result<Option<String>> {
+        let inner: Vec<String> = wrapper.command
+            .iter()
+            .map(|word| quote_word(&wsl::to_wsl_path(word).unwrap_or_else(|| word.clone())))
+            .collect();
+        let probe = self.wsl_probe.clone().unwrap_or_else(|| {
+            let distro = wrapper.distro.clone();
+            Arc::new(move |tool: &str| wsl::tool_available(distro.as_deref(), tool))
+        });
+        // Pinned tools are Windows paths, and `[[rewrites]]` already saw
+        // the whole command
+        let config = Config { tools: HashMap::new(), rewrites: Vec::new(), ..self.config.clone() };
+        let engine = Self::for_context(config, RuleContext { wsl: true, ..self.context.clone() })?
+            .with_os("linux")
+            .with_tool_probe(move |tool| probe(tool));
+        let Some(rewritten) = engine.replace_command(&inner.join(" "))? else {
+            return match engine.last_fallback_reason() {
+                Some(reason) => self.decline(reason),
+                None => Ok(None),
+            };
+        };
+        
+        // `-e` runs a program, not a shell, so operators need one
+        let mut words = shlex::split(&rewritten).context("Failed to parse the rewritten command")?;
+        if wrapper.exec && words.iter().any(|word| is_shell_operator(word)) {
+            words = vec!["sh".to_string(), "-c".to_string(), rewritten];
+        }
+        let mut parts: Vec<String> = wrapper.prefix.iter().map(|word| self.quote(word)).collect();
+        parts.push(if wrapper.exec { "-e" } else { "--" }.to_string());
+        parts.extend(words.iter().map(|word| self.quote(word)));
+        let mut new_command = parts.join(" ");
+        if !tail.is_empty() {
+            new_command.push(' ');
+            new_command.push_str(&self.join_shell_tail(tail));
+        }
+        Ok(Some(new_command))
+    }
+    
+    /// Rewrite the command line a `cmd /c` or `powershell -Command` wrapper
+    /// runs, keeping the wrapper
+    fn replace_in_shell(&self, wrapper: shell_wrappers::Wrapper, tail: &[String]) -> Result<Option<String>> {
+        let payload = match wrapper.shell {
+            Shell::Cmd => self.replace_in_cmd(&wrapper.payload)?,
+            Shell::PowerShell => self.replace_in_powershell(&wrapper.payload)?,
+        };
+        let Some(payload) = payload else {
+            return Ok(None);
+        };
+        let mut parts: Vec<String> = wrapper.prefix.iter().map(|word| self.quote(word)).collect();
+        parts.push(self.quote(&payload));
+        let mut new_command = parts.join(" ");
+        if !tail.is_empty() {
+            new_command.push(' ');
+            new_command.push_str(&self.join_shell_tail(tail));
+        }
+        Ok(Some(new_command))
+    }
+    
+    /// Rewrite a cmd command line as on Windows, quoted for cmd
+    fn replace_in_cmd(&self, payload: &str) -> Result<Option<String>> {
+        // `[[rewrites]]` already saw the whole command
+        let mut config = Config { rewrites: Vec::new(), ..self.config.clone() };
+        // cmd.exe runs from WSL too, where the builtins are off and pinned
+        // tools are Linux paths
+        if !self.windows() {
+            config.tools.clear();
+            for command in cmd_builtins::COMMANDS {
+                if let Some(replacement) = config.replacements.get_mut(command) {
+                    replacement.enabled = true;
+                }
+            }
+        }
+        let mut engine = Self::for_context(config, self.context.clone())?.with_os("windows");
+        if let Some(probe) = self.tool_probe.clone() {
+            engine = engine.with_tool_probe(move |tool| probe(tool));
+        }
+        let Some(rewritten) = engine.replace_command(payload)? else {
+            return match engine.last_fallback_reason() {
+                Some(reason) => self.decline(reason),
+                None => Ok(None),
+            };
+        };
+        
+        let words = shlex::split(&rewritten).context("Failed to parse the rewritten command")?;
+        let quoted: Vec<String> = words
+            .iter()
+            .map(|word| if is_shell_operator(word) { word.clone() } else { shell_wrappers::cmd_quote(word) })
+            .collect();
+        Ok(Some(quoted.join(" ")))
+    }
+    
+    /// Rewrite a PowerShell command line that calls one cmdlet with literal
+    /// arguments, quoted for PowerShell
+    fn replace_in_powershell(&self, payload: &str) -> Result<Option<String>> {
+        let words = match powershell::split_words(payload) {
+            Ok(words) => words,
+            Err(reason) => return self.decline(reason),
+        };
+        let Some((name, args)) = words.split_first() else {
+            return Ok(None);
+        };
+        let Some(cmdlet) = powershell::cmdlet(name) else {
+            return Ok(None);
+        };
+        match self.config.replacements.get(cmdlet) {
+            Some(replacement_config) => self.try_builtin(payload, cmdlet, args, replacement_config),
+            None => Ok(None),
+        }
+    }
+    
+    /// `parts` with paths from the other side of WSL translated for this one
+    fn translate_wsl_paths(&self, parts: Vec<String>) -> Vec<String> {
+        let translate = if self.windows() {
+            wsl::to_windows_path
+        } else if self.context.wsl {
+            wsl::to_wsl_path
+        } else {
+            return parts;
+        };
+        parts.into_iter().map(|part| translate(&part).unwrap_or(part)).collect()
+    }
+    
+    /// What the engine makes of `command`, noting every decision on the way
+    /// (bypasses the rewrite cache)
+    pub fn explain(&self, command: &str) -> Result<Explanation> {
+        *self.trace.lock().unwrap() = Some(Trace::default());
+        let rewritten = self.replace_uncached(command);
+        let trace = self.trace.lock().unwrap().take().unwrap_or_default();
+        let rewritten = rewritten?;
+        Ok(Explanation {
+            command: command.to_string(),
+            parsed: ParsedCommand::new(&self.parse_command(command).unwrap_or_default()),
+            steps: trace.steps,
+            candidates: trace.candidates,
+            fallback: self.last_fallback_reason().filter(|_| rewritten.is_none()),
+            rewritten,
+        })
+    }
+    
+    /// Note a pipeline stage, when explaining
+    fn note(&self, stage: &str, detail: impl Into<String>, command: Option<&str>) {
+        if let Some(trace) = self.trace.lock().unwrap().as_mut() {
+            trace.steps.push(Step { stage: stage.to_string(), detail: detail.into(), command: command.map(str::to_string) });
+        }
+    }
+    
+    /// Note the candidates about to be tried, when explaining
+    fn note_candidates(&self, candidates: &[Candidate], args: &[String]) {
+        let mut trace = self.trace.lock().unwrap();
+        let Some(trace) = trace.as_mut() else {
+            return;
+        };
+        trace.candidates = candidates
+            .iter()
+            .map(|candidate| {
+                let (kind, tool, flags) = match candidate {
+                    Candidate::Rule(rule) => {
+                        let (rule_args, _) = split_at_shell_operator(args);
+                        ("rule", rules::rule_tool(rule).to_string(), rules::explain_rule_flags(rule, rule_args))
+                    }
+                    Candidate::Builtin(replacement) => ("builtin", replacement.replacement.clone(), explain::builtin_flags(args, replacement)),
                 };
-                return self.apply_replacement(cmd, args, &alt_config);
+                CandidateReport { kind, tool, priority: candidate.priority(), outcome: "not-reached", rewritten: None, reason: None, flags }
+            })
+            .collect();
+    }
+    
+    /// Note how the `index`th candidate did, when explaining
+    fn note_outcome(&self, index: usize, rewritten: Option<&str>, reason: Option<FallbackReason>) {
+        let mut trace = self.trace.lock().unwrap();
+        let Some(report) = trace.as_mut().and_then(|trace| trace.candidates.get_mut(index)) else {
+            return;
+        };
+        report.outcome = match (rewritten, &reason) {
+            (Some(_), _) => "applied",
+            (None, Some(_)) => "declined",
+            (None, None) => "skipped",
+        };
+        report.rewritten = rewritten.map(str::to_string);
+        report.reason = reason.filter(|_| rewritten.is_none());
+    }
+    
+    /// Apply a built-in replacement, if enabled and its tool is installed
+    fn try_builtin(&self, command: &str, cmd: &str, args: &[String], replacement_config: &ReplacementConfig) -> Result<Option<String>> {
+        if !replacement_config.enabled {
+            return Ok(None);
+        }
+        if self.config.settings.semantic_analysis {
+            if let Some(pattern) = self.replacement_fallbacks.get(cmd).and_then(|patterns| patterns.first_match(command)) {
+                return self.decline(FallbackReason::new(cmd, "fallback-pattern", format!("matched `{}`", pattern)));
+            }
+        }
+        if !self.when_satisfied(replacement_config.when.as_ref()) {
+            return self.decline(FallbackReason::new(cmd, "when-condition", "context does not match the replacement's `when` clause"));
+        }
+        
+        // The configured tool and its alternatives are looked up together
+        if replacement_config.use_fallback {
+            let mut tools = vec![replacement_config.replacement.as_str()];
+            tools.extend(alternative_tools(cmd, &self.os));
+            self.prefetch_tools(&tools);
+        }
+        
+        // Check if replacement tool is available
+        if self.is_tool_available(&replacement_config.replacement)? {
+            if let Some(required) = &replacement_config.min_version {
+                if !self.meets_min_version(&replacement_config.replacement, required)? {
+                    return self.decline(FallbackReason::new(
+                        cmd,
+                        "tool-version",
+                        format!("{} is older than the required {}", replacement_config.replacement, required),
+                    ));
+                }
             }
+            return self.apply_replacement(cmd, args, replacement_config);
+        } else if !replacement_config.use_fallback {
+            // Replacement not available and fallback disabled
+            return Ok(None);
+        }
+        
+        // Try alternative tools for some commands
+        if let Some(alternative) = self.get_alternative_tool(cmd)? {
+            // min_version describes the configured tool, not the alternative
+            let alt_config = ReplacementConfig {
+                replacement: alternative,
+                min_version: None,
+                ..replacement_config.clone()
+            };
+            return self.apply_replacement(cmd, args, &alt_config);
         }
         
+        self.decline(FallbackReason::new(cmd, "tool-missing", format!("{} is not installed", replacement_config.replacement)))
+    }
+    
+    /// `(command, tool)` for every enabled built-in whose tool is installed
+    /// and whose `when` clause holds, sorted by command (PowerShell cmdlets
+    /// aside, which bash never runs)
+    pub fn available_replacements(&self) -> Result<Vec<(String, String)>> {
+        let tools: Vec<&str> = self.config.replacements.values()
+            .filter(|replacement| replacement.enabled)
+            .map(|replacement| replacement.replacement.as_str())
+            .collect();
+        self.prefetch_tools(&tools);
+        
+        let mut available = Vec::new();
+        for (command, replacement) in &self.config.replacements {
+            if replacement.enabled
+                && !powershell::CMDLETS.contains(&command.as_str())
+                && self.when_satisfied(replacement.when.as_ref())
+                && self.is_tool_available(&replacement.replacement)?
+            {
+                available.push((command.clone(), replacement.replacement.clone()));
+            }
+        }
+        available.sort();
+        Ok(available)
+    }
+    
+    /// Whether an optional `when` clause holds in the detected context
+    fn when_satisfied(&self, when: Option<&WhenCondition>) -> bool {
+        when.is_none_or(|when| self.context.satisfies(when))
+    }
+    
+    /// Tool the built-in replacement for `command` rewrites to
+    pub fn replacement_tool(&self, command: &str) -> Option<&str> {
+        self.config.replacements.get(command).map(|replacement| replacement.replacement.as_str())
+    }
+    
+    /// Reason recorded by the last `replace_command` call that fell back, if any
+    pub fn last_fallback_reason(&self) -> Option<FallbackReason> {
+        self.last_fallback.lock().unwrap().clone()
+    }
+    
+    /// Record why a rewrite was declined and fall back to the original command
+    fn decline(&self, reason: FallbackReason) -> Result<Option<String>> {
+        *self.last_fallback.lock().unwrap() = Some(reason);
         Ok(None)
     }
     
-    /// Check if command matches any fallback patterns
-    fn matches_fallback_patterns(&self, command: &str) -> Result<bool> {
-        for pattern_str in &self.config.settings.fallback_patterns {
-            let regex = Regex::new(pattern_str)
-                .with_context(|| format!("Invalid fallback pattern: {}", pattern_str))?;
-            
-            if regex.is_match(command) {
-                return Ok(true);
+    /// Apply a `[[rules]]` entry, if enabled, applicable, and its tool is installed
+    fn try_rule(&self, command: &str, cmd: &str, args: &[String], rule: &RuleConfig) -> Result<Option<String>> {
+        if !rule.enabled || !self.when_satisfied(rule.when.as_ref()) {
+            return Ok(None);
+        }
+        if let Some(pattern) = rules::rule_fallback_pattern(rule, command) {
+            return self.decline(FallbackReason::new(cmd, "rule-fallback-pattern", format!("matched `{}`", pattern)));
+        }
+        if !self.is_tool_available(rules::rule_tool(rule))? {
+            return Ok(None);
+        }
+        
+        let (rule_args, tail) = split_at_shell_operator(args);
+        match rules::apply_rule(rule, rule_args, |word| self.shell_word(word)) {
+            Ok(mut new_command) => {
+                if !tail.is_empty() {
+                    new_command.push(' ');
+                    new_command.push_str(&self.join_shell_tail(tail));
+                }
+                Ok(Some(new_command))
             }
+            Err(reason) => self.decline(reason),
         }
-        Ok(false)
     }
     
     /// Apply a specific replacement transformation
@@ -120,12 +797,32 @@ impl ReplacementEngine {
             "ls" => self.replace_ls(args, config),
             "sed" => self.replace_sed(args, config),
             "ps" => self.replace_ps(args, config),
+            "dir" | "type" | "findstr" | "where" => self.replace_cmd_builtin(original_cmd, args, config),
+            "Get-ChildItem" | "Get-Content" | "Select-String" => self.replace_cmdlet(original_cmd, args, config),
             _ => Ok(None),
         }
     }
     
     /// Replace grep with ripgrep (rg)
     fn replace_grep(&self, args: &[String], config: &ReplacementConfig) -> Result<Option<String>> {
+        let (grep_args, tail) = split_at_shell_operator(args);
+        let args = &expand_grep_bundles(grep_args);
+        
+        let perl = args.iter().any(|arg| arg == "-P" || arg == "--perl-regexp");
+        if self.bsd_dialect("grep") {
+            if perl {
+                return self.decline(FallbackReason::new("grep", "dialect", "BSD grep has no -P, so the command fails as written"));
+            }
+            // BSD grep -r reads stdin without a path, where rg searches the cwd
+            let recursive = args.iter().any(|arg| matches!(arg.as_str(), "-r" | "-R" | "--recursive" | "--dereference-recursive"));
+            if recursive && !grep_has_path(args) {
+                return self.decline(FallbackReason::new("grep", "stdin", "BSD grep -r without a path reads stdin"));
+            }
+        }
+        // rg only speaks PCRE with --pcre2, and not every build has it
+        if perl && !self.has_capability(&config.replacement, Capability::RgPcre2) {
+            return self.decline(FallbackReason::new("grep", "pcre", "grep -P patterns use PCRE, and the installed rg was built without PCRE2"));
+        }
         // Check if we should use fallback due to semantic differences
         if self.should_use_grep_fallback(args)? {
             return Ok(None);
@@ -156,9 +853,36 @@ impl ReplacementEngine {
                     "-F" | "--fixed-strings" => {
                         new_args.push("--fixed-strings".to_string());
                     }
+                    // rg recurses by default, and its -r means --replace
+                    "-r" | "--recursive" => {}
+                    "-R" | "--dereference-recursive" => {
+                        new_args.push("--follow".to_string());
+                    }
+                    // rg's -h is --help
+                    "-h" | "--no-filename" => {
+                        new_args.push("--no-filename".to_string());
+                    }
+                    "-H" | "--with-filename" => {
+                        new_args.push("--with-filename".to_string());
+                    }
+                    // grep's bare --color means auto; rg requires a value
+                    "--color" | "--colour" => {
+                        new_args.push("--color".to_string());
+                        new_args.push("auto".to_string());
+                    }
+                    arg if arg.starts_with("--color=") || arg.starts_with("--colour=") => {
+                        let when = &arg[arg.find('=').unwrap() + 1..];
+                        match when {
+                            "always" | "never" | "auto" => {
+                                new_args.push("--color".to_string());
+                                new_args.push(when.to_string());
+                            }
+                            _ => return Ok(None),
+                        }
+                    }
+                    // Only reached when rg has PCRE2 (checked above)
                     "-P" | "--perl-regexp" => {
-                        // rg doesn't support PCRE, this is a semantic difference
-                        return Ok(None);
+                        new_args.push("--pcre2".to_string());
                     }
                     // Output format flags that might differ
                     "-o" | "--only-matching" => {
@@ -251,7 +975,25 @@ impl ReplacementEngine {
             i += 1;
         }
         
-        let new_command = format!("{} {}", config.replacement, new_args.join(" "));
+        // Downstream awk/cut stages expect grep's `file:line:match` shape, not
+        // rg's tty heading layout, so make the format explicit when piped
+        let piped = tail.first().is_some_and(|op| matches!(op.as_str(), "|" | "|&" | ">" | ">>"));
+        if piped {
+            new_args.push("--no-heading".to_string());
+            if args.iter().any(|arg| arg == "-n" || arg == "--line-number") {
+                new_args.retain(|arg| arg != "-n" && arg != "--line-number");
+                new_args.push("--line-number".to_string());
+            } else {
+                new_args.push("--no-line-number".to_string());
+            }
+        }
+        
+        let quoted: Vec<String> = new_args.iter().map(|arg| self.shell_word(arg)).collect();
+        let mut new_command = format!("{} {}", config.replacement, quoted.join(" "));
+        if !tail.is_empty() {
+            new_command.push(' ');
+            new_command.push_str(&self.join_shell_tail(tail));
+        }
         Ok(Some(new_command))
     }
     
@@ -259,14 +1001,8 @@ impl ReplacementEngine {
     fn should_use_grep_fallback(&self, args: &[String]) -> Result<bool> {
         for arg in args {
             match arg.as_str() {
-                // Regex flavors that differ significantly
-                "-P" | "--perl-regexp" => return Ok(true),
                 // Binary file handling differences
-                "-a" | "--text" => {
-                    if self.compatibility_mode {
-                        return Ok(true);
-                    }
-                }
+                "-a" | "--text" if self.compatibility_mode => return Ok(true),
                 // Some GNU grep specific options
                 "--null-data" | "-z" => return Ok(true),
                 _ => {}
@@ -320,6 +1056,23 @@ impl ReplacementEngine {
     
     /// Replace find with fd
     fn replace_find(&self, args: &[String], config: &ReplacementConfig) -> Result<Option<String>> {
+        if let Some(action) = args.iter().find(|arg| matches!(arg.as_str(), "-exec" | "-execdir" | "-ok" | "-okdir")) {
+            return self.decline(FallbackReason::new("find", "find-exec", format!("fd's --exec doesn't mirror find {}", action)));
+        }
+        if args.iter().any(|arg| arg == "-size") {
+            if !self.has_capability(&config.replacement, Capability::FdSize) {
+                return self.decline(FallbackReason::new("find", "find-size", "the installed fd is older than 7.1 and can't filter by size"));
+            }
+            // fd only sizes files, find sizes directories too
+            if !args.windows(2).any(|pair| pair[0] == "-type" && pair[1] == "f") {
+                return self.decline(FallbackReason::new("find", "find-size", "fd applies --size to files only; find -size without -type f also matches directories"));
+            }
+        }
+        if self.bsd_dialect("find") {
+            if let Some(detail) = bsd_find_difference(args) {
+                return self.decline(FallbackReason::new("find", "dialect", detail));
+            }
+        }
         // Check if we should use fallback due to semantic differences
         if self.should_use_find_fallback(args)? {
             return Ok(None);
@@ -393,8 +1146,14 @@ impl ReplacementEngine {
                         }
                     }
                 }
-                // Size restrictions (fd doesn't support these directly)
-                "-size" => return Ok(None),
+                // Only reached when fd can filter by size (checked above)
+                "-size" => {
+                    let Some(filters) = args.get(i + 1).and_then(|spec| fd_size_filters(spec)) else {
+                        return Ok(None);
+                    };
+                    new_args.extend(filters);
+                    i += 1;
+                }
                 // Time restrictions (fd has limited support)
                 "-mtime" | "-ctime" | "-atime" => return Ok(None),
                 // Actions (fd doesn't support find actions)
@@ -444,7 +1203,12 @@ impl ReplacementEngine {
         // Handle search paths - fd takes pattern first, then paths
         let has_pattern = pattern.is_some();
         if let Some(p) = pattern {
-            new_args.insert(0, p);
+            // -name patterns are globs, and fd's are regexes unless --glob
+            new_args.insert(0, self.shell_word(&p));
+            new_args.insert(0, "--glob".to_string());
+        } else if search_paths.iter().any(|path| path != ".") && !args.iter().any(|arg| arg == "-path" || arg == "-ipath") {
+            // Without a pattern fd would take the first path for one
+            new_args.push(".".to_string());
         }
         
         // If no search path was specified, add current directory
@@ -452,7 +1216,7 @@ impl ReplacementEngine {
         
         // Add search paths at the end
         for path in &search_paths {
-            new_args.push(path.clone());
+            new_args.push(self.shell_word(path));
         }
         
         if search_paths_empty && has_pattern {
@@ -474,17 +1238,12 @@ impl ReplacementEngine {
                 // Actions are not supported by fd
                 "-exec" | "-execdir" | "-ok" | "-okdir" | "-delete" | "-print0" => return Ok(true),
                 // Complex predicates not supported
-                "-size" | "-mtime" | "-ctime" | "-atime" | "-perm" | 
+                "-mtime" | "-ctime" | "-atime" | "-perm" | 
                 "-user" | "-group" | "-uid" | "-gid" => return Ok(true),
                 // Logic operators
                 "-and" | "-or" | "-not" | "!" | "(" | ")" => return Ok(true),
                 // File type tests beyond basic f/d/l
-                "-type" => {
-                    if i + 1 < args.len() && 
-                       !matches!(args[i + 1].as_str(), "f" | "d" | "l") {
-                        return Ok(true);
-                    }
-                }
+                "-type" if args.get(i + 1).is_some_and(|kind| !matches!(kind.as_str(), "f" | "d" | "l")) => return Ok(true),
                 _ => {}
             }
             
@@ -501,17 +1260,12 @@ impl ReplacementEngine {
     
     /// Convert glob pattern to regex pattern for fd
     fn convert_glob_to_regex(&self, glob_pattern: &str) -> Result<String> {
-        // fd supports glob patterns natively, but we need to handle some differences
-        let mut pattern = glob_pattern.to_string();
-        
-        // Remove quotes if present
-        if pattern.starts_with('"') && pattern.ends_with('"') {
-            pattern = pattern[1..pattern.len()-1].to_string();
-        } else if pattern.starts_with('\'') && pattern.ends_with('\'') {
-            pattern = pattern[1..pattern.len()-1].to_string();
-        }
+        // fd supports glob patterns natively, so only surrounding quotes are removed
+        let unquoted = ['"', '\''].into_iter().find_map(|quote| {
+            glob_pattern.strip_prefix(quote)?.strip_suffix(quote)
+        });
         
-        Ok(pattern)
+        Ok(unquoted.unwrap_or(glob_pattern).to_string())
     }
     
     /// Check if a find flag is known to be problematic with fd
@@ -545,110 +1299,583 @@ impl ReplacementEngine {
     
     /// Replace cat with bat (with plain output for compatibility)
     fn replace_cat(&self, args: &[String], config: &ReplacementConfig) -> Result<Option<String>> {
+        let (cat_args, tail) = split_at_shell_operator(args);
         let mut new_args = vec!["--style=plain".to_string()];
+        let mut files_only = false;
         
-        for arg in args {
-            if arg.starts_with('-') {
-                if let Some(mapped) = config.flag_mappings.get(arg) {
-                    new_args.push(mapped.clone());
-                } else if config.preserve_flags.contains(&arg.to_string()) {
-                    new_args.push(arg.to_string());
-                }
-            } else {
-                new_args.push(arg.to_string());
-            }
-        }
-        
-        let new_command = format!("{} {}", config.replacement, new_args.join(" "));
-        Ok(Some(new_command))
-    }
-    
-    /// Replace ls with eza/exa
-    fn replace_ls(&self, args: &[String], config: &ReplacementConfig) -> Result<Option<String>> {
-        let mut new_args = Vec::new();
-        
-        for arg in args {
-            if config.preserve_flags.contains(&arg.to_string()) {
+        for arg in cat_args {
+            if files_only || !arg.starts_with('-') || arg == "-" {
+                new_args.push(self.shell_word(arg));
+            } else if arg == "--" {
+                files_only = true;
                 new_args.push(arg.to_string());
-            } else if !arg.starts_with('-') {
+            } else if let Some(mapped) = config.flag_mappings.get(arg) {
+                new_args.push(mapped.clone());
+            } else if config.preserve_flags.contains(&arg.to_string()) {
                 new_args.push(arg.to_string());
+            } else {
+                let flags: Vec<String> = if arg.starts_with("--") {
+                    vec![arg.clone()]
+                } else {
+                    // Split bundles like -ns into single flags
+                    arg[1..].chars().map(String::from).collect()
+                };
+                for flag in &flags {
+                    match self.translate_cat_flag(flag) {
+                        Ok(Some(translated)) => new_args.push(translated.to_string()),
+                        Ok(None) => {}
+                        Err(reason) => return self.decline(reason),
+                    }
+                }
             }
         }
         
-        let new_command = format!("{} {}", config.replacement, new_args.join(" "));
+        new_args.dedup();
+        let mut new_command = format!("{} {}", config.replacement, new_args.join(" "));
+        if !tail.is_empty() {
+            new_command.push(' ');
+            new_command.push_str(&self.join_shell_tail(tail));
+        }
         Ok(Some(new_command))
     }
     
-    /// Replace sed with sd (simple cases only)
-    fn replace_sed(&self, args: &[String], config: &ReplacementConfig) -> Result<Option<String>> {
-        // Only handle simple s/pattern/replacement/ cases
-        if args.len() >= 1 {
-            let expr = &args[0];
-            if let Some(captures) = self.parse_sed_expression(expr)? {
-                let mut new_args = vec![captures.0, captures.1];
-                // Add remaining arguments (files)
-                new_args.extend_from_slice(&args[1..]);
-                
-                let new_command = format!("{} {}", config.replacement, new_args.join(" "));
-                return Ok(Some(new_command));
+    /// Translate a single cat flag to bat, `Ok(None)` meaning "no-op"
+    fn translate_cat_flag(&self, flag: &str) -> std::result::Result<Option<&'static str>, FallbackReason> {
+        let exact = |flag: &str, what: &str| FallbackReason::new(
+            "cat",
+            "output-format",
+            format!("cat {} {}; bat's rendering differs in compatibility mode", flag, what),
+        );
+        
+        match flag {
+            "n" | "--number" if self.compatibility_mode => Err(exact(flag, "pads line numbers differently")),
+            "n" | "--number" => Ok(Some("--number")),
+            // bat has no "number non-blank lines" mode; numbering every line is the closest match
+            "b" | "--number-nonblank" if self.compatibility_mode => Err(exact(flag, "skips blank lines when numbering")),
+            "b" | "--number-nonblank" => Ok(Some("--number")),
+            "A" | "--show-all" if self.compatibility_mode => Err(exact(flag, "marks line ends with `$` and tabs with `^I`")),
+            "A" | "--show-all" => Ok(Some("--show-all")),
+            "s" | "--squeeze-blank" => Ok(Some("--squeeze-blank")),
+            // POSIX no-op
+            "u" => Ok(None),
+            "E" | "--show-ends" | "T" | "--show-tabs" | "v" | "--show-nonprinting" | "e" | "t" => {
+                Err(FallbackReason::new(
+                    "cat",
+                    "option",
+                    format!("cat {} can't be expressed without bat's --show-all extras", flag),
+                ))
             }
+            _ => Err(FallbackReason::new(
+                "cat",
+                "option",
+                format!("cat option {} has no bat equivalent", flag),
+            )),
         }
-        
-        // Complex sed expressions - use fallback
-        Ok(None)
     }
     
-    /// Replace ps with procs
-    fn replace_ps(&self, args: &[String], config: &ReplacementConfig) -> Result<Option<String>> {
+    /// Replace ls with eza/exa
+    fn replace_ls(&self, args: &[String], config: &ReplacementConfig) -> Result<Option<String>> {
+        let (ls_args, tail) = split_at_shell_operator(args);
         let mut new_args = Vec::new();
+        // exa is the fallback when eza is missing, and lacks eza's newer flags
+        let eza = config.replacement == "eza" || self.has_capability(&config.replacement, Capability::EzaFlags);
         
-        for arg in args {
-            if config.preserve_flags.contains(&arg.to_string()) {
-                new_args.push(arg.to_string());
-            } else if !arg.starts_with('-') {
+        for arg in ls_args {
+            let supported = eza || !EZA_ONLY_FLAGS.contains(&arg.as_str());
+            if !arg.starts_with('-') {
+                new_args.push(self.shell_word(arg));
+            } else if config.preserve_flags.contains(&arg.to_string()) && supported {
                 new_args.push(arg.to_string());
             }
         }
         
-        let new_command = format!("{} {}", config.replacement, new_args.join(" "));
+        let mut new_command = format!("{} {}", config.replacement, new_args.join(" "));
+        if !tail.is_empty() {
+            new_command.push(' ');
+            new_command.push_str(&self.join_shell_tail(tail));
+        }
         Ok(Some(new_command))
     }
     
-    /// Parse sed s/pattern/replacement/ expressions
-    fn parse_sed_expression(&self, expr: &str) -> Result<Option<(String, String)>> {
-        static SED_REGEX: Lazy<Regex> = Lazy::new(|| {
-            Regex::new(r"^s/([^/]+)/([^/]*)/[gi]*$").unwrap()
-        });
+    /// Replace a cmd.exe builtin (dir, type, findstr, where)
+    fn replace_cmd_builtin(&self, cmd: &str, args: &[String], config: &ReplacementConfig) -> Result<Option<String>> {
+        let (cmd_args, tail) = split_at_shell_operator(args);
+        let new_args = match cmd_builtins::translate(cmd, cmd_args) {
+            Ok(new_args) => new_args,
+            Err(reason) => return self.decline(reason),
+        };
         
-        if let Some(captures) = SED_REGEX.captures(expr) {
-            let pattern = captures.get(1).unwrap().as_str().to_string();
-            let replacement = captures.get(2).unwrap().as_str().to_string();
-            Ok(Some((pattern, replacement)))
-        } else {
-            Ok(None)
+        // cmd expands wildcards in the builtin, not the shell, so globs are
+        // quoted for the tool to match; flags are the translation's own
+        let quoted: Vec<String> = new_args
+            .iter()
+            .map(|arg| if arg.starts_with("--") { arg.clone() } else { self.quote(arg) })
+            .collect();
+        let mut new_command = format!("{} {}", config.replacement, quoted.join(" "));
+        if !tail.is_empty() {
+            new_command.push(' ');
+            new_command.push_str(&self.join_shell_tail(tail));
         }
+        Ok(Some(new_command))
     }
     
-    /// Get alternative tool if primary replacement isn't available
-    fn get_alternative_tool(&self, original_cmd: &str) -> Result<Option<String>> {
-        match original_cmd {
-            "ls" => {
-                // Try exa if eza isn't available
-                if self.is_tool_available("exa")? {
-                    Ok(Some("exa".to_string()))
-                } else {
-                    Ok(None)
-                }
-            }
-            _ => Ok(None),
-        }
+    /// Replace a PowerShell cmdlet, quoting for PowerShell rather than bash
+    fn replace_cmdlet(&self, cmdlet: &str, args: &[String], config: &ReplacementConfig) -> Result<Option<String>> {
+        let new_args = match powershell::translate(cmdlet, args) {
+            Ok(new_args) => new_args,
+            Err(reason) => return self.decline(reason),
+        };
+        
+        // The whole payload is one word to bash, so tools are pinned here;
+        // PowerShell only runs a quoted path with `&`
+        let tool = self.tool_location(&config.replacement).unwrap_or_else(|| config.replacement.clone());
+        let program = powershell::quote(&tool);
+        let mut words = vec![if program == tool { program } else { format!("& {}", program) }];
+        words.extend(new_args.iter().map(|arg| powershell::quote(arg)));
+        Ok(Some(words.join(" ")))
     }
     
-    /// Check if a tool is available on the system
-    pub fn is_tool_available(&self, tool: &str) -> Result<bool> {
-        if !self.config.settings.cache_tool_checks {
-            return Ok(which(tool).is_ok());
-        }
+    /// Replace sed with sd (substitution scripts only)
+    ///
+    /// Handles any `s` delimiter, repeated `-e` scripts (chained sd calls),
+    /// `-i[SUFFIX]` in-place edits with backups (BSD sed's `-i SUFFIX` on macOS),
+    /// and `-E`/`-r` extended regexes.
+    /// sd always edits files in place, so non-`-i` invocations read the file
+    /// through stdin instead to keep sed's print-to-stdout behavior.
+    fn replace_sed(&self, args: &[String], config: &ReplacementConfig) -> Result<Option<String>> {
+        let (sed_args, tail) = split_at_shell_operator(args);
+        
+        let mut scripts: Vec<String> = Vec::new();
+        let mut explicit_scripts = false;
+        let mut extended = false;
+        let mut quiet = false;
+        let mut in_place: Option<Option<String>> = None;
+        let mut files: Vec<String> = Vec::new();
+        let mut i = 0;
+        // BSD sed's -i always takes a suffix, `''` for none, as the next word
+        let bsd = self.bsd_dialect("sed");
+        
+        while i < sed_args.len() {
+            let arg = sed_args[i].as_str();
+            
+            match arg {
+                arg if bsd && arg.starts_with("--") && arg != "--" => return self.decline(FallbackReason::new(
+                    "sed",
+                    "dialect",
+                    format!("BSD sed has no long options such as {}", arg),
+                )),
+                "-i" if bsd => {
+                    if i + 1 >= sed_args.len() {
+                        return Ok(None);
+                    }
+                    i += 1;
+                    in_place = Some((!sed_args[i].is_empty()).then(|| sed_args[i].clone()));
+                }
+                "-e" | "--expression" => {
+                    if i + 1 >= sed_args.len() {
+                        return Ok(None);
+                    }
+                    i += 1;
+                    scripts.push(sed_args[i].clone());
+                    explicit_scripts = true;
+                }
+                arg if arg.starts_with("--expression=") => {
+                    scripts.push(arg[13..].to_string());
+                    explicit_scripts = true;
+                }
+                "-E" | "-r" | "--regexp-extended" => extended = true,
+                "-i" | "--in-place" => in_place = Some(None),
+                arg if arg.starts_with("--in-place=") => in_place = Some(Some(arg[11..].to_string())),
+                "--" => {
+                    files.extend(sed_args[i + 1..].iter().cloned());
+                    break;
+                }
+                "--quiet" | "--silent" => quiet = true,
+                arg if arg.starts_with("--") => return self.decline(FallbackReason::new(
+                    "sed",
+                    "option",
+                    format!("sed option {} has no sd equivalent", arg),
+                )),
+                // Bundled short options such as -Ei, -i.bak, or -ne
+                arg if arg.starts_with('-') && arg.len() > 1 => {
+                    let flags = &arg[1..];
+                    for (idx, c) in flags.char_indices() {
+                        match c {
+                            'E' | 'r' => extended = true,
+                            'i' => {
+                                let mut suffix = flags[idx + 1..].to_string();
+                                if bsd && suffix.is_empty() {
+                                    if i + 1 >= sed_args.len() {
+                                        return Ok(None);
+                                    }
+                                    i += 1;
+                                    suffix = sed_args[i].clone();
+                                }
+                                in_place = Some((!suffix.is_empty()).then_some(suffix));
+                                break;
+                            }
+                            'e' if idx + 1 == flags.len() => {
+                                if i + 1 >= sed_args.len() {
+                                    return Ok(None);
+                                }
+                                i += 1;
+                                scripts.push(sed_args[i].clone());
+                                explicit_scripts = true;
+                            }
+                            // Auto-print suppression only matters for p/w, classified below
+                            'n' => quiet = true,
+                            // -s, -z, -f script files, etc. change semantics sd can't match
+                            _ => return self.decline(FallbackReason::new(
+                                "sed",
+                                "option",
+                                format!("sed option -{} has no sd equivalent", c),
+                            )),
+                        }
+                    }
+                }
+                _ => {
+                    if !explicit_scripts && scripts.is_empty() {
+                        scripts.push(arg.to_string());
+                    } else {
+                        files.push(arg.to_string());
+                    }
+                }
+            }
+            
+            i += 1;
+        }
+        
+        if scripts.is_empty() {
+            return Ok(None);
+        }
+        
+        // Classify every script first so the reason names the construct, not just -n
+        for script in &scripts {
+            let construct = classify_sed_script(script);
+            if construct != SedConstruct::Substitution {
+                return self.decline(construct.fallback_reason(script));
+            }
+        }
+        if quiet {
+            return self.decline(FallbackReason::new(
+                "sed",
+                "quiet-mode",
+                "sed -n suppresses output that sd would always print",
+            ));
+        }
+        
+        let mut calls = Vec::with_capacity(scripts.len());
+        for script in &scripts {
+            let Some(substitution) = self.parse_sed_expression(script, extended)? else {
+                return self.decline(FallbackReason::new(
+                    "sed",
+                    "substitution-flags",
+                    format!("`{}` uses substitution flags other than g/i", script),
+                ));
+            };
+            // sd has no per-line "first match only" mode
+            if !substitution.global && self.compatibility_mode {
+                return Ok(None);
+            }
+            // GNU's basic-regex \+, \?, and \| are literal characters to BSD sed
+            if bsd && !extended && ["\\+", "\\?", "\\|"].iter().any(|op| substitution.pattern.contains(op)) {
+                return self.decline(FallbackReason::new(
+                    "sed",
+                    "dialect",
+                    format!("`{}` uses GNU basic-regex operators that BSD sed reads differently", script),
+                ));
+            }
+            let Some(pattern) = sed_pattern_to_sd(&substitution.pattern, extended) else {
+                return Ok(None);
+            };
+            let Some(replacement) = sed_replacement_to_sd(&substitution.replacement) else {
+                return Ok(None);
+            };
+            
+            let mut call = vec![config.replacement.clone()];
+            if substitution.case_insensitive {
+                call.push("-f".to_string());
+                call.push("i".to_string());
+            }
+            if pattern.starts_with('-') || replacement.starts_with('-') {
+                call.push("--".to_string());
+            }
+            call.push(quote_word(&pattern));
+            call.push(quote_word(&replacement));
+            calls.push(call.join(" "));
+        }
+        
+        let mut new_command = match in_place {
+            Some(suffix) => {
+                if files.is_empty() {
+                    return Ok(None);
+                }
+                let quoted_files: Vec<String> = files.iter().map(|f| self.shell_word(f)).collect();
+                let mut steps = Vec::new();
+                if let Some(suffix) = suffix {
+                    // GNU treats `*` in the suffix as a basename template
+                    if suffix.contains('*') || suffix.contains('/') {
+                        return Ok(None);
+                    }
+                    for file in &files {
+                        steps.push(format!("cp -p -- {} {}", self.shell_word(file), self.quote(&format!("{}{}", file, suffix))));
+                    }
+                }
+                for call in &calls {
+                    steps.push(format!("{} {}", call, quoted_files.join(" ")));
+                }
+                steps.join(" && ")
+            }
+            None => {
+                if files.len() > 1 {
+                    return Ok(None);
+                }
+                if let Some(file) = files.first() {
+                    calls[0] = format!("{} < {}", calls[0], self.shell_word(file));
+                }
+                calls.join(" | ")
+            }
+        };
+        
+        if !tail.is_empty() {
+            new_command.push(' ');
+            new_command.push_str(&self.join_shell_tail(tail));
+        }
+        Ok(Some(new_command))
+    }
+    
+    /// Replace ps with procs, translating process selection rather than dropping it
+    fn replace_ps(&self, args: &[String], config: &ReplacementConfig) -> Result<Option<String>> {
+        let (ps_args, tail) = split_at_shell_operator(args);
+        
+        let mut tree = false;
+        let mut pids: Vec<String> = Vec::new();
+        let mut user: Option<String> = None;
+        let mut passthrough = Vec::new();
+        let mut i = 0;
+        
+        while i < ps_args.len() {
+            let arg = ps_args[i].as_str();
+            
+            match arg {
+                // Custom output formats are parsed by scripts; procs columns differ
+                "-o" | "-O" | "o" | "O" | "--format" => return Ok(None),
+                arg if arg.starts_with("-o") || arg.starts_with("--format=") => return Ok(None),
+                // Process ID selection becomes a procs keyword search
+                "-p" | "p" | "--pid" | "-q" | "q" | "--quick-pid" => {
+                    if i + 1 >= ps_args.len() {
+                        return Ok(None);
+                    }
+                    i += 1;
+                    pids.extend(ps_args[i].split(',').map(str::to_string));
+                }
+                arg if arg.starts_with("--pid=") => {
+                    pids.extend(arg[6..].split(',').map(str::to_string));
+                }
+                arg if arg.starts_with("-p") && arg[2..].chars().all(|c| c.is_ascii_digit() || c == ',') => {
+                    pids.extend(arg[2..].split(',').map(str::to_string));
+                }
+                // User selection
+                "-u" | "-U" | "--user" | "--User" => {
+                    if i + 1 >= ps_args.len() {
+                        return Ok(None);
+                    }
+                    i += 1;
+                    user = Some(ps_args[i].clone());
+                }
+                arg if arg.starts_with("--user=") => user = Some(arg[7..].to_string()),
+                // Hierarchy display
+                "--forest" | "-H" => tree = true,
+                // Whole-system selectors and format modifiers, possibly bundled (aux, -ef, -eLf)
+                arg if is_ps_listing_bundle(arg) => {
+                    tree |= arg.starts_with('-') && arg.contains('H') ||
+                        !arg.starts_with('-') && arg.contains('f');
+                }
+                arg if arg.starts_with('-') && config.preserve_flags.contains(&arg.to_string()) => {
+                    passthrough.push(arg.to_string());
+                }
+                // Flags mapped to nothing, like MSYS ps -W on Windows
+                arg if config.flag_mappings.get(arg).is_some_and(String::is_empty) => {}
+                // Anything else (sorting, thread views, tty selection) has no safe equivalent
+                _ => return Ok(None),
+            }
+            
+            i += 1;
+        }
+        
+        // ps ORs selectors together; procs mixes keyword and flag filters differently
+        if user.is_some() && !pids.is_empty() {
+            return Ok(None);
+        }
+        if pids.iter().any(|pid| pid.is_empty() || !pid.chars().all(|c| c.is_ascii_digit())) {
+            return Ok(None);
+        }
+        
+        let mut new_args = passthrough;
+        if tree {
+            new_args.push("--tree".to_string());
+        }
+        if let Some(user) = user {
+            new_args.push("--uid".to_string());
+            new_args.push(user);
+        }
+        new_args.extend(pids);
+        
+        let mut new_command = config.replacement.clone();
+        if !new_args.is_empty() {
+            new_command.push(' ');
+            new_command.push_str(&new_args.join(" "));
+        }
+        if !tail.is_empty() {
+            new_command.push(' ');
+            new_command.push_str(&self.join_shell_tail(tail));
+        }
+        Ok(Some(new_command))
+    }
+    
+    /// Parse a sed substitution expression with any delimiter (`s/a/b/g`, `s|a|b|`, `s#a#b#`)
+    fn parse_sed_expression(&self, expr: &str, extended: bool) -> Result<Option<SedSubstitution>> {
+        let mut chars = expr.chars();
+        if chars.next() != Some('s') {
+            return Ok(None);
+        }
+        let delimiter = match chars.next() {
+            Some(c) if c != '\\' && c != '\n' && !c.is_alphanumeric() && !c.is_whitespace() => c,
+            _ => return Ok(None),
+        };
+        
+        let mut sections = vec![String::new()];
+        while let Some(c) = chars.next() {
+            if sections.len() == 3 {
+                sections[2].push(c);
+                continue;
+            }
+            let in_pattern = sections.len() == 1;
+            let current = sections.last_mut().unwrap();
+            if c == '\\' {
+                match chars.next() {
+                    // An escaped delimiter is a literal delimiter character
+                    Some(next) if next == delimiter => {
+                        if in_pattern && (extended || !"(){}+?|".contains(next)) {
+                            current.push_str(&regex::escape(&next.to_string()));
+                        } else {
+                            current.push(next);
+                        }
+                    }
+                    Some(next) => {
+                        current.push('\\');
+                        current.push(next);
+                    }
+                    None => return Ok(None),
+                }
+            } else if c == delimiter {
+                sections.push(String::new());
+            } else {
+                current.push(c);
+            }
+        }
+        
+        if sections.len() != 3 || sections[0].is_empty() {
+            return Ok(None);
+        }
+        
+        let mut substitution = SedSubstitution {
+            pattern: std::mem::take(&mut sections[0]),
+            replacement: std::mem::take(&mut sections[1]),
+            global: false,
+            case_insensitive: false,
+        };
+        for flag in sections[2].chars() {
+            match flag {
+                'g' => substitution.global = true,
+                'i' | 'I' => substitution.case_insensitive = true,
+                // Occurrence numbers, p, w, e, m: no sd equivalent
+                _ => return Ok(None),
+            }
+        }
+        
+        Ok(Some(substitution))
+    }
+    
+    /// Get alternative tool if primary replacement isn't available
+    fn get_alternative_tool(&self, original_cmd: &str) -> Result<Option<String>> {
+        for tool in alternative_tools(original_cmd, &self.os) {
+            if self.is_tool_available(tool)? {
+                return Ok(Some(tool.to_string()));
+            }
+        }
+        Ok(None)
+    }
+    
+    /// Look up on PATH, concurrently, whichever of `tools` would otherwise
+    /// each need a sequential search, waiting no longer than the deadline
+    fn prefetch_tools(&self, tools: &[&str]) {
+        if self.tool_probe.is_some() {
+            return;
+        }
+        let pending: Vec<String> = {
+            let prefetched = self.prefetched.lock().unwrap();
+            let cached = TOOL_CACHE.lock().unwrap();
+            let cache_duration = Duration::from_millis(self.config.settings.tool_check_timeout);
+            tools.iter()
+                .filter(|tool| {
+                    !self.tool_overrides.contains_key(**tool)
+                        && !self.config.tools.contains_key(**tool)
+                        && !prefetched.contains_key(**tool)
+                        && (!self.config.settings.cache_tool_checks
+                            || (self.tool_cache.get(tool).is_none()
+                                && cached.get(**tool).is_none_or(|(_, at)| at.elapsed() >= cache_duration)))
+                })
+                .map(|tool| tool.to_string())
+                .collect()
+        };
+        // One lookup costs no more done in place
+        if pending.len() < 2 {
+            return;
+        }
+        
+        let found = self.probe(|| probe_concurrently(pending.clone(), self.deadline, |tool: &String| find_program(tool).is_some()));
+        if found.len() < pending.len() {
+            self.probes_timed_out.store(true, Ordering::Relaxed);
+        }
+        if self.config.settings.cache_tool_checks {
+            let now = Instant::now();
+            let mut cached = TOOL_CACHE.lock().unwrap();
+            for (tool, available) in &found {
+                cached.insert(tool.clone(), (*available, now));
+            }
+        }
+        let mut prefetched = self.prefetched.lock().unwrap();
+        for tool in pending {
+            let available = found.iter().any(|(found, available)| *found == tool && *available);
+            prefetched.insert(tool, available);
+        }
+    }
+    
+    /// Check if a tool is available on the system
+    pub fn is_tool_available(&self, tool: &str) -> Result<bool> {
+        if let Some(available) = self.tool_overrides.get(tool) {
+            return Ok(*available);
+        }
+        // A pinned path replaces the PATH lookup entirely
+        if let Some(path) = self.config.tools.get(tool) {
+            return Ok(self.probe(|| std::path::Path::new(path).is_file()));
+        }
+        if let Some(available) = self.prefetched.lock().unwrap().get(tool) {
+            return Ok(*available);
+        }
+
+        // The embedder's probe is the authority, and does its own caching
+        if let Some(tool_probe) = &self.tool_probe {
+            return Ok(self.probe(|| tool_probe(tool)));
+        }
+        if !self.config.settings.cache_tool_checks {
+            return Ok(self.probe(|| find_program(tool).is_some()));
+        }
+        
+        if let Some(entry) = self.tool_cache.get(tool) {
+            return Ok(entry.path.is_some());
+        }
         
         let cache_duration = Duration::from_millis(self.config.settings.tool_check_timeout);
         let now = Instant::now();
@@ -661,15 +1888,710 @@ impl ReplacementEngine {
             }
         }
         
-        let available = which(tool).is_ok();
+        let available = self.probe(|| find_program(tool).is_some());
         cache.insert(tool.to_string(), (available, now));
         
         Ok(available)
     }
     
+    /// The path to name `tool` by in emitted commands: pinned in `[tools]`,
+    /// or where it was found outside PATH (a scoop shim, say)
+    fn tool_location(&self, tool: &str) -> Option<String> {
+        if let Some(path) = self.config.tools.get(tool) {
+            return Some(path.clone());
+        }
+        if self.tool_overrides.contains_key(tool) || self.tool_probe.is_some() {
+            return None;
+        }
+        off_path_location(tool, &self.tool_cache).map(|path| path.to_string_lossy().into_owned())
+    }
+    
+    /// Whether the installed tool is at least `required`; an unparseable
+    /// version counts as too old
+    fn meets_min_version(&self, tool: &str, required: &str) -> Result<bool> {
+        let required = parse_version(required)
+            .with_context(|| format!("Invalid min_version '{}' for {}", required, tool))?;
+        Ok(self.tool_version(tool).is_some_and(|installed| version_at_least(&installed, &required)))
+    }
+    
+    /// The installed tool's parsed version
+    fn tool_version(&self, tool: &str) -> Option<Vec<u64>> {
+        if !self.version_overrides.contains_key(tool) {
+            if let Some(version) = self.tool_cache.get(tool).and_then(|entry| entry.version.clone()) {
+                return Some(version);
+            }
+        }
+        parse_version(&self.version_text(tool)?)
+    }
+    
+    /// Run `tool --version` once per process and keep what it prints
+    fn version_text(&self, tool: &str) -> Option<String> {
+        if let Some(output) = self.version_overrides.get(tool) {
+            return Some(output.clone());
+        }
+        if let Some(text) = self.tool_cache.get(tool).and_then(|entry| entry.version_text.clone()) {
+            return Some(text);
+        }
+        // A test override stands in for the tool, so there is nothing to run
+        if self.tool_overrides.contains_key(tool) {
+            return None;
+        }
+        
+        let mut versions = TOOL_VERSIONS.lock().unwrap();
+        versions
+            .entry(tool.to_string())
+            .or_insert_with(|| {
+                let program = self.config.tools.get(tool).map_or(tool, String::as_str);
+                self.probe(|| probe_version_text(std::path::Path::new(program)))
+            })
+            .clone()
+    }
+    
+    /// Run a tool probe, counting its time towards [`Self::probe_time`]
+    fn probe<T>(&self, probe: impl FnOnce() -> T) -> T {
+        let started = Instant::now();
+        let result = probe();
+        *self.probe_time.lock().unwrap() += started.elapsed();
+        result
+    }
+    
+    /// Total time this engine has spent probing tools
+    pub fn probe_time(&self) -> Duration {
+        *self.probe_time.lock().unwrap()
+    }
+    
+    /// Whether the installed `tool` has `capability`; unknown counts as no
+    pub fn has_capability(&self, tool: &str, capability: Capability) -> bool {
+        self.version_text(tool).is_some_and(|text| capability.detect(&text))
+    }
+    
+    /// Whether `tool` is the BSD build, as macOS and the BSDs ship it, unless
+    /// a GNU build (say Homebrew's gnubin) comes first on PATH
+    fn bsd_dialect(&self, tool: &str) -> bool {
+        matches!(self.os.as_str(), "macos" | "freebsd" | "openbsd" | "netbsd" | "dragonfly")
+            && !self.has_capability(tool, Capability::Gnu)
+    }
+    
+    /// Quote a word for the rewritten command, leaving globs the user wrote
+    /// unquoted bare so the shell still expands them
+    fn shell_word(&self, word: &str) -> String {
+        if !self.unquoted_globs.lock().unwrap().contains(word) {
+            self.quote(word)
+        } else if self.windows() {
+            // A backslash would escape the next character instead
+            word.replace('\\', "/")
+        } else {
+            word.to_string()
+        }
+    }
+    
+    /// Quote a word, keeping Windows paths readable (and valid in cmd too)
+    /// by double-quoting words with backslashes
+    fn quote(&self, word: &str) -> String {
+        if self.windows() && word.contains('\\') {
+            quote_windows_word(word)
+        } else {
+            quote_word(word)
+        }
+    }
+    
+    /// Whether commands follow Windows conventions, where a backslash is a
+    /// path separator rather than an escape
+    fn windows(&self) -> bool {
+        self.os == "windows"
+    }
+    
+    /// Re-join a pipeline tail, quoting words again but leaving operators bare
+    fn join_shell_tail(&self, tail: &[String]) -> String {
+        tail.iter()
+            .map(|token| if is_shell_operator(token) { token.clone() } else { self.shell_word(token) })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+    
     /// Parse command string into parts using shell parsing
     fn parse_command(&self, command: &str) -> Result<Vec<String>> {
-        shlex::split(command)
-            .context("Failed to parse command")
+        let words = if self.windows() { split_windows_words(command) } else { shlex::split(command) };
+        words.context("Failed to parse command")
+    }
+}
+
+/// Check whether a ps argument only selects every process and/or widens the output.
+///
+/// procs lists all processes with full command lines by default, so BSD bundles
+/// like `aux`/`axjf` and UNIX bundles like `-ef`/`-eLf` carry no extra meaning
+/// beyond the tree view, which the caller handles separately.
+fn is_ps_listing_bundle(arg: &str) -> bool {
+    match arg.strip_prefix('-') {
+        Some(flags) => !flags.is_empty() && flags.chars().all(|c| matches!(c, 'e' | 'A' | 'a' | 'x' | 'd' | 'f' | 'F' | 'l' | 'y' | 'w' | 'H' | 'j')),
+        None => !arg.is_empty() && arg.chars().all(|c| matches!(c, 'a' | 'u' | 'x' | 'w' | 'f' | 'j' | 'l')),
+    }
+}
+
+/// fd `--size` filters matching find's `-size` test exactly
+///
+/// find rounds sizes up to whole units before comparing, so `-size -1M` only
+/// matches empty files; working the bounds out in bytes keeps that.
+fn fd_size_filters(spec: &str) -> Option<Vec<String>> {
+    let (sign, rest) = match spec.strip_prefix(['+', '-']) {
+        Some(rest) => (&spec[..1], rest),
+        None => ("", spec),
+    };
+    let split = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+    let (digits, unit) = rest.split_at(split);
+    let count: u64 = digits.parse().ok()?;
+    let unit: u64 = match unit {
+        "" | "b" => 512,
+        "c" => 1,
+        "w" => 2,
+        "k" => 1 << 10,
+        "M" => 1 << 20,
+        "G" => 1 << 30,
+        _ => return None,
+    };
+    let size = |bound: &str, bytes: u64| vec!["--size".to_string(), format!("{}{}b", bound, bytes)];
+    
+    match sign {
+        "+" => Some(size("+", count.checked_mul(unit)?.checked_add(1)?)),
+        // Nothing is smaller than zero units
+        "-" if count == 0 => None,
+        "-" => Some(size("-", (count - 1).checked_mul(unit)?)),
+        _ if count == 0 => Some(size("-", 0)),
+        _ => {
+            let mut filters = size("+", (count - 1).checked_mul(unit)? + 1);
+            filters.extend(size("-", count.checked_mul(unit)?));
+            Some(filters)
+        }
+    }
+}
+
+/// Split bundled grep short flags (`-rni`) into separate flags so each one is
+/// translated; bundles containing value-taking flags (`-A3`, `-e`) are kept
+fn expand_grep_bundles(args: &[String]) -> Vec<String> {
+    const NO_VALUE_FLAGS: &str = "abcEFGHhiIlLnoqrRsTuUvVwxyzZP";
+    
+    let mut expanded = Vec::with_capacity(args.len());
+    for arg in args {
+        let is_bundle = arg.len() > 2 &&
+            arg.starts_with('-') &&
+            !arg.starts_with("--") &&
+            arg[1..].chars().all(|c| NO_VALUE_FLAGS.contains(c));
+        if is_bundle {
+            expanded.extend(arg[1..].chars().map(|c| format!("-{}", c)));
+        } else {
+            expanded.push(arg.clone());
+        }
+    }
+    expanded
+}
+
+/// Whether a grep command names a file or directory, not just a pattern
+fn grep_has_path(args: &[String]) -> bool {
+    const VALUE_FLAGS: &[&str] = &["-A", "-B", "-C", "-m", "-d", "-D", "--include", "--exclude", "--exclude-dir", "--context", "--max-count"];
+    
+    let mut pattern_given = false;
+    let mut operands = 0;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--" => {
+                operands += args.len();
+                break;
+            }
+            "-e" | "-f" | "--regexp" | "--file" => {
+                pattern_given = true;
+                args.next();
+            }
+            arg if arg.starts_with("--regexp=") || arg.starts_with("--file=") => pattern_given = true,
+            // -e or -f bundled with its value (`-efoo`, `-ie`)
+            arg if arg.starts_with('-') && !arg.starts_with("--") && arg[1..].contains(['e', 'f']) => pattern_given = true,
+            arg if VALUE_FLAGS.contains(&arg) => {
+                args.next();
+            }
+            arg if arg.starts_with('-') && arg.len() > 1 => {}
+            _ => operands += 1,
+        }
+    }
+    operands > usize::from(!pattern_given)
+}
+
+/// Why BSD find would read `args` differently from GNU find, which the fd
+/// translation follows
+fn bsd_find_difference(args: &[String]) -> Option<String> {
+    const GNU_ONLY: &[&str] = &[
+        "-printf", "-fprintf", "-fprint", "-fprint0", "-fls", "-readable", "-writable", "-executable", "-regextype", "-xtype",
+    ];
+    
+    let mut args = args.iter().skip_while(|arg| matches!(arg.as_str(), "-H" | "-L" | "-P"));
+    match args.next().map(String::as_str) {
+        Some(option @ ("-E" | "-s" | "-x" | "-d" | "-X" | "-f")) => {
+            return Some(format!("BSD find option {} has no fd equivalent", option));
+        }
+        // GNU find searches `.` by default; BSD find needs a path
+        None => return Some("BSD find needs a path to search".to_string()),
+        Some(first) if first.starts_with('-') || first == "!" || first == "(" => {
+            return Some("BSD find needs a path to search".to_string());
+        }
+        Some(_) => {}
     }
-}
\ No newline at end of file
+    args.find(|arg| GNU_ONLY.contains(&arg.as_str()))
+        .map(|predicate| format!("{} is a GNU find predicate that BSD find doesn't have", predicate))
+}
+
+/// First dotted version number in `text` (`ripgrep 13.0.0 (rev af6b6c5)`
+/// → `[13, 0, 0]`)
+pub fn parse_version(text: &str) -> Option<Vec<u64>> {
+    static VERSION: Lazy<Regex> = Lazy::new(|| Regex::new(r"\d+(?:\.\d+)*").unwrap());
+    let version = VERSION.find(text)?;
+    version.as_str().split('.').map(|part| part.parse().ok()).collect()
+}
+
+/// Compare versions component-wise, treating missing components as 0
+pub fn version_at_least(installed: &[u64], required: &[u64]) -> bool {
+    let len = installed.len().max(required.len());
+    let component = |version: &[u64], i: usize| version.get(i).copied().unwrap_or(0);
+    (0..len)
+        .map(|i| component(installed, i).cmp(&component(required, i)))
+        .find(|ordering| ordering.is_ne())
+        .is_none_or(|ordering| ordering.is_gt())
+}
+
+/// Tools that stand in for a command's configured replacement on `os` when
+/// it isn't installed, in order of preference
+pub fn alternative_tools(cmd: &str, os: &str) -> &'static [&'static str] {
+    match (cmd, os) {
+        // eza's predecessor
+        ("ls", _) => &["exa"],
+        // Debian and Ubuntu rename bat and fd to avoid package clashes
+        ("cat", "linux") => &["batcat"],
+        ("find", "linux") => &["fdfind"],
+        _ => &[],
+    }
+}
+
+/// Fallback regexes compiled into one set, so a command is scanned once
+/// however many patterns there are
+struct FallbackPatterns {
+    patterns: Vec<String>,
+    set: RegexSet,
+}
+
+impl FallbackPatterns {
+    /// Compile `patterns`, naming the first that isn't a valid regex
+    fn new(patterns: &[String]) -> Result<Self> {
+        let set = RegexSet::new(patterns).map_err(|e| {
+            // The set's error doesn't say which pattern is at fault
+            match patterns.iter().find_map(|pattern| Some((pattern, Regex::new(pattern).err()?))) {
+                Some((pattern, e)) => anyhow::Error::new(e).context(format!("Invalid fallback pattern: {}", pattern)),
+                None => e.into(),
+            }
+        })?;
+        Ok(Self { patterns: patterns.to_vec(), set })
+    }
+    
+    /// First pattern, in declaration order, matching the command
+    fn first_match(&self, command: &str) -> Option<&str> {
+        if !self.set.is_match(command) {
+            return None;
+        }
+        self.set.matches(command).iter().next().map(|i| self.patterns[i].as_str())
+    }
+}
+
+/// First pattern in an allow/deny list matching the whole command. Patterns
+/// containing `*`, `?`, or `[` are globs (where `*` also spans `/` and
+/// spaces); anything else must equal the trimmed command exactly.
+pub(crate) fn matching_command_pattern<'a>(patterns: &'a [String], command: &str) -> Result<Option<&'a str>> {
+    let command = command.trim();
+    for pattern in patterns {
+        let matched = if pattern.contains(['*', '?', '[']) {
+            globset::Glob::new(pattern)
+                .with_context(|| format!("Invalid command glob: {}", pattern))?
+                .compile_matcher()
+                .is_match(command)
+        } else {
+            pattern.trim() == command
+        };
+        if matched {
+            return Ok(Some(pattern));
+        }
+    }
+    Ok(None)
+}
+
+/// Check whether a parsed token is a shell control operator
+fn is_shell_operator(token: &str) -> bool {
+    matches!(token, "|" | "||" | "&&" | ";" | "&" | ">" | ">>" | "<" | "2>" | "2>&1" | "|&")
+}
+
+/// Split arguments at the first shell operator, so a pipeline tail such as
+/// `| grep node` is carried over verbatim instead of being treated as arguments
+pub(crate) fn split_at_shell_operator(args: &[String]) -> (&[String], &[String]) {
+    let split = args.iter().position(|arg| is_shell_operator(arg)).unwrap_or(args.len());
+    args.split_at(split)
+}
+
+/// Collect words the user left unquoted that contain glob characters, so the
+/// rewritten command keeps letting the shell expand them
+fn unquoted_glob_words(command: &str, windows: bool) -> HashSet<String> {
+    let mut words = HashSet::new();
+    let mut word = String::new();
+    let mut quoted = false;
+    let mut quote: Option<char> = None;
+    
+    for c in command.chars().chain(std::iter::once(' ')) {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => word.push(c),
+            None if c == '\\' && windows => word.push(c),
+            None if c == '\'' || c == '"' || c == '\\' => {
+                quote = (c != '\\').then_some(c);
+                quoted = true;
+            }
+            None if c.is_whitespace() => {
+                if !quoted && word.contains(['*', '?', '[']) {
+                    words.insert(std::mem::take(&mut word));
+                }
+                word.clear();
+                quoted = false;
+            }
+            None => word.push(c),
+        }
+    }
+    words
+}
+
+/// `.wasm` plugin files with their modification times, so replacing a
+/// module invalidates remembered rewrites
+fn wasm_plugin_files() -> Vec<(String, u64)> {
+    if !cfg!(feature = "wasm-plugins") {
+        return Vec::new();
+    }
+    let Ok(entries) = std::fs::read_dir(Config::plugins_dir()) else {
+        return Vec::new();
+    };
+    let mut files: Vec<_> = entries
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let modified = entry.metadata().ok()?.modified().ok()?;
+            let nanos = modified.duration_since(std::time::UNIX_EPOCH).ok()?.as_nanos() as u64;
+            Some((entry.file_name().to_string_lossy().into_owned(), nanos))
+        })
+        .collect();
+    files.sort();
+    files
+}
+
+/// Replace tools in command position (start of the command or after a
+/// control operator) with the absolute paths `location` gives them
+fn pin_tool_paths(command: &str, location: impl Fn(&str) -> Option<String>, quote: impl Fn(&str) -> String) -> String {
+    let mut result = String::with_capacity(command.len());
+    let mut command_position = true;
+    let mut rest = command;
+    while !rest.is_empty() {
+        let trimmed = rest.trim_start();
+        result.push_str(&rest[..rest.len() - trimmed.len()]);
+        if trimmed.is_empty() {
+            break;
+        }
+        
+        let end = unquoted_word_end(trimmed);
+        let word = &trimmed[..end];
+        match command_position.then(|| location(word)).flatten() {
+            Some(path) => result.push_str(&quote(&path)),
+            None => result.push_str(word),
+        }
+        command_position = is_shell_operator(word);
+        rest = &trimmed[end..];
+    }
+    result
+}
+
+/// Byte length of the leading shell word, honoring quotes and escapes
+fn unquoted_word_end(text: &str) -> usize {
+    let mut quote = None;
+    let mut escaped = false;
+    for (index, c) in text.char_indices() {
+        match (quote, c) {
+            _ if escaped => escaped = false,
+            (Some('\''), '\'') | (Some('"'), '"') => quote = None,
+            (Some('"'), '\\') | (None, '\\') => escaped = true,
+            (None, '\'' | '"') => quote = Some(c),
+            (None, c) if c.is_whitespace() => return index,
+            _ => {}
+        }
+    }
+    text.len()
+}
+
+/// Quote a single word for the shell, only when it needs quoting
+fn quote_word(word: &str) -> String {
+    shlex::try_quote(word).map(|q| q.into_owned()).unwrap_or_else(|_| word.to_string())
+}
+
+/// Split a command into words as Windows shells would, where `C:\Users` is a
+/// path: quotes group words, but a backslash is literal except before a
+/// quote inside double quotes. `None` for an unterminated quote.
+fn split_windows_words(command: &str) -> Option<Vec<String>> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut quote = None;
+    let mut chars = command.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some('"'), '\\') if chars.peek() == Some(&'"') => word.get_or_insert_with(String::new).extend(chars.next()),
+            (Some(q), c) if c == q => quote = None,
+            (None, '\'' | '"') => {
+                quote = Some(c);
+                word.get_or_insert_with(String::new);
+            }
+            (None, c) if c.is_whitespace() => words.extend(word.take()),
+            (_, c) => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    if quote.is_some() {
+        return None;
+    }
+    words.extend(word);
+    Some(words)
+}
+
+/// Double-quote a word containing backslashes, which keeps a Windows path
+/// as written in cmd and PowerShell, and in bash too once `"`, `$`, and
+/// backticks are escaped and backslashes before them doubled
+fn quote_windows_word(word: &str) -> String {
+    let mut quoted = String::from("\"");
+    let mut chars = word.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if chars.peek().is_none_or(|next| matches!(next, '\\' | '"' | '$' | '`')) => quoted.push_str("\\\\"),
+            '"' | '$' | '`' => {
+                quoted.push('\\');
+                quoted.push(c);
+            }
+            _ => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// A single sed `s` command, as written (before translation to sd syntax)
+#[derive(Debug, Clone, PartialEq)]
+pub struct SedSubstitution {
+    pub pattern: String,
+    pub replacement: String,
+    pub global: bool,
+    pub case_insensitive: bool,
+}
+
+/// Translate a sed regex into sd's (Rust regex) syntax.
+///
+/// Basic regexes invert the meaning of escaping for `( ) { } + ? |`, so those
+/// are swapped; GNU word anchors `\<`/`\>` become `\b`. Returns `None` for
+/// constructs without an equivalent (back-references inside the pattern).
+fn sed_pattern_to_sd(pattern: &str, extended: bool) -> Option<String> {
+    let mut out = String::with_capacity(pattern.len());
+    let mut chars = pattern.chars();
+    
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next()? {
+                '<' | '>' => out.push_str("\\b"),
+                d if d.is_ascii_digit() => return None,
+                next if !extended && "(){}+?|".contains(next) => out.push(next),
+                next => {
+                    out.push('\\');
+                    out.push(next);
+                }
+            },
+            c if !extended && "(){}+?|".contains(c) => {
+                out.push('\\');
+                out.push(c);
+            }
+            c => out.push(c),
+        }
+    }
+    
+    Some(out)
+}
+
+/// Translate a sed replacement into sd's: `\N` and `&` become `${N}`/`${0}`,
+/// and literal `$` is doubled. GNU case-conversion escapes are rejected.
+fn sed_replacement_to_sd(replacement: &str) -> Option<String> {
+    let mut out = String::with_capacity(replacement.len());
+    let mut chars = replacement.chars();
+    
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next()? {
+                d if d.is_ascii_digit() => out.push_str(&format!("${{{}}}", d)),
+                'L' | 'U' | 'l' | 'u' | 'E' => return None,
+                'n' => out.push_str("\\n"),
+                't' => out.push_str("\\t"),
+                '$' => out.push_str("$$"),
+                next => out.push(next),
+            },
+            '&' => out.push_str("${0}"),
+            '$' => out.push_str("$$"),
+            c => out.push(c),
+        }
+    }
+    
+    Some(out)
+}
+
+/// Top-level construct of a sed script, as far as replacement decisions go
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SedConstruct {
+    /// A bare `s` command, the only thing sd can express
+    Substitution,
+    /// Two addresses (`10,20p`, `/start/,/end/d`, `1~2`) selecting a line range
+    AddressRange,
+    /// A single address (`5d`, `/re/s/a/b/`, `$p`) restricting the command
+    Address,
+    /// `d`/`D` line deletion
+    Delete,
+    /// `p`/`P` explicit printing
+    Print,
+    /// `y/abc/xyz/` transliteration
+    Transliterate,
+    /// `h`/`H`/`g`/`G`/`x` hold-space manipulation
+    HoldSpace,
+    /// Several commands separated by `;`, newlines, or `{}` blocks
+    MultipleCommands,
+    /// Any other command (`a`, `i`, `c`, `q`, `N`, branches, ...)
+    Other(char),
+}
+
+impl SedConstruct {
+    /// Identifier used in structured fallback reasons
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Substitution => "substitution",
+            Self::AddressRange => "address-range",
+            Self::Address => "address",
+            Self::Delete => "delete",
+            Self::Print => "print",
+            Self::Transliterate => "transliterate",
+            Self::HoldSpace => "hold-space",
+            Self::MultipleCommands => "multiple-commands",
+            Self::Other(_) => "command",
+        }
+    }
+    
+    /// Explain why this construct prevents an sd rewrite
+    pub fn fallback_reason(&self, script: &str) -> FallbackReason {
+        let detail = match self {
+            Self::Substitution => format!("`{}` is a plain substitution", script),
+            Self::AddressRange => format!("`{}` selects a line range; sd has no line addressing", script),
+            Self::Address => format!("`{}` restricts the command to matching lines; sd applies everywhere", script),
+            Self::Delete => format!("`{}` deletes lines; sd only substitutes", script),
+            Self::Print => format!("`{}` prints lines explicitly; sd only substitutes", script),
+            Self::Transliterate => format!("`{}` transliterates characters; sd has no y command", script),
+            Self::HoldSpace => format!("`{}` uses the hold space, which sd doesn't have", script),
+            Self::MultipleCommands => format!("`{}` contains several commands; pass them as separate -e scripts", script),
+            Self::Other(c) => format!("sed command `{}` in `{}` has no sd equivalent", c, script),
+        };
+        FallbackReason::new("sed", self.name(), detail)
+    }
+}
+
+/// Classify a sed script by its addresses and first command.
+///
+/// Only a bare `s` command is translatable; everything else is named so the
+/// fallback reason tells users which construct cost them the rewrite.
+pub fn classify_sed_script(script: &str) -> SedConstruct {
+    let script = script.trim();
+    let mut rest = script;
+    
+    let after_address = skip_sed_address(rest);
+    let address = &rest[..rest.len() - after_address.len()];
+    let has_address = !address.is_empty();
+    // GNU first~step addresses select every Nth line, which is a range too
+    let mut has_range = address.contains('~');
+    rest = after_address.trim_start();
+    if has_address {
+        if let Some(after_comma) = rest.strip_prefix(',') {
+            rest = skip_sed_address(after_comma.trim_start()).trim_start();
+            has_range = true;
+        }
+    }
+    rest = rest.strip_prefix('!').unwrap_or(rest).trim_start();
+    
+    let Some(command) = rest.chars().next() else {
+        return SedConstruct::Other(' ');
+    };
+    let construct = match command {
+        's' => SedConstruct::Substitution,
+        'd' | 'D' => SedConstruct::Delete,
+        'p' | 'P' => SedConstruct::Print,
+        'y' => SedConstruct::Transliterate,
+        'h' | 'H' | 'g' | 'G' | 'x' => SedConstruct::HoldSpace,
+        '{' => SedConstruct::MultipleCommands,
+        c => SedConstruct::Other(c),
+    };
+    
+    if has_range {
+        return SedConstruct::AddressRange;
+    }
+    if has_address {
+        return SedConstruct::Address;
+    }
+    if construct == SedConstruct::Substitution && has_multiple_sed_commands(rest) {
+        return SedConstruct::MultipleCommands;
+    }
+    construct
+}
+
+/// Skip one sed address (`12`, `$`, `/re/`, `\%re%`, `1~2`, `+3`, `~4`) and return the remainder
+fn skip_sed_address(script: &str) -> &str {
+    let bytes = script.as_bytes();
+    match bytes.first() {
+        Some(b'0'..=b'9') | Some(b'+') | Some(b'~') => {
+            let end = script[1..]
+                .find(|c: char| !(c.is_ascii_digit() || c == '~'))
+                .map(|i| i + 1)
+                .unwrap_or(script.len());
+            &script[end..]
+        }
+        Some(b'$') => &script[1..],
+        Some(b'/') => skip_delimited(&script[1..], '/'),
+        Some(b'\\') => match script[1..].chars().next() {
+            Some(delim) => skip_delimited(&script[1 + delim.len_utf8()..], delim),
+            None => script,
+        },
+        _ => script,
+    }
+    .trim_start_matches(['I', 'M'])
+}
+
+/// Skip past the next unescaped `delim`, returning what follows it
+fn skip_delimited(text: &str, delim: char) -> &str {
+    let mut escaped = false;
+    for (idx, c) in text.char_indices() {
+        if escaped {
+            escaped = false;
+        } else if c == '\\' {
+            escaped = true;
+        } else if c == delim {
+            return &text[idx + c.len_utf8()..];
+        }
+    }
+    ""
+}
+
+/// Check whether an `s` command is followed by further commands
+fn has_multiple_sed_commands(script: &str) -> bool {
+    let mut chars = script.chars();
+    chars.next(); // the `s`
+    let Some(delim) = chars.next() else {
+        return false;
+    };
+    // Skip the pattern and replacement; whatever follows the flags is another command
+    let mut rest = chars.as_str();
+    for _ in 0..2 {
+        rest = skip_delimited(rest, delim);
+    }
+    rest.contains([';', '\n', '}'])
+}
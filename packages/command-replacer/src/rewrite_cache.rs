@@ -0,0 +1,210 @@
+//! Remembered rewrite decisions for repeated commands
+//!
+//! Agent sessions run the same few commands over and over, so the decision
+//! for each normalized command is kept in a small LRU and a repeat skips
+//! parsing and translation entirely. Every entry belongs to a fingerprint
+//! of whatever the decision depends on (config, detected context, tool
+//! answers); a different fingerprint starts from an empty cache. With
+//! `rewrite_cache.persist` the cache is also kept on disk between hook
+//! processes: a new decision is appended to a journal next to the cache
+//! file, which is folded into the file once it grows long or the session
+//! stops, so a cache miss never rewrites the whole file.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::replacements::FallbackReason;
+
+/// What the engine decided for one command
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Decision {
+    pub rewrite: Option<String>,
+    /// Why it declined, when it said why
+    pub fallback: Option<FallbackReason>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Entry {
+    decision: Decision,
+    /// Tick of the most recent use
+    used: u64,
+}
+
+/// Journaled decisions that trigger folding the journal into the cache file
+const JOURNAL_LIMIT: usize = 64;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RewriteCache {
+    fingerprint: u64,
+    tick: u64,
+    entries: HashMap<String, Entry>,
+    /// Lines in the journal, as far as this process knows
+    #[serde(skip)]
+    journaled: usize,
+}
+
+/// One line of the journal
+#[derive(Debug, Serialize, Deserialize)]
+struct JournalEntry {
+    fingerprint: u64,
+    command: String,
+    decision: Decision,
+}
+
+/// The journal kept next to the cache file at `path`
+pub fn journal_path(path: &Path) -> PathBuf {
+    path.with_extension("journal")
+}
+
+impl RewriteCache {
+    /// The decision remembered for `command` under `fingerprint`
+    pub fn get(&mut self, fingerprint: u64, command: &str) -> Option<Decision> {
+        if fingerprint != self.fingerprint {
+            return None;
+        }
+        self.tick += 1;
+        let entry = self.entries.get_mut(command)?;
+        entry.used = self.tick;
+        Some(entry.decision.clone())
+    }
+    
+    /// Remember `decision`, forgetting the least recently used command once
+    /// more than `capacity` are kept
+    pub fn insert(&mut self, fingerprint: u64, command: &str, decision: Decision, capacity: usize) {
+        if fingerprint != self.fingerprint {
+            self.entries.clear();
+            self.fingerprint = fingerprint;
+        }
+        if capacity == 0 {
+            return;
+        }
+        self.tick += 1;
+        self.entries.insert(command.to_string(), Entry { decision, used: self.tick });
+        while self.entries.len() > capacity {
+            let oldest = self.entries.iter().min_by_key(|(_, entry)| entry.used).map(|(command, _)| command.clone());
+            match oldest {
+                Some(command) => self.entries.remove(&command),
+                None => break,
+            };
+        }
+    }
+    
+    /// Read a persisted cache and replay its journal; a missing or
+    /// unreadable file is an empty cache, and unreadable lines are skipped
+    pub fn load(path: &Path, capacity: usize) -> Self {
+        let mut cache: Self = std::fs::read_to_string(path)
+            .ok()
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default();
+        let journal = std::fs::read_to_string(journal_path(path)).unwrap_or_default();
+        for line in journal.lines() {
+            if let Ok(entry) = serde_json::from_str::<JournalEntry>(line) {
+                cache.insert(entry.fingerprint, &entry.command, entry.decision, capacity);
+            }
+            cache.journaled += 1;
+        }
+        cache
+    }
+    
+    /// Persist a decision just inserted by appending it to the journal,
+    /// folding the journal into the cache file once it is long enough
+    pub fn record(&mut self, path: &Path, fingerprint: u64, command: &str, decision: Decision) -> Result<()> {
+        let journal = journal_path(path);
+        if let Some(parent) = journal.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        let line = serde_json::to_string(&JournalEntry { fingerprint, command: command.to_string(), decision })?;
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&journal)
+            .with_context(|| format!("Failed to open {}", journal.display()))?;
+        writeln!(file, "{}", line).with_context(|| format!("Failed to write {}", journal.display()))?;
+        
+        self.journaled += 1;
+        if self.journaled >= JOURNAL_LIMIT {
+            self.compact(path)?;
+        }
+        Ok(())
+    }
+    
+    /// Write the cache file, which then holds everything journaled, and
+    /// start a new journal
+    pub fn compact(&mut self, path: &Path) -> Result<()> {
+        if self.journaled == 0 {
+            return Ok(());
+        }
+        self.save(path)?;
+        let journal = journal_path(path);
+        match std::fs::remove_file(&journal) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                return Err(e).with_context(|| format!("Failed to remove {}", journal.display()));
+            }
+            _ => {}
+        }
+        self.journaled = 0;
+        Ok(())
+    }
+    
+    /// Persist the cache, replacing the file atomically
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        let temp = path.with_extension(format!("tmp.{}", std::process::id()));
+        std::fs::write(&temp, serde_json::to_string(self)?)
+            .with_context(|| format!("Failed to write {}", temp.display()))?;
+        std::fs::rename(&temp, path)
+            .with_context(|| format!("Failed to replace {}", path.display()))
+    }
+}
+
+/// The cache key for `command`: trimmed, with runs of unquoted spaces and
+/// tabs collapsed to one space. Newlines separate commands and heredoc
+/// bodies are data, so both are left alone.
+pub fn normalize(command: &str) -> String {
+    let command = command.trim();
+    if command.contains("<<") {
+        return command.to_string();
+    }
+    
+    let mut normalized = String::with_capacity(command.len());
+    let mut quote = None;
+    let mut space = false;
+    let mut chars = command.chars();
+    while let Some(c) = chars.next() {
+        if quote.is_none() && matches!(c, ' ' | '\t') {
+            space = true;
+            continue;
+        }
+        if std::mem::take(&mut space) {
+            normalized.push(' ');
+        }
+        normalized.push(c);
+        match (quote, c) {
+            (None | Some('"'), '\\') => normalized.extend(chars.next()),
+            (None, '\'' | '"') => quote = Some(c),
+            (Some(open), _) if c == open => quote = None,
+            _ => {}
+        }
+    }
+    normalized
+}
+
+/// Order-independent fingerprint of serializable state
+pub fn fingerprint(state: &impl Serialize) -> u64 {
+    // Converting to a `Value` sorts map keys, so two equal configs hash the
+    // same whatever order their maps iterate in
+    let canonical = serde_json::to_value(state).map(|value| value.to_string()).unwrap_or_default();
+    let mut hasher = DefaultHasher::new();
+    canonical.hash(&mut hasher);
+    hasher.finish()
+}
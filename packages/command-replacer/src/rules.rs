@@ -0,0 +1,160 @@
+//! Generic transformer for declarative `[[rules]]` replacements
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use crate::config::RuleConfig;
+use crate::explain::FlagMapping;
+use crate::replacements::FallbackReason;
+
+/// The replacement tool a rule invokes (first word of its template)
+pub fn rule_tool(rule: &RuleConfig) -> &str {
+    rule.template.split_whitespace().next().unwrap_or("")
+}
+
+/// Check the rule's whole-command fallback regexes
+pub fn rule_fallback_pattern<'a>(rule: &'a RuleConfig, command: &str) -> Option<&'a str> {
+    rule.fallback_patterns
+        .iter()
+        .find(|pattern| Regex::new(pattern).map(|re| re.is_match(command)).unwrap_or(true))
+        .map(String::as_str)
+}
+
+/// Translate arguments through a rule's flag map and render its template.
+///
+/// `quote` re-quotes positional words for the shell. Flags the rule doesn't
+/// know about fall back unless `passthrough_flags` is set, so an unmapped
+/// option is never silently dropped or misinterpreted.
+pub fn apply_rule<F>(rule: &RuleConfig, args: &[String], quote: F) -> Result<String, FallbackReason>
+where
+    F: Fn(&str) -> String,
+{
+    translate(rule, args, quote, &mut Vec::new())
+}
+
+/// What [`apply_rule`] does with each flag in `args`, up to the one that
+/// makes it fall back
+pub fn explain_rule_flags(rule: &RuleConfig, args: &[String]) -> Vec<FlagMapping> {
+    let mut mappings = Vec::new();
+    let _ = translate(rule, args, str::to_string, &mut mappings);
+    mappings
+}
+
+/// [`apply_rule`], noting each flag's fate in `mappings`
+fn translate<F>(rule: &RuleConfig, args: &[String], quote: F, mappings: &mut Vec<FlagMapping>) -> Result<String, FallbackReason>
+where
+    F: Fn(&str) -> String,
+{
+    let decline = |construct: &str, detail: String| FallbackReason::new(&rule.command, construct, detail);
+    
+    let mut flags: Vec<String> = Vec::new();
+    let mut positionals: Vec<String> = Vec::new();
+    let mut only_positionals = false;
+    let mut i = 0;
+    
+    let args = expand_short_bundles(rule, args);
+    
+    while i < args.len() {
+        let arg = &args[i];
+        
+        if only_positionals || !arg.starts_with('-') || arg == "-" {
+            positionals.push(quote(arg));
+        } else if arg == "--" {
+            only_positionals = true;
+        } else {
+            let (name, inline_value) = match arg.split_once('=') {
+                Some((name, value)) if arg.starts_with("--") => (name, Some(value)),
+                _ => (arg.as_str(), None),
+            };
+            
+            if rule.fallback_flags.iter().any(|flag| flag == name) {
+                mappings.push(FlagMapping::new(name, None, None, "fallback"));
+                return Err(decline("rule-fallback-flag", format!("{} triggers a fallback in the {} rule", name, rule.command)));
+            }
+            
+            let value = if inline_value.is_none() && rule.value_flags.iter().any(|flag| flag == name) {
+                i += 1;
+                match args.get(i) {
+                    Some(value) => Some(quote(value)),
+                    None => {
+                        mappings.push(FlagMapping::new(name, None, None, "missing-value"));
+                        return Err(decline("rule-missing-value", format!("{} expects a value", name)));
+                    }
+                }
+            } else {
+                inline_value.map(&quote)
+            };
+            
+            let (mapped, how) = match rule.flags.get(name) {
+                Some(mapped) if mapped.is_empty() => (String::new(), "dropped"),
+                Some(mapped) => (mapped.clone(), "mapped"),
+                None if rule.passthrough_flags => (name.to_string(), "passthrough"),
+                None => {
+                    mappings.push(FlagMapping::new(name, value.as_deref(), None, "unmapped"));
+                    return Err(decline("rule-unmapped-flag", format!("{} has no mapping in the {} rule", name, rule.command)));
+                }
+            };
+            mappings.push(FlagMapping::new(name, value.as_deref(), Some(mapped.as_str()).filter(|m| !m.is_empty()), how));
+            if !mapped.is_empty() {
+                match value {
+                    Some(value) if mapped.starts_with("--") => flags.push(format!("{}={}", mapped, value)),
+                    Some(value) => flags.push(format!("{} {}", mapped, value)),
+                    None => flags.push(mapped),
+                }
+            }
+        }
+        
+        i += 1;
+    }
+    
+    Ok(render_template(rule, &flags, &positionals))
+}
+
+/// Split `-sh` into `-s -h` unless the rule names the bundle itself or its
+/// first letter takes a value (`-d1`)
+fn expand_short_bundles(rule: &RuleConfig, args: &[String]) -> Vec<String> {
+    let known = |flag: &str| rule.flags.contains_key(flag) || rule.fallback_flags.iter().any(|f| f == flag);
+    
+    let mut expanded = Vec::with_capacity(args.len());
+    for (index, arg) in args.iter().enumerate() {
+        let is_bundle = arg.len() > 2 && arg.starts_with('-') && !arg.starts_with("--") && arg.is_char_boundary(2);
+        let after_separator = args[..index].iter().any(|a| a == "--");
+        if !is_bundle || after_separator || known(arg) {
+            expanded.push(arg.clone());
+            continue;
+        }
+        
+        let first = &arg[..2];
+        if rule.value_flags.iter().any(|flag| flag == first) {
+            expanded.push(first.to_string());
+            expanded.push(arg[2..].to_string());
+        } else {
+            expanded.extend(arg.chars().skip(1).map(|c| format!("-{}", c)));
+        }
+    }
+    expanded
+}
+
+/// Substitute `{flags}`, `{args}`, and named positionals into the template
+fn render_template(rule: &RuleConfig, flags: &[String], positionals: &[String]) -> String {
+    static PLACEHOLDER: Lazy<Regex> = Lazy::new(|| Regex::new(r"\{([A-Za-z_][A-Za-z0-9_]*)\}").unwrap());
+    
+    let named = |name: &str| -> String {
+        let Some(index) = rule.positionals.iter().position(|p| p == name) else {
+            return String::new();
+        };
+        if index + 1 == rule.positionals.len() {
+            positionals.get(index..).map(|rest| rest.join(" ")).unwrap_or_default()
+        } else {
+            positionals.get(index).cloned().unwrap_or_default()
+        }
+    };
+    
+    let rendered = PLACEHOLDER.replace_all(&rule.template, |caps: &regex::Captures| match &caps[1] {
+        "flags" => flags.join(" "),
+        "args" => positionals.join(" "),
+        name => named(name),
+    });
+    
+    rendered.split_whitespace().collect::<Vec<_>>().join(" ")
+}
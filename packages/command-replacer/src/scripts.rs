@@ -0,0 +1,77 @@
+//! `[[plugins]]` written in rhai (`script = "..."`)
+//!
+//! A script defines `fn rewrite(cmd, ctx)` and returns the new command, or
+//! `()` to leave it to the next plugin and the built-in engine. `ctx` holds
+//! the detected context (`git_repo`, `ci`, `project_types`, `wsl`, `repo`), and
+//! `path_exists(path)`, `env(name)`, and `hour()` (UTC, 0-23) cover the
+//! conditions a `when` table can't express. Scripts can't run programs,
+//! import modules, or print into the hook's output, and are stopped after
+//! the plugin's `timeout_ms`.
+
+use anyhow::Result;
+use std::path::Path;
+
+use crate::config::PluginConfig;
+use crate::context::RuleContext;
+use crate::plugins::Answer;
+
+/// Run `script`'s `rewrite` for `command`
+#[cfg(feature = "scripting")]
+pub fn ask(plugin: &PluginConfig, script: &Path, command: &str, context: &RuleContext) -> Result<Answer> {
+    use anyhow::{anyhow, Context};
+    use rhai::{Dynamic, Scope};
+    use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+    
+    let path = crate::config::Config::config_dir().join(script);
+    let mut engine = engine();
+    let deadline = Instant::now() + Duration::from_millis(plugin.timeout_ms);
+    engine.on_progress(move |_| (Instant::now() > deadline).then_some(Dynamic::UNIT));
+    engine.register_fn("path_exists", |path: &str| Path::new(path).exists());
+    engine.register_fn("env", |name: &str| std::env::var(name).unwrap_or_default());
+    engine.register_fn("hour", || {
+        let secs = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+        ((secs / 3600) % 24) as i64
+    });
+    
+    let ast = engine
+        .compile_file(path.clone())
+        .map_err(|e| anyhow!("{}", e))
+        .with_context(|| format!("Failed to load plugin script {}", path.display()))?;
+    let ctx = rhai::serde::to_dynamic(context).map_err(|e| anyhow!("{}", e))?;
+    let result: Dynamic = engine
+        .call_fn(&mut Scope::new(), &ast, "rewrite", (command.to_string(), ctx))
+        .map_err(|e| match *e {
+            rhai::EvalAltResult::ErrorTerminated(..) => anyhow!("Plugin {} did not answer within {}ms", plugin.name, plugin.timeout_ms),
+            e => anyhow!("Plugin {} failed: {}", plugin.name, e),
+        })?;
+    
+    if result.is_unit() {
+        return Ok(Answer::Pass);
+    }
+    let rewritten = result
+        .into_string()
+        .map_err(|kind| anyhow!("Plugin {} returned a {}, not a command", plugin.name, kind))?;
+    Ok(if rewritten == command { Answer::Pass } else { Answer::Rewrite(rewritten) })
+}
+
+#[cfg(not(feature = "scripting"))]
+pub fn ask(plugin: &PluginConfig, _script: &Path, _command: &str, _context: &RuleContext) -> Result<Answer> {
+    anyhow::bail!("Plugin {} is a script, which needs a build with the `scripting` feature", plugin.name)
+}
+
+/// Check that `script` compiles
+#[cfg(feature = "scripting")]
+pub fn check(script: &Path) -> Result<()> {
+    let path = crate::config::Config::config_dir().join(script);
+    engine().compile_file(path).map(drop).map_err(|e| anyhow::anyhow!("{}", e))
+}
+
+/// An engine without any way to reach outside the script
+#[cfg(feature = "scripting")]
+fn engine() -> rhai::Engine {
+    let mut engine = rhai::Engine::new();
+    engine.set_module_resolver(rhai::module_resolvers::DummyModuleResolver::new());
+    engine.on_print(|_| {});
+    engine.on_debug(|_, _, _| {});
+    engine
+}
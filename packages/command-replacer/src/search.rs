@@ -0,0 +1,92 @@
+//! Defaults for Claude's built-in Grep and Glob tools
+//!
+//! The same idea as rewriting Bash commands, applied to tool input: a search
+//! without a `path` is scoped to the active package of a monorepo, and with
+//! `settings.search.project_type` a Grep without a `type` or `glob` filter
+//! is limited to the project's language.
+
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+
+use crate::config::SearchSettings;
+use crate::context::{git_root, RuleContext};
+
+/// Files marking the root of a package within a repository
+const PACKAGE_MARKERS: &[&str] = &["Cargo.toml", "package.json", "pyproject.toml", "go.mod", "pom.xml", "build.gradle"];
+
+/// The tool input with defaults filled in, and a note telling Claude what
+/// changed, or `None` if nothing did
+pub fn tune(
+    tool: &str,
+    input: &Value,
+    cwd: &Path,
+    root: &Path,
+    context: &RuleContext,
+    settings: &SearchSettings,
+) -> Option<(Value, String)> {
+    let fields = input.as_object()?;
+    let mut updated = fields.clone();
+    let mut notes = Vec::new();
+    
+    if settings.package_scope && !fields.contains_key("path") {
+        if let Some(package) = active_package(cwd, root) {
+            let relative = package.strip_prefix(root).unwrap_or(&package).display().to_string();
+            notes.push(format!("scoped to the active package `{}`", relative));
+            updated.insert("path".to_string(), package.display().to_string().into());
+        }
+    }
+    let unfiltered = !fields.contains_key("type") && !fields.contains_key("glob");
+    if tool == "Grep" && settings.project_type && unfiltered {
+        if let Some(file_type) = rg_type(context, root) {
+            notes.push(format!("limited to `{}` files", file_type));
+            updated.insert("type".to_string(), file_type.into());
+        }
+    }
+    
+    if notes.is_empty() {
+        return None;
+    }
+    let note = format!(
+        "command-replacer tuned this {} ({}); pass `path` or `type` explicitly to search elsewhere.",
+        tool,
+        notes.join(", "),
+    );
+    Some((Value::Object(updated), note))
+}
+
+/// The nearest package root at or above `cwd`, when that is below the
+/// repository `root` (so there is a narrower scope than the whole repo)
+pub fn active_package(cwd: &Path, root: &Path) -> Option<PathBuf> {
+    cwd.ancestors()
+        .take_while(|dir| dir.starts_with(root) && *dir != root)
+        .find(|dir| PACKAGE_MARKERS.iter().any(|marker| dir.join(marker).is_file()))
+        .map(Path::to_path_buf)
+}
+
+/// The repository root: `CLAUDE_PROJECT_DIR` when set, else the nearest
+/// git work tree above `cwd`, else `cwd` itself
+pub fn repo_root(cwd: &Path) -> PathBuf {
+    if let Some(dir) = std::env::var_os("CLAUDE_PROJECT_DIR").filter(|dir| !dir.is_empty()) {
+        return PathBuf::from(dir);
+    }
+    git_root(cwd).unwrap_or(cwd).to_path_buf()
+}
+
+/// The ripgrep file type for a single-language project
+fn rg_type(context: &RuleContext, root: &Path) -> Option<&'static str> {
+    let [project_type] = context.project_types.as_slice() else {
+        return None;
+    };
+    match project_type.as_str() {
+        "rust" => Some("rust"),
+        "node" if root.join("tsconfig.json").is_file() => Some("ts"),
+        "node" => Some("js"),
+        "python" => Some("py"),
+        "go" => Some("go"),
+        "java" => Some("java"),
+        "ruby" => Some("ruby"),
+        "php" => Some("php"),
+        "cpp" => Some("cpp"),
+        _ => None,
+    }
+}
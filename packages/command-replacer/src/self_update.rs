@@ -0,0 +1,195 @@
+//! `command-replacer self-update`: replace this binary with the latest release
+//!
+//! Releases publish each platform's binary on its own
+//! (`command-replacer-linux-x64`, ...) next to a `.sha256` checksum and a
+//! `.minisig` signature. The download is checked against both before it
+//! replaces the running binary, which is swapped by a rename in the same
+//! directory so an interrupted update never leaves a half-written hook
+//! behind. The signature is checked with the release key built into the
+//! binary (or `--public-key`); only `--allow-unsigned` skips it.
+
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Latest release of this repository
+pub const DEFAULT_FEED: &str = "https://api.github.com/repos/david-t-martel/claude-code-integration/releases/latest";
+
+/// Minisign public key releases are signed with, built in by the release
+/// workflow
+pub const RELEASE_PUBLIC_KEY: Option<&str> = option_env!("COMMAND_REPLACER_RELEASE_PUBLIC_KEY");
+
+/// Largest binary accepted from the feed
+const MAX_BINARY_BYTES: u64 = 64 * 1024 * 1024;
+
+#[derive(Debug, Deserialize)]
+pub struct Release {
+    pub tag_name: String,
+    #[serde(default)]
+    pub assets: Vec<Asset>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Asset {
+    pub name: String,
+    pub browser_download_url: String,
+}
+
+impl Release {
+    fn asset(&self, name: &str) -> Option<&Asset> {
+        self.assets.iter().find(|asset| asset.name == name)
+    }
+}
+
+/// What `self-update` found or did
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    UpToDate { tag: String },
+    Available { tag: String },
+    Updated { tag: String, path: PathBuf },
+}
+
+/// The key the download must be signed with: `explicit`, else the built-in
+/// release key. `None` only when the user opted out with `allow_unsigned`.
+pub fn signing_key(explicit: Option<&str>, allow_unsigned: bool) -> Result<Option<&str>> {
+    if allow_unsigned {
+        return Ok(None);
+    }
+    let key = explicit
+        .or(RELEASE_PUBLIC_KEY)
+        .filter(|key| !key.trim().is_empty())
+        .context("This build has no release signing key; pass --public-key, or --allow-unsigned to rely on the checksum alone")?;
+    Ok(Some(key))
+}
+
+/// Name of this platform's binary among the release assets
+pub fn asset_name() -> Option<String> {
+    let platform = match (std::env::consts::OS, std::env::consts::ARCH) {
+        ("linux", "x86_64") => "linux-x64",
+        ("linux", "aarch64") => "linux-arm64",
+        ("macos", "x86_64") => "darwin-x64",
+        ("macos", "aarch64") => "darwin-arm64",
+        ("windows", "x86_64") => "win32-x64",
+        _ => return None,
+    };
+    Some(format!("command-replacer-{}{}", platform, std::env::consts::EXE_SUFFIX))
+}
+
+/// Check `feed` and, unless `check_only`, install its binary over `target`.
+/// With `public_key` (minisign, see [`signing_key`]) the release must carry
+/// a valid signature.
+pub fn self_update(feed: &str, target: &Path, public_key: Option<&str>, check_only: bool) -> Result<Outcome> {
+    let name = asset_name().context("No release binaries are published for this platform")?;
+    let agent = ureq::AgentBuilder::new().timeout(Duration::from_secs(60)).build();
+    let release: Release = serde_json::from_str(&get(&agent, feed)?.into_string().with_context(|| format!("Failed to read {}", feed))?)
+        .with_context(|| format!("Invalid release feed {}", feed))?;
+    let asset = release.asset(&name).with_context(|| format!("Release {} has no {}", release.tag_name, name))?;
+    let checksum_name = format!("{}.sha256", name);
+    let checksum = release.asset(&checksum_name).with_context(|| format!("Release {} has no {}", release.tag_name, checksum_name))?;
+    let checksum = get(&agent, &checksum.browser_download_url)?
+        .into_string()
+        .with_context(|| format!("Failed to read {}", checksum_name))?;
+    
+    // The checksum names what a current install already is
+    let current = std::fs::read(target).with_context(|| format!("Failed to read {}", target.display()))?;
+    if verify_checksum(&current, &checksum, &name).is_ok() {
+        return Ok(Outcome::UpToDate { tag: release.tag_name });
+    }
+    if check_only {
+        return Ok(Outcome::Available { tag: release.tag_name });
+    }
+    
+    let mut binary = Vec::new();
+    get(&agent, &asset.browser_download_url)?
+        .into_reader()
+        .take(MAX_BINARY_BYTES + 1)
+        .read_to_end(&mut binary)
+        .with_context(|| format!("Failed to download {}", name))?;
+    if binary.len() as u64 > MAX_BINARY_BYTES {
+        bail!("{} is larger than {} bytes", name, MAX_BINARY_BYTES);
+    }
+    let signature = match public_key {
+        Some(_) => {
+            let signature_name = format!("{}.minisig", name);
+            let asset = release.asset(&signature_name).with_context(|| format!("Release {} is not signed", release.tag_name))?;
+            Some(get(&agent, &asset.browser_download_url)?.into_string().with_context(|| format!("Failed to read {}", signature_name))?)
+        }
+        None => None,
+    };
+    
+    install_verified(&binary, &name, &checksum, public_key.zip(signature.as_deref()), target)?;
+    Ok(Outcome::Updated { tag: release.tag_name, path: target.to_path_buf() })
+}
+
+/// Verify `binary` against its checksum file (and, given a `(public key,
+/// signature)` pair, its minisign signature), then swap it in for `target`
+pub fn install_verified(binary: &[u8], name: &str, checksum: &str, signed: Option<(&str, &str)>, target: &Path) -> Result<()> {
+    verify_checksum(binary, checksum, name)?;
+    if let Some((public_key, signature)) = signed {
+        let public_key = minisign_verify::PublicKey::from_base64(public_key.trim()).context("Invalid minisign public key")?;
+        let signature = minisign_verify::Signature::decode(signature).context("Invalid minisign signature")?;
+        public_key.verify(binary, &signature, false).with_context(|| format!("Signature check failed for {}", name))?;
+    }
+    replace_binary(target, binary)
+}
+
+/// Check `bytes` against a `sha256sum`-style line for `name` (or a bare digest)
+pub fn verify_checksum(bytes: &[u8], checksum: &str, name: &str) -> Result<()> {
+    let expected = checksum
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let digest = fields.next()?;
+            match fields.next() {
+                Some(file) if file.trim_start_matches('*') != name => None,
+                _ => Some(digest.to_ascii_lowercase()),
+            }
+        })
+        .next()
+        .with_context(|| format!("No checksum for {}", name))?;
+    let actual: String = Sha256::digest(bytes).iter().map(|byte| format!("{:02x}", byte)).collect();
+    if actual != expected {
+        bail!("Checksum mismatch for {}: expected {}, got {}", name, expected, actual);
+    }
+    Ok(())
+}
+
+/// Write `binary` next to `target` and rename it into place
+fn replace_binary(target: &Path, binary: &[u8]) -> Result<()> {
+    let staged = target.with_extension(format!("new.{}", std::process::id()));
+    std::fs::write(&staged, binary).with_context(|| format!("Failed to write {}", staged.display()))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&staged, std::fs::Permissions::from_mode(0o755))
+            .with_context(|| format!("Failed to make {} executable", staged.display()))?;
+    }
+    // A running executable can't be replaced on Windows, but it can be moved
+    #[cfg(windows)]
+    let old = {
+        let old = target.with_extension("old");
+        let _ = std::fs::remove_file(&old);
+        std::fs::rename(target, &old).with_context(|| format!("Failed to move {} aside", target.display()))?;
+        old
+    };
+    if let Err(e) = std::fs::rename(&staged, target) {
+        let _ = std::fs::remove_file(&staged);
+        #[cfg(windows)]
+        let _ = std::fs::rename(&old, target);
+        return Err(e).with_context(|| format!("Failed to replace {}", target.display()));
+    }
+    Ok(())
+}
+
+fn get(agent: &ureq::Agent, url: &str) -> Result<ureq::Response> {
+    if !url.starts_with("https://") {
+        bail!("Refusing to update from a non-https URL: {}", url);
+    }
+    agent.get(url)
+        .set("User-Agent", concat!("command-replacer/", env!("CARGO_PKG_VERSION")))
+        .call()
+        .with_context(|| format!("Failed to fetch {}", url))
+}
@@ -0,0 +1,74 @@
+//! Answers shared by the long-running servers (`mcp-serve`, `serve`)
+//!
+//! The config is reloaded for every request, so edits apply without
+//! restarting a server; the layer cache keeps that cheap while the files
+//! are unchanged.
+
+use anyhow::Result;
+use serde_json::{json, Value};
+use std::path::Path;
+
+use crate::config::Config;
+use crate::context::RuleContext;
+use crate::explain::Explanation;
+use crate::replacements::{FallbackReason, ReplacementEngine};
+use crate::tool_cache::{ToolCache, ToolEntry};
+
+/// What the hook would do with one command in `project_dir`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Optimized {
+    pub rewritten: Option<String>,
+    /// Why the command was left alone, when the engine said
+    pub fallback: Option<FallbackReason>,
+}
+
+impl Optimized {
+    /// `{"rewritten": ..., "reason": ..., "construct": ...}`
+    pub fn to_json(&self) -> Value {
+        json!({
+            "rewritten": self.rewritten,
+            "reason": self.fallback.as_ref().map(|reason| &reason.detail),
+            "construct": self.fallback.as_ref().map(|reason| &reason.construct),
+        })
+    }
+}
+
+/// Rewrite `command` the way the hook would for a session in `project_dir`
+pub fn optimize(project_dir: &Path, command: &str) -> Result<Optimized> {
+    let engine = engine(project_dir)?;
+    let rewritten = engine.replace_command(command)?;
+    let fallback = engine.last_fallback_reason().filter(|_| rewritten.is_none());
+    Ok(Optimized { rewritten, fallback })
+}
+
+/// Every decision behind what the hook would do with `command` in `project_dir`
+pub fn explain(project_dir: &Path, command: &str) -> Result<Explanation> {
+    engine(project_dir)?.explain(command)
+}
+
+/// Whether `name` (or the path pinned for it) is installed, and which
+/// version
+pub fn check_tool(project_dir: &Path, name: &str) -> Result<Value> {
+    let config = Config::load_for_project(Some(project_dir))?;
+    let program = config.tools.get(name).map(String::as_str).unwrap_or(name);
+    let entry = ToolEntry::probe(program);
+    Ok(json!({
+        "name": name,
+        "installed": entry.path.is_some(),
+        "path": entry.path,
+        "version": entry.version.map(|version| {
+            version.iter().map(u64::to_string).collect::<Vec<_>>().join(".")
+        }),
+    }))
+}
+
+/// The engine as the hook would build it for a session in `project_dir`
+fn engine(project_dir: &Path) -> Result<ReplacementEngine> {
+    let config = Config::load_for_project(Some(project_dir))?;
+    let tool_cache = if config.settings.cache_tool_checks {
+        ToolCache::load(&ToolCache::default_path())
+    } else {
+        ToolCache::default()
+    };
+    Ok(ReplacementEngine::for_context(config, RuleContext::detect(project_dir))?.with_tool_cache(tool_cache))
+}
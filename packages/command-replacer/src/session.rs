@@ -0,0 +1,120 @@
+//! Session-level hook events
+//!
+//! On SessionStart, everything PreToolUse would otherwise work out on its
+//! first call — which tools are installed, their versions, the project type
+//! and git status — is gathered once. On UserPromptSubmit, Claude can be
+//! told up front which fast tools to reach for.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::config::Config;
+use crate::context::RuleContext;
+use crate::replacements::alternative_tools;
+use crate::rules;
+use crate::tool_cache::{probe_concurrently, ToolCache, ToolEntry};
+use crate::tracking::session_file;
+
+/// Detected context saved for the rest of a session
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SessionContext {
+    pub project_dir: PathBuf,
+    pub context: RuleContext,
+}
+
+impl SessionContext {
+    /// The saved context, if it was detected for this project
+    pub fn load(state_dir: &Path, session_id: &str, project_dir: &Path) -> Option<Self> {
+        let content = std::fs::read_to_string(session_file(state_dir, session_id, "context.json")).ok()?;
+        serde_json::from_str::<Self>(&content)
+            .ok()
+            .filter(|saved| saved.project_dir == project_dir)
+    }
+    
+    pub fn save(&self, state_dir: &Path, session_id: &str) -> Result<()> {
+        std::fs::create_dir_all(state_dir)
+            .with_context(|| format!("Failed to create {}", state_dir.display()))?;
+        let path = session_file(state_dir, session_id, "context.json");
+        std::fs::write(&path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+}
+
+/// The context for `project_dir`, detected at most once per session
+pub fn context_for(state_dir: &Path, session_id: &str, project_dir: &Path) -> RuleContext {
+    if let Some(saved) = SessionContext::load(state_dir, session_id, project_dir) {
+        return saved.context;
+    }
+    let detected = SessionContext {
+        project_dir: project_dir.to_path_buf(),
+        context: RuleContext::detect(project_dir),
+    };
+    // Not being able to save only means detecting again next call
+    if !session_id.is_empty() {
+        let _ = detected.save(state_dir, session_id);
+    }
+    detected.context
+}
+
+/// Every tool an enabled replacement or rule might rewrite to
+pub fn configured_tools(config: &Config) -> Vec<String> {
+    let mut tools: Vec<String> = Vec::new();
+    for (command, replacement) in &config.replacements {
+        if !replacement.enabled {
+            continue;
+        }
+        tools.push(replacement.replacement.clone());
+        tools.extend(alternative_tools(command, std::env::consts::OS).iter().map(|tool| tool.to_string()));
+    }
+    tools.extend(config.rules.iter().filter(|rule| rule.enabled).map(|rule| rules::rule_tool(rule).to_string()));
+    tools.retain(|tool| !tool.is_empty());
+    tools.sort();
+    tools.dedup();
+    tools
+}
+
+/// Probe the configured tools into the cache at `cache_path` and save the
+/// detected context for the session
+pub fn prewarm(
+    config: &Config,
+    session_id: &str,
+    project_dir: &Path,
+    state_dir: &Path,
+    cache_path: &Path,
+) -> Result<ToolCache> {
+    let mut cache = ToolCache::load(cache_path);
+    let programs = configured_tools(config)
+        .into_iter()
+        .map(|tool| {
+            let program = config.tools.get(&tool).cloned().unwrap_or_else(|| tool.clone());
+            (tool, program)
+        })
+        .collect();
+    for ((tool, _), entry) in probe_concurrently(programs, None, |(_, program): &(String, String)| ToolEntry::probe(program)) {
+        cache.insert(&tool, entry);
+    }
+    cache.save(cache_path)?;
+    
+    SessionContext {
+        project_dir: project_dir.to_path_buf(),
+        context: RuleContext::detect(project_dir),
+    }
+    .save(state_dir, session_id)?;
+    
+    Ok(cache)
+}
+
+/// Context describing the installed replacements, e.g. "Fast tools are
+/// installed: rg (use instead of grep), fd (use instead of find). Prefer
+/// them in Bash commands."
+pub fn tooling_context(available: &[(String, String)]) -> Option<String> {
+    if available.is_empty() {
+        return None;
+    }
+    let tools: Vec<String> = available
+        .iter()
+        .map(|(command, tool)| format!("{} (use instead of {})", tool, command))
+        .collect();
+    Some(format!("Fast tools are installed: {}. Prefer them in Bash commands.", tools.join(", ")))
+}
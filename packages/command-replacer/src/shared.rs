@@ -0,0 +1,88 @@
+//! The configuration shared with `packages/windows-tools`
+//!
+//! windows-tools reads the same tool paths, replacements, and policies as
+//! the hook, but not the rest of the config, whose shape is this crate's own
+//! business. `config export` writes the effective config in this smaller,
+//! versioned shape, and `config schema` describes it; the TypeScript types
+//! in `windows-tools/src/src/shared-config.ts` mirror it.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::config::Config;
+
+/// Bumped whenever a field changes meaning or goes away
+pub const SHARED_CONFIG_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
+#[schemars(deny_unknown_fields)]
+pub struct SharedConfig {
+    /// Version of this format, not of the hook's config
+    pub version: u32,
+    
+    /// System the config was exported on (`windows`, `macos`, `linux`)
+    pub os: String,
+    
+    /// Pinned tool paths, by tool name (`[tools]`)
+    pub tools: BTreeMap<String, String>,
+    
+    /// Built-in replacements, by the command they replace
+    pub replacements: BTreeMap<String, SharedReplacement>,
+    
+    pub policies: SharedPolicies,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
+#[schemars(deny_unknown_fields)]
+pub struct SharedReplacement {
+    pub enabled: bool,
+    /// Tool the command is rewritten to
+    pub tool: String,
+    /// Higher wins when a command has several candidates
+    pub priority: u8,
+}
+
+/// Which commands are rewritten or need confirmation (exact commands, or
+/// globs where `*` matches anything)
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize, JsonSchema)]
+#[schemars(deny_unknown_fields)]
+pub struct SharedPolicies {
+    pub never_replace: Vec<String>,
+    /// When non-empty, nothing else is rewritten
+    pub only_replace: Vec<String>,
+    pub ask: Vec<String>,
+}
+
+impl SharedConfig {
+    /// The shared part of `config`, as seen on `os`
+    pub fn from_config(config: &Config, os: &str) -> Self {
+        Self {
+            version: SHARED_CONFIG_VERSION,
+            os: os.to_string(),
+            tools: config.tools.iter().map(|(tool, path)| (tool.clone(), path.clone())).collect(),
+            replacements: config
+                .replacements
+                .iter()
+                .map(|(command, replacement)| {
+                    let shared = SharedReplacement {
+                        enabled: replacement.enabled,
+                        tool: replacement.replacement.clone(),
+                        priority: replacement.priority,
+                    };
+                    (command.clone(), shared)
+                })
+                .collect(),
+            policies: SharedPolicies {
+                never_replace: config.settings.never_replace.clone(),
+                only_replace: config.settings.only_replace.clone(),
+                ask: config.settings.ask.clone(),
+            },
+        }
+    }
+}
+
+/// JSON Schema of `config export`'s output
+pub fn shared_schema() -> serde_json::Value {
+    serde_json::to_value(schemars::schema_for!(SharedConfig)).expect("schema is always serializable")
+}
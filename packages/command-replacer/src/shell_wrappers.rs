@@ -0,0 +1,119 @@
+//! `cmd /c "..."` and `powershell -Command "..."` wrappers
+//!
+//! The payload is a command line for another shell. cmd's is rewritten by
+//! the engine as on Windows, cmd builtins included, and PowerShell's by
+//! [`crate::powershell`]; either way the result is quoted the way that shell
+//! reads it and handed back to the wrapper as one argument.
+
+/// The shell a wrapper runs its payload in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shell {
+    Cmd,
+    PowerShell,
+}
+
+/// `cmd [/d /q /s ...] /c PAYLOAD` or `powershell [-NoProfile ...] -Command PAYLOAD`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Wrapper {
+    pub shell: Shell,
+    /// The shell and its options, up to and including `/c` or `-Command`
+    pub prefix: Vec<String>,
+    /// The command line the shell runs; several words are joined by spaces,
+    /// as the shell itself would
+    pub payload: String,
+}
+
+impl Wrapper {
+    /// The wrapper in `words`, if they hand a command line to cmd or PowerShell
+    pub fn parse(words: &[String]) -> Option<Self> {
+        let (program, args) = words.split_first()?;
+        let shell = shell(program)?;
+        let mut prefix = vec![program.clone()];
+        let mut args = args.iter();
+        loop {
+            let arg = args.next()?;
+            prefix.push(arg.clone());
+            let option = match shell {
+                Shell::Cmd => cmd_option(arg)?,
+                Shell::PowerShell => powershell_option(arg)?,
+            };
+            match option {
+                ShellArg::Payload => break,
+                ShellArg::Flag => {}
+                ShellArg::Value => prefix.push(args.next()?.clone()),
+            }
+        }
+        let payload = args.cloned().collect::<Vec<_>>().join(" ");
+        // `-Command -` reads the script from stdin
+        (!payload.trim().is_empty() && payload != "-").then_some(Self { shell, prefix, payload })
+    }
+}
+
+/// What an option before the payload is
+enum ShellArg {
+    /// `/c` or `-Command`: the rest is the payload
+    Payload,
+    Flag,
+    /// Takes the next word as its value
+    Value,
+}
+
+/// The shell `program` is, by name, in any case and with or without `.exe`
+fn shell(program: &str) -> Option<Shell> {
+    let name = program.rsplit(['/', '\\']).next().unwrap_or(program).to_ascii_lowercase();
+    match name.strip_suffix(".exe").unwrap_or(&name) {
+        "cmd" => Some(Shell::Cmd),
+        "powershell" | "pwsh" => Some(Shell::PowerShell),
+        _ => None,
+    }
+}
+
+/// cmd's switches, spelled `/c` or, to get past MSYS path conversion, `//c`
+fn cmd_option(arg: &str) -> Option<ShellArg> {
+    let switch = arg.strip_prefix("//").or_else(|| arg.strip_prefix('/'))?.to_ascii_lowercase();
+    match switch.as_str() {
+        "c" => Some(ShellArg::Payload),
+        "d" | "q" | "s" | "a" | "u" => Some(ShellArg::Flag),
+        _ if ["e:", "v:", "f:", "t:"].iter().any(|prefix| switch.starts_with(prefix)) => Some(ShellArg::Flag),
+        // /k keeps the shell open, and anything else isn't a command line
+        _ => None,
+    }
+}
+
+/// PowerShell's options, which may be shortened (`-c`, `-nop`)
+fn powershell_option(arg: &str) -> Option<ShellArg> {
+    let name = arg.strip_prefix('-')?.to_ascii_lowercase();
+    match name.as_str() {
+        "c" => Some(ShellArg::Payload),
+        _ if name.len() >= 3 && "command".starts_with(&name) => Some(ShellArg::Payload),
+        "noprofile" | "nop" | "noninteractive" | "noni" | "nologo" | "mta" | "sta" => Some(ShellArg::Flag),
+        "executionpolicy" | "ep" | "ex" | "windowstyle" | "w" => Some(ShellArg::Value),
+        // -File, -EncodedCommand, ... don't take a command line
+        _ => None,
+    }
+}
+
+/// `word` as cmd hands it to a program: bare when nothing in it would split
+/// it or mean something to cmd, otherwise double-quoted with the quotes, and
+/// the backslashes before them, escaped for the program's argument parser
+pub fn cmd_quote(word: &str) -> String {
+    if !word.is_empty() && !word.contains(|c: char| c.is_whitespace() || "\"&|<>^()".contains(c)) {
+        return word.to_string();
+    }
+    let mut quoted = String::from("\"");
+    let mut backslashes = 0;
+    for c in word.chars() {
+        if c == '\\' {
+            backslashes += 1;
+            continue;
+        }
+        let escapes = if c == '"' { backslashes * 2 + 1 } else { backslashes };
+        quoted.push_str(&"\\".repeat(escapes));
+        quoted.push(c);
+        backslashes = 0;
+    }
+    // The closing quote would be escaped by a trailing backslash
+    quoted.push_str(&"\\".repeat(backslashes * 2));
+    quoted.push('"');
+    quoted
+}
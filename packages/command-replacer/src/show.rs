@@ -0,0 +1,196 @@
+//! Effective configuration with the origin of every setting, for `show-config`
+
+use anyhow::{Context, Result};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::config::{apply_platform_section, read_config_value, Config};
+
+/// Key path of a setting, e.g. `["settings", "debug"]`
+pub type KeyPath = Vec<String>;
+
+/// The fully resolved config and where each setting came from
+pub struct EffectiveConfig {
+    pub config: Config,
+    /// Files merged, lowest precedence first
+    pub sources: Vec<PathBuf>,
+    /// Layer that last set each key; unlisted keys come from the defaults
+    origins: BTreeMap<KeyPath, String>,
+}
+
+impl EffectiveConfig {
+    /// Resolve the config the hook would use in `cwd`, tracking every layer:
+    /// files (with includes and remote policy), directory overrides, profile,
+    /// and environment
+    pub fn resolve<I>(project_dir: Option<&Path>, cwd: &Path, vars: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = (String, String)>,
+    {
+        let mut config = Config::load_file_layers(project_dir)?;
+        let sources = config.sources.clone();
+        let mut origins = BTreeMap::new();
+        
+        for path in &sources {
+            let mut value = read_config_value(path)?;
+            if let Some(table) = value.as_table_mut() {
+                table.remove("include");
+                table.remove("version");
+            }
+            apply_platform_section(&mut value, std::env::consts::OS);
+            for (key, _) in leaves(&value) {
+                origins.insert(key, path.display().to_string());
+            }
+        }
+        
+        let before = snapshot(&config)?;
+        config = config.with_directory_overrides(cwd, project_dir)?;
+        record_changes(&mut origins, &before, &snapshot(&config)?, "directory overrides");
+        
+        if let Some(profile) = config.settings.profile {
+            let before = snapshot(&config)?;
+            config.apply_profile();
+            let label = format!("profile {}", format!("{:?}", profile).to_lowercase());
+            record_changes(&mut origins, &before, &snapshot(&config)?, &label);
+        }
+        
+        let before = snapshot(&config)?;
+        config.apply_env_overrides(vars)?;
+        record_changes(&mut origins, &before, &snapshot(&config)?, "environment (CLAUDE_CMDREPL_*)");
+        
+        Ok(Self { config, sources, origins })
+    }
+    
+    /// Origin of a key: the nearest annotated ancestor, else the defaults
+    pub fn origin(&self, key: &[String]) -> &str {
+        (0..=key.len())
+            .rev()
+            .find_map(|len| self.origins.get(&key[..len]))
+            .map_or("defaults", String::as_str)
+    }
+    
+    /// Config plus an `origins` map keyed by dotted path
+    pub fn to_json(&self) -> Result<serde_json::Value> {
+        let value = snapshot(&self.config)?;
+        let origins: serde_json::Map<String, serde_json::Value> = leaves(&value)
+            .into_iter()
+            .map(|(key, _)| (key.join("."), self.origin(&key).into()))
+            .collect();
+        
+        Ok(serde_json::json!({
+            "sources": self.sources,
+            "config": value,
+            "origins": origins,
+        }))
+    }
+    
+    /// Config as TOML with `# from <origin>` after every key and table header
+    pub fn to_annotated_toml(&self) -> Result<String> {
+        let rendered = toml::to_string_pretty(&self.config).context("Failed to serialize config")?;
+        
+        let mut out = String::from("# Effective command-replacer configuration\n# Sources, lowest precedence first:\n");
+        if self.sources.is_empty() {
+            out.push_str("#   (none; built-in defaults)\n");
+        }
+        for source in &self.sources {
+            out.push_str(&format!("#   {}\n", source.display()));
+        }
+        out.push('\n');
+        
+        let mut table: KeyPath = Vec::new();
+        let mut in_multiline_array = false;
+        for line in rendered.lines() {
+            let trimmed = line.trim();
+            let key = if in_multiline_array {
+                in_multiline_array = trimmed != "]";
+                None
+            } else if let Some(header) = trimmed.strip_prefix("[[").and_then(|h| h.strip_suffix("]]")) {
+                table = split_key(header);
+                Some(table.clone())
+            } else if let Some(header) = trimmed.strip_prefix('[').and_then(|h| h.strip_suffix(']')) {
+                // Plain tables only get an origin when set as a whole (e.g. empty)
+                table = split_key(header);
+                self.origins.contains_key(&table).then(|| table.clone())
+            } else if let Some((name, value)) = split_assignment(trimmed) {
+                in_multiline_array = value == "[";
+                let mut key = table.clone();
+                key.extend(split_key(name));
+                Some(key)
+            } else {
+                None
+            };
+            
+            out.push_str(line);
+            if let Some(key) = key {
+                out.push_str(&format!("  # from {}", self.origin(&key)));
+            }
+            out.push('\n');
+        }
+        Ok(out)
+    }
+}
+
+fn snapshot(config: &Config) -> Result<toml::Value> {
+    toml::Value::try_from(config).context("Failed to serialize config")
+}
+
+/// Attribute every leaf that differs between two snapshots to `label`
+fn record_changes(origins: &mut BTreeMap<KeyPath, String>, before: &toml::Value, after: &toml::Value, label: &str) {
+    let before: BTreeMap<KeyPath, &toml::Value> = leaves(before).into_iter().collect();
+    for (key, value) in leaves(after) {
+        if before.get(&key) != Some(&value) {
+            origins.insert(key, label.to_string());
+        }
+    }
+}
+
+/// Flatten nested tables into key paths; arrays are treated as single values
+fn leaves(value: &toml::Value) -> Vec<(KeyPath, &toml::Value)> {
+    fn walk<'a>(value: &'a toml::Value, prefix: &mut KeyPath, out: &mut Vec<(KeyPath, &'a toml::Value)>) {
+        match value {
+            toml::Value::Table(table) if !table.is_empty() => {
+                for (key, child) in table {
+                    prefix.push(key.clone());
+                    walk(child, prefix, out);
+                    prefix.pop();
+                }
+            }
+            _ => out.push((prefix.clone(), value)),
+        }
+    }
+    
+    let mut out = Vec::new();
+    walk(value, &mut Vec::new(), &mut out);
+    out
+}
+
+/// Split a TOML key like `replacements.grep."-i"` into segments
+fn split_key(key: &str) -> KeyPath {
+    let mut segments = Vec::new();
+    let mut current = String::new();
+    let mut quote = None;
+    for c in key.chars() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), c) => current.push(c),
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '.') => segments.push(std::mem::take(&mut current).trim().to_string()),
+            (None, c) => current.push(c),
+        }
+    }
+    segments.push(current.trim().to_string());
+    segments
+}
+
+/// Split `key = value` outside quotes
+fn split_assignment(line: &str) -> Option<(&str, &str)> {
+    let mut quote = None;
+    for (index, c) in line.char_indices() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '=') => return Some((line[..index].trim(), line[index + 1..].trim())),
+            _ => {}
+        }
+    }
+    None
+}
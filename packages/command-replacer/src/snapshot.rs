@@ -0,0 +1,90 @@
+//! Session snapshot written at SessionStart
+//!
+//! SessionStart already probes the tools and detects the project; it also
+//! saves those, with the config files parsed and merged, as one compact
+//! MessagePack file per session. PreToolUse memory-maps the file instead of
+//! re-reading the config layers, the tool cache, and the session context.
+//! The snapshot is only trusted while every config file keeps its stamp and
+//! no new one has appeared; otherwise the call derives everything itself as
+//! before.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+use crate::config::{stamp_files, Config, FileStamp};
+use crate::context::RuleContext;
+use crate::tool_cache::ToolCache;
+use crate::tracking::session_file;
+
+/// Bumped whenever the layout changes; other versions are ignored
+const SNAPSHOT_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionSnapshot {
+    version: u32,
+    pub project_dir: PathBuf,
+    stamps: Vec<FileStamp>,
+    /// Config as merged from its files, before per-call overrides
+    pub layers: Config,
+    /// Files `layers` was merged from
+    sources: Vec<PathBuf>,
+    pub context: RuleContext,
+    pub tools: ToolCache,
+}
+
+impl SessionSnapshot {
+    pub fn new(project_dir: &Path, layers: Config, context: RuleContext, tools: ToolCache) -> Self {
+        Self {
+            version: SNAPSHOT_VERSION,
+            project_dir: project_dir.to_path_buf(),
+            stamps: layers.layer_stamps(Some(project_dir)),
+            sources: layers.sources.clone(),
+            layers,
+            context,
+            tools,
+        }
+    }
+    
+    pub fn path(state_dir: &Path, session_id: &str) -> PathBuf {
+        session_file(state_dir, session_id, "snapshot")
+    }
+    
+    /// Write the snapshot atomically; it is replaced, never rewritten in
+    /// place, which is what makes mapping it safe
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        let bytes = rmp_serde::to_vec_named(self).context("Failed to encode session snapshot")?;
+        let temp = path.with_extension(format!("tmp.{}", std::process::id()));
+        std::fs::write(&temp, bytes)
+            .with_context(|| format!("Failed to write {}", temp.display()))?;
+        std::fs::rename(&temp, path)
+            .with_context(|| format!("Failed to replace {}", path.display()))
+    }
+    
+    /// The snapshot at `path`, if it was taken for `project_dir` and is
+    /// still current
+    pub fn load(path: &Path, project_dir: &Path) -> Option<Self> {
+        let file = File::open(path).ok()?;
+        // SAFETY: snapshots are only ever replaced by a rename, so the
+        // mapped file is never modified while it is mapped
+        #[cfg(feature = "native")]
+        let map = unsafe { memmap2::Mmap::map(&file) }.ok()?;
+        #[cfg(not(feature = "native"))]
+        let map = std::io::Read::bytes(file).collect::<std::io::Result<Vec<u8>>>().ok()?;
+        let mut snapshot: Self = rmp_serde::from_slice(&map).ok()?;
+        if snapshot.version != SNAPSHOT_VERSION || snapshot.project_dir != project_dir {
+            return None;
+        }
+        let paths: Vec<PathBuf> = snapshot.stamps.iter().map(|(path, _, _)| path.clone()).collect();
+        if stamp_files(&paths) != snapshot.stamps {
+            return None;
+        }
+        snapshot.layers.sources = std::mem::take(&mut snapshot.sources);
+        Some(snapshot)
+    }
+}
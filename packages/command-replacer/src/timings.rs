@@ -0,0 +1,101 @@
+//! Per-phase latency of one hook call
+//!
+//! Every call is split into phases (parsing the input, loading config,
+//! detecting the project, probing tools, rewriting). With `settings.debug`
+//! on, the timings are appended to the debug log and, in the legacy output
+//! protocol, to the response context, so a hook that feels slow can be
+//! diagnosed on the machine it's slow on.
+
+use anyhow::{Context, Result};
+use std::io::Write;
+use std::path::Path;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Timings {
+    started: Instant,
+    last: Instant,
+    phases: Vec<(&'static str, Duration)>,
+}
+
+impl Timings {
+    /// Start timing a call that began at `started`
+    pub fn new(started: Instant) -> Self {
+        Self { started, last: started, phases: Vec::new() }
+    }
+    
+    /// End `phase`, which ran from the previous mark until now
+    pub fn mark(&mut self, phase: &'static str) {
+        let now = Instant::now();
+        self.phases.push((phase, now - self.last));
+        self.last = now;
+    }
+    
+    /// Move `duration` of the most recent phase into a phase of its own,
+    /// e.g. the tool probes that ran during rewriting
+    pub fn split_off(&mut self, phase: &'static str, duration: Duration) {
+        if duration.is_zero() {
+            return;
+        }
+        if let Some((_, last)) = self.phases.last_mut() {
+            let duration = duration.min(*last);
+            *last -= duration;
+            self.phases.push((phase, duration));
+        }
+    }
+    
+    /// When the call began
+    pub fn started(&self) -> Instant {
+        self.started
+    }
+    
+    pub fn phases(&self) -> &[(&'static str, Duration)] {
+        &self.phases
+    }
+    
+    /// Time spent in every phase named `phase`
+    pub fn phase(&self, phase: &str) -> Duration {
+        self.phases.iter().filter(|(name, _)| *name == phase).map(|(_, duration)| *duration).sum()
+    }
+    
+    /// Time since the call began
+    pub fn total(&self) -> Duration {
+        self.started.elapsed()
+    }
+    
+    /// `{"parse_ms": 0.08, "config_ms": 0.41, ..., "total_ms": 1.2}`
+    pub fn to_json(&self) -> serde_json::Value {
+        let mut object = serde_json::Map::new();
+        for (phase, duration) in &self.phases {
+            object.insert(format!("{}_ms", phase), millis(*duration).into());
+        }
+        object.insert("total_ms".to_string(), millis(self.total()).into());
+        object.into()
+    }
+    
+    /// Append one line for this call to the debug log
+    pub fn log(&self, path: &Path, event: &str, reason_code: &str) -> Result<()> {
+        let ts_ms = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0);
+        let line = serde_json::json!({
+            "timestamp": claude_hooks_sdk::rfc3339(ts_ms),
+            "event": event,
+            "reason_code": reason_code,
+            "timings": self.to_json(),
+        });
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("Failed to open {}", path.display()))?;
+        writeln!(file, "{}", line).with_context(|| format!("Failed to write {}", path.display()))
+    }
+}
+
+/// Milliseconds, to the microsecond
+fn millis(duration: Duration) -> f64 {
+    (duration.as_secs_f64() * 1e6).round() / 1e3
+}
@@ -0,0 +1,256 @@
+//! On-disk record of which replacement tools are installed
+//!
+//! SessionStart probes every configured tool once and saves the result, so
+//! PreToolUse calls in the same session can skip the PATH search and
+//! `--version` runs.
+
+use anyhow::{Context, Result};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use crate::config::Config;
+use crate::replacements::parse_version;
+
+/// How long a "not installed" answer is trusted, in seconds
+const MISSING_TTL_SECS: u64 = 24 * 60 * 60;
+
+/// Most probes run at once
+const PROBE_THREADS: usize = 4;
+
+/// What probing one tool found
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolEntry {
+    /// Resolved executable, `None` when the tool is not installed
+    pub path: Option<PathBuf>,
+    /// Parsed `--version` output
+    pub version: Option<Vec<u64>>,
+    /// Raw `--version` output, for capability checks
+    #[serde(default)]
+    pub version_text: Option<String>,
+    /// Unix time of the probe, in seconds
+    pub checked_at: u64,
+}
+
+impl ToolEntry {
+    /// Look up `program` (a name or pinned path) and ask it for its version
+    pub fn probe(program: &str) -> Self {
+        let path = find_program(program);
+        let version_text = path.as_deref().and_then(probe_version_text);
+        let version = version_text.as_deref().and_then(parse_version);
+        Self { path, version, version_text, checked_at: now_secs() }
+    }
+    
+    /// Installed tools stay valid while their executable exists; missing
+    /// ones are re-probed once the TTL passes
+    fn is_fresh(&self) -> bool {
+        match &self.path {
+            Some(path) => path.is_file(),
+            None => now_secs().saturating_sub(self.checked_at) < MISSING_TTL_SECS,
+        }
+    }
+}
+
+/// Probed tools by name
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct ToolCache {
+    tools: BTreeMap<String, ToolEntry>,
+}
+
+impl ToolCache {
+    /// Where the hook keeps the cache between invocations
+    pub fn default_path() -> PathBuf {
+        Config::cache_dir().join("tools.json")
+    }
+    
+    /// Load the cache; a missing or corrupt file is an empty cache
+    pub fn load(path: &Path) -> Self {
+        std::fs::read_to_string(path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+    
+    /// Write the cache atomically so a concurrent hook never reads half a file
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        let temp = path.with_extension(format!("tmp.{}", std::process::id()));
+        std::fs::write(&temp, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to write {}", temp.display()))?;
+        std::fs::rename(&temp, path)
+            .with_context(|| format!("Failed to replace {}", path.display()))
+    }
+    
+    pub fn insert(&mut self, tool: &str, entry: ToolEntry) {
+        self.tools.insert(tool.to_string(), entry);
+    }
+    
+    /// The cached entry for `tool`, if it is still trustworthy
+    pub fn get(&self, tool: &str) -> Option<&ToolEntry> {
+        self.tools.get(tool).filter(|entry| entry.is_fresh())
+    }
+}
+
+/// Extensions Windows runs without being named, when `PATHEXT` isn't set
+#[cfg(feature = "native")]
+const DEFAULT_PATHEXT: &str = ".COM;.EXE;.BAT;.CMD";
+
+/// Programs found outside PATH by [`find_program`], by name
+static OFF_PATH: Lazy<Mutex<HashMap<String, PathBuf>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Resolve `program` on PATH; without the `native` feature there is no PATH
+/// to search and nothing is found
+///
+/// On Windows this honors `PATHEXT` (so `rg` finds `rg.exe` and `rg.exe`
+/// itself works), then falls back to the shim directories of package
+/// managers, which a shell started without the user's profile may lack.
+pub fn find_program(program: &str) -> Option<PathBuf> {
+    #[cfg(feature = "native")]
+    {
+        if !cfg!(windows) {
+            return which::which(program).ok();
+        }
+        let pathext = std::env::var("PATHEXT").unwrap_or_else(|_| DEFAULT_PATHEXT.to_string());
+        if program.contains(['/', '\\']) {
+            return find_with_pathext(program, &[PathBuf::new()], &pathext);
+        }
+        let path_dirs: Vec<PathBuf> = std::env::var_os("PATH").map(|path| std::env::split_paths(&path).collect()).unwrap_or_default();
+        if let Some(found) = find_with_pathext(program, &path_dirs, &pathext) {
+            return Some(found);
+        }
+        let found = find_with_pathext(program, &shim_dirs(), &pathext)?;
+        OFF_PATH.lock().unwrap().insert(program.to_string(), found.clone());
+        Some(found)
+    }
+    #[cfg(not(feature = "native"))]
+    {
+        let _ = program;
+        None
+    }
+}
+
+/// The first `dir/program` that is a file, trying each `pathext` extension
+/// (`.COM;.EXE;...`) unless `program` already has one of them
+#[cfg(feature = "native")]
+pub fn find_with_pathext(program: &str, dirs: &[PathBuf], pathext: &str) -> Option<PathBuf> {
+    let extensions: Vec<String> = pathext
+        .split(';')
+        .filter(|ext| ext.starts_with('.'))
+        .map(str::to_ascii_lowercase)
+        .collect();
+    let named = Path::new(program)
+        .extension()
+        .is_some_and(|ext| extensions.contains(&format!(".{}", ext.to_string_lossy().to_ascii_lowercase())));
+    let names: Vec<String> = match named {
+        true => vec![program.to_string()],
+        false => extensions.iter().map(|ext| format!("{}{}", program, ext)).collect(),
+    };
+    dirs.iter()
+        .flat_map(|dir| names.iter().map(move |name| dir.join(name)))
+        .find(|path| path.is_file())
+}
+
+/// Where scoop, cargo, winget, and Chocolatey put the programs they install
+#[cfg(feature = "native")]
+fn shim_dirs() -> Vec<PathBuf> {
+    let var = |name: &str| std::env::var_os(name).filter(|value| !value.is_empty()).map(PathBuf::from);
+    let home = var("USERPROFILE");
+    [
+        var("SCOOP").or_else(|| home.as_ref().map(|home| home.join("scoop"))).map(|scoop| scoop.join("shims")),
+        var("CARGO_HOME").or_else(|| home.as_ref().map(|home| home.join(".cargo"))).map(|cargo| cargo.join("bin")),
+        var("LOCALAPPDATA").map(|local| local.join("Microsoft").join("WinGet").join("Links")),
+        var("ChocolateyInstall").map(|choco| choco.join("bin")),
+    ]
+    .into_iter()
+    .flatten()
+    .collect()
+}
+
+/// Where `tool` was found outside PATH, by this process or the probe in
+/// `cache`; emitted commands name it by that path, since a shell wouldn't
+/// find it by name
+pub fn off_path_location(tool: &str, cache: &ToolCache) -> Option<PathBuf> {
+    if let Some(path) = OFF_PATH.lock().unwrap().get(tool) {
+        return Some(path.clone());
+    }
+    let path = cache.get(tool)?.path.clone()?;
+    let dir = path.parent()?;
+    let path_dirs = std::env::var_os("PATH")?;
+    (!std::env::split_paths(&path_dirs).any(|path_dir| path_dir == dir)).then_some(path)
+}
+
+/// Run `probe` on every item, a few at a time, and return the results that
+/// arrive before `deadline`. Probes still running then are abandoned; their
+/// threads finish on their own. WebAssembly has no threads, so there the
+/// probes run one after another until the deadline.
+pub fn probe_concurrently<T, R, F>(items: Vec<T>, deadline: Option<Instant>, probe: F) -> Vec<(T, R)>
+where
+    T: Send + 'static,
+    R: Send + 'static,
+    F: Fn(&T) -> R + Send + Sync + 'static,
+{
+    let count = items.len();
+    if count == 0 {
+        return Vec::new();
+    }
+    if cfg!(target_family = "wasm") {
+        return items
+            .into_iter()
+            .take_while(|_| deadline.is_none_or(|deadline| Instant::now() < deadline))
+            .map(|item| {
+                let result = probe(&item);
+                (item, result)
+            })
+            .collect();
+    }
+    let queue = Arc::new(Mutex::new(items));
+    let probe = Arc::new(probe);
+    let (sender, receiver) = mpsc::channel();
+    for _ in 0..count.min(PROBE_THREADS) {
+        let (queue, probe, sender) = (Arc::clone(&queue), Arc::clone(&probe), sender.clone());
+        std::thread::spawn(move || loop {
+            // The lock is released before probing, so workers run in parallel
+            let Some(item) = queue.lock().unwrap().pop() else {
+                return;
+            };
+            let result = probe(&item);
+            if sender.send((item, result)).is_err() {
+                return;
+            }
+        });
+    }
+    drop(sender);
+    
+    let mut results = Vec::with_capacity(count);
+    while results.len() < count {
+        let received = match deadline {
+            Some(deadline) => receiver.recv_timeout(deadline.saturating_duration_since(Instant::now())).ok(),
+            None => receiver.recv().ok(),
+        };
+        match received {
+            Some(result) => results.push(result),
+            None => break,
+        }
+    }
+    results
+}
+
+/// Run `program --version` and return what it prints
+pub fn probe_version_text(program: &Path) -> Option<String> {
+    let output = Command::new(program).arg("--version").output().ok()?;
+    Some(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
@@ -0,0 +1,142 @@
+//! Install commands for missing replacement tools
+//!
+//! A replacement whose tool isn't installed just passes commands through,
+//! which is easy to never notice. This knows which package provides each
+//! tool under the package managers of each platform, so `doctor` can say
+//! exactly how to install it (and `doctor --fix` can run that), and the
+//! hook can mention it once per session with `settings.install_hints`.
+
+use anyhow::{bail, Context, Result};
+use std::fmt;
+use std::path::Path;
+
+use crate::tracking::session_file;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Installer {
+    Brew,
+    Apt,
+    Dnf,
+    Pacman,
+    Winget,
+    Scoop,
+    Cargo,
+}
+
+use Installer::*;
+
+/// Packages providing each tool. apt is missing where Debian renames the
+/// binary (`fdfind`, `batcat`), since the engine wouldn't find it.
+const PACKAGES: &[(&str, &[(Installer, &str)])] = &[
+    ("rg", &[(Brew, "ripgrep"), (Apt, "ripgrep"), (Dnf, "ripgrep"), (Pacman, "ripgrep"), (Winget, "BurntSushi.ripgrep.MSVC"), (Scoop, "ripgrep"), (Cargo, "ripgrep")]),
+    ("fd", &[(Brew, "fd"), (Dnf, "fd-find"), (Pacman, "fd"), (Winget, "sharkdp.fd"), (Scoop, "fd"), (Cargo, "fd-find")]),
+    ("bat", &[(Brew, "bat"), (Dnf, "bat"), (Pacman, "bat"), (Winget, "sharkdp.bat"), (Scoop, "bat"), (Cargo, "bat")]),
+    ("eza", &[(Brew, "eza"), (Apt, "eza"), (Pacman, "eza"), (Winget, "eza-community.eza"), (Scoop, "eza"), (Cargo, "eza")]),
+    ("sd", &[(Brew, "sd"), (Pacman, "sd"), (Winget, "chmln.sd"), (Scoop, "sd"), (Cargo, "sd")]),
+    ("procs", &[(Brew, "procs"), (Pacman, "procs"), (Winget, "dalance.procs"), (Scoop, "procs"), (Cargo, "procs")]),
+    ("dust", &[(Brew, "dust"), (Pacman, "dust"), (Winget, "bootandy.dust"), (Scoop, "dust"), (Cargo, "du-dust")]),
+];
+
+impl Installer {
+    pub fn program(self) -> &'static str {
+        match self {
+            Brew => "brew",
+            Apt => "apt-get",
+            Dnf => "dnf",
+            Pacman => "pacman",
+            Winget => "winget",
+            Scoop => "scoop",
+            Cargo => "cargo",
+        }
+    }
+    
+    /// Installers worth trying on `os`, most native first
+    pub fn for_os(os: &str) -> &'static [Installer] {
+        match os {
+            "macos" => &[Brew, Cargo],
+            "windows" => &[Winget, Scoop, Cargo],
+            _ => &[Apt, Dnf, Pacman, Brew, Cargo],
+        }
+    }
+    
+    fn package(self, tool: &str) -> Option<&'static str> {
+        let (_, packages) = PACKAGES.iter().find(|(name, _)| *name == tool)?;
+        packages.iter().find(|(installer, _)| *installer == self).map(|(_, package)| *package)
+    }
+    
+    fn argv(self, package: &str) -> Vec<String> {
+        let argv: &[&str] = match self {
+            Brew => &["brew", "install", package],
+            Apt => &["sudo", "apt-get", "install", "-y", package],
+            Dnf => &["sudo", "dnf", "install", "-y", package],
+            Pacman => &["sudo", "pacman", "-S", "--noconfirm", package],
+            Winget => &["winget", "install", "--exact", "--id", package],
+            Scoop => &["scoop", "install", package],
+            Cargo => &["cargo", "install", "--locked", package],
+        };
+        argv.iter().map(|arg| arg.to_string()).collect()
+    }
+}
+
+/// A command that installs one tool
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InstallCommand {
+    pub tool: String,
+    pub installer: Installer,
+    pub argv: Vec<String>,
+}
+
+impl InstallCommand {
+    /// Run the install, inheriting the terminal so prompts (sudo) work
+    pub fn run(&self) -> Result<()> {
+        let status = std::process::Command::new(&self.argv[0])
+            .args(&self.argv[1..])
+            .status()
+            .with_context(|| format!("Failed to run `{}`", self))?;
+        if !status.success() {
+            bail!("`{}` failed ({})", self, status);
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for InstallCommand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.argv.join(" "))
+    }
+}
+
+/// How to install `tool` with the package managers found on this machine
+pub fn install_command(tool: &str) -> Option<InstallCommand> {
+    install_command_for(tool, std::env::consts::OS, |program| crate::tool_cache::find_program(program).is_some())
+}
+
+/// [`install_command`] for `os`, with `installed` saying which package
+/// managers are present
+pub fn install_command_for(tool: &str, os: &str, installed: impl Fn(&str) -> bool) -> Option<InstallCommand> {
+    Installer::for_os(os).iter().find_map(|&installer| {
+        let package = installer.package(tool)?;
+        installed(installer.program()).then(|| InstallCommand {
+            tool: tool.to_string(),
+            installer,
+            argv: installer.argv(package),
+        })
+    })
+}
+
+/// Whether `tool` has not been hinted at yet this session, remembering
+/// that it now has
+pub fn first_hint(state_dir: &Path, session_id: &str, tool: &str) -> bool {
+    if session_id.is_empty() {
+        return false;
+    }
+    let path = session_file(state_dir, session_id, "hints");
+    let hinted = std::fs::read_to_string(&path).unwrap_or_default();
+    if hinted.lines().any(|line| line == tool) {
+        return false;
+    }
+    // Failing to record it only means hinting again
+    let _ = std::fs::create_dir_all(state_dir);
+    let _ = std::fs::write(&path, format!("{}{}\n", hinted, tool));
+    true
+}
@@ -0,0 +1,302 @@
+//! Rewrite outcome tracking across PreToolUse and PostToolUse
+//!
+//! Every rewrite is recorded in a per-session log. When the PostToolUse
+//! event for the rewritten command arrives, it is matched back to that
+//! record so the exit status and duration can be logged, and a failure can
+//! be reported to Claude along with the original command.
+//!
+//! Declined rewrites are logged too, so Stop can summarize the session and
+//! flush its totals to the history store.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// One line of a session log
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum Entry {
+    Rewrite {
+        original: String,
+        command: String,
+        at_ms: u64,
+    },
+    Outcome {
+        original: String,
+        command: String,
+        exit_code: Option<i32>,
+        /// Interrupted or reported as an error, whatever the exit status
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        errored: bool,
+        duration_ms: u64,
+    },
+    Fallback {
+        command: String,
+        construct: String,
+    },
+    /// Everything above has been written to the history store
+    Flushed,
+}
+
+/// Rough speedup of each replacement over the command it replaces, used to
+/// estimate time saved from the measured duration
+const ESTIMATED_SPEEDUP: &[(&str, f64)] = &[
+    ("rg", 3.0),
+    ("fd", 3.0),
+    ("sd", 1.5),
+];
+
+/// Totals over (part of) a session log
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SessionStats {
+    pub rewrites: u64,
+    pub fallbacks: u64,
+    pub failures: u64,
+    pub estimated_saved_ms: u64,
+}
+
+impl SessionStats {
+    fn add(&mut self, entry: &Entry) {
+        match entry {
+            Entry::Rewrite { .. } => self.rewrites += 1,
+            Entry::Fallback { .. } => self.fallbacks += 1,
+            Entry::Outcome { command, exit_code, errored, duration_ms, .. } => {
+                if *errored || exit_code.is_some_and(|code| code != 0) {
+                    self.failures += 1;
+                }
+                self.estimated_saved_ms += estimated_saved_ms(command, *duration_ms);
+            }
+            Entry::Flushed => {}
+        }
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.rewrites == 0 && self.fallbacks == 0
+    }
+    
+    /// One line for the user, e.g. "command-replacer: 12 rewrites,
+    /// 3 fallbacks, 1 failed, ~2.4s saved (estimated)"
+    pub fn summary(&self) -> Option<String> {
+        if self.is_empty() {
+            return None;
+        }
+        let mut parts = vec![
+            format!("{} rewrite{}", self.rewrites, if self.rewrites == 1 { "" } else { "s" }),
+            format!("{} fallback{}", self.fallbacks, if self.fallbacks == 1 { "" } else { "s" }),
+        ];
+        if self.failures > 0 {
+            parts.push(format!("{} failed", self.failures));
+        }
+        if self.estimated_saved_ms > 0 {
+            parts.push(format!("~{:.1}s saved (estimated)", self.estimated_saved_ms as f64 / 1000.0));
+        }
+        Some(format!("command-replacer: {}", parts.join(", ")))
+    }
+}
+
+/// A session's totals as written to the history store
+#[derive(Debug, Serialize)]
+struct HistoryRecord<'a> {
+    session_id: &'a str,
+    at_ms: u64,
+    #[serde(flatten)]
+    stats: &'a SessionStats,
+}
+
+/// How a rewritten command fared
+#[derive(Debug, Clone, PartialEq)]
+pub struct Outcome {
+    pub original: String,
+    pub command: String,
+    pub exit_code: Option<i32>,
+    /// Interrupted or reported as an error, whatever the exit status
+    pub errored: bool,
+    pub duration: Duration,
+}
+
+impl Outcome {
+    /// A missing exit status is not treated as a failure unless the call
+    /// errored
+    pub fn failed(&self) -> bool {
+        self.errored || self.exit_code.is_some_and(|code| code != 0)
+    }
+    
+    /// Context telling Claude the replacement may be to blame
+    pub fn retry_context(&self) -> String {
+        format!(
+            "command-replacer ran `{}` in place of `{}` and it {} after {} ms. \
+             If the failure looks like a difference between the tools, retry with the original command.",
+            self.command,
+            self.original,
+            match self.exit_code {
+                Some(code) => format!("exited with status {}", code),
+                None => "failed".to_string(),
+            },
+            self.duration.as_millis(),
+        )
+    }
+}
+
+/// Per-session rewrite logs under one directory
+pub struct Tracker {
+    dir: PathBuf,
+}
+
+impl Tracker {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+    
+    /// Remember that `original` was rewritten to `command`
+    pub fn record_rewrite(&self, session_id: &str, original: &str, command: &str) -> Result<()> {
+        self.append(session_id, &Entry::Rewrite {
+            original: original.to_string(),
+            command: command.to_string(),
+            at_ms: now_ms(),
+        })
+    }
+    
+    /// Remember that a rewrite was declined
+    pub fn record_fallback(&self, session_id: &str, command: &str, construct: &str) -> Result<()> {
+        self.append(session_id, &Entry::Fallback {
+            command: command.to_string(),
+            construct: construct.to_string(),
+        })
+    }
+    
+    /// Totals for the whole session
+    pub fn stats(&self, session_id: &str) -> Result<SessionStats> {
+        let mut stats = SessionStats::default();
+        for entry in self.read(session_id)? {
+            stats.add(&entry);
+        }
+        Ok(stats)
+    }
+    
+    /// Append the totals recorded since the last flush to `history_path`.
+    /// Stop fires after every turn, so each flush only covers what is new.
+    pub fn flush(&self, session_id: &str, history_path: &Path) -> Result<SessionStats> {
+        let mut pending = SessionStats::default();
+        for entry in self.read(session_id)? {
+            match entry {
+                Entry::Flushed => pending = SessionStats::default(),
+                entry => pending.add(&entry),
+            }
+        }
+        if pending.is_empty() {
+            return Ok(pending);
+        }
+        
+        let record = HistoryRecord { session_id, at_ms: now_ms(), stats: &pending };
+        append_line(history_path, &serde_json::to_string(&record)?)?;
+        self.append(session_id, &Entry::Flushed)?;
+        Ok(pending)
+    }
+    
+    /// Match a finished command to its pending rewrite, if there is one, and
+    /// log the outcome
+    pub fn record_outcome(&self, session_id: &str, command: &str, exit_code: Option<i32>, errored: bool) -> Result<Option<Outcome>> {
+        let entries = self.read(session_id)?;
+        
+        // The latest rewrite of this command that has no outcome yet
+        let mut pending = Vec::new();
+        for entry in entries {
+            match entry {
+                Entry::Rewrite { original, command: rewritten, at_ms } if rewritten == command => {
+                    pending.push((original, at_ms));
+                }
+                Entry::Outcome { command: finished, .. } if finished == command => {
+                    pending.pop();
+                }
+                _ => {}
+            }
+        }
+        let Some((original, at_ms)) = pending.pop() else {
+            return Ok(None);
+        };
+        
+        let outcome = Outcome {
+            original,
+            command: command.to_string(),
+            exit_code,
+            errored,
+            duration: Duration::from_millis(now_ms().saturating_sub(at_ms)),
+        };
+        self.append(session_id, &Entry::Outcome {
+            original: outcome.original.clone(),
+            command: outcome.command.clone(),
+            exit_code,
+            errored,
+            duration_ms: outcome.duration.as_millis() as u64,
+        })?;
+        
+        Ok(Some(outcome))
+    }
+    
+    fn session_path(&self, session_id: &str) -> PathBuf {
+        session_file(&self.dir, session_id, "jsonl")
+    }
+    
+    fn append(&self, session_id: &str, entry: &Entry) -> Result<()> {
+        append_line(&self.session_path(session_id), &serde_json::to_string(entry)?)
+    }
+    
+    fn read(&self, session_id: &str) -> Result<Vec<Entry>> {
+        read_entries(&self.session_path(session_id))
+    }
+}
+
+/// A per-session file under `dir`. Session ids come from the hook input, so
+/// they are reduced to safe file names.
+pub fn session_file(dir: &Path, session_id: &str, extension: &str) -> PathBuf {
+    let name: String = session_id
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect();
+    dir.join(format!("{}.{}", name, extension))
+}
+
+fn append_line(path: &Path, line: &str) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+    writeln!(file, "{}", line)
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// Time `command` would likely have taken without the rewrite, minus what
+/// it took
+fn estimated_saved_ms(command: &str, duration_ms: u64) -> u64 {
+    let program = command.split_whitespace().next().unwrap_or("");
+    let tool = Path::new(program).file_name().and_then(|name| name.to_str()).unwrap_or(program);
+    ESTIMATED_SPEEDUP
+        .iter()
+        .find(|(name, _)| *name == tool)
+        .map_or(0, |(_, speedup)| (duration_ms as f64 * (speedup - 1.0)) as u64)
+}
+
+/// Entries of a session log; a missing log is empty and unreadable lines
+/// are skipped
+fn read_entries(path: &Path) -> Result<Vec<Entry>> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    };
+    Ok(content.lines().filter_map(|line| serde_json::from_str(line).ok()).collect())
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_millis() as u64)
+}
@@ -0,0 +1,161 @@
+//! Configuration validation against the JSON Schema derived from `Config`
+//!
+//! Unknown keys are rejected by the schema, so typos like `preserve_flag`
+//! are reported instead of silently falling back to defaults.
+
+use anyhow::{Context, Result};
+use jsonschema::error::ValidationErrorKind;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::fmt;
+use std::path::Path;
+
+use crate::config::{merge_toml, Config, ConfigFormat};
+use crate::migrate;
+
+/// A single problem found in a configuration file
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValidationIssue {
+    /// JSON pointer to the offending value (e.g. `/replacements/grep`)
+    pub path: String,
+    pub message: String,
+    /// 1-based position in the source file, when it could be located
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+}
+
+impl fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let (Some(line), Some(column)) = (self.line, self.column) {
+            write!(f, "{}:{}: ", line, column)?;
+        }
+        if !self.path.is_empty() {
+            write!(f, "{}: ", self.path)?;
+        }
+        f.write_str(&self.message)
+    }
+}
+
+/// Generate the JSON Schema for the configuration file
+pub fn config_schema() -> serde_json::Value {
+    serde_json::to_value(schemars::schema_for!(Config)).expect("schema is always serializable")
+}
+
+/// Validate a configuration file, detecting its format from the extension.
+///
+/// Project-local overlays (`command-replacer.*`) are only partial configs, so
+/// they are checked after merging over the defaults, as `Config::load` would.
+pub fn validate_config_file(path: &Path) -> Result<Vec<ValidationIssue>> {
+    let format = ConfigFormat::from_path(path)
+        .with_context(|| format!("Unrecognized config format: {}", path.display()))?;
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read config file {}", path.display()))?;
+    let overlay = path.file_stem().is_some_and(|stem| stem == "command-replacer");
+
+    Ok(validate_config_str(&content, format, overlay))
+}
+
+/// Validate configuration content in the given format
+pub fn validate_config_str(content: &str, format: ConfigFormat, overlay: bool) -> Vec<ValidationIssue> {
+    let mut value = match parse_with_position(content, format) {
+        Ok(value) => value,
+        Err(issue) => return vec![issue],
+    };
+    // Older layouts are valid as long as they migrate cleanly
+    if let Err(e) = migrate::migrate(&mut value) {
+        return vec![issue_at("/version".to_string(), e.to_string(), locate_key(content, &["version".to_string()]))];
+    }
+
+    if overlay {
+        let mut base = toml::Value::try_from(Config::default()).expect("defaults are serializable");
+        merge_toml(&mut base, value);
+        value = base;
+    }
+
+    let instance = match serde_json::to_value(&value) {
+        Ok(instance) => instance,
+        Err(e) => return vec![issue_at(String::new(), e.to_string(), None)],
+    };
+    let validator = jsonschema::validator_for(&config_schema()).expect("generated schema is valid");
+
+    validator
+        .iter_errors(&instance)
+        .map(|error| {
+            let pointer = error.instance_path.as_str().to_string();
+            let mut segments: Vec<String> = pointer
+                .split('/')
+                .skip(1)
+                .map(|segment| segment.replace("~1", "/").replace("~0", "~"))
+                .collect();
+            // Point at the unexpected key itself rather than its parent table
+            let message = match &error.kind {
+                ValidationErrorKind::AdditionalProperties { unexpected } => {
+                    segments.extend(unexpected.first().cloned());
+                    format!("unknown key(s): {}", unexpected.join(", "))
+                }
+                _ => error.to_string(),
+            };
+            issue_at(pointer, message, locate_key(content, &segments))
+        })
+        .collect()
+}
+
+/// Parse content, turning syntax errors into issues with a source position
+fn parse_with_position(content: &str, format: ConfigFormat) -> Result<toml::Value, ValidationIssue> {
+    match format {
+        ConfigFormat::Toml => toml::from_str(content).map_err(|e| {
+            let position = e.span().map(|span| line_column(content, span.start));
+            issue_at(String::new(), e.message().to_string(), position)
+        }),
+        ConfigFormat::Json => {
+            let value: serde_json::Value = serde_json::from_str(content).map_err(|e| {
+                issue_at(String::new(), e.to_string(), Some((e.line(), e.column())))
+            })?;
+            toml::Value::try_from(value).map_err(|e| issue_at(String::new(), e.to_string(), None))
+        }
+        ConfigFormat::Yaml => {
+            let value: serde_json::Value = serde_yaml::from_str(content).map_err(|e| {
+                let position = e.location().map(|l| (l.line(), l.column()));
+                issue_at(String::new(), e.to_string(), position)
+            })?;
+            toml::Value::try_from(value).map_err(|e| issue_at(String::new(), e.to_string(), None))
+        }
+    }
+}
+
+fn issue_at(path: String, message: String, position: Option<(usize, usize)>) -> ValidationIssue {
+    ValidationIssue {
+        path,
+        message,
+        line: position.map(|(line, _)| line),
+        column: position.map(|(_, column)| column),
+    }
+}
+
+/// Find where a nested key is written, searching for each path segment in turn
+/// so `replacements/grep/preserve_flag` resolves inside the right table.
+/// Works for TOML, JSON, and YAML since all three spell keys as bare or
+/// quoted words.
+fn locate_key(content: &str, segments: &[String]) -> Option<(usize, usize)> {
+    static ARRAY_INDEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"^\d+$").unwrap());
+
+    let mut offset = 0;
+    let mut found = None;
+    for segment in segments.iter().filter(|s| !ARRAY_INDEX.is_match(s)) {
+        let pattern = format!(r#"(^|[\s\[{{.,"'])"?{}"?\s*([=:\].]|$)"#, regex::escape(segment));
+        let regex = Regex::new(&format!("(?m){}", pattern)).ok()?;
+        let m = regex.find(&content[offset..])?;
+        let start = offset + m.start() + content[offset + m.start()..].find(segment.as_str())?;
+        found = Some(start);
+        offset = start + segment.len();
+    }
+    found.map(|start| line_column(content, start))
+}
+
+/// Convert a byte offset into a 1-based line and column
+fn line_column(content: &str, offset: usize) -> (usize, usize) {
+    let before = &content[..offset.min(content.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before.rfind('\n').map_or(before.len(), |newline| before.len() - newline - 1) + 1;
+    (line, column)
+}
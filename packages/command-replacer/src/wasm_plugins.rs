@@ -0,0 +1,165 @@
+//! Sandboxed `.wasm` replacers from the plugins directory
+//!
+//! Every `*.wasm` (or `*.wat`) file in `~/.claude/hooks/command-replacer/plugins/`
+//! is a plugin speaking the same JSON as process plugins, through a small
+//! guest ABI. A module exports its `memory`, `alloc(len: i32) -> i32`, and
+//! `rewrite(ptr: i32, len: i32) -> i64`: the host copies the request into a
+//! buffer from `alloc`, calls `rewrite`, and reads the answer from the
+//! returned `(ptr << 32) | len`, where 0 means no opinion.
+//!
+//! Modules get no imports at all (no WASI, so no files, network, or clock),
+//! and each call runs in a fresh instance that is interrupted after the
+//! plugin's `timeout_ms` and can't grow past its `memory_mb`. Both come from
+//! a `[[plugins]]` entry named after the file without `run` or `script`,
+//! along with `commands` and `enabled`. Compiling a module takes far longer
+//! than a hook call should, so compiled code is cached by checksum under
+//! `cache/wasm/`.
+
+use anyhow::{bail, Context, Result};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::Duration;
+use wasmtime::{Engine, Instance, Module, Store, StoreLimits, StoreLimitsBuilder};
+
+use crate::config::PluginConfig;
+use crate::context::RuleContext;
+use crate::plugins::{self, Answer};
+
+/// Largest answer read from a plugin's memory
+const MAX_RESPONSE_BYTES: usize = 64 * 1024;
+
+pub struct WasmPlugin {
+    pub config: PluginConfig,
+    pub path: PathBuf,
+    module: Module,
+}
+
+/// The modules of one plugins directory, ready to run
+pub struct WasmRuntime {
+    engine: Engine,
+    pub plugins: Vec<WasmPlugin>,
+    /// Modules that failed to load, with why
+    pub failed: Vec<(PathBuf, String)>,
+}
+
+impl WasmRuntime {
+    /// Load the modules in `dir`, configured by the matching entries of
+    /// `configs`. A module that doesn't load is recorded in `failed` and
+    /// otherwise ignored.
+    pub fn load(dir: &Path, cache_dir: &Path, configs: &[PluginConfig]) -> Result<Self> {
+        let mut wasm_config = wasmtime::Config::new();
+        wasm_config.epoch_interruption(true);
+        let engine = Engine::new(&wasm_config).context("Failed to start the wasm runtime")?;
+        let mut runtime = Self { engine, plugins: Vec::new(), failed: Vec::new() };
+        
+        let mut paths: Vec<PathBuf> = match std::fs::read_dir(dir) {
+            Ok(entries) => entries.filter_map(|entry| Some(entry.ok()?.path())).collect(),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {}", dir.display())),
+        };
+        paths.retain(|path| path.extension().is_some_and(|ext| ext == "wasm" || ext == "wat"));
+        paths.sort();
+        
+        for path in paths {
+            let name = path.file_stem().unwrap_or_default().to_string_lossy().into_owned();
+            let config = configs
+                .iter()
+                .find(|config| config.name == name && !plugins::external(config))
+                .cloned()
+                .unwrap_or_else(|| PluginConfig::named(&name));
+            if !config.enabled {
+                continue;
+            }
+            match compile(&runtime.engine, &path, cache_dir) {
+                Ok(module) => runtime.plugins.push(WasmPlugin { config, path, module }),
+                Err(e) => runtime.failed.push((path, format!("{:#}", e))),
+            }
+        }
+        Ok(runtime)
+    }
+    
+    /// Ask `plugin` about `command`, in a fresh sandbox
+    pub fn ask(&self, plugin: &WasmPlugin, command: &str, context: &RuleContext) -> Result<Answer> {
+        let limits = StoreLimitsBuilder::new()
+            .memory_size(usize::try_from(plugin.config.memory_mb.saturating_mul(1024 * 1024)).unwrap_or(usize::MAX))
+            .instances(1)
+            .build();
+        let mut store = Store::new(&self.engine, limits);
+        store.limiter(|limits: &mut StoreLimits| limits);
+        store.set_epoch_deadline(1);
+        
+        // Bump the epoch (which traps the guest) unless the call finishes
+        // first and drops `done`
+        let (done, timer) = mpsc::channel::<()>();
+        let engine = self.engine.clone();
+        let timeout = Duration::from_millis(plugin.config.timeout_ms);
+        std::thread::spawn(move || {
+            if timer.recv_timeout(timeout) == Err(mpsc::RecvTimeoutError::Timeout) {
+                engine.increment_epoch();
+            }
+        });
+        let output = call(&mut store, plugin, &plugins::request(command, context).to_string());
+        drop(done);
+        
+        let output = output.map_err(|e| match e.downcast_ref::<wasmtime::Trap>() {
+            Some(wasmtime::Trap::Interrupt) => anyhow::anyhow!("Plugin {} did not answer within {}ms", plugin.config.name, plugin.config.timeout_ms),
+            _ => e.context(format!("Plugin {} failed", plugin.config.name)),
+        })?;
+        plugins::answer(&plugin.config, command, &output)
+    }
+}
+
+impl PluginConfig {
+    /// Defaults for a plugin no `[[plugins]]` entry mentions
+    fn named(name: &str) -> Self {
+        toml::from_str(&format!("name = {:?}", name)).expect("plugin defaults")
+    }
+}
+
+/// Run the guest ABI for one request, returning the answer's bytes
+fn call(store: &mut Store<StoreLimits>, plugin: &WasmPlugin, request: &str) -> Result<Vec<u8>> {
+    let instance = Instance::new(&mut *store, &plugin.module, &[])?;
+    let memory = instance.get_memory(&mut *store, "memory").context("module exports no `memory`")?;
+    let alloc = instance.get_typed_func::<i32, i32>(&mut *store, "alloc")?;
+    let rewrite = instance.get_typed_func::<(i32, i32), i64>(&mut *store, "rewrite")?;
+    
+    let len = i32::try_from(request.len()).context("request too large")?;
+    let ptr = alloc.call(&mut *store, len)?;
+    memory.write(&mut *store, ptr as u32 as usize, request.as_bytes())?;
+    let packed = rewrite.call(&mut *store, (ptr, len))? as u64;
+    if packed == 0 {
+        return Ok(Vec::new());
+    }
+    
+    let (ptr, len) = ((packed >> 32) as usize, (packed & 0xffff_ffff) as usize);
+    if len > MAX_RESPONSE_BYTES {
+        bail!("answer is larger than {} bytes", MAX_RESPONSE_BYTES);
+    }
+    let mut output = vec![0; len];
+    memory.read(&*store, ptr, &mut output)?;
+    Ok(output)
+}
+
+/// The module at `path`, compiled or from the cache of compiled modules
+fn compile(engine: &Engine, path: &Path, cache_dir: &Path) -> Result<Module> {
+    let source = std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let checksum: String = Sha256::digest(&source).iter().take(8).map(|byte| format!("{:02x}", byte)).collect();
+    let cached = cache_dir.join("wasm").join(format!("{}.cwasm", checksum));
+    
+    // Safety: only this function writes the cache, always from `Module::serialize`,
+    // and wasmtime refuses artifacts built by another version or config
+    if let Ok(module) = unsafe { Module::deserialize_file(engine, &cached) } {
+        return Ok(module);
+    }
+    let module = Module::new(engine, &source).with_context(|| format!("Failed to compile {}", path.display()))?;
+    // The cache only saves time, so failing to write it is fine
+    if let Ok(compiled) = module.serialize() {
+        let _ = std::fs::create_dir_all(cached.parent().unwrap_or(cache_dir));
+        let temp = cached.with_extension(format!("tmp.{}", std::process::id()));
+        if std::fs::write(&temp, compiled).is_ok() {
+            let _ = std::fs::rename(&temp, &cached);
+        }
+    }
+    Ok(module)
+}
@@ -0,0 +1,147 @@
+//! WSL interop: `wsl.exe -- grep ...` wrappers and `/mnt/c` paths
+//!
+//! A wrapped command runs in the Linux distribution, so it is rewritten for
+//! the tools installed there, asked of `wsl.exe which` and remembered in
+//! `cache/wsl-tools.json`. Paths cross over as well: `C:\src` becomes
+//! `/mnt/c/src` inside WSL, and `/mnt/c/src` becomes `C:/src` on Windows.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::process::{Command, Stdio};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::config::Config;
+
+/// How long a probed answer is trusted, in seconds
+const PROBE_TTL_SECS: u64 = 24 * 60 * 60;
+
+/// `wsl.exe [OPTIONS] [-e | --] COMMAND...`, split into the part that stays
+/// and the command that runs inside WSL
+#[derive(Debug, Clone, PartialEq)]
+pub struct Wrapper {
+    /// `wsl.exe` and its options, up to the command
+    pub prefix: Vec<String>,
+    /// `-e`: the command is executed directly rather than by the login shell
+    pub exec: bool,
+    /// `-d NAME`
+    pub distro: Option<String>,
+    pub command: Vec<String>,
+}
+
+impl Wrapper {
+    /// The wrapper in `words`, if they run a command through `wsl.exe`
+    pub fn parse(words: &[String]) -> Option<Self> {
+        let (program, args) = words.split_first()?;
+        if !is_wsl(program) {
+            return None;
+        }
+        let mut wrapper = Self { prefix: vec![program.clone()], exec: false, distro: None, command: Vec::new() };
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "-e" | "--exec" | "--" => {
+                    wrapper.exec = arg != "--";
+                    break;
+                }
+                "-d" | "--distribution" | "-u" | "--user" | "--cd" | "--shell-type" => {
+                    let value = args.next()?;
+                    if matches!(arg.as_str(), "-d" | "--distribution") {
+                        wrapper.distro = Some(value.clone());
+                    }
+                    wrapper.prefix.extend([arg.clone(), value.clone()]);
+                }
+                "--system" => wrapper.prefix.push(arg.clone()),
+                // --list, --shutdown, ... manage WSL rather than run anything
+                _ if arg.starts_with('-') => return None,
+                _ => {
+                    wrapper.command.push(arg.clone());
+                    break;
+                }
+            }
+        }
+        wrapper.command.extend(args.cloned());
+        (!wrapper.command.is_empty()).then_some(wrapper)
+    }
+}
+
+/// Whether `program` is `wsl` or `wsl.exe`, in any case
+fn is_wsl(program: &str) -> bool {
+    let name = program.rsplit(['/', '\\']).next().unwrap_or(program);
+    name.eq_ignore_ascii_case("wsl") || name.eq_ignore_ascii_case("wsl.exe")
+}
+
+/// Whether `command` starts with `wsl.exe`, checked before parsing it
+pub fn wraps(command: &str) -> bool {
+    command.split_whitespace().next().is_some_and(is_wsl)
+}
+
+/// Whether this process runs inside WSL
+pub fn inside() -> bool {
+    std::env::var_os("WSL_DISTRO_NAME").is_some_and(|name| !name.is_empty())
+}
+
+/// `C:\src` or `C:/src` as WSL sees it (`/mnt/c/src`)
+pub fn to_wsl_path(word: &str) -> Option<String> {
+    let mut chars = word.chars();
+    let (drive, colon, separator) = (chars.next()?, chars.next()?, chars.next()?);
+    if !drive.is_ascii_alphabetic() || colon != ':' || !matches!(separator, '\\' | '/') {
+        return None;
+    }
+    Some(format!("/mnt/{}/{}", drive.to_ascii_lowercase(), chars.as_str().replace('\\', "/")))
+}
+
+/// `/mnt/c/src` as Windows sees it (`C:/src`)
+pub fn to_windows_path(word: &str) -> Option<String> {
+    let rest = word.strip_prefix("/mnt/")?;
+    let mut chars = rest.chars();
+    let drive = chars.next().filter(char::is_ascii_alphabetic)?;
+    match chars.as_str() {
+        "" => Some(format!("{}:/", drive.to_ascii_uppercase())),
+        path if path.starts_with('/') => Some(format!("{}:{}", drive.to_ascii_uppercase(), path)),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Probe {
+    available: bool,
+    checked_at: u64,
+}
+
+/// Whether `tool` is on PATH inside `distro` (the default distribution when
+/// `None`), probed with `wsl.exe which` once a day
+pub fn tool_available(distro: Option<&str>, tool: &str) -> bool {
+    let path = Config::cache_dir().join("wsl-tools.json");
+    let mut probes: BTreeMap<String, Probe> = std::fs::read_to_string(&path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default();
+    let key = format!("{}/{}", distro.unwrap_or_default(), tool);
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    if let Some(probe) = probes.get(&key).filter(|probe| now.saturating_sub(probe.checked_at) < PROBE_TTL_SECS) {
+        return probe.available;
+    }
+    
+    let mut which = Command::new("wsl.exe");
+    if let Some(distro) = distro {
+        which.args(["-d", distro]);
+    }
+    let available = which
+        .args(["-e", "which", tool])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success());
+    
+    // The cache only saves time, so failing to write it is fine
+    probes.insert(key, Probe { available, checked_at: now });
+    if let Ok(content) = serde_json::to_string(&probes) {
+        let _ = std::fs::create_dir_all(Config::cache_dir());
+        let temp = path.with_extension(format!("tmp.{}", std::process::id()));
+        if std::fs::write(&temp, content).is_ok() {
+            let _ = std::fs::rename(&temp, &path);
+        }
+    }
+    available
+}
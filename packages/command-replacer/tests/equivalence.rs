@@ -0,0 +1,169 @@
+//! Golden equivalence tests: run commands and their rewrites with the real
+//! tools over `tests/fixtures/tree`, and check both print the same thing
+//!
+//! Run with `cargo test --features integration`. A case whose replacement
+//! tool isn't installed is skipped, so the suite passes anywhere but only
+//! checks what the machine has. Each command runs in its own copy of the
+//! tree, so in-place edits can be compared too.
+#![cfg(unix)]
+
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Output};
+
+use command_replacer::{Config, ReplacementEngine, RuleContext};
+
+/// How two outputs have to agree; anything looser than [`Compare::Exact`]
+/// is a documented difference between the tools
+#[derive(Clone, Copy)]
+enum Compare {
+    Exact,
+    /// rg and fd search directories in parallel, so lines come in any order
+    Lines,
+    /// As `Lines`, and find prints `./` before paths and the start directory
+    /// itself, where fd prints neither and newer fd adds `/` to directories
+    Paths,
+}
+
+struct Case {
+    command: &'static str,
+    compare: Compare,
+    /// Files whose contents have to agree afterwards
+    files: &'static [&'static str],
+}
+
+const fn case(command: &'static str, compare: Compare) -> Case {
+    Case { command, compare, files: &[] }
+}
+
+const CASES: &[Case] = &[
+    // grep → rg
+    case("grep -rn TODO .", Compare::Lines),
+    case("grep -rni todo src", Compare::Lines),
+    case("grep -rl TODO .", Compare::Lines),
+    case("grep -rn --include='*.rs' TODO .", Compare::Lines),
+    case("grep -n fn src/main.rs src/lib.rs", Compare::Lines),
+    case("grep -rn TODO . | sort", Compare::Exact),
+    case("grep -c info logs/app.log", Compare::Exact),
+    case("grep -v '^#' config.ini", Compare::Exact),
+    case("grep -w error logs/app.log", Compare::Exact),
+    case("grep -E 'warn|error' logs/app.log", Compare::Exact),
+    case("grep -F 'a.b' data.txt", Compare::Exact),
+    case("grep -i ab data.txt", Compare::Exact),
+    case("grep -o 'id=[0-9]*' logs/app.log", Compare::Exact),
+    case("grep -A1 error logs/app.log", Compare::Exact),
+    case("grep missing data.txt", Compare::Exact),
+    // find → fd
+    case("find . -name '*.rs'", Compare::Paths),
+    case("find . -iname 'readme*'", Compare::Paths),
+    case("find src -type f -name '*.rs'", Compare::Paths),
+    case("find src -type f", Compare::Paths),
+    case("find . -type d", Compare::Paths),
+    case("find . -maxdepth 1 -type f", Compare::Paths),
+    case("find . -mindepth 2 -type f", Compare::Paths),
+    // cat → bat
+    case("cat README.md", Compare::Exact),
+    case("cat README.md notes.md", Compare::Exact),
+    // sed → sd
+    case("sed 's/foo/bar/g' notes.md", Compare::Exact),
+    case("sed -E 's/(a+)b/[\\1]/g' data.txt", Compare::Exact),
+    Case { command: "sed -i 's/foo/bar/g' notes.md", compare: Compare::Exact, files: &["notes.md"] },
+    Case { command: "sed -i.bak -e 's/foo/bar/g' notes.md", compare: Compare::Exact, files: &["notes.md", "notes.md.bak"] },
+];
+
+#[test]
+fn rewrites_match_originals() {
+    let fixture = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/tree");
+    let scratch = std::env::temp_dir().join(format!("command-replacer-equivalence-{}", std::process::id()));
+    // The hook usually runs in a git repo, where grep's rewrite keeps the
+    // hidden and ignored files grep would search
+    let context = RuleContext { git_repo: true, ..RuleContext::default() };
+    let engine = ReplacementEngine::for_context(Config::default(), context).unwrap();
+    
+    let mut failures = Vec::new();
+    for (index, case) in CASES.iter().enumerate() {
+        let Some(rewritten) = engine.replace_command(case.command).unwrap() else {
+            let reason = engine.last_fallback_reason().expect("a declined case says why");
+            if reason.construct == "tool-missing" {
+                eprintln!("skipping `{}`: {}", case.command, reason.detail);
+                continue;
+            }
+            failures.push(format!("`{}` is no longer rewritten: {}", case.command, reason.detail));
+            continue;
+        };
+        
+        let original_dir = scratch.join(format!("{}-original", index));
+        let rewritten_dir = scratch.join(format!("{}-rewritten", index));
+        copy_tree(&fixture, &original_dir);
+        copy_tree(&fixture, &rewritten_dir);
+        let original = run(case.command, &original_dir);
+        let replacement = run(&rewritten, &rewritten_dir);
+        
+        let mismatch = |what: &str, expected: &str, actual: &str| {
+            format!("`{}` → `{}`: {} differs\n--- original\n{}\n--- rewritten\n{}", case.command, rewritten, what, expected, actual)
+        };
+        if original.status.code() != replacement.status.code() {
+            let stderr = String::from_utf8_lossy(&replacement.stderr);
+            failures.push(mismatch("exit status", &format!("{:?}", original.status.code()), &format!("{:?}\n{}", replacement.status.code(), stderr)));
+            continue;
+        }
+        let expected = normalize(&original.stdout, case.compare);
+        let actual = normalize(&replacement.stdout, case.compare);
+        if expected != actual {
+            failures.push(mismatch("output", &expected, &actual));
+        }
+        for file in case.files {
+            let expected = fs::read_to_string(original_dir.join(file)).unwrap_or_default();
+            let actual = fs::read_to_string(rewritten_dir.join(file)).unwrap_or_default();
+            if expected != actual {
+                failures.push(mismatch(file, &expected, &actual));
+            }
+        }
+    }
+    
+    let _ = fs::remove_dir_all(&scratch);
+    assert!(failures.is_empty(), "{}", failures.join("\n\n"));
+}
+
+/// Run `command` the way the Bash tool would, without the user's rg and
+/// bat config files changing the output
+fn run(command: &str, dir: &Path) -> Output {
+    Command::new("sh")
+        .args(["-c", command])
+        .current_dir(dir)
+        .env("LC_ALL", "C")
+        .env("BAT_CONFIG_PATH", dir.join("no-bat-config"))
+        .env_remove("RIPGREP_CONFIG_PATH")
+        .env_remove("GREP_OPTIONS")
+        .output()
+        .unwrap_or_else(|e| panic!("failed to run `{}`: {}", command, e))
+}
+
+fn normalize(stdout: &[u8], compare: Compare) -> String {
+    let text = String::from_utf8_lossy(stdout);
+    let mut lines: Vec<&str> = match compare {
+        Compare::Exact => return text.into_owned(),
+        Compare::Lines => text.lines().collect(),
+        Compare::Paths => text
+            .lines()
+            .filter(|line| *line != ".")
+            .map(|line| line.strip_prefix("./").unwrap_or(line))
+            .map(|line| line.strip_suffix('/').unwrap_or(line))
+            .collect(),
+    };
+    lines.sort_unstable();
+    lines.join("\n")
+}
+
+fn copy_tree(from: &Path, to: &Path) {
+    fs::create_dir_all(to).unwrap();
+    for entry in fs::read_dir(from).unwrap() {
+        let entry = entry.unwrap();
+        let target = to.join(entry.file_name());
+        if entry.file_type().unwrap().is_dir() {
+            copy_tree(&entry.path(), &target);
+        } else {
+            fs::copy(entry.path(), target).unwrap();
+        }
+    }
+}
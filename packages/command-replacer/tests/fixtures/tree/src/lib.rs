@@ -0,0 +1,6 @@
+/// The answer
+pub fn answer() -> u32 {
+    42
+}
+
+// todo: lower-case
@@ -0,0 +1,6 @@
+mod nested;
+
+fn main() {
+    // TODO: parse arguments
+    println!("fixture");
+}
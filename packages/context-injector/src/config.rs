@@ -0,0 +1,82 @@
+//! Injector configuration
+//!
+//! Read from `~/.claude/hooks/context-injector/config.toml`, then the
+//! project's `.claude/context-injector.toml`.
+//!
+//! ```toml
+//! events = ["SessionStart", "UserPromptSubmit"]
+//! tools = ["rg", "fd", "jq"]
+//! max_dirty_files = 5
+//! ```
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::path::Path;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct InjectorConfig {
+    pub enabled: bool,
+    /// Hook events that inject the summary
+    pub events: Vec<String>,
+    /// CLI tools reported when installed
+    pub tools: Vec<String>,
+    /// Report the branch and uncommitted files
+    pub git: bool,
+    /// Uncommitted files listed by name; the rest are counted
+    pub max_dirty_files: usize,
+}
+
+impl Default for InjectorConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            events: vec!["SessionStart".to_string()],
+            tools: ["rg", "fd", "bat", "eza", "sd", "procs", "jq", "gh"]
+                .iter()
+                .map(|tool| tool.to_string())
+                .collect(),
+            git: true,
+            max_dirty_files: 10,
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigLayer {
+    enabled: Option<bool>,
+    events: Option<Vec<String>>,
+    tools: Option<Vec<String>>,
+    git: Option<bool>,
+    max_dirty_files: Option<usize>,
+}
+
+impl InjectorConfig {
+    /// Load the global config, then layer the project's on top
+    pub fn load(project_dir: Option<&Path>) -> Result<Self> {
+        let global = dirs::home_dir().map(|home| home.join(".claude/hooks/context-injector/config.toml"));
+        let project = project_dir.map(|dir| dir.join(".claude").join("context-injector.toml"));
+        
+        let mut config = Self::default();
+        for path in global.into_iter().chain(project) {
+            if path.is_file() {
+                let content = std::fs::read_to_string(&path)
+                    .with_context(|| format!("Failed to read {}", path.display()))?;
+                config.merge_toml(&content)
+                    .with_context(|| format!("Failed to parse {}", path.display()))?;
+            }
+        }
+        Ok(config)
+    }
+    
+    /// Layer one config file's contents on top of this config
+    pub fn merge_toml(&mut self, content: &str) -> Result<()> {
+        let layer: ConfigLayer = toml::from_str(content)?;
+        self.enabled = layer.enabled.unwrap_or(self.enabled);
+        self.events = layer.events.unwrap_or(std::mem::take(&mut self.events));
+        self.tools = layer.tools.unwrap_or(std::mem::take(&mut self.tools));
+        self.git = layer.git.unwrap_or(self.git);
+        self.max_dirty_files = layer.max_dirty_files.unwrap_or(self.max_dirty_files);
+        Ok(())
+    }
+}
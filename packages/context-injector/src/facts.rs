@@ -0,0 +1,159 @@
+//! Project fact detection
+//!
+//! Everything except the dirty-file list comes from marker files, so
+//! detection stays fast enough to run on every session start.
+
+use std::path::Path;
+use std::process::Command;
+
+use crate::config::InjectorConfig;
+
+/// `(marker file, language, build system, test command)`; `None` for the
+/// test command means it depends on the file's contents
+const MARKERS: &[(&str, &str, &str, Option<&str>)] = &[
+    ("Cargo.toml", "Rust", "cargo", Some("cargo test")),
+    ("package.json", "JavaScript/TypeScript", "npm", None),
+    ("pyproject.toml", "Python", "pyproject", Some("pytest")),
+    ("setup.py", "Python", "setuptools", Some("pytest")),
+    ("go.mod", "Go", "go", Some("go test ./...")),
+    ("pom.xml", "Java", "maven", Some("mvn test")),
+    ("build.gradle", "Java/Kotlin", "gradle", Some("./gradlew test")),
+    ("build.gradle.kts", "Java/Kotlin", "gradle", Some("./gradlew test")),
+    ("Gemfile", "Ruby", "bundler", Some("bundle exec rake test")),
+    ("CMakeLists.txt", "C/C++", "cmake", Some("ctest --test-dir build")),
+];
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ProjectFacts {
+    pub languages: Vec<String>,
+    pub build_systems: Vec<String>,
+    pub test_commands: Vec<String>,
+    pub branch: Option<String>,
+    pub dirty_files: Vec<String>,
+    /// Uncommitted files beyond `dirty_files`
+    pub more_dirty: usize,
+    pub tools: Vec<String>,
+}
+
+impl ProjectFacts {
+    pub fn detect(project_dir: &Path, config: &InjectorConfig) -> Self {
+        let mut facts = Self::default();
+        
+        for (marker, language, build, test) in MARKERS {
+            let path = project_dir.join(marker);
+            if !path.is_file() {
+                continue;
+            }
+            push_unique(&mut facts.languages, language);
+            let (build, test) = match *marker {
+                "package.json" => node_commands(project_dir, &path),
+                _ => (build.to_string(), test.map(str::to_string)),
+            };
+            push_unique(&mut facts.build_systems, &build);
+            if let Some(test) = test {
+                push_unique(&mut facts.test_commands, &test);
+            }
+        }
+        if has_make_target(&project_dir.join("Makefile"), "test") {
+            push_unique(&mut facts.build_systems, "make");
+            push_unique(&mut facts.test_commands, "make test");
+        }
+        
+        if config.git {
+            facts.branch = git_branch(project_dir);
+            let dirty = dirty_files(project_dir);
+            facts.more_dirty = dirty.len().saturating_sub(config.max_dirty_files);
+            facts.dirty_files = dirty.into_iter().take(config.max_dirty_files).collect();
+        }
+        
+        facts.tools = config.tools.iter().filter(|tool| which::which(tool).is_ok()).cloned().collect();
+        facts
+    }
+    
+    /// The context blob, or `None` if nothing was detected
+    pub fn summary(&self) -> Option<String> {
+        let mut lines = Vec::new();
+        if !self.languages.is_empty() {
+            lines.push(format!("- Languages: {}", self.languages.join(", ")));
+        }
+        if !self.build_systems.is_empty() {
+            lines.push(format!("- Build: {}", self.build_systems.join(", ")));
+        }
+        if !self.test_commands.is_empty() {
+            lines.push(format!("- Test: {}", self.test_commands.join("; ")));
+        }
+        if let Some(branch) = &self.branch {
+            let dirty = match self.dirty_files.len() + self.more_dirty {
+                0 => "clean".to_string(),
+                count => {
+                    let more = if self.more_dirty > 0 { format!(", +{} more", self.more_dirty) } else { String::new() };
+                    format!("{} uncommitted ({}{})", count, self.dirty_files.join(", "), more)
+                }
+            };
+            lines.push(format!("- Git: branch {}, {}", branch, dirty));
+        }
+        if !self.tools.is_empty() {
+            lines.push(format!("- Fast tools installed: {}", self.tools.join(", ")));
+        }
+        
+        (!lines.is_empty()).then(|| format!("Project facts (detected by context-injector):\n{}", lines.join("\n")))
+    }
+}
+
+fn push_unique(list: &mut Vec<String>, value: &str) {
+    if !list.iter().any(|existing| existing == value) {
+        list.push(value.to_string());
+    }
+}
+
+/// The package manager (from the lockfile) and its test script, if any
+fn node_commands(project_dir: &Path, package_json: &Path) -> (String, Option<String>) {
+    let manager = [("pnpm-lock.yaml", "pnpm"), ("yarn.lock", "yarn"), ("bun.lockb", "bun")]
+        .iter()
+        .find(|(lockfile, _)| project_dir.join(lockfile).is_file())
+        .map_or("npm", |(_, manager)| manager);
+    let has_test_script = std::fs::read_to_string(package_json)
+        .ok()
+        .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).ok())
+        .is_some_and(|package| package["scripts"]["test"].is_string());
+    (manager.to_string(), has_test_script.then(|| format!("{} test", manager)))
+}
+
+fn has_make_target(makefile: &Path, target: &str) -> bool {
+    std::fs::read_to_string(makefile)
+        .is_ok_and(|content| content.lines().any(|line| line.strip_prefix(target).is_some_and(|rest| rest.starts_with(':'))))
+}
+
+/// The checked-out branch, read straight from `.git/HEAD`
+fn git_branch(project_dir: &Path) -> Option<String> {
+    let git_dir = project_dir.ancestors().map(|dir| dir.join(".git")).find(|git| git.exists())?;
+    // Worktrees and submodules point elsewhere with `gitdir: <path>`
+    let git_dir = match std::fs::read_to_string(&git_dir) {
+        Ok(pointer) => git_dir.parent()?.join(pointer.strip_prefix("gitdir:")?.trim()),
+        Err(_) => git_dir,
+    };
+    let head = std::fs::read_to_string(git_dir.join("HEAD")).ok()?;
+    match head.trim().strip_prefix("ref: refs/heads/") {
+        Some(branch) => Some(branch.to_string()),
+        None => Some(format!("detached at {}", head.trim().chars().take(8).collect::<String>())),
+    }
+}
+
+/// Paths with uncommitted changes, from `git status --porcelain`
+fn dirty_files(project_dir: &Path) -> Vec<String> {
+    let Ok(output) = Command::new("git")
+        .args(["status", "--porcelain", "--untracked-files=normal"])
+        .current_dir(project_dir)
+        .output()
+    else {
+        return Vec::new();
+    };
+    if !output.status.success() {
+        return Vec::new();
+    }
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| line.get(3..))
+        .map(str::to_string)
+        .collect()
+}
@@ -0,0 +1,32 @@
+//! Context Injector Hook
+//!
+//! On SessionStart (and optionally UserPromptSubmit) it detects the
+//! project's languages, build system, test command, git branch and dirty
+//! files, and the fast CLI tools installed, and hands Claude a compact
+//! summary — the boilerplate people otherwise maintain by hand in CLAUDE.md.
+
+use anyhow::Result;
+use claude_hooks_sdk::{HookInput, HookOutput};
+
+mod config;
+mod facts;
+#[cfg(test)]
+mod tests;
+
+use config::InjectorConfig;
+use facts::ProjectFacts;
+
+fn main() {
+    claude_hooks_sdk::run("Context injector", run);
+}
+
+fn run(input: HookInput) -> Result<HookOutput> {
+    let project_dir = input.project_dir();
+    let config = InjectorConfig::load(Some(project_dir))?;
+    if !config.enabled || !config.events.contains(&input.event.event_type) {
+        return Ok(HookOutput::approve());
+    }
+    
+    let facts = ProjectFacts::detect(project_dir, &config);
+    Ok(facts.summary().map_or_else(HookOutput::approve, HookOutput::additional_context))
+}
@@ -0,0 +1,69 @@
+//! Tests for project fact detection
+
+use std::path::PathBuf;
+
+use crate::config::InjectorConfig;
+use crate::facts::ProjectFacts;
+
+fn temp_project(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("context-injector-{}-{}", name, std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Git and tool detection depend on the machine, so tests turn them off
+fn offline_config() -> InjectorConfig {
+    InjectorConfig { git: false, tools: Vec::new(), ..InjectorConfig::default() }
+}
+
+#[test]
+fn test_detects_languages_and_commands() {
+    let dir = temp_project("detect");
+    std::fs::write(dir.join("Cargo.toml"), "[package]").unwrap();
+    std::fs::write(dir.join("package.json"), r#"{"scripts": {"test": "vitest"}}"#).unwrap();
+    std::fs::write(dir.join("pnpm-lock.yaml"), "").unwrap();
+    std::fs::write(dir.join("Makefile"), "build:\n\tcargo build\ntest:\n\tcargo test\n").unwrap();
+    
+    let facts = ProjectFacts::detect(&dir, &offline_config());
+    assert_eq!(facts.languages, vec!["Rust", "JavaScript/TypeScript"]);
+    assert_eq!(facts.build_systems, vec!["cargo", "pnpm", "make"]);
+    assert_eq!(facts.test_commands, vec!["cargo test", "pnpm test", "make test"]);
+    
+    let summary = facts.summary().unwrap();
+    assert!(summary.contains("- Test: cargo test; pnpm test; make test"));
+    assert!(!summary.contains("Git"));
+    
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_summary_formatting() {
+    assert_eq!(ProjectFacts::default().summary(), None);
+    
+    let facts = ProjectFacts {
+        branch: Some("main".to_string()),
+        dirty_files: vec!["src/a.rs".to_string(), "src/b.rs".to_string()],
+        more_dirty: 3,
+        tools: vec!["rg".to_string(), "fd".to_string()],
+        ..ProjectFacts::default()
+    };
+    let summary = facts.summary().unwrap();
+    assert!(summary.contains("- Git: branch main, 5 uncommitted (src/a.rs, src/b.rs, +3 more)"));
+    assert!(summary.contains("- Fast tools installed: rg, fd"));
+    
+    let clean = ProjectFacts { branch: Some("dev".to_string()), ..ProjectFacts::default() };
+    assert!(clean.summary().unwrap().contains("branch dev, clean"));
+}
+
+#[test]
+fn test_git_branch_from_head() {
+    let dir = temp_project("git");
+    std::fs::create_dir_all(dir.join(".git")).unwrap();
+    std::fs::write(dir.join(".git/HEAD"), "ref: refs/heads/feature/login\n").unwrap();
+    
+    let config = InjectorConfig { tools: Vec::new(), ..InjectorConfig::default() };
+    let facts = ProjectFacts::detect(&dir, &config);
+    assert_eq!(facts.branch.as_deref(), Some("feature/login"));
+    
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,119 @@
+//! Cost tracker configuration
+//!
+//! Read from `~/.claude/hooks/cost-tracker/config.toml`, then the project's
+//! `.claude/cost-tracker.toml`.
+//!
+//! ```toml
+//! ledger_dir = "~/.claude/costs"
+//!
+//! # USD per million tokens
+//! [prices]
+//! input = 3.0
+//! output = 15.0
+//! cache_read = 0.3
+//! cache_write = 3.75
+//! ```
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CostConfig {
+    pub enabled: bool,
+    /// Directory holding one ledger file per project
+    pub ledger_dir: PathBuf,
+    pub prices: Prices,
+}
+
+/// USD per million tokens of each kind
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Prices {
+    pub input: f64,
+    pub output: f64,
+    pub cache_read: f64,
+    pub cache_write: f64,
+}
+
+impl Default for Prices {
+    fn default() -> Self {
+        Self {
+            input: 3.0,
+            output: 15.0,
+            cache_read: 0.3,
+            cache_write: 3.75,
+        }
+    }
+}
+
+impl Default for CostConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            ledger_dir: dirs::home_dir()
+                .unwrap_or_else(|| PathBuf::from("."))
+                .join(".claude")
+                .join("costs"),
+            prices: Prices::default(),
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigLayer {
+    enabled: Option<bool>,
+    ledger_dir: Option<String>,
+    prices: Option<PricesLayer>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PricesLayer {
+    input: Option<f64>,
+    output: Option<f64>,
+    cache_read: Option<f64>,
+    cache_write: Option<f64>,
+}
+
+impl CostConfig {
+    /// Load the global config, then layer the project's on top
+    pub fn load(project_dir: Option<&Path>) -> Result<Self> {
+        let global = dirs::home_dir().map(|home| home.join(".claude/hooks/cost-tracker/config.toml"));
+        let project = project_dir.map(|dir| dir.join(".claude").join("cost-tracker.toml"));
+        
+        let mut config = Self::default();
+        for path in global.into_iter().chain(project) {
+            if path.is_file() {
+                let content = std::fs::read_to_string(&path)
+                    .with_context(|| format!("Failed to read {}", path.display()))?;
+                config.merge_toml(&content)
+                    .with_context(|| format!("Failed to parse {}", path.display()))?;
+            }
+        }
+        Ok(config)
+    }
+    
+    /// Layer one config file's contents on top of this config
+    pub fn merge_toml(&mut self, content: &str) -> Result<()> {
+        let layer: ConfigLayer = toml::from_str(content)?;
+        self.enabled = layer.enabled.unwrap_or(self.enabled);
+        if let Some(ledger_dir) = layer.ledger_dir {
+            self.ledger_dir = expand_home(&ledger_dir);
+        }
+        if let Some(prices) = layer.prices {
+            self.prices.input = prices.input.unwrap_or(self.prices.input);
+            self.prices.output = prices.output.unwrap_or(self.prices.output);
+            self.prices.cache_read = prices.cache_read.unwrap_or(self.prices.cache_read);
+            self.prices.cache_write = prices.cache_write.unwrap_or(self.prices.cache_write);
+        }
+        Ok(())
+    }
+}
+
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), dirs::home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest),
+        _ => PathBuf::from(path),
+    }
+}
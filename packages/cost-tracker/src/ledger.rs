@@ -0,0 +1,152 @@
+//! The per-project ledger and the token usage read from transcripts
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use crate::config::{CostConfig, Prices};
+
+/// Tokens used, by kind
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Usage {
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cache_read_tokens: u64,
+    pub cache_write_tokens: u64,
+}
+
+impl Usage {
+    /// Estimated USD cost at `prices`
+    pub fn cost(&self, prices: &Prices) -> f64 {
+        let per_million = |tokens: u64, price: f64| tokens as f64 * price / 1_000_000.0;
+        per_million(self.input_tokens, prices.input)
+            + per_million(self.output_tokens, prices.output)
+            + per_million(self.cache_read_tokens, prices.cache_read)
+            + per_million(self.cache_write_tokens, prices.cache_write)
+    }
+    
+    pub fn add(&mut self, other: &Usage) {
+        self.input_tokens += other.input_tokens;
+        self.output_tokens += other.output_tokens;
+        self.cache_read_tokens += other.cache_read_tokens;
+        self.cache_write_tokens += other.cache_write_tokens;
+    }
+    
+    /// Total usage of the assistant messages in a JSONL transcript
+    ///
+    /// A message split over several lines repeats its usage on each, so
+    /// every message id is only counted once.
+    pub fn from_transcript(transcript: &str) -> Self {
+        let mut seen = HashSet::new();
+        let mut total = Usage::default();
+        for line in transcript.lines() {
+            let Ok(line) = serde_json::from_str::<serde_json::Value>(line) else {
+                continue;
+            };
+            let Some(message) = line.get("message") else {
+                continue;
+            };
+            let Some(usage) = message.get("usage") else {
+                continue;
+            };
+            if let Some(id) = message.get("id").and_then(|id| id.as_str()) {
+                if !seen.insert(id.to_string()) {
+                    continue;
+                }
+            }
+            let tokens = |name: &str| usage.get(name).and_then(|n| n.as_u64()).unwrap_or(0);
+            total.add(&Usage {
+                input_tokens: tokens("input_tokens"),
+                output_tokens: tokens("output_tokens"),
+                cache_read_tokens: tokens("cache_read_input_tokens"),
+                cache_write_tokens: tokens("cache_creation_input_tokens"),
+            });
+        }
+        total
+    }
+}
+
+/// What one session has used so far
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SessionCost {
+    /// First and latest event seen, in milliseconds since the epoch
+    pub started_ms: u64,
+    pub last_ms: u64,
+    /// Calls per tool name
+    #[serde(default)]
+    pub tool_calls: BTreeMap<String, u64>,
+    /// As of the session's latest Stop
+    #[serde(default)]
+    pub usage: Usage,
+}
+
+impl SessionCost {
+    pub fn wall_clock_ms(&self) -> u64 {
+        self.last_ms.saturating_sub(self.started_ms)
+    }
+    
+    pub fn total_calls(&self) -> u64 {
+        self.tool_calls.values().sum()
+    }
+}
+
+/// Every session recorded for one project
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Ledger {
+    pub project_dir: String,
+    #[serde(default)]
+    pub sessions: BTreeMap<String, SessionCost>,
+}
+
+impl Ledger {
+    /// The ledger file for `project_dir`
+    pub fn path(config: &CostConfig, project_dir: &str) -> PathBuf {
+        let name: String = project_dir
+            .trim_matches(|c| c == '/' || c == '\\')
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.' { c } else { '_' })
+            .collect();
+        let name = if name.is_empty() { "root".to_string() } else { name };
+        config.ledger_dir.join(format!("{}.json", name))
+    }
+    
+    /// The ledger at `path`, or an empty one for `project_dir` if there is
+    /// none yet
+    pub fn load(path: &Path, project_dir: &str) -> Result<Self> {
+        if !path.is_file() {
+            return Ok(Self {
+                project_dir: project_dir.to_string(),
+                sessions: BTreeMap::new(),
+            });
+        }
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        serde_json::from_str(&content).with_context(|| format!("Failed to parse {}", path.display()))
+    }
+    
+    /// Write the ledger to `path`, replacing it in one step so a reader
+    /// never sees half a file
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+        }
+        let temp = path.with_extension(format!("json.{}", std::process::id()));
+        std::fs::write(&temp, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to write {}", temp.display()))?;
+        std::fs::rename(&temp, path).with_context(|| format!("Failed to replace {}", path.display()))
+    }
+    
+    /// The entry for `session_id`, with an event at `ts_ms` counted towards
+    /// its wall-clock time
+    pub fn touch(&mut self, session_id: &str, ts_ms: u64) -> &mut SessionCost {
+        let session = self.sessions.entry(session_id.to_string()).or_insert_with(|| SessionCost {
+            started_ms: ts_ms,
+            last_ms: ts_ms,
+            ..SessionCost::default()
+        });
+        session.started_ms = session.started_ms.min(ts_ms);
+        session.last_ms = session.last_ms.max(ts_ms);
+        session
+    }
+}
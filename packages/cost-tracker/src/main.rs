@@ -0,0 +1,120 @@
+//! Cost Tracker Hook
+//!
+//! Registered for PostToolUse and Stop. Each tool call is counted against
+//! its session, and on Stop the session's token usage is tallied from the
+//! transcript. Sessions are kept in a ledger per project under
+//! `~/.claude/costs/`, along with how long each ran; `cost-tracker report`
+//! summarises it with estimated costs. It never blocks anything.
+
+use anyhow::Result;
+use clap::{Parser, Subcommand};
+use claude_hooks_sdk::{HookInput, HookOutput};
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+mod config;
+mod ledger;
+mod report;
+#[cfg(test)]
+mod tests;
+
+use config::CostConfig;
+use ledger::{Ledger, Usage};
+
+#[derive(Debug, Parser)]
+#[command(name = "cost-tracker", version, about = "Track tool calls, tokens, and time per Claude Code session")]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Summarise a project's sessions and their estimated cost
+    Report {
+        /// Project directory to report on (defaults to the current one)
+        #[arg(long, value_name = "DIR")]
+        project: Option<PathBuf>,
+        /// Print the ledger as JSON
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+fn main() {
+    // Without a subcommand we are running as the hook
+    match Cli::parse().command {
+        Some(Command::Report { project, json }) => {
+            if let Err(e) = print_report(project, json) {
+                eprintln!("Error: {:#}", e);
+                std::process::exit(1);
+            }
+        }
+        None => claude_hooks_sdk::run("Cost tracker", run),
+    }
+}
+
+fn run(input: HookInput) -> Result<HookOutput> {
+    // Tracking must never get in the way of the session
+    if let Err(e) = record(&input) {
+        eprintln!("Hook error: {}", e);
+    }
+    Ok(HookOutput::approve())
+}
+
+fn record(input: &HookInput) -> Result<()> {
+    let config = CostConfig::load(Some(input.project_dir()))?;
+    if !config.enabled {
+        return Ok(());
+    }
+    let ts_ms = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_millis() as u64);
+    
+    let path = Ledger::path(&config, &input.session.project_dir);
+    let mut ledger = Ledger::load(&path, &input.session.project_dir)?;
+    let session = ledger.touch(&input.session.id, ts_ms);
+    match input.event.event_type.as_str() {
+        "PostToolUse" => {
+            // Only Bash carries a bare command when the tool isn't named
+            let tool = input.event.tool_name.clone().unwrap_or_else(|| {
+                let is_bash = input.event.data.get("command").is_some();
+                if is_bash { "Bash" } else { "unknown" }.to_string()
+            });
+            *session.tool_calls.entry(tool).or_default() += 1;
+        }
+        "Stop" => {
+            // The official schema keeps the path with the session, the
+            // envelope with the event
+            let transcript = input.session.context.get("transcript_path")
+                .or_else(|| input.event.data.get("transcript_path"))
+                .and_then(|path| path.as_str());
+            if let Some(transcript) = transcript {
+                // The transcript holds the whole session, so this replaces
+                // rather than adds to the last tally
+                if let Ok(content) = std::fs::read_to_string(transcript) {
+                    session.usage = Usage::from_transcript(&content);
+                }
+            }
+        }
+        _ => {}
+    }
+    ledger.save(&path)
+}
+
+fn print_report(project: Option<PathBuf>, json: bool) -> Result<()> {
+    let project = match project {
+        Some(project) => project,
+        None => std::env::current_dir()?,
+    };
+    let config = CostConfig::load(Some(&project))?;
+    let project_dir = project.display().to_string();
+    let ledger = Ledger::load(&Ledger::path(&config, &project_dir), &project_dir)?;
+    
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report::json(&ledger, &config.prices))?);
+    } else {
+        print!("{}", report::text(&ledger, &config.prices));
+    }
+    Ok(())
+}
@@ -0,0 +1,82 @@
+//! The `report` subcommand's output
+
+use serde_json::json;
+
+use crate::config::Prices;
+use crate::ledger::{Ledger, Usage};
+
+/// One line per session, oldest first, then the project's totals
+pub fn text(ledger: &Ledger, prices: &Prices) -> String {
+    let mut sessions: Vec<_> = ledger.sessions.iter().collect();
+    sessions.sort_by_key(|(_, session)| session.started_ms);
+    
+    let mut out = format!("Project: {}\n", ledger.project_dir);
+    out.push_str(&format!(
+        "{:<24} {:>10} {:>7} {:>12} {:>12} {:>10}\n",
+        "session", "duration", "calls", "input", "output", "cost",
+    ));
+    let mut total = Usage::default();
+    let (mut calls, mut wall_clock_ms) = (0, 0);
+    for (id, session) in &sessions {
+        let input = session.usage.input_tokens + session.usage.cache_read_tokens + session.usage.cache_write_tokens;
+        out.push_str(&format!(
+            "{:<24} {:>10} {:>7} {:>12} {:>12} {:>10}\n",
+            truncate(id, 24),
+            duration(session.wall_clock_ms()),
+            session.total_calls(),
+            input,
+            session.usage.output_tokens,
+            format!("${:.2}", session.usage.cost(prices)),
+        ));
+        total.add(&session.usage);
+        calls += session.total_calls();
+        wall_clock_ms += session.wall_clock_ms();
+    }
+    out.push_str(&format!(
+        "{:<24} {:>10} {:>7} {:>12} {:>12} {:>10}\n",
+        format!("total ({} sessions)", sessions.len()),
+        duration(wall_clock_ms),
+        calls,
+        total.input_tokens + total.cache_read_tokens + total.cache_write_tokens,
+        total.output_tokens,
+        format!("${:.2}", total.cost(prices)),
+    ));
+    out
+}
+
+/// The ledger with each session's estimated cost and the totals added
+pub fn json(ledger: &Ledger, prices: &Prices) -> serde_json::Value {
+    let mut total = Usage::default();
+    let sessions: serde_json::Map<_, _> = ledger.sessions.iter().map(|(id, session)| {
+        total.add(&session.usage);
+        let mut value = serde_json::to_value(session).unwrap_or_default();
+        value["wall_clock_ms"] = session.wall_clock_ms().into();
+        value["cost_usd"] = session.usage.cost(prices).into();
+        (id.clone(), value)
+    }).collect();
+    
+    json!({
+        "project_dir": ledger.project_dir,
+        "sessions": sessions,
+        "total": {
+            "usage": total,
+            "tool_calls": ledger.sessions.values().map(|s| s.total_calls()).sum::<u64>(),
+            "wall_clock_ms": ledger.sessions.values().map(|s| s.wall_clock_ms()).sum::<u64>(),
+            "cost_usd": total.cost(prices),
+        },
+    })
+}
+
+/// `1h02m`, `3m05s`, `42s`
+fn duration(ms: u64) -> String {
+    let secs = ms / 1000;
+    match (secs / 3600, secs % 3600 / 60, secs % 60) {
+        (0, 0, s) => format!("{}s", s),
+        (0, m, s) => format!("{}m{:02}s", m, s),
+        (h, m, _) => format!("{}h{:02}m", h, m),
+    }
+}
+
+fn truncate(id: &str, width: usize) -> &str {
+    id.char_indices().nth(width).map_or(id, |(end, _)| &id[..end])
+}
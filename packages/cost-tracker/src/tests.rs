@@ -0,0 +1,69 @@
+//! Tests for the cost ledger and its report
+
+use crate::config::{CostConfig, Prices};
+use crate::ledger::{Ledger, Usage};
+use crate::report;
+
+#[test]
+fn test_usage_from_transcript() {
+    // The first message is split over two lines that repeat its usage
+    let transcript = r#"{"type":"user","message":{"role":"user","content":"hi"}}
+{"type":"assistant","message":{"id":"m1","usage":{"input_tokens":100,"output_tokens":20,"cache_read_input_tokens":1000,"cache_creation_input_tokens":50}}}
+{"type":"assistant","message":{"id":"m1","usage":{"input_tokens":100,"output_tokens":20,"cache_read_input_tokens":1000,"cache_creation_input_tokens":50}}}
+{"type":"assistant","message":{"id":"m2","usage":{"input_tokens":5,"output_tokens":7}}}
+"#;
+    let usage = Usage::from_transcript(transcript);
+    assert_eq!(usage, Usage {
+        input_tokens: 105,
+        output_tokens: 27,
+        cache_read_tokens: 1000,
+        cache_write_tokens: 50,
+    });
+    
+    let prices = Prices { input: 1.0, output: 2.0, cache_read: 0.5, cache_write: 4.0 };
+    assert!((usage.cost(&prices) - 0.000_859).abs() < 1e-9);
+}
+
+#[test]
+fn test_ledger_round_trip() {
+    let dir = std::env::temp_dir().join(format!("cost-tracker-{}", std::process::id()));
+    let mut config = CostConfig::default();
+    config.merge_toml(&format!("ledger_dir = {:?}\n[prices]\noutput = 10.0", dir.display().to_string())).unwrap();
+    assert_eq!(config.prices.output, 10.0);
+    assert_eq!(config.prices.input, 3.0);
+    
+    let path = Ledger::path(&config, "/work/my project");
+    assert_eq!(path, dir.join("work_my_project.json"));
+    
+    let mut ledger = Ledger::load(&path, "/work/my project").unwrap();
+    *ledger.touch("s1", 5_000).tool_calls.entry("Bash".to_string()).or_default() += 1;
+    *ledger.touch("s1", 65_000).tool_calls.entry("Bash".to_string()).or_default() += 1;
+    ledger.touch("s1", 1_000);
+    ledger.save(&path).unwrap();
+    
+    let loaded = Ledger::load(&path, "/work/my project").unwrap();
+    assert_eq!(loaded, ledger);
+    assert_eq!(loaded.sessions["s1"].wall_clock_ms(), 64_000);
+    assert_eq!(loaded.sessions["s1"].total_calls(), 2);
+    
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_report() {
+    let mut ledger = Ledger { project_dir: "/work".to_string(), ..Ledger::default() };
+    let session = ledger.touch("s1", 0);
+    session.last_ms = 185_000;
+    session.tool_calls.insert("Read".to_string(), 3);
+    session.usage.output_tokens = 1_000_000;
+    
+    let prices = Prices::default();
+    let text = report::text(&ledger, &prices);
+    assert!(text.contains("3m05s"), "{}", text);
+    assert!(text.contains("$15.00"), "{}", text);
+    assert!(text.contains("total (1 sessions)"), "{}", text);
+    
+    let json = report::json(&ledger, &prices);
+    assert_eq!(json["sessions"]["s1"]["cost_usd"], 15.0);
+    assert_eq!(json["total"]["tool_calls"], 3);
+}
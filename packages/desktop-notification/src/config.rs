@@ -0,0 +1,79 @@
+//! Notification configuration
+//!
+//! Read from `~/.claude/hooks/desktop-notification/config.toml`, then the
+//! project's `.claude/desktop-notification.toml`.
+//!
+//! ```toml
+//! events = ["Notification", "Stop"]
+//! sound = true
+//! timeout_ms = 8000
+//! ```
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::path::Path;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NotifyConfig {
+    pub enabled: bool,
+    /// Hook events that raise a notification
+    pub events: Vec<String>,
+    /// Title prefix; the project name is appended
+    pub title: String,
+    /// Play the platform's default notification sound
+    pub sound: bool,
+    /// How long the notification stays up, where the platform allows it
+    pub timeout_ms: u32,
+}
+
+impl Default for NotifyConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            events: vec!["Notification".to_string(), "Stop".to_string()],
+            title: "Claude Code".to_string(),
+            sound: false,
+            timeout_ms: 8000,
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigLayer {
+    enabled: Option<bool>,
+    events: Option<Vec<String>>,
+    title: Option<String>,
+    sound: Option<bool>,
+    timeout_ms: Option<u32>,
+}
+
+impl NotifyConfig {
+    /// Load the global config, then layer the project's on top
+    pub fn load(project_dir: Option<&Path>) -> Result<Self> {
+        let global = dirs::home_dir().map(|home| home.join(".claude/hooks/desktop-notification/config.toml"));
+        let project = project_dir.map(|dir| dir.join(".claude").join("desktop-notification.toml"));
+        
+        let mut config = Self::default();
+        for path in global.into_iter().chain(project) {
+            if path.is_file() {
+                let content = std::fs::read_to_string(&path)
+                    .with_context(|| format!("Failed to read {}", path.display()))?;
+                config.merge_toml(&content)
+                    .with_context(|| format!("Failed to parse {}", path.display()))?;
+            }
+        }
+        Ok(config)
+    }
+    
+    /// Layer one config file's contents on top of this config
+    pub fn merge_toml(&mut self, content: &str) -> Result<()> {
+        let layer: ConfigLayer = toml::from_str(content)?;
+        self.enabled = layer.enabled.unwrap_or(self.enabled);
+        self.events = layer.events.unwrap_or(std::mem::take(&mut self.events));
+        self.title = layer.title.unwrap_or(std::mem::take(&mut self.title));
+        self.sound = layer.sound.unwrap_or(self.sound);
+        self.timeout_ms = layer.timeout_ms.unwrap_or(self.timeout_ms);
+        Ok(())
+    }
+}
@@ -0,0 +1,39 @@
+//! Desktop Notification Hook
+//!
+//! Raises a native notification when a long-running session needs
+//! attention:
+//! - Notification events (permission requests, idle prompts)
+//! - Stop, when Claude has finished responding
+//! - SubagentStop, when enabled
+//!
+//! Notifications go through notify-rust: D-Bus on Linux, Notification
+//! Center on macOS, and toasts on Windows.
+
+use anyhow::Result;
+use claude_hooks_sdk::{HookInput, HookOutput};
+
+mod config;
+mod notification;
+#[cfg(test)]
+mod tests;
+
+use config::NotifyConfig;
+
+fn main() {
+    claude_hooks_sdk::run("Desktop notification", |input| {
+        // A missing notification daemon is not the session's problem
+        if let Err(e) = notify(&input) {
+            eprintln!("Hook error: {}", e);
+        }
+        Ok(HookOutput::approve())
+    });
+}
+
+fn notify(input: &HookInput) -> Result<()> {
+    let project_dir = input.project_dir();
+    let config = NotifyConfig::load(Some(project_dir))?;
+    let Some(message) = notification::message_for(&config, &input.event.event_type, &input.event.data, project_dir) else {
+        return Ok(());
+    };
+    notification::show(&config, &message)
+}
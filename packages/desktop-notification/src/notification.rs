@@ -0,0 +1,57 @@
+//! What to say for each event, and saying it
+
+use anyhow::{Context, Result};
+use std::path::Path;
+
+use crate::config::NotifyConfig;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Message {
+    pub title: String,
+    pub body: String,
+}
+
+/// The notification for an event, if it is one the user asked about
+pub fn message_for(config: &NotifyConfig, event: &str, data: &serde_json::Value, project_dir: &Path) -> Option<Message> {
+    if !config.enabled || !config.events.iter().any(|wanted| wanted == event) {
+        return None;
+    }
+    let text = |name: &str| data.get(name).and_then(|v| v.as_str()).filter(|s| !s.is_empty());
+    
+    let body = match event {
+        // Claude Code's own wording ("Claude needs your permission to use Bash")
+        "Notification" => text("message").unwrap_or("Claude is waiting for your input").to_string(),
+        "Stop" => "Claude has finished and is waiting for you".to_string(),
+        "SubagentStop" => "A subagent has finished".to_string(),
+        other => text("message").map_or_else(|| format!("{} event", other), str::to_string),
+    };
+    let title = match project_dir.file_name().and_then(|name| name.to_str()) {
+        Some(project) => format!("{} · {}", config.title, project),
+        None => config.title.clone(),
+    };
+    Some(Message { title, body })
+}
+
+/// Raise the notification
+pub fn show(config: &NotifyConfig, message: &Message) -> Result<()> {
+    let mut notification = notify_rust::Notification::new();
+    notification
+        .summary(&message.title)
+        .body(&message.body)
+        .timeout(notify_rust::Timeout::Milliseconds(config.timeout_ms));
+    if config.sound {
+        notification.sound_name(default_sound());
+    }
+    notification.show().context("Failed to show notification")?;
+    Ok(())
+}
+
+fn default_sound() -> &'static str {
+    if cfg!(target_os = "macos") {
+        "default"
+    } else if cfg!(windows) {
+        "Default"
+    } else {
+        "message-new-instant"
+    }
+}
@@ -0,0 +1,44 @@
+//! Tests for notification selection and wording
+
+use std::path::Path;
+
+use crate::config::NotifyConfig;
+use crate::notification::message_for;
+
+#[test]
+fn test_messages_per_event() {
+    let config = NotifyConfig::default();
+    let project = Path::new("/work/my-app");
+    
+    let data = serde_json::json!({ "message": "Claude needs your permission to use Bash" });
+    let message = message_for(&config, "Notification", &data, project).unwrap();
+    assert_eq!(message.title, "Claude Code · my-app");
+    assert_eq!(message.body, "Claude needs your permission to use Bash");
+    
+    let message = message_for(&config, "Notification", &serde_json::Value::Null, project).unwrap();
+    assert_eq!(message.body, "Claude is waiting for your input");
+    
+    assert!(message_for(&config, "Stop", &serde_json::Value::Null, project).unwrap().body.contains("finished"));
+    // Not subscribed by default
+    assert!(message_for(&config, "SubagentStop", &serde_json::Value::Null, project).is_none());
+    assert!(message_for(&config, "PreToolUse", &serde_json::Value::Null, project).is_none());
+}
+
+#[test]
+fn test_config_layers() {
+    let mut config = NotifyConfig::default();
+    config.merge_toml(r#"
+        events = ["Stop", "SubagentStop"]
+        title = "Agent"
+        sound = true
+    "#).unwrap();
+    
+    let project = Path::new("/work/my-app");
+    assert!(message_for(&config, "Notification", &serde_json::Value::Null, project).is_none());
+    let message = message_for(&config, "SubagentStop", &serde_json::Value::Null, project).unwrap();
+    assert_eq!(message.title, "Agent · my-app");
+    assert!(config.sound);
+    
+    config.merge_toml("enabled = false").unwrap();
+    assert!(message_for(&config, "Stop", &serde_json::Value::Null, project).is_none());
+}
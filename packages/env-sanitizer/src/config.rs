@@ -0,0 +1,155 @@
+//! Sanitizer configuration
+//!
+//! Read from `~/.claude/hooks/env-sanitizer/config.toml`, then the
+//! project's `.claude/env-sanitizer.toml`; `sensitive`, `names`, and `allow`
+//! lists are combined.
+//!
+//! The project file is checked in with the code, so it can only tighten
+//! the sanitizer: its `allow` is ignored, it can turn `block_prints` and
+//! `block_uploads` on and `mask_dumps` off but not the other way round, and
+//! if it doesn't parse it is ignored with a warning.
+//!
+//! ```toml
+//! # Name fragments that mark a variable as sensitive, besides the defaults
+//! sensitive = ["DSN"]
+//! # Exact names that are sensitive whatever they contain
+//! names = ["DATABASE_URL"]
+//! # Names that are never sensitive
+//! allow = ["TOKENIZERS_PARALLELISM"]
+//! # Block environment dumps instead of masking them
+//! mask_dumps = false
+//! ```
+
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use std::path::Path;
+
+/// Name fragments that mark a variable as sensitive
+pub const DEFAULT_SENSITIVE: &[&str] = &[
+    "SECRET", "TOKEN", "PASSWORD", "PASSWD", "API_KEY", "APIKEY", "PRIVATE_KEY", "ACCESS_KEY", "CREDENTIAL",
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SanitizerConfig {
+    /// Case-insensitive fragments of sensitive variable names
+    pub sensitive: Vec<String>,
+    /// Exact sensitive names
+    pub names: Vec<String>,
+    /// Exact names that are never sensitive
+    pub allow: Vec<String>,
+    /// Rewrite environment dumps to mask sensitive values; when false
+    /// they are blocked
+    pub mask_dumps: bool,
+    /// Block commands that print a sensitive variable
+    pub block_prints: bool,
+    /// Block network commands that send a sensitive variable or dump
+    pub block_uploads: bool,
+    /// Problems with the files merged, to pass on to the user
+    pub warnings: Vec<String>,
+}
+
+impl Default for SanitizerConfig {
+    fn default() -> Self {
+        Self {
+            sensitive: DEFAULT_SENSITIVE.iter().map(|s| s.to_string()).collect(),
+            names: Vec::new(),
+            allow: Vec::new(),
+            mask_dumps: true,
+            block_prints: true,
+            block_uploads: true,
+            warnings: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigLayer {
+    #[serde(default)]
+    sensitive: Vec<String>,
+    #[serde(default)]
+    names: Vec<String>,
+    #[serde(default)]
+    allow: Vec<String>,
+    mask_dumps: Option<bool>,
+    block_prints: Option<bool>,
+    block_uploads: Option<bool>,
+}
+
+impl ConfigLayer {
+    fn parse(content: &str) -> Result<Self> {
+        let layer: ConfigLayer = toml::from_str(content)?;
+        // Names end up in the `sed` expression that masks dumps
+        for name in layer.sensitive.iter().chain(&layer.names).chain(&layer.allow) {
+            if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+                bail!("'{}' is not a valid environment variable name or fragment", name);
+            }
+        }
+        Ok(layer)
+    }
+}
+
+impl SanitizerConfig {
+    /// Load the global config, then layer the project's on top
+    pub fn load(project_dir: Option<&Path>) -> Result<Self> {
+        let global = dirs::home_dir().map(|home| home.join(".claude/hooks/env-sanitizer/config.toml"));
+        Self::load_from(global.as_deref(), project_dir)
+    }
+    
+    /// [`Self::load`] with the global config given
+    pub fn load_from(global: Option<&Path>, project_dir: Option<&Path>) -> Result<Self> {
+        let project = project_dir.map(|dir| dir.join(".claude").join("env-sanitizer.toml"));
+        let read = |path: &Path| {
+            std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))
+        };
+        
+        let mut config = Self::default();
+        if let Some(path) = global.filter(|path| path.is_file()) {
+            config.merge_toml(&read(path)?)
+                .with_context(|| format!("Failed to parse {}", path.display()))?;
+        }
+        if let Some(path) = project.filter(|path| path.is_file()) {
+            // Failing the hook would approve the command, so carry on without it
+            let merged = read(&path).and_then(|content| {
+                config.merge_project_toml(&content)
+                    .with_context(|| format!("Failed to parse {}", path.display()))
+            });
+            if let Err(e) = merged {
+                config.warnings.push(format!("Ignoring the project's config: {:#}", e));
+            }
+        }
+        Ok(config)
+    }
+    
+    /// Layer one config file's contents on top of this config
+    pub fn merge_toml(&mut self, content: &str) -> Result<()> {
+        let layer = ConfigLayer::parse(content)?;
+        self.sensitive.extend(layer.sensitive.iter().map(|s| s.to_ascii_uppercase()));
+        self.names.extend(layer.names);
+        self.allow.extend(layer.allow);
+        self.mask_dumps = layer.mask_dumps.unwrap_or(self.mask_dumps);
+        self.block_prints = layer.block_prints.unwrap_or(self.block_prints);
+        self.block_uploads = layer.block_uploads.unwrap_or(self.block_uploads);
+        Ok(())
+    }
+    
+    /// Layer a project config file on top, taking only what tightens the
+    /// sanitizer
+    pub fn merge_project_toml(&mut self, content: &str) -> Result<()> {
+        let layer = ConfigLayer::parse(content)?;
+        self.sensitive.extend(layer.sensitive.iter().map(|s| s.to_ascii_uppercase()));
+        self.names.extend(layer.names);
+        self.mask_dumps &= layer.mask_dumps.unwrap_or(true);
+        self.block_prints |= layer.block_prints.unwrap_or(false);
+        self.block_uploads |= layer.block_uploads.unwrap_or(false);
+        Ok(())
+    }
+    
+    pub fn is_sensitive(&self, name: &str) -> bool {
+        if self.allow.iter().any(|allowed| allowed == name) {
+            return false;
+        }
+        let upper = name.to_ascii_uppercase();
+        self.names.iter().any(|exact| exact == name) || self.sensitive.iter().any(|fragment| upper.contains(fragment.as_str()))
+    }
+}
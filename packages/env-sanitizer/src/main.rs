@@ -0,0 +1,54 @@
+//! Env Sanitizer PreToolUse Hook
+//!
+//! Keeps secrets held in environment variables out of the transcript and
+//! off the network:
+//! - `env`, `printenv`, and friends are rewritten to mask sensitive values
+//! - printing a sensitive variable (`echo $AWS_SECRET_ACCESS_KEY`) is blocked
+//! - sending one, or a whole environment dump, with `curl`, `wget`, `nc`,
+//!   and similar tools is blocked
+
+use anyhow::Result;
+use claude_hooks_sdk::{HookInput, HookOutput};
+use serde::Deserialize;
+
+mod config;
+mod sanitize;
+mod shell;
+#[cfg(test)]
+mod tests;
+
+use config::SanitizerConfig;
+use sanitize::Verdict;
+
+#[derive(Debug, Default, Deserialize)]
+struct BashToolData {
+    #[serde(default)]
+    command: Option<String>,
+}
+
+fn main() {
+    claude_hooks_sdk::run("Env sanitizer", run);
+}
+
+fn run(input: HookInput) -> Result<HookOutput> {
+    let tool_data: BashToolData = input.event.tool_data().unwrap_or_default();
+    let (true, Some(command)) = (input.event.event_type == "PreToolUse", tool_data.command) else {
+        return Ok(HookOutput::approve());
+    };
+    
+    let config = SanitizerConfig::load(Some(input.project_dir()))?;
+    let output = match sanitize::check(&command, &config) {
+        Verdict::Allow => HookOutput::approve(),
+        Verdict::Mask { command: masked, message } => HookOutput::approve()
+            .with_message(message)
+            .with_context(serde_json::json!({
+                "modified_command": masked,
+                "original_command": command,
+            })),
+        Verdict::Block(message) => HookOutput::block(message),
+    };
+    if config.warnings.is_empty() {
+        return Ok(output);
+    }
+    Ok(output.with_system_message(format!("env-sanitizer: {}", config.warnings.join("\n"))))
+}
@@ -0,0 +1,171 @@
+//! Deciding what to do with a command
+
+use crate::config::SanitizerConfig;
+use crate::shell::{segments, Segment, Word};
+
+/// Tools that send data over the network
+const NETWORK: &[&str] = &["curl", "wget", "nc", "ncat", "netcat", "socat", "telnet", "http", "https", "xh", "scp", "sftp", "ftp"];
+
+/// Options whose value is a credential meant for the server being contacted
+const AUTH_OPTIONS: &[&str] = &["-H", "--header", "-u", "--user", "--oauth2-bearer", "--password", "--http-password"];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Verdict {
+    Allow,
+    /// Run `command` instead, which masks sensitive values
+    Mask { command: String, message: String },
+    Block(String),
+}
+
+pub fn check(command: &str, config: &SanitizerConfig) -> Verdict {
+    let segments = segments(command);
+    
+    for (index, segment) in segments.iter().enumerate() {
+        let program = segment.program();
+        
+        if config.block_uploads && NETWORK.contains(&program) {
+            if let Some(name) = sent_variable(segment, config) {
+                return Verdict::Block(format!(
+                    "Blocked by env-sanitizer: `{}` would send ${} over the network",
+                    program, name,
+                ));
+            }
+            if segment.words.iter().flat_map(|word| &word.substitutions).any(|body| substitution_dumps(body)) {
+                return Verdict::Block(format!("Blocked by env-sanitizer: `{}` would send the environment over the network", program));
+            }
+            // Anything earlier in the same pipeline feeds this command
+            let upstream = segments[..index].iter().rev().take_while(|earlier| earlier.piped);
+            for earlier in upstream {
+                if is_dump(earlier) || printed_variable(earlier, config).is_some() {
+                    return Verdict::Block(format!(
+                        "Blocked by env-sanitizer: `{}` would send the output of `{}`, which includes secrets, over the network",
+                        program, earlier.program(),
+                    ));
+                }
+            }
+        }
+        
+        // Output that is piped or written to a file isn't shown to Claude
+        if config.block_prints && !segment.piped && !segment.redirected {
+            if let Some(name) = printed_variable(segment, config) {
+                return Verdict::Block(format!(
+                    "Blocked by env-sanitizer: this would print ${} into the transcript; check it is set with `[ -n \"${}\" ]` instead",
+                    name, name,
+                ));
+            }
+        }
+    }
+    
+    let dumps: Vec<&Segment> = segments.iter().filter(|segment| is_dump(segment) && !segment.redirected).collect();
+    if dumps.is_empty() {
+        return Verdict::Allow;
+    }
+    if !config.mask_dumps {
+        return Verdict::Block(format!(
+            "Blocked by env-sanitizer: `{}` would print the environment, which may include secrets",
+            dumps[0].program(),
+        ));
+    }
+    
+    // Rewrite from the end so earlier spans stay valid
+    let mut masked = command.to_string();
+    for dump in dumps.iter().rev() {
+        let text = command[dump.span.clone()].trim();
+        let start = dump.span.start + command[dump.span.clone()].find(text).unwrap_or(0);
+        masked.replace_range(start..start + text.len(), &format!("{} | {}", text, mask_filter(config)));
+    }
+    Verdict::Mask {
+        command: masked,
+        message: format!("env-sanitizer masked sensitive values in the output of `{}`", dumps[0].program()),
+    }
+}
+
+/// Whether this command prints the whole environment
+fn is_dump(segment: &Segment) -> bool {
+    let words = segment.command_words();
+    let operands = || words.iter().skip(1).filter(|word| !word.text.starts_with('-'));
+    match segment.program() {
+        "env" | "printenv" => operands().next().is_none(),
+        "set" => words.len() == 1,
+        "export" => words.len() == 1 || words.iter().skip(1).all(|word| word.text == "-p"),
+        "declare" | "typeset" => words.len() > 1 && words.iter().skip(1).all(|word| matches!(word.text.as_str(), "-x" | "-p" | "-px" | "-xp")),
+        _ => false,
+    }
+}
+
+fn substitution_dumps(body: &str) -> bool {
+    segments(body).iter().any(is_dump)
+}
+
+/// A sensitive variable this command prints
+fn printed_variable(segment: &Segment, config: &SanitizerConfig) -> Option<String> {
+    let words = segment.command_words();
+    match segment.program() {
+        "echo" | "printf" => sensitive_var(&words[1..], config),
+        "printenv" => words[1..].iter().find(|word| config.is_sensitive(&word.text)).map(|word| word.text.clone()),
+        _ => None,
+    }
+}
+
+/// A sensitive variable a network command would send, other than as the
+/// credential for the request itself
+fn sent_variable(segment: &Segment, config: &SanitizerConfig) -> Option<String> {
+    let words = segment.command_words();
+    let mut payload = Vec::new();
+    let mut iter = words.iter().skip(1);
+    while let Some(word) = iter.next() {
+        if AUTH_OPTIONS.contains(&word.text.as_str()) {
+            iter.next();
+        } else if AUTH_OPTIONS.iter().any(|option| word.text.starts_with(&format!("{}=", option))) {
+            continue;
+        } else if is_header_item(word) {
+            // httpie/xh take headers as `Name:value`
+            continue;
+        } else {
+            payload.push(word.clone());
+        }
+    }
+    sensitive_var(&payload, config)
+}
+
+fn is_header_item(word: &Word) -> bool {
+    let Some((name, _)) = word.text.split_once(':') else {
+        return false;
+    };
+    !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') && !word.text.contains("://")
+}
+
+fn sensitive_var(words: &[Word], config: &SanitizerConfig) -> Option<String> {
+    words
+        .iter()
+        .flat_map(|word| word.vars.iter().cloned().chain(word.substitutions.iter().flat_map(|body| substitution_vars(body))))
+        .find(|name| config.is_sensitive(name))
+}
+
+fn substitution_vars(body: &str) -> Vec<String> {
+    segments(body).into_iter().flat_map(|segment| segment.words).flat_map(|word| word.vars).collect()
+}
+
+/// A `sed` filter replacing sensitive values with `****`
+pub fn mask_filter(config: &SanitizerConfig) -> String {
+    let mut fragments: Vec<String> = Vec::new();
+    for fragment in &config.sensitive {
+        for variant in [fragment.to_ascii_uppercase(), fragment.to_ascii_lowercase()] {
+            if !fragments.contains(&variant) {
+                fragments.push(variant);
+            }
+        }
+    }
+    let mut names = format!("[A-Za-z0-9_]*({})[A-Za-z0-9_]*", fragments.join("|"));
+    for name in &config.names {
+        names.push('|');
+        names.push_str(name);
+    }
+    let substitute = format!("s/^({})=.*/\\1=****/", names);
+    
+    if config.allow.is_empty() {
+        format!("sed -E '{}'", substitute)
+    } else {
+        format!("sed -E '/^({})=/!{}'", config.allow.join("|"), substitute)
+    }
+}
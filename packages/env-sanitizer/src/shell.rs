@@ -0,0 +1,235 @@
+//! Shell parsing that keeps track of variable references
+//!
+//! Commands are split into simple commands at `;`, `&&`, `||`, `|`, `&`,
+//! and newlines, remembering where each one sits in the original text (so
+//! it can be rewritten in place) and whether its output is piped or
+//! redirected. Each word records the variables it expands and the command
+//! substitutions it contains; single-quoted text expands nothing.
+
+use std::ops::Range;
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Word {
+    /// The word with quotes removed; expansions are kept as written
+    pub text: String,
+    /// Names of variables expanded in this word
+    pub vars: Vec<String>,
+    /// Bodies of `$(...)` and backtick substitutions
+    pub substitutions: Vec<String>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Segment {
+    pub words: Vec<Word>,
+    /// Byte range of this command in the original text
+    pub span: Range<usize>,
+    /// Its output feeds the next command through a pipe
+    pub piped: bool,
+    /// Its input comes from the previous command through a pipe
+    pub from_pipe: bool,
+    /// Its standard output goes to a file
+    pub redirected: bool,
+}
+
+impl Segment {
+    /// Words with leading wrappers (`sudo`, `env FOO=1`, ...) removed
+    pub fn command_words(&self) -> &[Word] {
+        let mut words = self.words.as_slice();
+        while let Some((first, rest)) = words.split_first() {
+            let is_assignment = first.text.contains('=') && !first.text.starts_with(['-', '=']);
+            // `env` alone is a dump, not a wrapper
+            let is_wrapper = matches!(first.text.as_str(), "sudo" | "nohup" | "time" | "command" | "exec")
+                || (first.text == "env" && !rest.is_empty());
+            if is_wrapper || is_assignment {
+                words = rest;
+            } else {
+                break;
+            }
+        }
+        words
+    }
+    
+    pub fn program(&self) -> &str {
+        self.command_words().first().map_or("", |word| word.text.rsplit('/').next().unwrap_or(""))
+    }
+}
+
+struct Parser<'a> {
+    chars: std::iter::Peekable<std::str::CharIndices<'a>>,
+    segments: Vec<Segment>,
+    word: Word,
+    in_word: bool,
+    skip_word: bool,
+}
+
+/// Split `command` into simple commands
+pub fn segments(command: &str) -> Vec<Segment> {
+    let mut parser = Parser {
+        chars: command.char_indices().peekable(),
+        segments: vec![Segment::default()],
+        word: Word::default(),
+        in_word: false,
+        skip_word: false,
+    };
+    parser.run(command.len());
+    parser.segments.retain(|segment| !segment.words.is_empty());
+    parser.segments
+}
+
+impl Parser<'_> {
+    fn run(&mut self, len: usize) {
+        while let Some((index, c)) = self.chars.next() {
+            match c {
+                '\'' => {
+                    self.in_word = true;
+                    for (_, c) in self.chars.by_ref() {
+                        if c == '\'' {
+                            break;
+                        }
+                        self.word.text.push(c);
+                    }
+                }
+                '"' => {
+                    self.in_word = true;
+                    while let Some((_, c)) = self.chars.next() {
+                        match c {
+                            '"' => break,
+                            '\\' => self.word.text.extend(self.chars.next().map(|(_, c)| c)),
+                            '$' => self.expansion(),
+                            '`' => self.backticks(),
+                            c => self.word.text.push(c),
+                        }
+                    }
+                }
+                '\\' => {
+                    self.in_word = true;
+                    self.word.text.extend(self.chars.next().map(|(_, c)| c));
+                }
+                '$' => {
+                    self.in_word = true;
+                    self.expansion();
+                }
+                '`' => {
+                    self.in_word = true;
+                    self.backticks();
+                }
+                ';' | '|' | '&' | '\n' => {
+                    self.finish_word();
+                    let doubled = self.chars.peek().map(|(_, next)| *next) == Some(c);
+                    if doubled {
+                        self.chars.next();
+                    }
+                    let piped = c == '|' && !doubled;
+                    // `|&` pipes stderr too
+                    if piped && self.chars.peek().map(|(_, next)| *next) == Some('&') {
+                        self.chars.next();
+                    }
+                    let current = self.segments.last_mut().unwrap();
+                    current.span.end = index;
+                    current.piped = piped;
+                    let start = self.chars.peek().map_or(len, |(next, _)| *next);
+                    self.segments.push(Segment { span: start..len, from_pipe: piped, ..Segment::default() });
+                }
+                '>' | '<' => {
+                    // A file descriptor number belongs to the operator (`2>`)
+                    let descriptor = self.in_word && !self.word.text.is_empty() && self.word.text.chars().all(|c| c.is_ascii_digit());
+                    let stdout = c == '>' && (!descriptor || self.word.text == "1");
+                    if descriptor {
+                        self.word = Word::default();
+                        self.in_word = false;
+                    }
+                    self.finish_word();
+                    if matches!(self.chars.peek(), Some((_, '>' | '|'))) {
+                        self.chars.next();
+                    }
+                    // `>&2` duplicates a descriptor rather than naming a file
+                    if matches!(self.chars.peek(), Some((_, '&'))) {
+                        self.chars.next();
+                        while matches!(self.chars.peek(), Some((_, c)) if c.is_ascii_digit() || *c == '-') {
+                            self.chars.next();
+                        }
+                        continue;
+                    }
+                    if stdout {
+                        self.segments.last_mut().unwrap().redirected = true;
+                    }
+                    self.skip_word = true;
+                }
+                c if c.is_whitespace() => self.finish_word(),
+                c => {
+                    self.in_word = true;
+                    self.word.text.push(c);
+                }
+            }
+        }
+        self.finish_word();
+        self.segments.last_mut().unwrap().span.end = len;
+    }
+    
+    fn finish_word(&mut self) {
+        if !self.in_word {
+            return;
+        }
+        let word = std::mem::take(&mut self.word);
+        self.in_word = false;
+        if !std::mem::take(&mut self.skip_word) {
+            self.segments.last_mut().unwrap().words.push(word);
+        }
+    }
+    
+    /// After a `$`: a variable, `${...}`, or `$(...)`
+    fn expansion(&mut self) {
+        self.word.text.push('$');
+        match self.chars.peek().map(|(_, c)| *c) {
+            Some('{') => {
+                let body = self.until_closing('{', '}');
+                let name: String = body.chars().take_while(|c| c.is_ascii_alphanumeric() || *c == '_').collect();
+                self.word.text.push_str(&format!("{{{}}}", body));
+                if !name.is_empty() {
+                    self.word.vars.push(name);
+                }
+            }
+            Some('(') => {
+                let body = self.until_closing('(', ')');
+                self.word.text.push_str(&format!("({})", body));
+                self.word.substitutions.push(body);
+            }
+            Some(c) if c.is_ascii_alphabetic() || c == '_' => {
+                let mut name = String::new();
+                while let Some((_, c)) = self.chars.peek().copied().filter(|(_, c)| c.is_ascii_alphanumeric() || *c == '_') {
+                    name.push(c);
+                    self.chars.next();
+                }
+                self.word.text.push_str(&name);
+                self.word.vars.push(name);
+            }
+            _ => {}
+        }
+    }
+    
+    /// The text between an opening bracket (next in the input) and its
+    /// matching close
+    fn until_closing(&mut self, open: char, close: char) -> String {
+        self.chars.next();
+        let mut depth = 1;
+        let mut body = String::new();
+        for (_, c) in self.chars.by_ref() {
+            if c == open {
+                depth += 1;
+            } else if c == close {
+                depth -= 1;
+                if depth == 0 {
+                    break;
+                }
+            }
+            body.push(c);
+        }
+        body
+    }
+    
+    fn backticks(&mut self) {
+        let body: String = self.chars.by_ref().map(|(_, c)| c).take_while(|c| *c != '`').collect();
+        self.word.text.push_str(&format!("`{}`", body));
+        self.word.substitutions.push(body);
+    }
+}
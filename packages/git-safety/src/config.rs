@@ -0,0 +1,166 @@
+//! Git safety policy
+//!
+//! Read from `~/.claude/hooks/git-safety/config.toml`, then the project's
+//! `.claude/git-safety.toml`, so each repository can tighten or relax the
+//! global policy.
+//!
+//! ```toml
+//! protected_branches = ["main", "release/*"]
+//!
+//! [checks]
+//! history_rewrite = "allow"
+//! force_push = "warn"
+//! ```
+
+use anyhow::{Context, Result};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use serde::Deserialize;
+use std::path::Path;
+
+/// What to do when a check matches
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Action {
+    Block,
+    /// Let the command run, with a warning
+    Warn,
+    Allow,
+}
+
+/// The operations this hook recognizes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Check {
+    ForcePush,
+    ProtectedPush,
+    HistoryRewrite,
+    Clean,
+    DiscardChanges,
+}
+
+impl Check {
+    pub fn id(self) -> &'static str {
+        match self {
+            Check::ForcePush => "force_push",
+            Check::ProtectedPush => "protected_push",
+            Check::HistoryRewrite => "history_rewrite",
+            Check::Clean => "clean",
+            Check::DiscardChanges => "discard_changes",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Checks {
+    pub force_push: Action,
+    pub protected_push: Action,
+    pub history_rewrite: Action,
+    pub clean: Action,
+    pub discard_changes: Action,
+}
+
+impl Default for Checks {
+    fn default() -> Self {
+        Self {
+            force_push: Action::Block,
+            protected_push: Action::Block,
+            history_rewrite: Action::Warn,
+            clean: Action::Block,
+            discard_changes: Action::Block,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SafetyConfig {
+    /// Branch names or globs that may not be pushed to directly
+    pub protected_branches: Vec<String>,
+    /// `--force-with-lease` counts as a force push too
+    pub block_force_with_lease: bool,
+    pub checks: Checks,
+}
+
+impl Default for SafetyConfig {
+    fn default() -> Self {
+        Self {
+            protected_branches: vec!["main".to_string(), "master".to_string()],
+            block_force_with_lease: false,
+            checks: Checks::default(),
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigLayer {
+    protected_branches: Option<Vec<String>>,
+    block_force_with_lease: Option<bool>,
+    #[serde(default)]
+    checks: ChecksLayer,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ChecksLayer {
+    force_push: Option<Action>,
+    protected_push: Option<Action>,
+    history_rewrite: Option<Action>,
+    clean: Option<Action>,
+    discard_changes: Option<Action>,
+}
+
+impl SafetyConfig {
+    /// Load the global config, then layer the project's on top
+    pub fn load(project_dir: Option<&Path>) -> Result<Self> {
+        let global = dirs::home_dir().map(|home| home.join(".claude/hooks/git-safety/config.toml"));
+        let project = project_dir.map(|dir| dir.join(".claude").join("git-safety.toml"));
+        
+        let mut config = Self::default();
+        for path in global.into_iter().chain(project) {
+            if path.is_file() {
+                let content = std::fs::read_to_string(&path)
+                    .with_context(|| format!("Failed to read {}", path.display()))?;
+                config.merge_toml(&content)
+                    .with_context(|| format!("Failed to parse {}", path.display()))?;
+            }
+        }
+        Ok(config)
+    }
+    
+    /// Layer one config file's contents on top of this config
+    pub fn merge_toml(&mut self, content: &str) -> Result<()> {
+        let layer: ConfigLayer = toml::from_str(content)?;
+        if let Some(branches) = layer.protected_branches {
+            self.protected_branches = branches;
+        }
+        self.block_force_with_lease = layer.block_force_with_lease.unwrap_or(self.block_force_with_lease);
+        
+        let checks = &mut self.checks;
+        checks.force_push = layer.checks.force_push.unwrap_or(checks.force_push);
+        checks.protected_push = layer.checks.protected_push.unwrap_or(checks.protected_push);
+        checks.history_rewrite = layer.checks.history_rewrite.unwrap_or(checks.history_rewrite);
+        checks.clean = layer.checks.clean.unwrap_or(checks.clean);
+        checks.discard_changes = layer.checks.discard_changes.unwrap_or(checks.discard_changes);
+        Ok(())
+    }
+    
+    pub fn action(&self, check: Check) -> Action {
+        match check {
+            Check::ForcePush => self.checks.force_push,
+            Check::ProtectedPush => self.checks.protected_push,
+            Check::HistoryRewrite => self.checks.history_rewrite,
+            Check::Clean => self.checks.clean,
+            Check::DiscardChanges => self.checks.discard_changes,
+        }
+    }
+    
+    /// Matcher for `protected_branches`; invalid globs are skipped
+    pub fn protected_set(&self) -> GlobSet {
+        let mut builder = GlobSetBuilder::new();
+        for pattern in &self.protected_branches {
+            if let Ok(glob) = Glob::new(pattern) {
+                builder.add(glob);
+            }
+        }
+        builder.build().unwrap_or_else(|_| GlobSet::empty())
+    }
+}
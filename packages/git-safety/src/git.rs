@@ -0,0 +1,232 @@
+//! Recognizing dangerous git invocations
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use claude_hooks_sdk::shell::simple_commands;
+
+use crate::config::{Action, Check, SafetyConfig};
+
+/// Global options that take a separate value (`git -C dir push`)
+const VALUE_OPTIONS: &[&str] = &["-C", "-c", "--git-dir", "--work-tree", "--namespace"];
+
+/// `git rebase` options that carry on or stop a rebase already underway
+const REBASE_CONTROL: &[&str] = &["--continue", "--abort", "--skip", "--quit", "--edit-todo", "--show-current-patch"];
+
+/// A git operation the policy doesn't allow outright
+#[derive(Debug, Clone, PartialEq)]
+pub struct Finding {
+    pub check: Check,
+    pub action: Action,
+    pub detail: String,
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let verdict = match self.action {
+            Action::Block => "Blocked",
+            _ => "Warning",
+        };
+        write!(f, "{} by git-safety ({}): {}", verdict, self.check.id(), self.detail)
+    }
+}
+
+/// The repository the command runs in, queried lazily
+pub struct Repo {
+    dir: PathBuf,
+}
+
+impl Repo {
+    pub fn new(dir: &Path) -> Self {
+        Self { dir: dir.to_path_buf() }
+    }
+    
+    /// The checked-out branch, or `None` when detached or not a repository
+    pub fn current_branch(&self) -> Option<String> {
+        let output = Command::new("git")
+            .args(["symbolic-ref", "--short", "-q", "HEAD"])
+            .current_dir(&self.dir)
+            .output()
+            .ok()?;
+        let branch = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        (output.status.success() && !branch.is_empty()).then_some(branch)
+    }
+    
+    /// Tracked files under `paths` with uncommitted changes
+    pub fn modified_files(&self, paths: &[String]) -> Vec<String> {
+        let Ok(output) = Command::new("git")
+            .args(["status", "--porcelain", "--untracked-files=no", "--"])
+            .args(paths)
+            .current_dir(&self.dir)
+            .output()
+        else {
+            return Vec::new();
+        };
+        if !output.status.success() {
+            return Vec::new();
+        }
+        String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter_map(|line| line.get(3..))
+            .map(str::to_string)
+            .collect()
+    }
+}
+
+/// Every policy finding for the git commands in `command`; checks set to
+/// `allow` are left out
+pub fn check_command(command: &str, config: &SafetyConfig, repo: &Repo) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    for words in simple_commands(command) {
+        let Some((subcommand, args)) = git_subcommand(&words) else {
+            continue;
+        };
+        for (check, detail) in check_git(subcommand, args, config, repo) {
+            let action = config.action(check);
+            if action != Action::Allow {
+                findings.push(Finding { check, action, detail });
+            }
+        }
+    }
+    findings
+}
+
+/// Split `git [global options] <subcommand> <args>`, skipping wrappers
+/// like `sudo` and `env`
+fn git_subcommand(words: &[String]) -> Option<(&str, &[String])> {
+    let start = words.iter().position(|word| word == "git" || word.ends_with("/git"))?;
+    if words[..start].iter().any(|word| !matches!(word.as_str(), "sudo" | "env" | "command" | "nohup" | "time") && !word.contains('=')) {
+        return None;
+    }
+    let mut index = start + 1;
+    while let Some(word) = words.get(index) {
+        if VALUE_OPTIONS.contains(&word.as_str()) {
+            index += 2;
+        } else if word.starts_with('-') {
+            index += 1;
+        } else {
+            return Some((word.as_str(), &words[index + 1..]));
+        }
+    }
+    None
+}
+
+fn has_flag(args: &[String], long: &[&str], short: char) -> bool {
+    args.iter().any(|arg| {
+        long.contains(&arg.as_str())
+            || (arg.starts_with('-') && !arg.starts_with("--") && arg[1..].contains(short))
+    })
+}
+
+fn check_git(subcommand: &str, args: &[String], config: &SafetyConfig, repo: &Repo) -> Vec<(Check, String)> {
+    let mut found = Vec::new();
+    match subcommand {
+        "push" => {
+            let operands: Vec<&String> = args.iter().filter(|arg| !arg.starts_with('-')).collect();
+            let refspecs = operands.get(1..).unwrap_or_default();
+            
+            let lease = args.iter().any(|arg| arg.starts_with("--force-with-lease"));
+            let forced = has_flag(args, &["--force", "--mirror"], 'f')
+                || (lease && config.block_force_with_lease)
+                || refspecs.iter().any(|refspec| refspec.starts_with('+'));
+            if forced {
+                found.push((Check::ForcePush, format!("`git push {}` rewrites the remote's history", args.join(" "))));
+            }
+            
+            // Without a refspec, git pushes the current branch
+            let targets: Vec<String> = if refspecs.is_empty() && !has_flag(args, &["--all", "--tags", "--delete"], 'd') {
+                repo.current_branch().into_iter().collect()
+            } else {
+                refspecs.iter().map(|refspec| push_destination(refspec)).collect()
+            };
+            let protected = config.protected_set();
+            for branch in targets.iter().filter(|branch| protected.is_match(branch.as_str())) {
+                found.push((Check::ProtectedPush, format!("`{}` is a protected branch; push to a feature branch and open a pull request", branch)));
+            }
+        }
+        "rebase" if !args.iter().any(|arg| REBASE_CONTROL.contains(&arg.as_str())) => {
+            found.push((Check::HistoryRewrite, "`git rebase` rewrites commits".to_string()));
+        }
+        "commit" if args.iter().any(|arg| arg == "--amend") => {
+            found.push((Check::HistoryRewrite, "`git commit --amend` rewrites the last commit".to_string()));
+        }
+        "filter-branch" | "filter-repo" => {
+            found.push((Check::HistoryRewrite, format!("`git {}` rewrites the repository's history", subcommand)));
+        }
+        "reset" if args.iter().any(|arg| arg == "--hard") => {
+            match args.iter().find(|arg| !arg.starts_with('-')).filter(|target| *target != "HEAD") {
+                Some(target) => {
+                    found.push((Check::HistoryRewrite, format!("`git reset --hard {}` moves the branch and discards work", target)));
+                }
+                None => {
+                    let modified = repo.modified_files(&[]);
+                    if !modified.is_empty() {
+                        found.push((Check::DiscardChanges, discard_detail("git reset --hard", &modified)));
+                    }
+                }
+            }
+        }
+        "clean" => {
+            let force = has_flag(args, &["--force"], 'f');
+            let broad = has_flag(args, &[], 'x') || has_flag(args, &[], 'd');
+            let dry_run = has_flag(args, &["--dry-run"], 'n');
+            if force && broad && !dry_run {
+                found.push((Check::Clean, format!("`git clean {}` permanently deletes untracked files", args.join(" "))));
+            }
+        }
+        "checkout" | "restore" => {
+            let paths = discarded_paths(subcommand, args);
+            if !paths.is_empty() {
+                let modified = repo.modified_files(&paths);
+                if !modified.is_empty() {
+                    found.push((Check::DiscardChanges, discard_detail(&format!("git {}", subcommand), &modified)));
+                }
+            }
+        }
+        _ => {}
+    }
+    found
+}
+
+/// The branch a refspec pushes to (`src:dst`, `+branch`, `refs/heads/x`)
+fn push_destination(refspec: &str) -> String {
+    let refspec = refspec.trim_start_matches('+');
+    let destination = refspec.rsplit_once(':').map_or(refspec, |(_, dst)| dst);
+    destination.trim_start_matches("refs/heads/").to_string()
+}
+
+/// The paths whose working-tree edits the command would overwrite
+fn discarded_paths(subcommand: &str, args: &[String]) -> Vec<String> {
+    match subcommand {
+        // `git checkout [<tree-ish>] -- <paths>`
+        "checkout" => match args.iter().position(|arg| arg == "--") {
+            Some(separator) => args[separator + 1..].to_vec(),
+            None => Vec::new(),
+        },
+        // `git restore --staged` alone only touches the index
+        _ => {
+            let staged_only = args.iter().any(|arg| arg == "--staged" || arg == "-S")
+                && !args.iter().any(|arg| arg == "--worktree" || arg == "-W");
+            if staged_only {
+                return Vec::new();
+            }
+            let mut paths = Vec::new();
+            let mut iter = args.iter();
+            while let Some(arg) = iter.next() {
+                if arg == "-s" || arg == "--source" {
+                    iter.next();
+                } else if !arg.starts_with('-') {
+                    paths.push(arg.clone());
+                }
+            }
+            paths
+        }
+    }
+}
+
+fn discard_detail(command: &str, modified: &[String]) -> String {
+    let shown: Vec<&str> = modified.iter().take(5).map(String::as_str).collect();
+    let more = if modified.len() > shown.len() { format!(" and {} more", modified.len() - shown.len()) } else { String::new() };
+    format!("`{}` would discard uncommitted edits to {}{}", command, shown.join(", "), more)
+}
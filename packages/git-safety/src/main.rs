@@ -0,0 +1,53 @@
+//! Git Safety PreToolUse Hook
+//!
+//! Inspects every `git` invocation in a Bash command and blocks (or warns
+//! about) force pushes, pushes to protected branches, history rewrites,
+//! `git clean -fdx`, and checkouts that would discard uncommitted edits.
+//! What happens for each is set per repository in `.claude/git-safety.toml`.
+
+use anyhow::Result;
+use claude_hooks_sdk::{HookInput, HookOutput};
+use serde::Deserialize;
+
+mod config;
+mod git;
+#[cfg(test)]
+mod tests;
+
+use config::{Action, SafetyConfig};
+use git::Repo;
+
+#[derive(Debug, Default, Deserialize)]
+struct BashToolData {
+    #[serde(default)]
+    command: Option<String>,
+}
+
+fn main() {
+    claude_hooks_sdk::run("Git safety", run);
+}
+
+fn run(input: HookInput) -> Result<HookOutput> {
+    if input.event.event_type != "PreToolUse" {
+        return Ok(HookOutput::approve());
+    }
+    let tool_data: BashToolData = input.event.tool_data().unwrap_or_default();
+    let Some(command) = tool_data.command else {
+        return Ok(HookOutput::approve());
+    };
+    
+    let project_dir = input.project_dir();
+    let config = SafetyConfig::load(Some(project_dir))?;
+    let findings = git::check_command(&command, &config, &Repo::new(project_dir));
+    if findings.is_empty() {
+        return Ok(HookOutput::approve());
+    }
+    
+    // The strictest action among the findings decides
+    let message = findings.iter().map(|finding| finding.to_string()).collect::<Vec<_>>().join("\n");
+    Ok(if findings.iter().any(|finding| finding.action == Action::Block) {
+        HookOutput::block(message)
+    } else {
+        HookOutput::approve().with_message(message)
+    })
+}
@@ -0,0 +1,115 @@
+//! Tests for git command classification
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use claude_hooks_sdk::shell::simple_commands;
+
+use crate::config::{Action, Check, SafetyConfig};
+use crate::git::{check_command, Repo};
+
+fn git(dir: &Path, args: &[&str]) {
+    let status = Command::new("git").args(args).current_dir(dir).output().unwrap().status;
+    assert!(status.success(), "git {:?}", args);
+}
+
+/// A repository on `main` with one committed file
+fn repo(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("git-safety-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    git(&dir, &["init", "-q", "-b", "main"]);
+    std::fs::write(dir.join("lib.rs"), "fn main() {}\n").unwrap();
+    git(&dir, &["add", "."]);
+    git(&dir, &["-c", "user.name=test", "-c", "user.email=test@example.com", "commit", "-q", "-m", "init"]);
+    dir
+}
+
+fn checks(command: &str, config: &SafetyConfig, dir: &Path) -> Vec<(Check, Action)> {
+    check_command(command, config, &Repo::new(dir))
+        .into_iter()
+        .map(|finding| (finding.check, finding.action))
+        .collect()
+}
+
+#[test]
+fn test_simple_commands() {
+    let parsed = simple_commands("cd app && git push -f origin 'my branch' 2>&1 | tee log > out.txt");
+    assert_eq!(parsed, vec![
+        vec!["cd", "app"],
+        vec!["git", "push", "-f", "origin", "my branch"],
+        vec!["tee", "log"],
+    ]);
+}
+
+#[test]
+fn test_push_and_rewrite_checks() {
+    let dir = repo("push");
+    let config = SafetyConfig::default();
+    
+    assert_eq!(checks("git push --force origin feature", &config, &dir), vec![(Check::ForcePush, Action::Block)]);
+    assert_eq!(checks("git push origin +feature", &config, &dir), vec![(Check::ForcePush, Action::Block)]);
+    assert!(checks("git push --force-with-lease origin feature", &config, &dir).is_empty());
+    // A bare push goes to the current branch, which is main
+    assert_eq!(checks("git push", &config, &dir), vec![(Check::ProtectedPush, Action::Block)]);
+    assert_eq!(checks("git -C . push origin HEAD:refs/heads/master", &config, &dir), vec![(Check::ProtectedPush, Action::Block)]);
+    assert!(checks("git push -u origin feature/login", &config, &dir).is_empty());
+    
+    assert_eq!(checks("git rebase -i HEAD~3", &config, &dir), vec![(Check::HistoryRewrite, Action::Warn)]);
+    assert!(checks("git rebase --continue", &config, &dir).is_empty());
+    assert_eq!(checks("git commit --amend --no-edit", &config, &dir), vec![(Check::HistoryRewrite, Action::Warn)]);
+    assert_eq!(checks("git clean -fdx", &config, &dir), vec![(Check::Clean, Action::Block)]);
+    assert!(checks("git clean -ndx", &config, &dir).is_empty());
+    assert!(checks("git status && git log --oneline", &config, &dir).is_empty());
+    
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_discarding_uncommitted_edits() {
+    let dir = repo("discard");
+    let config = SafetyConfig::default();
+    
+    // Nothing to lose while the tree is clean
+    assert!(checks("git checkout -- lib.rs", &config, &dir).is_empty());
+    assert!(checks("git reset --hard", &config, &dir).is_empty());
+    
+    std::fs::write(dir.join("lib.rs"), "fn main() { todo!() }\n").unwrap();
+    let findings = check_command("git checkout -- .", &config, &Repo::new(&dir));
+    assert_eq!(findings.len(), 1);
+    assert_eq!(findings[0].check, Check::DiscardChanges);
+    assert!(findings[0].to_string().contains("lib.rs"));
+    assert_eq!(checks("git restore lib.rs", &config, &dir), vec![(Check::DiscardChanges, Action::Block)]);
+    assert!(checks("git restore --staged lib.rs", &config, &dir).is_empty());
+    assert_eq!(checks("git reset --hard", &config, &dir), vec![(Check::DiscardChanges, Action::Block)]);
+    
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_per_repo_policy() {
+    let dir = repo("policy");
+    let mut config = SafetyConfig::default();
+    config.merge_toml(r#"
+        protected_branches = ["release/*"]
+        block_force_with_lease = true
+        
+        [checks]
+        force_push = "warn"
+        history_rewrite = "allow"
+    "#).unwrap();
+    
+    assert!(checks("git push", &config, &dir).is_empty());
+    assert_eq!(
+        checks("git push origin release/1.2", &config, &dir),
+        vec![(Check::ProtectedPush, Action::Block)],
+    );
+    assert_eq!(
+        checks("git push --force-with-lease origin feature", &config, &dir),
+        vec![(Check::ForcePush, Action::Warn)],
+    );
+    assert!(checks("git rebase main", &config, &dir).is_empty());
+    assert!(config.merge_toml("[checks]\nclean = \"maybe\"").is_err());
+    
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,96 @@
+//! Multiplexer configuration
+//!
+//! Read from `~/.claude/hooks/hook-mux/config.toml`, then the project's
+//! `.claude/hook-mux.toml`. The global `modules` list replaces the default
+//! one; the project's, which is checked in with the code, can only add
+//! modules to the end of the chain, never drop a guard. A project file that
+//! doesn't parse is ignored with a warning.
+//!
+//! ```toml
+//! # Run in this order
+//! modules = ["security-guard", "command-replacer", "audit-logger"]
+//! ```
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::path::Path;
+
+use crate::mux::Module;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MuxConfig {
+    pub modules: Vec<Module>,
+    /// Problems with the files merged, to pass on to the user
+    pub warnings: Vec<String>,
+}
+
+impl Default for MuxConfig {
+    fn default() -> Self {
+        Self {
+            // Guards first, so a blocked command is never rewritten; the
+            // audit log sees every event last
+            modules: vec![Module::SecurityGuard, Module::CommandReplacer, Module::AuditLogger],
+            warnings: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigLayer {
+    modules: Option<Vec<Module>>,
+}
+
+impl MuxConfig {
+    /// Load the global config, then layer the project's on top
+    pub fn load(project_dir: Option<&Path>) -> Result<Self> {
+        let global = dirs::home_dir().map(|home| home.join(".claude/hooks/hook-mux/config.toml"));
+        Self::load_from(global.as_deref(), project_dir)
+    }
+    
+    /// [`Self::load`] with the global config given
+    pub fn load_from(global: Option<&Path>, project_dir: Option<&Path>) -> Result<Self> {
+        let project = project_dir.map(|dir| dir.join(".claude").join("hook-mux.toml"));
+        let read = |path: &Path| {
+            std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))
+        };
+        
+        let mut config = Self::default();
+        if let Some(path) = global.filter(|path| path.is_file()) {
+            config.merge_toml(&read(path)?)
+                .with_context(|| format!("Failed to parse {}", path.display()))?;
+        }
+        if let Some(path) = project.filter(|path| path.is_file()) {
+            // Failing the hook would approve the call, so run the chain without it
+            let merged = read(&path).and_then(|content| {
+                config.merge_project_toml(&content)
+                    .with_context(|| format!("Failed to parse {}", path.display()))
+            });
+            if let Err(e) = merged {
+                config.warnings.push(format!("Ignoring the project's config: {:#}", e));
+            }
+        }
+        Ok(config)
+    }
+    
+    /// Layer one config file's contents on top of this config
+    pub fn merge_toml(&mut self, content: &str) -> Result<()> {
+        let layer: ConfigLayer = toml::from_str(content)?;
+        if let Some(modules) = layer.modules {
+            self.modules = modules;
+        }
+        Ok(())
+    }
+    
+    /// Layer a project config file on top, only adding modules the chain
+    /// doesn't already run
+    pub fn merge_project_toml(&mut self, content: &str) -> Result<()> {
+        let layer: ConfigLayer = toml::from_str(content)?;
+        for module in layer.modules.unwrap_or_default() {
+            if !self.modules.contains(&module) {
+                self.modules.push(module);
+            }
+        }
+        Ok(())
+    }
+}
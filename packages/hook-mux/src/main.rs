@@ -0,0 +1,31 @@
+//! Hook Multiplexer
+//!
+//! One binary registered in settings.json in place of several: each event
+//! is handed to an ordered list of hooks running in this process, and their
+//! answers are merged — a block from any of them wins, then an ask, and
+//! messages and additional context are concatenated. That is one process
+//! spawn per tool call instead of one per hook.
+
+use anyhow::Result;
+use claude_hooks_sdk::{HookInput, HookOutput};
+
+mod config;
+mod mux;
+#[cfg(test)]
+mod tests;
+
+use config::MuxConfig;
+
+fn main() {
+    claude_hooks_sdk::run("Hook mux", run);
+}
+
+fn run(input: HookInput) -> Result<HookOutput> {
+    let config = MuxConfig::load(Some(input.project_dir()))?;
+    let mut output = mux::dispatch(&config.modules, &input);
+    if !config.warnings.is_empty() {
+        let notes = output.system_message.take().into_iter().chain(config.warnings.iter().map(|warning| format!("hook-mux: {}", warning)));
+        output.system_message = Some(notes.collect::<Vec<_>>().join("\n"));
+    }
+    Ok(output)
+}
@@ -0,0 +1,99 @@
+//! Dispatching an event to the in-process hooks and merging their answers
+
+use anyhow::Result;
+use claude_hooks_sdk::{HookInput, HookOutput};
+use serde::Deserialize;
+
+use command_replacer::config::OutputProtocol;
+
+/// A hook that can run inside the multiplexer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Module {
+    AuditLogger,
+    CommandReplacer,
+    SecurityGuard,
+}
+
+impl Module {
+    pub fn name(self) -> &'static str {
+        match self {
+            Module::AuditLogger => "audit-logger",
+            Module::CommandReplacer => "command-replacer",
+            Module::SecurityGuard => "security-guard",
+        }
+    }
+    
+    /// The hook's answer to `input`
+    pub fn run(self, input: &HookInput) -> Result<HookOutput> {
+        match self {
+            Module::AuditLogger => audit_logger::hook(input.clone()),
+            Module::CommandReplacer => {
+                let (response, _) = command_replacer::hook(input.clone());
+                let emission = response.render(OutputProtocol::LegacyDecision)?;
+                if let Some(stderr) = emission.stderr {
+                    eprintln!("{}", stderr);
+                }
+                Ok(serde_json::from_str(emission.stdout.as_deref().unwrap_or_default())?)
+            }
+            Module::SecurityGuard => security_guard::hook(input.clone()),
+        }
+    }
+}
+
+/// Run `modules` in order on `input` and merge their answers. Once a hook
+/// has blocked, command-replacer is skipped: there is nothing left to
+/// rewrite.
+pub fn dispatch(modules: &[Module], input: &HookInput) -> HookOutput {
+    let mut outputs: Vec<HookOutput> = Vec::new();
+    for &module in modules {
+        if module == Module::CommandReplacer && outputs.iter().any(HookOutput::is_block) {
+            continue;
+        }
+        let output = module.run(input).unwrap_or_else(|e| {
+            eprintln!("Hook error: {}", e);
+            HookOutput::error(module.name(), e)
+        });
+        outputs.push(output);
+    }
+    merge(&outputs)
+}
+
+/// The most severe decision wins (block, then ask); messages and system
+/// messages are joined by newlines and `additional_context` by blank lines,
+/// while other context keys from later hooks override earlier ones
+pub fn merge(outputs: &[HookOutput]) -> HookOutput {
+    let mut merged = HookOutput::approve();
+    let mut messages = Vec::new();
+    let mut notes = Vec::new();
+    let mut additional = Vec::new();
+    let mut context = serde_json::Map::new();
+    
+    for output in outputs {
+        merged.decision = merged.decision.max(output.decision);
+        if let Some(message) = output.message.as_ref().filter(|message| !message.is_empty()) {
+            messages.push(message.clone());
+        }
+        notes.extend(output.system_message.clone());
+        merged.suppress_output |= output.suppress_output;
+        let Some(fields) = output.context.as_ref().and_then(|context| context.as_object()) else {
+            continue;
+        };
+        for (key, value) in fields {
+            match (key.as_str(), value.as_str()) {
+                ("additional_context", Some(text)) => additional.push(text.to_string()),
+                _ => {
+                    context.insert(key.clone(), value.clone());
+                }
+            }
+        }
+    }
+    if !additional.is_empty() {
+        context.insert("additional_context".to_string(), additional.join("\n\n").into());
+    }
+    
+    merged.message = (!messages.is_empty()).then(|| messages.join("\n"));
+    merged.system_message = (!notes.is_empty()).then(|| notes.join("\n"));
+    merged.context = (!context.is_empty()).then_some(serde_json::Value::Object(context));
+    merged
+}
@@ -0,0 +1,89 @@
+//! Tests for decision merging
+
+use claude_hooks_sdk::{Decision, HookInput, HookOutput};
+use serde_json::json;
+
+use crate::config::MuxConfig;
+use crate::mux::{dispatch, merge, Module};
+
+fn output(value: serde_json::Value) -> HookOutput {
+    serde_json::from_value(value).unwrap()
+}
+
+#[test]
+fn test_merge_block_wins_and_contexts_concatenate() {
+    let merged = merge(&[
+        output(json!({ "decision": "approve" })),
+        output(json!({
+            "decision": "approve",
+            "message": "Using `rg` instead of `grep`",
+            "context": { "modified_command": "rg foo", "additional_context": "first" },
+        })),
+        output(json!({
+            "decision": "block",
+            "message": "no",
+            "context": { "additional_context": "second" },
+        })),
+    ]);
+    
+    assert_eq!(merged.decision, Decision::Block);
+    assert_eq!(merged.message.as_deref(), Some("Using `rg` instead of `grep`\nno"));
+    let context = merged.context.unwrap();
+    assert_eq!(context["additional_context"], "first\n\nsecond");
+    assert_eq!(context["modified_command"], "rg foo");
+    
+    let quiet = merge(&[HookOutput::approve()]);
+    assert_eq!(serde_json::to_value(&quiet).unwrap(), json!({ "decision": "approve" }));
+}
+
+#[test]
+fn test_blocked_commands_are_not_rewritten() {
+    let project = std::env::temp_dir().join(format!("hook-mux-{}", std::process::id()));
+    std::fs::create_dir_all(&project).unwrap();
+    let input = json!({
+        "session": { "id": "mux-test", "projectDir": project },
+        "event": { "type": "PreToolUse", "data": { "command": "grep -r x / && rm -rf /" } },
+    })
+    .to_string();
+    let input = HookInput::parse(&input).unwrap();
+    
+    let output = dispatch(&[Module::SecurityGuard, Module::CommandReplacer], &input);
+    assert_eq!(output.decision, Decision::Block);
+    assert!(output.message.unwrap().contains("rm-rf-root"));
+    assert!(output.context.is_none());
+    
+    std::fs::remove_dir_all(&project).unwrap();
+}
+
+#[test]
+fn test_module_order_config() {
+    let mut config = MuxConfig::default();
+    assert_eq!(config.modules.first(), Some(&Module::SecurityGuard));
+    
+    config.merge_toml(r#"modules = ["audit-logger", "command-replacer"]"#).unwrap();
+    assert_eq!(config.modules, vec![Module::AuditLogger, Module::CommandReplacer]);
+    assert!(config.merge_toml(r#"modules = ["unknown-hook"]"#).is_err());
+}
+
+#[test]
+fn test_project_can_only_add_modules() {
+    let project = std::env::temp_dir().join(format!("hook-mux-project-{}", std::process::id()));
+    std::fs::create_dir_all(project.join(".claude")).unwrap();
+    
+    // Dropping the guards from the chain isn't possible from the project
+    std::fs::write(project.join(".claude/hook-mux.toml"), r#"modules = ["audit-logger", "command-replacer"]"#).unwrap();
+    let config = MuxConfig::load_from(None, Some(&project)).unwrap();
+    assert_eq!(config.modules, MuxConfig::default().modules);
+    
+    let mut config = MuxConfig { modules: vec![Module::SecurityGuard], ..MuxConfig::default() };
+    config.merge_project_toml(r#"modules = ["audit-logger", "security-guard"]"#).unwrap();
+    assert_eq!(config.modules, vec![Module::SecurityGuard, Module::AuditLogger]);
+    
+    // A broken project file leaves the chain as it was
+    std::fs::write(project.join(".claude/hook-mux.toml"), r#"modules = ["unknown-hook"]"#).unwrap();
+    let config = MuxConfig::load_from(None, Some(&project)).unwrap();
+    assert_eq!(config.modules, MuxConfig::default().modules);
+    assert_eq!(config.warnings.len(), 1);
+    
+    std::fs::remove_dir_all(&project).unwrap();
+}
@@ -0,0 +1,156 @@
+//! Linter configuration
+//!
+//! Read from `~/.claude/hooks/lint-on-edit/config.toml`, then the project's
+//! `.claude/lint-on-edit.toml`. Linters are keyed by name; a later file
+//! replaces the fields it sets.
+//!
+//! ```toml
+//! max_lines = 20
+//!
+//! [linters.eslint]
+//! enabled = false
+//!
+//! [linters.mypy]
+//! extensions = ["py"]
+//! command = ["mypy", "{file}"]
+//! ```
+//!
+//! Commands may use `{file}` (the edited file) and `{manifest}` (the
+//! nearest `Cargo.toml`).
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Linter {
+    pub enabled: bool,
+    /// File extensions handled, without the dot
+    pub extensions: Vec<String>,
+    /// Program and arguments
+    pub command: Vec<String>,
+    /// Keep only output lines that mention the edited file (for linters
+    /// that check more than the file, like clippy)
+    pub filter_to_file: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LintConfig {
+    /// Turn the hook off entirely
+    pub enabled: bool,
+    /// How long one linter run may take
+    pub timeout_ms: u64,
+    /// Most diagnostic lines passed on to Claude
+    pub max_lines: usize,
+    pub linters: BTreeMap<String, Linter>,
+}
+
+impl Default for LintConfig {
+    fn default() -> Self {
+        let linter = |extensions: &[&str], command: &[&str], filter_to_file: bool| Linter {
+            enabled: true,
+            extensions: extensions.iter().map(|s| s.to_string()).collect(),
+            command: command.iter().map(|s| s.to_string()).collect(),
+            filter_to_file,
+        };
+        
+        let mut linters = BTreeMap::new();
+        linters.insert("clippy".to_string(), linter(
+            &["rs"],
+            &["cargo", "clippy", "--no-deps", "--quiet", "--message-format=short", "--manifest-path", "{manifest}"],
+            true,
+        ));
+        linters.insert("eslint".to_string(), linter(
+            &["js", "jsx", "mjs", "cjs", "ts", "tsx"],
+            &["eslint", "--cache", "--format", "unix", "{file}"],
+            false,
+        ));
+        linters.insert("ruff".to_string(), linter(
+            &["py"],
+            &["ruff", "check", "--output-format", "concise", "{file}"],
+            false,
+        ));
+        
+        Self {
+            enabled: true,
+            timeout_ms: 60_000,
+            max_lines: 30,
+            linters,
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigLayer {
+    enabled: Option<bool>,
+    timeout_ms: Option<u64>,
+    max_lines: Option<usize>,
+    #[serde(default)]
+    linters: BTreeMap<String, LinterLayer>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LinterLayer {
+    enabled: Option<bool>,
+    extensions: Option<Vec<String>>,
+    command: Option<Vec<String>>,
+    filter_to_file: Option<bool>,
+}
+
+impl LintConfig {
+    /// Load the global config, then layer the project's on top
+    pub fn load(project_dir: Option<&Path>) -> Result<Self> {
+        let global = dirs::home_dir().map(|home| home.join(".claude/hooks/lint-on-edit/config.toml"));
+        let project = project_dir.map(|dir| dir.join(".claude").join("lint-on-edit.toml"));
+        
+        let mut config = Self::default();
+        for path in global.into_iter().chain(project) {
+            if path.is_file() {
+                let content = std::fs::read_to_string(&path)
+                    .with_context(|| format!("Failed to read {}", path.display()))?;
+                config.merge_toml(&content)
+                    .with_context(|| format!("Failed to parse {}", path.display()))?;
+            }
+        }
+        Ok(config)
+    }
+    
+    /// Layer one config file's contents on top of this config
+    pub fn merge_toml(&mut self, content: &str) -> Result<()> {
+        let layer: ConfigLayer = toml::from_str(content)?;
+        self.enabled = layer.enabled.unwrap_or(self.enabled);
+        self.timeout_ms = layer.timeout_ms.unwrap_or(self.timeout_ms);
+        self.max_lines = layer.max_lines.unwrap_or(self.max_lines);
+        
+        for (name, update) in layer.linters {
+            let existing = self.linters.get(&name).cloned();
+            let command = update.command
+                .or_else(|| existing.as_ref().map(|l| l.command.clone()))
+                .with_context(|| format!("Linter '{}' needs a command", name))?;
+            let linter = Linter {
+                enabled: update.enabled.or(existing.as_ref().map(|l| l.enabled)).unwrap_or(true),
+                extensions: update.extensions
+                    .or_else(|| existing.as_ref().map(|l| l.extensions.clone()))
+                    .unwrap_or_default(),
+                command,
+                filter_to_file: update.filter_to_file
+                    .or(existing.as_ref().map(|l| l.filter_to_file))
+                    .unwrap_or(false),
+            };
+            self.linters.insert(name, linter);
+        }
+        Ok(())
+    }
+    
+    /// The enabled linter for `file`, by extension
+    pub fn linter_for(&self, file: &Path) -> Option<(&str, &Linter)> {
+        let extension = file.extension()?.to_str()?;
+        self.linters
+            .iter()
+            .find(|(_, linter)| linter.enabled && linter.extensions.iter().any(|e| e.eq_ignore_ascii_case(extension)))
+            .map(|(name, linter)| (name.as_str(), linter))
+    }
+}
@@ -0,0 +1,147 @@
+//! Running a linter and collecting the diagnostics that concern one file
+
+use anyhow::{Context, Result};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+use crate::config::LintConfig;
+
+/// Diagnostics for one file
+#[derive(Debug, Clone, PartialEq)]
+pub struct LintReport {
+    pub linter: String,
+    pub file: PathBuf,
+    pub lines: Vec<String>,
+    /// Lines dropped to stay within `max_lines`
+    pub truncated: usize,
+}
+
+impl fmt::Display for LintReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "lint-on-edit: {} reported problems in {}:", self.linter, self.file.display())?;
+        for line in &self.lines {
+            writeln!(f, "  {}", line)?;
+        }
+        if self.truncated > 0 {
+            writeln!(f, "  ... and {} more", self.truncated)?;
+        }
+        Ok(())
+    }
+}
+
+/// Lint `file`; `None` when it has no linter, the linter isn't installed,
+/// or nothing was reported
+pub fn lint_file(config: &LintConfig, project_dir: &Path, file: &Path) -> Result<Option<LintReport>> {
+    if !config.enabled || !file.is_file() {
+        return Ok(None);
+    }
+    let Some((name, linter)) = config.linter_for(file) else {
+        return Ok(None);
+    };
+    let Some((program, args)) = linter.command.split_first() else {
+        return Ok(None);
+    };
+    if which::which(program).is_err() {
+        return Ok(None);
+    }
+    
+    let manifest = nearest_manifest(file);
+    let uses_manifest = linter.command.iter().any(|arg| arg.contains("{manifest}"));
+    if uses_manifest && manifest.is_none() {
+        return Ok(None);
+    }
+    let file_arg = file.display().to_string();
+    let manifest_arg = manifest.as_ref().map(|m| m.display().to_string()).unwrap_or_default();
+    
+    let mut command = Command::new(program);
+    command
+        .args(args.iter().map(|arg| arg.replace("{file}", &file_arg).replace("{manifest}", &manifest_arg)))
+        .current_dir(project_dir)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    
+    let Some(output) = run_with_timeout(command, Duration::from_millis(config.timeout_ms))? else {
+        return Ok(Some(LintReport {
+            linter: name.to_string(),
+            file: file.to_path_buf(),
+            lines: vec![format!("timed out after {} ms", config.timeout_ms)],
+            truncated: 0,
+        }));
+    };
+    
+    // Paths the linter might print for this file
+    let keys: Vec<String> = [Some(project_dir), manifest.as_deref().and_then(Path::parent)]
+        .into_iter()
+        .flatten()
+        .filter_map(|base| file.strip_prefix(base).ok())
+        .map(|relative| relative.display().to_string())
+        .chain(std::iter::once(file_arg.clone()))
+        .collect();
+    let mut lines: Vec<String> = output
+        .lines()
+        .map(str::trim_end)
+        .filter(|line| !line.trim().is_empty())
+        .filter(|line| !linter.filter_to_file || keys.iter().any(|key| line.contains(key.as_str())))
+        .map(str::to_string)
+        .collect();
+    if lines.is_empty() {
+        return Ok(None);
+    }
+    
+    let truncated = lines.len().saturating_sub(config.max_lines);
+    lines.truncate(config.max_lines);
+    Ok(Some(LintReport {
+        linter: name.to_string(),
+        file: file.to_path_buf(),
+        lines,
+        truncated,
+    }))
+}
+
+/// The closest `Cargo.toml` above `file`
+pub fn nearest_manifest(file: &Path) -> Option<PathBuf> {
+    file.ancestors()
+        .skip(1)
+        .map(|dir| dir.join("Cargo.toml"))
+        .find(|manifest| manifest.is_file())
+}
+
+/// Run to completion, returning combined stdout and stderr, or `None`
+/// (after killing it) if it outlives `timeout`. The exit status is ignored:
+/// linters fail whenever they report something.
+fn run_with_timeout(mut command: Command, timeout: Duration) -> Result<Option<String>> {
+    let mut child = command.spawn().context("Failed to start linter")?;
+    
+    // Drain both pipes on threads so a chatty linter can't fill one and stall
+    let drain = |pipe: Option<Box<dyn std::io::Read + Send>>| {
+        std::thread::spawn(move || {
+            let mut text = String::new();
+            if let Some(mut pipe) = pipe {
+                let _ = pipe.read_to_string(&mut text);
+            }
+            text
+        })
+    };
+    let stdout = drain(child.stdout.take().map(|p| Box::new(p) as Box<dyn std::io::Read + Send>));
+    let stderr = drain(child.stderr.take().map(|p| Box::new(p) as Box<dyn std::io::Read + Send>));
+    
+    let started = Instant::now();
+    loop {
+        if child.try_wait()?.is_some() {
+            break;
+        }
+        if started.elapsed() >= timeout {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Ok(None);
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    
+    let mut output = stdout.join().unwrap_or_default();
+    output.push_str(&stderr.join().unwrap_or_default());
+    Ok(Some(output))
+}
@@ -0,0 +1,46 @@
+//! Lint-on-Edit PostToolUse Hook
+//!
+//! After Write, Edit, or MultiEdit, runs a fast linter scoped to the
+//! touched file and hands its diagnostics to Claude, so problems are fixed
+//! before the next build:
+//! - `.rs` → `cargo clippy --no-deps` on the enclosing crate, filtered to the file
+//! - `.js`, `.ts`, ... → `eslint --cache`
+//! - `.py` → `ruff check`
+
+use anyhow::Result;
+use claude_hooks_sdk::{HookInput, HookOutput};
+use serde::Deserialize;
+
+mod config;
+mod linter;
+#[cfg(test)]
+mod tests;
+
+use config::LintConfig;
+
+/// Tool data of a file-editing tool
+#[derive(Debug, Default, Deserialize)]
+struct EditToolData {
+    #[serde(default)]
+    file_path: Option<String>,
+}
+
+fn main() {
+    claude_hooks_sdk::run("Lint-on-edit", run);
+}
+
+fn run(input: HookInput) -> Result<HookOutput> {
+    if input.event.event_type != "PostToolUse" {
+        return Ok(HookOutput::approve());
+    }
+    let tool_data: EditToolData = input.event.tool_data().unwrap_or_default();
+    let Some(file_path) = tool_data.file_path else {
+        return Ok(HookOutput::approve());
+    };
+    
+    let project_dir = input.project_dir();
+    let config = LintConfig::load(Some(project_dir))?;
+    let report = linter::lint_file(&config, project_dir, &project_dir.join(&file_path))?;
+    
+    Ok(report.map_or_else(HookOutput::approve, |report| HookOutput::additional_context(report.to_string())))
+}
@@ -0,0 +1,72 @@
+//! Tests for linter selection and diagnostic filtering
+
+use std::path::Path;
+
+use crate::config::LintConfig;
+use crate::linter::{lint_file, nearest_manifest};
+
+/// A config whose only linter is a shell snippet, so tests don't depend on
+/// installed linters
+fn shell_linter(script: &str, filter_to_file: bool) -> LintConfig {
+    let mut config = LintConfig::default();
+    config.linters.clear();
+    config.merge_toml(&format!(
+        "[linters.fake]\nextensions = [\"txt\"]\nfilter_to_file = {}\ncommand = [\"sh\", \"-c\", {:?}, \"sh\", \"{{file}}\"]\n",
+        filter_to_file,
+        script,
+    )).unwrap();
+    config
+}
+
+fn temp_project(name: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("lint-on-edit-{}-{}", name, std::process::id()));
+    std::fs::create_dir_all(dir.join("src")).unwrap();
+    dir
+}
+
+#[test]
+fn test_linter_selection() {
+    let config = LintConfig::default();
+    assert_eq!(config.linter_for(Path::new("src/lib.rs")).unwrap().0, "clippy");
+    assert_eq!(config.linter_for(Path::new("app.tsx")).unwrap().0, "eslint");
+    assert_eq!(config.linter_for(Path::new("tool.py")).unwrap().0, "ruff");
+    assert!(config.linter_for(Path::new("README.md")).is_none());
+    
+    let mut config = LintConfig::default();
+    config.merge_toml("[linters.ruff]\nenabled = false").unwrap();
+    assert!(config.linter_for(Path::new("tool.py")).is_none());
+}
+
+#[test]
+fn test_diagnostics_are_reported() {
+    let dir = temp_project("report");
+    let file = dir.join("src/notes.txt");
+    std::fs::write(&file, "text").unwrap();
+    
+    let mut config = shell_linter("printf 'a:1: bad\\nb:2: worse\\nc:3: worst\\n'; exit 1", false);
+    config.max_lines = 2;
+    let report = lint_file(&config, &dir, &file).unwrap().unwrap();
+    assert_eq!(report.lines, vec!["a:1: bad", "b:2: worse"]);
+    assert_eq!(report.truncated, 1);
+    assert!(report.to_string().contains("... and 1 more"));
+    
+    // A clean run adds nothing
+    assert!(lint_file(&shell_linter("true", false), &dir, &file).unwrap().is_none());
+    
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_crate_wide_output_is_filtered_to_the_file() {
+    let dir = temp_project("filter");
+    std::fs::write(dir.join("Cargo.toml"), "[package]").unwrap();
+    let file = dir.join("src/notes.txt");
+    std::fs::write(&file, "text").unwrap();
+    assert_eq!(nearest_manifest(&file), Some(dir.join("Cargo.toml")));
+    
+    let config = shell_linter("echo 'src/other.txt:1:1: warning: elsewhere'; echo 'src/notes.txt:4:2: warning: here' >&2", true);
+    let report = lint_file(&config, &dir, &file).unwrap().unwrap();
+    assert_eq!(report.lines, vec!["src/notes.txt:4:2: warning: here"]);
+    
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,104 @@
+//! Guard configuration
+//!
+//! Read from `~/.claude/hooks/path-guard/config.toml`, then the project's
+//! `.claude/path-guard.toml`. The project file is checked in with the code,
+//! so it can only tighten the guard: its `allow` is ignored, and it can turn
+//! `default_allow` off and `check_reads` on but not the other way round. A
+//! project file that doesn't parse is ignored with a warning.
+//!
+//! ```toml
+//! # Directories outside the project that may still be modified
+//! allow = ["~/.cache/pip", "/var/tmp"]
+//! # Also block commands that only read outside the project
+//! check_reads = true
+//! ```
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::path::Path;
+
+/// Always allowed unless `default_allow = false`
+pub const DEFAULT_ALLOW: &[&str] = &["/tmp", "/dev/null", "/dev/stdout", "/dev/stderr"];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GuardConfig {
+    /// Extra directories (or files) that may be touched
+    pub allow: Vec<String>,
+    /// Include [`DEFAULT_ALLOW`] and the system temp directory
+    pub default_allow: bool,
+    /// Check every path-like argument, not just the ones being modified
+    pub check_reads: bool,
+    /// Problems with the files merged, to pass on to the user
+    pub warnings: Vec<String>,
+}
+
+impl Default for GuardConfig {
+    fn default() -> Self {
+        Self {
+            allow: Vec::new(),
+            default_allow: true,
+            check_reads: false,
+            warnings: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigLayer {
+    #[serde(default)]
+    allow: Vec<String>,
+    default_allow: Option<bool>,
+    check_reads: Option<bool>,
+}
+
+impl GuardConfig {
+    /// Load the global config, then layer the project's on top
+    pub fn load(project_dir: Option<&Path>) -> Result<Self> {
+        let global = dirs::home_dir().map(|home| home.join(".claude/hooks/path-guard/config.toml"));
+        Self::load_from(global.as_deref(), project_dir)
+    }
+    
+    /// [`Self::load`] with the global config given. A project file that
+    /// doesn't parse is treated as missing, with a warning, rather than
+    /// failing the hook, which would approve the call.
+    pub fn load_from(global: Option<&Path>, project_dir: Option<&Path>) -> Result<Self> {
+        let project = project_dir.map(|dir| dir.join(".claude").join("path-guard.toml"));
+        
+        let read = |path: &Path| {
+            std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))
+        };
+        let mut config = Self::default();
+        if let Some(path) = global.filter(|path| path.is_file()) {
+            config.merge_toml(&read(path)?)
+                .with_context(|| format!("Failed to parse {}", path.display()))?;
+        }
+        if let Some(path) = project.filter(|path| path.is_file()) {
+            let merged = read(&path).and_then(|content| {
+                config.merge_project_toml(&content)
+                    .with_context(|| format!("Failed to parse {}", path.display()))
+            });
+            if let Err(e) = merged {
+                config.warnings.push(format!("Ignoring the project's config: {:#}", e));
+            }
+        }
+        Ok(config)
+    }
+    
+    /// Layer one config file's contents on top of this config
+    pub fn merge_toml(&mut self, content: &str) -> Result<()> {
+        let layer: ConfigLayer = toml::from_str(content)?;
+        self.allow.extend(layer.allow);
+        self.default_allow = layer.default_allow.unwrap_or(self.default_allow);
+        self.check_reads = layer.check_reads.unwrap_or(self.check_reads);
+        Ok(())
+    }
+    
+    /// Layer a project config file on top, taking only what tightens the guard
+    pub fn merge_project_toml(&mut self, content: &str) -> Result<()> {
+        let layer: ConfigLayer = toml::from_str(content)?;
+        self.default_allow &= layer.default_allow.unwrap_or(true);
+        self.check_reads |= layer.check_reads.unwrap_or(false);
+        Ok(())
+    }
+}
@@ -0,0 +1,68 @@
+//! Path Guard PreToolUse Hook
+//!
+//! Resolves every path a Write, Edit, MultiEdit, NotebookEdit, or Bash call
+//! would modify (and, with `check_reads`, every path a Read or Bash call
+//! reads) and blocks the call if any of them falls outside the session's
+//! project directory or the configured allowlist (`/tmp` by default).
+
+use anyhow::Result;
+use claude_hooks_sdk::{HookInput, HookOutput};
+use serde::Deserialize;
+use std::path::Path;
+
+mod config;
+mod paths;
+mod shell;
+#[cfg(test)]
+mod tests;
+
+use config::GuardConfig;
+use paths::Guard;
+
+/// The fields of Bash and file-editing tool data this hook looks at
+#[derive(Debug, Default, Deserialize)]
+struct ToolData {
+    #[serde(default)]
+    command: Option<String>,
+    #[serde(default)]
+    file_path: Option<String>,
+    #[serde(default)]
+    notebook_path: Option<String>,
+}
+
+fn main() {
+    claude_hooks_sdk::run("Path guard", run);
+}
+
+fn run(input: HookInput) -> Result<HookOutput> {
+    if input.event.event_type != "PreToolUse" {
+        return Ok(HookOutput::approve());
+    }
+    let tool_data: ToolData = input.event.tool_data().unwrap_or_default();
+    
+    let project_dir = input.project_dir().to_path_buf();
+    let config = GuardConfig::load(Some(&project_dir))?;
+    let guard = Guard::new(&project_dir, &config);
+    
+    let tool_name = input.event.tool_name.as_deref().unwrap_or_default();
+    let file_path = tool_data.file_path.as_ref().or(tool_data.notebook_path.as_ref());
+    let outside = match (tool_name, &tool_data.command, file_path) {
+        ("Bash", Some(command), _) => guard.check_command(command),
+        (_, _, Some(file_path)) => guard.check_file_tool(tool_name, &project_dir, Path::new(file_path)),
+        _ => None,
+    };
+    
+    let output = match outside {
+        Some((operation, path)) => HookOutput::block(format!(
+            "path-guard: `{}` would touch {}, which is outside the project ({}) and not in the allowlist",
+            operation,
+            path.display(),
+            project_dir.display(),
+        )),
+        None => HookOutput::approve(),
+    };
+    if config.warnings.is_empty() {
+        return Ok(output);
+    }
+    Ok(output.with_system_message(format!("path-guard: {}", config.warnings.join("\n"))))
+}
@@ -0,0 +1,126 @@
+//! Path resolution and the inside/outside decision
+
+use std::path::{Component, Path, PathBuf};
+
+use crate::config::{GuardConfig, DEFAULT_ALLOW};
+use crate::shell;
+
+/// Resolved roots a path must fall under
+pub struct Guard {
+    roots: Vec<PathBuf>,
+    check_reads: bool,
+}
+
+impl Guard {
+    pub fn new(project_dir: &Path, config: &GuardConfig) -> Self {
+        let mut roots = vec![resolve(project_dir, project_dir)];
+        if config.default_allow {
+            roots.extend(DEFAULT_ALLOW.iter().map(|allowed| resolve(project_dir, Path::new(allowed))));
+            roots.push(resolve(project_dir, &std::env::temp_dir()));
+        }
+        roots.extend(config.allow.iter().map(|allowed| resolve(project_dir, Path::new(allowed))));
+        
+        Self { roots, check_reads: config.check_reads }
+    }
+    
+    /// The resolved path, if `path` (relative to `cwd`) escapes every root
+    pub fn outside(&self, cwd: &Path, path: &Path) -> Option<PathBuf> {
+        let resolved = resolve(cwd, path);
+        (!self.roots.iter().any(|root| resolved.starts_with(root))).then_some(resolved)
+    }
+    
+    /// The operation and resolved path, if a file tool's call on `path`
+    /// escapes every root; Read only counts with `check_reads`
+    pub fn check_file_tool(&self, tool: &str, cwd: &Path, path: &Path) -> Option<(String, PathBuf)> {
+        let operation = match tool {
+            "Write" | "Edit" | "MultiEdit" | "NotebookEdit" => "edit",
+            "Read" if self.check_reads => "read",
+            _ => return None,
+        };
+        self.outside(cwd, path).map(|path| (operation.to_string(), path))
+    }
+    
+    /// The first operation in a shell command that touches a path outside
+    /// the roots, with that path
+    pub fn check_command(&self, command: &str) -> Option<(String, PathBuf)> {
+        // None once a `cd` goes somewhere that can't be worked out statically
+        let mut cwd = Some(self.roots[0].clone());
+        let mut previous = None;
+        for segment in shell::segments(command) {
+            // `cd` changes what later relative paths mean
+            if let Some(dir) = segment.cd_target() {
+                let next = match dir {
+                    "-" => previous.clone(),
+                    dir => expand_home(dir).and_then(|dir| {
+                        let dir = Path::new(&dir);
+                        let base = if dir.is_absolute() { Some(dir) } else { cwd.as_deref() };
+                        base.map(|base| resolve(base, dir))
+                    }),
+                };
+                previous = std::mem::replace(&mut cwd, next);
+                continue;
+            }
+            for target in segment.targets(self.check_reads) {
+                let Some(target) = expand_home(&target) else {
+                    continue;
+                };
+                let target = Path::new(&target);
+                let outside = match &cwd {
+                    Some(cwd) => self.outside(cwd, target),
+                    None if target.is_absolute() => self.outside(target, target),
+                    // Relative to a directory nobody knows, so not provably inside
+                    None => Some(target.to_path_buf()),
+                };
+                if let Some(path) = outside {
+                    return Some((segment.program().to_string(), path));
+                }
+            }
+        }
+        None
+    }
+}
+
+/// Expand a leading `~` or `$HOME`; other variables can't be resolved
+/// statically, so those words are skipped
+fn expand_home(word: &str) -> Option<String> {
+    let home = || dirs::home_dir().map(|home| home.display().to_string());
+    for prefix in ["~", "$HOME", "${HOME}"] {
+        if let Some(rest) = word.strip_prefix(prefix) {
+            if rest.is_empty() || rest.starts_with('/') {
+                return Some(format!("{}{}", home()?, rest));
+            }
+        }
+    }
+    (!word.contains('$') && !word.contains('`')).then(|| word.to_string())
+}
+
+/// Absolute, normalized form of `path` relative to `base`. `..` is applied
+/// lexically, then the deepest existing ancestor is canonicalized so a
+/// symlink can't smuggle a path out of the project.
+pub fn resolve(base: &Path, path: &Path) -> PathBuf {
+    let joined = if path.is_absolute() { path.to_path_buf() } else { base.join(path) };
+    
+    let mut normalized = PathBuf::new();
+    for component in joined.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other),
+        }
+    }
+    
+    let mut existing = normalized.as_path();
+    let mut rest = Vec::new();
+    while !existing.exists() {
+        let (Some(parent), Some(name)) = (existing.parent(), existing.file_name()) else {
+            return normalized;
+        };
+        rest.push(name.to_os_string());
+        existing = parent;
+    }
+    let mut resolved = existing.canonicalize().unwrap_or_else(|_| existing.to_path_buf());
+    resolved.extend(rest.iter().rev());
+    resolved
+}
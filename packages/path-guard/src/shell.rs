@@ -0,0 +1,131 @@
+//! Just enough shell parsing to find the paths a command modifies
+//!
+//! Commands are split into simple commands at `;`, `&&`, `||`, `|`, and `&`.
+//! Within each, redirection targets and the file operands of known
+//! modifying commands (`rm`, `mv`, `cp`, `touch`, ...) are reported.
+
+use claude_hooks_sdk::shell::{self, SimpleCommand};
+
+/// One simple command: its words and any redirection targets
+#[derive(Debug, Default, PartialEq)]
+pub struct Segment {
+    pub words: Vec<String>,
+    pub redirects: Vec<String>,
+    /// Files read through `<`
+    pub inputs: Vec<String>,
+}
+
+impl From<SimpleCommand> for Segment {
+    fn from(command: SimpleCommand) -> Self {
+        Self {
+            words: command.words,
+            redirects: command.outputs,
+            inputs: command.inputs,
+        }
+    }
+}
+
+/// Wrappers that run the rest of the words as a command
+const PREFIXES: &[&str] = &["sudo", "doas", "env", "nohup", "time", "nice", "command", "exec"];
+
+impl Segment {
+    /// Words with leading wrappers (`sudo`, `env FOO=1`, ...) removed
+    fn command_words(&self) -> &[String] {
+        let mut words = self.words.as_slice();
+        while let Some(first) = words.first() {
+            let is_assignment = first.contains('=') && !first.starts_with('-') && !first.starts_with('=');
+            if PREFIXES.contains(&first.as_str()) || is_assignment || (first.starts_with('-') && words.len() < self.words.len()) {
+                words = &words[1..];
+            } else {
+                break;
+            }
+        }
+        words
+    }
+    
+    pub fn program(&self) -> &str {
+        self.command_words().first().map_or("", String::as_str)
+    }
+    
+    /// The directory of a `cd`, if this is one; a bare `cd` goes home
+    pub fn cd_target(&self) -> Option<&str> {
+        match self.command_words() {
+            [cd] if cd == "cd" => Some("~"),
+            [cd, dir] if cd == "cd" => Some(dir),
+            _ => None,
+        }
+    }
+    
+    /// Paths this command writes, or every path-like operand with
+    /// `check_reads`
+    pub fn targets(&self, check_reads: bool) -> Vec<String> {
+        let words = self.command_words();
+        let Some((program, args)) = words.split_first() else {
+            return self.redirects.clone();
+        };
+        let operands: Vec<&String> = args.iter().filter(|arg| !arg.starts_with('-')).collect();
+        
+        let mut targets: Vec<String> = match program.as_str() {
+            "rm" | "rmdir" | "mkdir" | "touch" | "mv" | "tee" | "shred" | "unlink" => {
+                operands.iter().map(|s| s.to_string()).collect()
+            }
+            // Copies only modify their destination
+            "cp" | "ln" | "install" | "rsync" | "scp" => destination(args).into_iter().collect(),
+            // The first operand is the mode/owner
+            "chmod" | "chown" | "chgrp" => operands.iter().skip(1).map(|s| s.to_string()).collect(),
+            "truncate" => operands_after_value_flags(args, &["-s", "--size", "-r", "--reference"]),
+            "dd" => args.iter().filter_map(|arg| arg.strip_prefix("of=")).map(str::to_string).collect(),
+            "sed" | "perl" if args.iter().any(|arg| arg.starts_with("-i")) => {
+                // The script is the first operand unless given with -e
+                let skip = usize::from(!args.iter().any(|arg| arg == "-e"));
+                operands.iter().skip(skip).map(|s| s.to_string()).collect()
+            }
+            _ if check_reads => operands.iter().filter(|arg| looks_like_path(arg)).map(|s| s.to_string()).collect(),
+            _ => Vec::new(),
+        };
+        targets.extend(self.redirects.iter().cloned());
+        if check_reads {
+            targets.extend(self.inputs.iter().filter(|input| looks_like_path(input)).cloned());
+        }
+        targets
+    }
+}
+
+/// Destination of a copy: `-t DIR` / `--target-directory=DIR`, else the
+/// last operand
+fn destination(args: &[String]) -> Option<String> {
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        if arg == "-t" || arg == "--target-directory" {
+            return iter.next().cloned();
+        }
+        if let Some(dir) = arg.strip_prefix("--target-directory=") {
+            return Some(dir.to_string());
+        }
+    }
+    let operands: Vec<&String> = args.iter().filter(|arg| !arg.starts_with('-')).collect();
+    // A single operand is a source only (e.g. `ln -s target`)
+    (operands.len() >= 2).then(|| operands[operands.len() - 1].clone())
+}
+
+fn operands_after_value_flags(args: &[String], value_flags: &[&str]) -> Vec<String> {
+    let mut operands = Vec::new();
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        if value_flags.contains(&arg.as_str()) {
+            iter.next();
+        } else if !arg.starts_with('-') {
+            operands.push(arg.clone());
+        }
+    }
+    operands
+}
+
+fn looks_like_path(word: &str) -> bool {
+    word.contains('/') || word.starts_with('~') || word.starts_with('.')
+}
+
+/// Split `command` into simple commands
+pub fn segments(command: &str) -> Vec<Segment> {
+    shell::parse(command).into_iter().map(Segment::from).collect()
+}
@@ -0,0 +1,145 @@
+//! Tests for path resolution and shell target extraction
+
+use std::path::{Path, PathBuf};
+
+use crate::config::GuardConfig;
+use crate::paths::{resolve, Guard};
+use crate::shell::segments;
+
+fn project() -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("path-guard-project-{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("src")).unwrap();
+    resolve(&dir, &dir)
+}
+
+/// A guard whose only root is the project, so the temp dir the project
+/// lives in doesn't count as allowed
+fn strict_guard(project: &Path) -> Guard {
+    Guard::new(project, &GuardConfig { default_allow: false, ..GuardConfig::default() })
+}
+
+#[test]
+fn test_resolve_normalizes_paths() {
+    let project = project();
+    assert_eq!(resolve(&project, Path::new("src/../README.md")), project.join("README.md"));
+    assert_eq!(resolve(&project, Path::new("./new/dir/file")), project.join("new/dir/file"));
+    assert_eq!(resolve(&project, Path::new("../../..")).parent(), None);
+}
+
+#[test]
+fn test_shell_segments() {
+    let parsed = segments("cd src && echo 'a > b' > out.txt 2>&1; cat x | tee -a log 2> err");
+    assert_eq!(parsed.len(), 4);
+    assert_eq!(parsed[0].words, vec!["cd", "src"]);
+    assert_eq!(parsed[1].words, vec!["echo", "a > b"]);
+    assert_eq!(parsed[1].redirects, vec!["out.txt"]);
+    assert_eq!(parsed[3].words, vec!["tee", "-a", "log"]);
+    assert_eq!(parsed[3].redirects, vec!["err"]);
+    
+    assert_eq!(parsed[3].targets(false), vec!["log", "err"]);
+    assert_eq!(segments("cp -r a b /opt/dest")[0].targets(false), vec!["/opt/dest"]);
+    assert_eq!(segments("sudo chmod 644 /etc/hosts")[0].targets(false), vec!["/etc/hosts"]);
+    assert_eq!(segments("sed -i 's/a/b/' f.txt")[0].targets(false), vec!["f.txt"]);
+    assert!(segments("cat /etc/hosts")[0].targets(false).is_empty());
+    assert_eq!(segments("cat /etc/hosts")[0].targets(true), vec!["/etc/hosts"]);
+}
+
+#[test]
+fn test_commands_outside_project_are_caught() {
+    let project = project();
+    let guard = strict_guard(&project);
+    
+    for (command, program) in [
+        ("rm -rf ../sibling", "rm"),
+        ("echo hi > /etc/motd", "echo"),
+        ("cd .. && touch escaped", "touch"),
+        ("cp notes.txt /opt/notes.txt", "cp"),
+        ("sudo mv src ~/elsewhere", "mv"),
+        // A `cd` that can't be followed leaves relative paths unknown
+        ("cd $X && rm -rf ../..", "rm"),
+        ("cd \"$(mktemp -d)\" && touch x", "touch"),
+        // A bare `cd` goes home, and `cd -` back to where the last one left
+        ("cd && rm -rf .cache", "rm"),
+        ("cd / && cd src && cd - && touch x", "touch"),
+    ] {
+        let (operation, _) = guard.check_command(command).unwrap_or_else(|| panic!("{}", command));
+        assert_eq!(operation, program, "{}", command);
+    }
+    
+    for command in [
+        "rm -rf target",
+        "mkdir -p src/new && touch src/new/mod.rs",
+        "cat /etc/hosts > hosts.copy",
+        "cp /etc/hosts .",
+        "cargo build 2>&1 | tee build.log",
+        "cd src && rm ../old.txt",
+        "cd /tmp && cd - && touch src/new.rs",
+        "cd $X && echo done",
+        "echo $SOMEWHERE > $OUT",
+    ] {
+        assert_eq!(guard.check_command(command), None, "{}", command);
+    }
+}
+
+#[test]
+fn test_allowlist() {
+    let project = project();
+    let outside = project.parent().unwrap().join("shared-cache");
+    
+    assert!(strict_guard(&project).outside(&project, &outside).is_some());
+    
+    let mut config = GuardConfig { default_allow: false, ..GuardConfig::default() };
+    config.merge_toml(&format!("allow = [{:?}]", outside.display().to_string())).unwrap();
+    let guard = Guard::new(&project, &config);
+    assert!(guard.outside(&project, &outside.join("pip/file")).is_none());
+    assert!(guard.check_command("echo x > /etc/motd").is_some());
+    
+    // A checked-in project file can't widen the allowlist, only tighten
+    let mut config = GuardConfig { check_reads: true, ..GuardConfig::default() };
+    config.merge_project_toml("allow = [\"~\", \"/\"]\ndefault_allow = true\ncheck_reads = false").unwrap();
+    assert_eq!(config, GuardConfig { check_reads: true, ..GuardConfig::default() });
+    let guard = Guard::new(&project, &config);
+    assert!(guard.check_command("rm -rf ~/.ssh").is_some());
+    config.merge_project_toml("default_allow = false").unwrap();
+    assert!(!config.default_allow);
+    
+    // /tmp is allowed by default
+    let guard = Guard::new(&project, &GuardConfig::default());
+    assert!(guard.outside(&project, Path::new("/tmp/scratch.txt")).is_none());
+    assert!(guard.check_command("echo x > /dev/null").is_none());
+}
+
+#[test]
+fn test_file_tools() {
+    let project = project();
+    let outside = Path::new("/etc/hosts");
+    
+    let guard = strict_guard(&project);
+    for tool in ["Write", "Edit", "MultiEdit", "NotebookEdit"] {
+        assert_eq!(guard.check_file_tool(tool, &project, outside).map(|(operation, _)| operation).as_deref(), Some("edit"), "{}", tool);
+    }
+    assert!(guard.check_file_tool("Edit", &project, Path::new("src/main.rs")).is_none());
+    // Reads outside the project are only checked with check_reads
+    assert!(guard.check_file_tool("Read", &project, outside).is_none());
+    let guard = Guard::new(&project, &GuardConfig { default_allow: false, check_reads: true, ..GuardConfig::default() });
+    assert_eq!(guard.check_file_tool("Read", &project, outside).map(|(operation, _)| operation).as_deref(), Some("read"));
+}
+
+#[test]
+fn test_broken_project_config_is_ignored() {
+    let project = std::env::temp_dir().join(format!("path-guard-broken-{}", std::process::id()));
+    std::fs::create_dir_all(project.join(".claude")).unwrap();
+    let global = project.join("global.toml");
+    std::fs::write(&global, "default_allow = false\n").unwrap();
+    
+    for broken in ["allow = 5", "bogus = 1", "check_reads = "] {
+        std::fs::write(project.join(".claude/path-guard.toml"), broken).unwrap();
+        let config = GuardConfig::load_from(Some(&global), Some(&project)).unwrap();
+        assert_eq!(config.warnings.len(), 1, "{}", broken);
+        assert!(!config.default_allow);
+        let guard = Guard::new(&project, &config);
+        assert!(guard.check_file_tool("Write", &project, Path::new("/etc/passwd")).is_some());
+    }
+    
+    std::fs::remove_dir_all(&project).unwrap();
+}
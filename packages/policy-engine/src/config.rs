@@ -0,0 +1,59 @@
+//! Policy configuration
+//!
+//! Read from `~/.claude/hooks/policy-engine/config.toml`, then the
+//! project's `.claude/policy-engine.toml`. Rules are checked in order and
+//! the first that applies decides; project rules come before global ones,
+//! so a project can carve out exceptions.
+//!
+//! ```toml
+//! rules = [
+//!     'deny bash when command matches "git push (-f|--force)" unless branch != "main" message "No force pushes to main"',
+//!     'ask webfetch when url starts_with "http://"',
+//! ]
+//! ```
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::path::Path;
+
+use crate::dsl::{self, Rule};
+
+#[derive(Debug, Clone, Default)]
+pub struct PolicyConfig {
+    pub rules: Vec<Rule>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigLayer {
+    #[serde(default)]
+    rules: Vec<String>,
+}
+
+impl PolicyConfig {
+    /// Load the global config, then layer the project's on top
+    pub fn load(project_dir: Option<&Path>) -> Result<Self> {
+        let global = dirs::home_dir().map(|home| home.join(".claude/hooks/policy-engine/config.toml"));
+        let project = project_dir.map(|dir| dir.join(".claude").join("policy-engine.toml"));
+        
+        let mut config = Self::default();
+        for path in global.into_iter().chain(project) {
+            if path.is_file() {
+                let content = std::fs::read_to_string(&path)
+                    .with_context(|| format!("Failed to read {}", path.display()))?;
+                config.merge_toml(&content)
+                    .with_context(|| format!("Failed to parse {}", path.display()))?;
+            }
+        }
+        Ok(config)
+    }
+    
+    /// Layer one config file's contents on top of this config; its rules
+    /// are checked before those already loaded
+    pub fn merge_toml(&mut self, content: &str) -> Result<()> {
+        let layer: ConfigLayer = toml::from_str(content)?;
+        let rules = layer.rules.iter().map(|rule| dsl::parse_rule(rule)).collect::<Result<Vec<_>>>()?;
+        self.rules.splice(0..0, rules);
+        Ok(())
+    }
+}
@@ -0,0 +1,268 @@
+//! The policy language
+//!
+//! A rule is one line:
+//!
+//! ```text
+//! deny bash when command matches "git push --force" unless branch != "main"
+//! ask webfetch when url starts_with "http://" message "Plain HTTP fetch"
+//! allow * when tool == "Read"
+//! ```
+//!
+//! - the action: `allow`, `deny`, or `ask`
+//! - the tool it applies to, matched case-insensitively, or `*` for any
+//! - optionally `when <condition>`: the rule only applies if it holds
+//! - optionally `unless <condition>`: the rule doesn't apply if it holds
+//! - optionally `message "<text>"`, shown with the decision
+//!
+//! A condition compares a field with a quoted string using `==`, `!=`,
+//! `matches` (a regex), `contains`, `starts_with`, or `ends_with`, and
+//! combines comparisons with `and`, `or`, `not`, and parentheses. Strings
+//! take `\"` and `\\` escapes.
+
+use anyhow::{anyhow, bail, Result};
+use regex::Regex;
+
+/// What a matching rule decides
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Allow,
+    Ask,
+    Deny,
+}
+
+#[derive(Debug, Clone)]
+pub enum Op {
+    Eq,
+    Ne,
+    Matches(Regex),
+    Contains,
+    StartsWith,
+    EndsWith,
+}
+
+#[derive(Debug, Clone)]
+pub enum Expr {
+    Compare { field: String, op: Op, value: String },
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+}
+
+#[derive(Debug, Clone)]
+pub struct Rule {
+    pub action: Action,
+    /// Lowercased tool name, or `*`
+    pub tool: String,
+    pub when: Option<Expr>,
+    pub unless: Option<Expr>,
+    pub message: Option<String>,
+    /// The rule as written
+    pub source: String,
+}
+
+/// Where field values come from when a condition is evaluated
+pub trait Facts {
+    fn get(&self, field: &str) -> Option<String>;
+}
+
+impl Expr {
+    /// Whether the condition holds; missing fields compare as ""
+    pub fn eval(&self, facts: &dyn Facts) -> bool {
+        match self {
+            Expr::Compare { field, op, value } => {
+                let actual = facts.get(field).unwrap_or_default();
+                match op {
+                    Op::Eq => actual == *value,
+                    Op::Ne => actual != *value,
+                    Op::Matches(regex) => regex.is_match(&actual),
+                    Op::Contains => actual.contains(value.as_str()),
+                    Op::StartsWith => actual.starts_with(value.as_str()),
+                    Op::EndsWith => actual.ends_with(value.as_str()),
+                }
+            }
+            Expr::And(left, right) => left.eval(facts) && right.eval(facts),
+            Expr::Or(left, right) => left.eval(facts) || right.eval(facts),
+            Expr::Not(inner) => !inner.eval(facts),
+        }
+    }
+}
+
+impl Rule {
+    /// Whether the rule applies to a call of `tool`
+    pub fn applies(&self, tool: &str, facts: &dyn Facts) -> bool {
+        (self.tool == "*" || self.tool.eq_ignore_ascii_case(tool))
+            && self.when.as_ref().is_none_or(|when| when.eval(facts))
+            && !self.unless.as_ref().is_some_and(|unless| unless.eval(facts))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Str(String),
+    Symbol(&'static str),
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = source.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '"' => {
+                chars.next();
+                let mut text = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(escaped @ ('"' | '\\')) => text.push(escaped),
+                            // Keep other escapes for the regex engine
+                            Some(other) => {
+                                text.push('\\');
+                                text.push(other);
+                            }
+                            None => bail!("unterminated string"),
+                        },
+                        Some(other) => text.push(other),
+                        None => bail!("unterminated string"),
+                    }
+                }
+                tokens.push(Token::Str(text));
+            }
+            '(' | ')' => {
+                chars.next();
+                tokens.push(Token::Symbol(if c == '(' { "(" } else { ")" }));
+            }
+            '=' | '!' => {
+                chars.next();
+                if chars.next() != Some('=') {
+                    bail!("expected `{}=`", c);
+                }
+                tokens.push(Token::Symbol(if c == '=' { "==" } else { "!=" }));
+            }
+            c if c.is_alphanumeric() || c == '_' || c == '*' => {
+                let mut word = String::new();
+                while let Some(&c) = chars.peek().filter(|c| c.is_alphanumeric() || matches!(c, '_' | '.' | '*' | '-')) {
+                    word.push(c);
+                    chars.next();
+                }
+                tokens.push(Token::Word(word));
+            }
+            other => bail!("unexpected `{}`", other),
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek_word(&self, word: &str) -> bool {
+        matches!(self.tokens.get(self.pos), Some(Token::Word(w)) if w == word)
+    }
+    
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+    
+    fn word(&mut self, what: &str) -> Result<String> {
+        match self.next() {
+            Some(Token::Word(word)) => Ok(word),
+            _ => bail!("expected {}", what),
+        }
+    }
+    
+    fn string(&mut self, what: &str) -> Result<String> {
+        match self.next() {
+            Some(Token::Str(text)) => Ok(text),
+            _ => bail!("expected {} as a quoted string", what),
+        }
+    }
+    
+    fn or(&mut self) -> Result<Expr> {
+        let mut expr = self.and()?;
+        while self.peek_word("or") {
+            self.pos += 1;
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+    
+    fn and(&mut self) -> Result<Expr> {
+        let mut expr = self.not()?;
+        while self.peek_word("and") {
+            self.pos += 1;
+            expr = Expr::And(Box::new(expr), Box::new(self.not()?));
+        }
+        Ok(expr)
+    }
+    
+    fn not(&mut self) -> Result<Expr> {
+        if self.peek_word("not") {
+            self.pos += 1;
+            return Ok(Expr::Not(Box::new(self.not()?)));
+        }
+        if self.tokens.get(self.pos) == Some(&Token::Symbol("(")) {
+            self.pos += 1;
+            let expr = self.or()?;
+            if self.next() != Some(Token::Symbol(")")) {
+                bail!("expected `)`");
+            }
+            return Ok(expr);
+        }
+        self.compare()
+    }
+    
+    fn compare(&mut self) -> Result<Expr> {
+        let field = self.word("a field name")?;
+        let op = match self.next() {
+            Some(Token::Symbol("==")) => Op::Eq,
+            Some(Token::Symbol("!=")) => Op::Ne,
+            Some(Token::Word(word)) if word == "matches" => {
+                let pattern = self.string("a regex")?;
+                let regex = Regex::new(&pattern).map_err(|e| anyhow!("invalid regex `{}`: {}", pattern, e))?;
+                return Ok(Expr::Compare { field, op: Op::Matches(regex), value: pattern });
+            }
+            Some(Token::Word(word)) if word == "contains" => Op::Contains,
+            Some(Token::Word(word)) if word == "starts_with" => Op::StartsWith,
+            Some(Token::Word(word)) if word == "ends_with" => Op::EndsWith,
+            _ => bail!("expected a comparison after `{}`", field),
+        };
+        Ok(Expr::Compare { field, op, value: self.string("a value")? })
+    }
+}
+
+/// Parse one rule
+pub fn parse_rule(source: &str) -> Result<Rule> {
+    parse(source).map_err(|e| anyhow!("invalid policy rule `{}`: {}", source, e))
+}
+
+fn parse(source: &str) -> Result<Rule> {
+    let mut parser = Parser { tokens: tokenize(source)?, pos: 0 };
+    let action = match parser.word("`allow`, `deny`, or `ask`")?.as_str() {
+        "allow" => Action::Allow,
+        "ask" => Action::Ask,
+        "deny" => Action::Deny,
+        other => bail!("unknown action `{}`", other),
+    };
+    let tool = parser.word("a tool name or `*`")?.to_ascii_lowercase();
+    
+    let mut rule = Rule { action, tool, when: None, unless: None, message: None, source: source.to_string() };
+    while let Some(token) = parser.next() {
+        match token {
+            Token::Word(word) if word == "when" && rule.when.is_none() => rule.when = Some(parser.or()?),
+            Token::Word(word) if word == "unless" && rule.unless.is_none() => rule.unless = Some(parser.or()?),
+            Token::Word(word) if word == "message" && rule.message.is_none() => rule.message = Some(parser.string("the message")?),
+            _ => bail!("expected `when`, `unless`, or `message`"),
+        }
+    }
+    Ok(rule)
+}
@@ -0,0 +1,102 @@
+//! Policy Engine PreToolUse Hook
+//!
+//! Evaluates user-written rules (see [`dsl`] for the language) against each
+//! tool call and allows, denies, or asks about it as the first applicable
+//! rule says. Calls no rule applies to are allowed.
+//!
+//! Conditions can use any field of the tool input (`command`, `file_path`,
+//! `url`, ... with dots for nested fields), plus `tool`, `event`,
+//! `session`, `project_dir`, and `branch`, the project's checked-out git
+//! branch.
+
+use anyhow::Result;
+use claude_hooks_sdk::{HookInput, HookOutput};
+use std::cell::OnceCell;
+use std::process::Command;
+
+mod config;
+mod dsl;
+#[cfg(test)]
+mod tests;
+
+use config::PolicyConfig;
+use dsl::{Action, Facts, Rule};
+
+fn main() {
+    claude_hooks_sdk::run("Policy engine", run);
+}
+
+fn run(input: HookInput) -> Result<HookOutput> {
+    if input.event.event_type != "PreToolUse" {
+        return Ok(HookOutput::approve());
+    }
+    let config = PolicyConfig::load(Some(input.project_dir()))?;
+    let facts = EventFacts::new(&input);
+    
+    Ok(match first_match(&config.rules, &facts) {
+        None => HookOutput::approve(),
+        Some(rule) => {
+            let message = rule.message.clone().unwrap_or_else(|| format!("Policy rule: {}", rule.source));
+            match rule.action {
+                Action::Allow => HookOutput::approve(),
+                Action::Ask => HookOutput::ask(message),
+                Action::Deny => HookOutput::block(message),
+            }
+        }
+    })
+}
+
+/// The rule that decides the call, if any applies
+fn first_match<'a>(rules: &'a [Rule], facts: &EventFacts) -> Option<&'a Rule> {
+    rules.iter().find(|rule| rule.applies(&facts.tool, facts))
+}
+
+/// Field values of one event
+struct EventFacts<'a> {
+    input: &'a HookInput,
+    tool: String,
+    /// Only asked of git when a rule needs it
+    branch: OnceCell<Option<String>>,
+}
+
+impl<'a> EventFacts<'a> {
+    fn new(input: &'a HookInput) -> Self {
+        // Only Bash carries a bare command when the tool isn't named
+        let tool = input.event.tool_name.clone().unwrap_or_else(|| {
+            let is_bash = input.event.data.get("command").is_some();
+            if is_bash { "Bash" } else { "" }.to_string()
+        });
+        Self { input, tool, branch: OnceCell::new() }
+    }
+}
+
+impl Facts for EventFacts<'_> {
+    fn get(&self, field: &str) -> Option<String> {
+        match field {
+            "tool" => Some(self.tool.clone()),
+            "event" => Some(self.input.event.event_type.clone()),
+            "session" => Some(self.input.session.id.clone()),
+            "project_dir" => Some(self.input.session.project_dir.clone()),
+            "branch" => self.branch.get_or_init(|| current_branch(self.input)).clone(),
+            _ => {
+                let value = field.split('.').try_fold(&self.input.event.data, |value, key| value.get(key))?;
+                match value {
+                    serde_json::Value::String(text) => Some(text.clone()),
+                    serde_json::Value::Null => None,
+                    other => Some(other.to_string()),
+                }
+            }
+        }
+    }
+}
+
+/// The checked-out branch, or `None` when detached or not a repository
+fn current_branch(input: &HookInput) -> Option<String> {
+    let output = Command::new("git")
+        .args(["symbolic-ref", "--short", "-q", "HEAD"])
+        .current_dir(input.project_dir())
+        .output()
+        .ok()?;
+    let branch = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (output.status.success() && !branch.is_empty()).then_some(branch)
+}
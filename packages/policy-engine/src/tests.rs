@@ -0,0 +1,62 @@
+//! Tests for the policy language and rule evaluation
+
+use claude_hooks_sdk::HookInput;
+use std::collections::HashMap;
+
+use crate::config::PolicyConfig;
+use crate::dsl::{parse_rule, Action, Facts};
+use crate::{first_match, EventFacts};
+
+struct MapFacts(HashMap<&'static str, &'static str>);
+
+impl Facts for MapFacts {
+    fn get(&self, field: &str) -> Option<String> {
+        self.0.get(field).map(|value| value.to_string())
+    }
+}
+
+#[test]
+fn test_parse_and_evaluate() {
+    let rule = parse_rule(r#"deny Bash when command matches "git push (-f|--force)" unless branch != "main" message "No force pushes to \"main\"""#).unwrap();
+    assert_eq!(rule.action, Action::Deny);
+    assert_eq!(rule.tool, "bash");
+    assert_eq!(rule.message.as_deref(), Some("No force pushes to \"main\""));
+    
+    let facts = |command, branch| MapFacts(HashMap::from([("command", command), ("branch", branch)]));
+    assert!(rule.applies("Bash", &facts("git push --force origin", "main")));
+    assert!(!rule.applies("Bash", &facts("git push --force origin", "feature")));
+    assert!(!rule.applies("Bash", &facts("git push origin", "main")));
+    assert!(!rule.applies("Read", &facts("git push -f", "main")));
+    
+    let rule = parse_rule(r#"ask * when not (file_path ends_with ".rs" or file_path contains "/src/") and tool == "Write""#).unwrap();
+    let facts = |path| MapFacts(HashMap::from([("file_path", path), ("tool", "Write")]));
+    assert!(rule.applies("Write", &facts("README.md")));
+    assert!(!rule.applies("Write", &facts("lib/main.rs")));
+    // Missing fields compare as empty
+    assert!(rule.applies("Write", &MapFacts(HashMap::from([("tool", "Write")]))));
+    
+    for bad in ["permit bash", "deny bash when command", "deny bash when command == main", "deny bash when (command == \"x\"", "deny bash when command matches \"(\""] {
+        assert!(parse_rule(bad).is_err(), "{}", bad);
+    }
+}
+
+#[test]
+fn test_first_match_wins() {
+    let mut config = PolicyConfig::default();
+    config.merge_toml(r#"rules = ['deny bash when command starts_with "rm "', 'ask webfetch']"#).unwrap();
+    // Project rules are checked first
+    config.merge_toml(r#"rules = ['allow bash when command == "rm -r target" and project_dir == "/work"']"#).unwrap();
+    assert!(config.merge_toml(r#"rules = ['deny']"#).is_err());
+    
+    let input = |data: &str| HookInput::parse(&format!(r#"{{"session": {{"id": "s", "projectDir": "/work"}}, "event": {{"type": "PreToolUse", "data": {}}}}}"#, data)).unwrap();
+    let decide = |input: &HookInput| first_match(&config.rules, &EventFacts::new(input)).map(|rule| rule.action);
+    
+    assert_eq!(decide(&input(r#"{"command": "rm -r target"}"#)), Some(Action::Allow));
+    assert_eq!(decide(&input(r#"{"command": "rm -r src"}"#)), Some(Action::Deny));
+    assert_eq!(decide(&input(r#"{"command": "ls"}"#)), None);
+    
+    let fetch = HookInput::parse(r#"{"session_id": "s", "cwd": "/work", "hook_event_name": "PreToolUse", "tool_name": "WebFetch", "tool_input": {"url": "https://x", "headers": {"a": 1}}}"#).unwrap();
+    let facts = EventFacts::new(&fetch);
+    assert_eq!(facts.get("headers.a").as_deref(), Some("1"));
+    assert_eq!(decide(&fetch), Some(Action::Ask));
+}
@@ -0,0 +1,121 @@
+//! Rate limiter configuration
+//!
+//! Read from `~/.claude/hooks/rate-limiter/config.toml`, then the project's
+//! `.claude/rate-limiter.toml`; a project limit replaces the global one for
+//! the same tool, and `max_calls = 0` turns a tool's limit off.
+//!
+//! ```toml
+//! state_dir = "~/.claude/state/rate-limiter"
+//!
+//! [limits.Bash]
+//! max_calls = 30
+//! window_secs = 60
+//! action = "ask"      # or "block"
+//!
+//! [limits.WebFetch]
+//! max_calls = 20
+//! window_secs = 60
+//! ```
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LimiterConfig {
+    pub enabled: bool,
+    /// Directory holding each session's recent calls
+    pub state_dir: PathBuf,
+    /// Tool name -> limit
+    pub limits: BTreeMap<String, Limit>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Limit {
+    /// Calls allowed within the window
+    pub max_calls: usize,
+    #[serde(default = "default_window_secs")]
+    pub window_secs: u64,
+    #[serde(default)]
+    pub action: Action,
+}
+
+/// What happens to a call over the limit
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    /// Let the user decide whether to carry on
+    #[default]
+    Ask,
+    Block,
+}
+
+fn default_window_secs() -> u64 {
+    60
+}
+
+impl Default for LimiterConfig {
+    fn default() -> Self {
+        let limit = |max_calls| Limit { max_calls, window_secs: 60, action: Action::Ask };
+        Self {
+            enabled: true,
+            state_dir: dirs::home_dir()
+                .unwrap_or_else(|| PathBuf::from("."))
+                .join(".claude")
+                .join("state")
+                .join("rate-limiter"),
+            limits: BTreeMap::from([
+                ("Bash".to_string(), limit(30)),
+                ("WebFetch".to_string(), limit(20)),
+            ]),
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigLayer {
+    enabled: Option<bool>,
+    state_dir: Option<String>,
+    #[serde(default)]
+    limits: BTreeMap<String, Limit>,
+}
+
+impl LimiterConfig {
+    /// Load the global config, then layer the project's on top
+    pub fn load(project_dir: Option<&Path>) -> Result<Self> {
+        let global = dirs::home_dir().map(|home| home.join(".claude/hooks/rate-limiter/config.toml"));
+        let project = project_dir.map(|dir| dir.join(".claude").join("rate-limiter.toml"));
+        
+        let mut config = Self::default();
+        for path in global.into_iter().chain(project) {
+            if path.is_file() {
+                let content = std::fs::read_to_string(&path)
+                    .with_context(|| format!("Failed to read {}", path.display()))?;
+                config.merge_toml(&content)
+                    .with_context(|| format!("Failed to parse {}", path.display()))?;
+            }
+        }
+        Ok(config)
+    }
+    
+    /// Layer one config file's contents on top of this config
+    pub fn merge_toml(&mut self, content: &str) -> Result<()> {
+        let layer: ConfigLayer = toml::from_str(content)?;
+        self.enabled = layer.enabled.unwrap_or(self.enabled);
+        if let Some(state_dir) = layer.state_dir {
+            self.state_dir = expand_home(&state_dir);
+        }
+        self.limits.extend(layer.limits);
+        Ok(())
+    }
+}
+
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), dirs::home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest),
+        _ => PathBuf::from(path),
+    }
+}
@@ -0,0 +1,76 @@
+//! Per-session call history and the limit check
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::config::{Limit, LimiterConfig};
+
+/// Recent calls of one session: tool name -> call times in milliseconds
+/// since the epoch, oldest first
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CallHistory {
+    #[serde(default)]
+    pub calls: BTreeMap<String, Vec<u64>>,
+}
+
+/// A call that would exceed its tool's limit
+#[derive(Debug, Clone, PartialEq)]
+pub struct Exceeded {
+    pub tool: String,
+    pub calls: usize,
+    pub limit: Limit,
+}
+
+impl CallHistory {
+    /// The state file for `session_id`
+    pub fn path(config: &LimiterConfig, session_id: &str) -> PathBuf {
+        let name: String = session_id
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+            .collect();
+        let name = if name.is_empty() { "unknown".to_string() } else { name };
+        config.state_dir.join(format!("{}.json", name))
+    }
+    
+    /// The history at `path`, or an empty one; a corrupt file is started over
+    pub fn load(path: &Path) -> Self {
+        std::fs::read_to_string(path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+    
+    /// Write the history to `path`, replacing it in one step
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+        }
+        let temp = path.with_extension(format!("json.{}", std::process::id()));
+        std::fs::write(&temp, serde_json::to_string(self)?)
+            .with_context(|| format!("Failed to write {}", temp.display()))?;
+        std::fs::rename(&temp, path).with_context(|| format!("Failed to replace {}", path.display()))
+    }
+    
+    /// Check a call to `tool` at `now_ms` against its limit, recording it
+    /// unless it is over
+    ///
+    /// Calls that have left every window are forgotten along the way, so the
+    /// history stays small. A `max_calls` of 0 turns the limit off.
+    pub fn check(&mut self, tool: &str, now_ms: u64, limits: &BTreeMap<String, Limit>) -> Option<Exceeded> {
+        for (name, times) in &mut self.calls {
+            let window_ms = limits.get(name).map_or(0, |limit| limit.window_secs * 1000);
+            times.retain(|&at| now_ms.saturating_sub(at) < window_ms);
+        }
+        self.calls.retain(|_, times| !times.is_empty());
+        
+        let limit = *limits.get(tool).filter(|limit| limit.max_calls > 0)?;
+        let times = self.calls.entry(tool.to_string()).or_default();
+        if times.len() >= limit.max_calls {
+            return Some(Exceeded { tool: tool.to_string(), calls: times.len(), limit });
+        }
+        times.push(now_ms);
+        None
+    }
+}
@@ -0,0 +1,63 @@
+//! Rate Limiter PreToolUse Hook
+//!
+//! Counts each session's calls per tool in a small state file under
+//! `~/.claude/state/rate-limiter/` and, once a tool is called more often
+//! than its limit allows (30 Bash calls a minute by default), asks the
+//! user before letting more through, or blocks them. This catches Claude
+//! stuck in a loop before it has run hundreds of commands.
+
+use anyhow::Result;
+use claude_hooks_sdk::{HookInput, HookOutput};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+mod config;
+mod limiter;
+#[cfg(test)]
+mod tests;
+
+use config::{Action, LimiterConfig};
+use limiter::CallHistory;
+
+fn main() {
+    claude_hooks_sdk::run("Rate limiter", run);
+}
+
+fn run(input: HookInput) -> Result<HookOutput> {
+    if input.event.event_type != "PreToolUse" {
+        return Ok(HookOutput::approve());
+    }
+    // Without a tool name, the input's shape tells the limited tools apart
+    let data = &input.event.data;
+    let tool = match &input.event.tool_name {
+        Some(tool) => tool.as_str(),
+        None if data.get("command").is_some() => "Bash",
+        None if data.get("url").is_some() => "WebFetch",
+        None => return Ok(HookOutput::approve()),
+    };
+    let config = LimiterConfig::load(Some(input.project_dir()))?;
+    if !config.enabled || !config.limits.contains_key(tool) {
+        return Ok(HookOutput::approve());
+    }
+    
+    let now_ms = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_millis() as u64);
+    let path = CallHistory::path(&config, &input.session.id);
+    let mut history = CallHistory::load(&path);
+    let exceeded = history.check(tool, now_ms, &config.limits);
+    history.save(&path)?;
+    
+    Ok(match exceeded {
+        None => HookOutput::approve(),
+        Some(exceeded) => {
+            let message = format!(
+                "{} has been called {} times in the last {}s (limit {}); this may be a runaway loop",
+                exceeded.tool, exceeded.calls, exceeded.limit.window_secs, exceeded.limit.max_calls,
+            );
+            match exceeded.limit.action {
+                Action::Ask => HookOutput::ask(message),
+                Action::Block => HookOutput::block(message),
+            }
+        }
+    })
+}
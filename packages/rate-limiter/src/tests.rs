@@ -0,0 +1,48 @@
+//! Tests for rate limiting
+
+use crate::config::{Action, LimiterConfig};
+use crate::limiter::CallHistory;
+
+#[test]
+fn test_limit_within_window() {
+    let mut config = LimiterConfig::default();
+    config.merge_toml("[limits.Bash]\nmax_calls = 3\nwindow_secs = 10\naction = \"block\"").unwrap();
+    assert_eq!(config.limits["Bash"].action, Action::Block);
+    assert_eq!(config.limits["WebFetch"].max_calls, 20);
+    
+    let mut history = CallHistory::default();
+    for at in [0, 1_000, 2_000] {
+        assert_eq!(history.check("Bash", at, &config.limits), None);
+    }
+    let exceeded = history.check("Bash", 3_000, &config.limits).unwrap();
+    assert_eq!(exceeded.calls, 3);
+    
+    // Once the first call leaves the window there is room for one more
+    assert_eq!(history.check("Bash", 10_500, &config.limits), None);
+    assert!(history.check("Bash", 10_600, &config.limits).is_some());
+    
+    // Unlimited tools are neither limited nor recorded
+    assert_eq!(history.check("Read", 10_700, &config.limits), None);
+    assert!(!history.calls.contains_key("Read"));
+}
+
+#[test]
+fn test_disabled_limit_and_state_file() {
+    let dir = std::env::temp_dir().join(format!("rate-limiter-{}", std::process::id()));
+    let mut config = LimiterConfig::default();
+    config.merge_toml(&format!("state_dir = {:?}\n[limits.WebFetch]\nmax_calls = 0", dir.display().to_string())).unwrap();
+    
+    let mut history = CallHistory::default();
+    assert_eq!(history.check("WebFetch", 0, &config.limits), None);
+    assert_eq!(history.check("Bash", 0, &config.limits), None);
+    
+    let path = CallHistory::path(&config, "session/1");
+    assert_eq!(path, dir.join("session_1.json"));
+    history.save(&path).unwrap();
+    assert_eq!(CallHistory::load(&path), history);
+    
+    std::fs::write(&path, "not json").unwrap();
+    assert_eq!(CallHistory::load(&path), CallHistory::default());
+    
+    let _ = std::fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,170 @@
+//! Scanner configuration
+//!
+//! Read from `~/.claude/hooks/secret-scanner/config.toml`, then the
+//! project's `.claude/secret-scanner.toml`. A project file that doesn't
+//! parse, or has a bad pattern or glob, is ignored with a warning.
+//!
+//! ```toml
+//! mode = "warn"
+//! ignore_paths = ["**/fixtures/**"]
+//! allow = ['EXAMPLE']
+//!
+//! [[patterns]]
+//! id = "internal-token"
+//! pattern = 'itk_[0-9a-f]{32}'
+//! description = "internal service token"
+//! ```
+
+use anyhow::{Context, Result};
+use globset::{Glob, GlobSetBuilder};
+use serde::Deserialize;
+use std::path::Path;
+
+use crate::scanner::Scanner;
+
+/// What to do when a secret is found
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Mode {
+    /// Stop the edit
+    #[default]
+    Block,
+    /// Let the edit through with a warning
+    Warn,
+}
+
+/// An extra secret format
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct PatternConfig {
+    pub id: String,
+    pub pattern: String,
+    pub description: String,
+}
+
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ScannerConfig {
+    #[serde(default)]
+    pub mode: Mode,
+    
+    /// Minimum Shannon entropy (bits per character) for an assigned value to
+    /// count as a secret
+    #[serde(default = "default_entropy_threshold")]
+    pub entropy_threshold: f64,
+    
+    /// Assigned values shorter than this are never flagged for entropy alone
+    #[serde(default = "default_min_length")]
+    pub min_length: usize,
+    
+    /// Files never scanned (globs, matched against the path as given)
+    #[serde(default)]
+    pub ignore_paths: Vec<String>,
+    
+    /// Regexes for matches that are known to be harmless
+    #[serde(default)]
+    pub allow: Vec<String>,
+    
+    #[serde(default)]
+    pub patterns: Vec<PatternConfig>,
+    
+    /// Problems with the files merged, to pass on to the user
+    #[serde(skip)]
+    pub warnings: Vec<String>,
+}
+
+impl Default for ScannerConfig {
+    fn default() -> Self {
+        Self {
+            mode: Mode::default(),
+            entropy_threshold: default_entropy_threshold(),
+            min_length: default_min_length(),
+            ignore_paths: Vec::new(),
+            allow: Vec::new(),
+            patterns: Vec::new(),
+            warnings: Vec::new(),
+        }
+    }
+}
+
+/// Keys a later file sets replace earlier values; lists are appended
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigLayer {
+    mode: Option<Mode>,
+    entropy_threshold: Option<f64>,
+    min_length: Option<usize>,
+    #[serde(default)]
+    ignore_paths: Vec<String>,
+    #[serde(default)]
+    allow: Vec<String>,
+    #[serde(default)]
+    patterns: Vec<PatternConfig>,
+}
+
+impl ScannerConfig {
+    /// Load the global config, then layer the project's on top
+    pub fn load(project_dir: Option<&Path>) -> Result<Self> {
+        let global = dirs::home_dir().map(|home| home.join(".claude/hooks/secret-scanner/config.toml"));
+        Self::load_from(global.as_deref(), project_dir)
+    }
+    
+    /// [`Self::load`] with the global config given
+    pub fn load_from(global: Option<&Path>, project_dir: Option<&Path>) -> Result<Self> {
+        let project = project_dir.map(|dir| dir.join(".claude").join("secret-scanner.toml"));
+        let read = |path: &Path| {
+            std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))
+        };
+        
+        let mut config = Self::default();
+        if let Some(path) = global.filter(|path| path.is_file()) {
+            config.merge_toml(&read(path)?)
+                .with_context(|| format!("Failed to parse {}", path.display()))?;
+        }
+        if let Some(path) = project.filter(|path| path.is_file()) {
+            // Failing the hook would approve the edit, so scan without it
+            let mut layered = config.clone();
+            let merged = read(&path).and_then(|content| {
+                layered.merge_toml(&content)
+                    .with_context(|| format!("Failed to parse {}", path.display()))?;
+                Scanner::new(&layered)?;
+                layered.ignores("")?;
+                Ok(())
+            });
+            match merged {
+                Ok(()) => config = layered,
+                Err(e) => config.warnings.push(format!("Ignoring the project's config: {:#}", e)),
+            }
+        }
+        Ok(config)
+    }
+    
+    /// Layer one config file's contents on top of this config
+    pub fn merge_toml(&mut self, content: &str) -> Result<()> {
+        let layer: ConfigLayer = toml::from_str(content)?;
+        self.mode = layer.mode.unwrap_or(self.mode);
+        self.entropy_threshold = layer.entropy_threshold.unwrap_or(self.entropy_threshold);
+        self.min_length = layer.min_length.unwrap_or(self.min_length);
+        self.ignore_paths.extend(layer.ignore_paths);
+        self.allow.extend(layer.allow);
+        self.patterns.extend(layer.patterns);
+        Ok(())
+    }
+    
+    /// Whether `file_path` is excluded from scanning
+    pub fn ignores(&self, file_path: &str) -> Result<bool> {
+        let mut builder = GlobSetBuilder::new();
+        for pattern in &self.ignore_paths {
+            builder.add(Glob::new(pattern).with_context(|| format!("Invalid ignore_paths glob '{}'", pattern))?);
+        }
+        Ok(builder.build()?.is_match(file_path))
+    }
+}
+
+fn default_entropy_threshold() -> f64 {
+    3.5
+}
+
+fn default_min_length() -> usize {
+    16
+}
@@ -0,0 +1,97 @@
+//! Secret Scanner PreToolUse Hook
+//!
+//! Inspects the text Write, Edit, and MultiEdit are about to put into a file
+//! and blocks (or warns about) anything that looks like a credential:
+//! - well-known key formats (AWS, GitHub, Slack, Stripe, Google, private keys)
+//! - high-entropy values assigned to names like `api_key` or `password`
+
+use anyhow::Result;
+use claude_hooks_sdk::{HookInput, HookOutput};
+use serde::Deserialize;
+
+mod config;
+mod scanner;
+#[cfg(test)]
+mod tests;
+
+use config::{Mode, ScannerConfig};
+use scanner::Scanner;
+
+/// The file-editing fields of Write, Edit, and MultiEdit tool data
+#[derive(Debug, Default, Deserialize)]
+struct EditToolData {
+    #[serde(default)]
+    file_path: String,
+    /// Write
+    #[serde(default)]
+    content: Option<String>,
+    /// Edit
+    #[serde(default)]
+    new_string: Option<String>,
+    /// MultiEdit
+    #[serde(default)]
+    edits: Vec<EditData>,
+}
+
+#[derive(Debug, Deserialize)]
+struct EditData {
+    new_string: String,
+}
+
+impl EditToolData {
+    /// Every piece of text that will end up in the file
+    fn new_text(&self) -> impl Iterator<Item = &str> {
+        self.content
+            .iter()
+            .chain(self.new_string.iter())
+            .map(String::as_str)
+            .chain(self.edits.iter().map(|edit| edit.new_string.as_str()))
+    }
+}
+
+fn main() {
+    claude_hooks_sdk::run("Secret scanner", run);
+}
+
+fn run(input: HookInput) -> Result<HookOutput> {
+    if input.event.event_type != "PreToolUse" {
+        return Ok(HookOutput::approve());
+    }
+    // Tools without file content (Bash, Read, ...) have nothing to scan
+    let tool_data: EditToolData = input.event.tool_data().unwrap_or_default();
+    
+    let config = ScannerConfig::load(Some(input.project_dir()))?;
+    if config.ignores(&tool_data.file_path)? {
+        return Ok(HookOutput::approve());
+    }
+    let scanner = Scanner::new(&config)?;
+    
+    let warnings = (!config.warnings.is_empty()).then(|| format!("secret-scanner: {}", config.warnings.join("\n")));
+    let findings: Vec<String> = tool_data
+        .new_text()
+        .flat_map(|text| scanner.scan(text))
+        .map(|finding| finding.to_string())
+        .collect();
+    if findings.is_empty() {
+        return Ok(with_warnings(HookOutput::approve(), warnings));
+    }
+    
+    let message = format!(
+        "secret-scanner: {} in {}:\n  {}",
+        if findings.len() == 1 { "possible secret" } else { "possible secrets" },
+        tool_data.file_path,
+        findings.join("\n  "),
+    );
+    let output = match config.mode {
+        Mode::Block => HookOutput::block(message),
+        Mode::Warn => HookOutput::approve().with_message(message),
+    };
+    Ok(with_warnings(output, warnings))
+}
+
+fn with_warnings(output: HookOutput, warnings: Option<String>) -> HookOutput {
+    match warnings {
+        Some(warnings) => output.with_system_message(warnings),
+        None => output,
+    }
+}
@@ -0,0 +1,151 @@
+//! Secret detection: known key formats plus entropy of assigned values
+
+use anyhow::{Context, Result};
+use regex::Regex;
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::config::ScannerConfig;
+
+/// `(id, pattern, description)` for well-known credential formats
+const KNOWN_FORMATS: &[(&str, &str, &str)] = &[
+    ("aws-access-key", r"\b(?:AKIA|ASIA)[0-9A-Z]{16}\b", "AWS access key"),
+    ("github-token", r"\b(?:gh[pousr]_[A-Za-z0-9]{36,}|github_pat_[A-Za-z0-9_]{60,})\b", "GitHub token"),
+    ("slack-token", r"\bxox[abprs]-[A-Za-z0-9-]{10,}", "Slack token"),
+    ("stripe-key", r"\b[sr]k_live_[A-Za-z0-9]{24,}\b", "Stripe live key"),
+    ("google-api-key", r"\bAIza[0-9A-Za-z_-]{35}\b", "Google API key"),
+    ("anthropic-key", r"\bsk-ant-[A-Za-z0-9_-]{20,}", "Anthropic API key"),
+    ("private-key", r"-----BEGIN (?:RSA |EC |DSA |OPENSSH |PGP |ENCRYPTED )?PRIVATE KEY(?: BLOCK)?-----", "private key"),
+];
+
+/// A quoted value assigned to a secret-sounding name, e.g.
+/// `api_key = "..."` or `"password": "..."`
+const ASSIGNMENT: &str = r#"(?i)\b[\w.-]*(?:api[_-]?key|secret|token|passw(?:or)?d|credential|auth)[\w.-]*["']?\s*[:=]\s*["']([^"'\s]+)["']"#;
+
+/// Values that are obviously placeholders
+const PLACEHOLDER: &str = r"(?i)^(?:x+|\*+|\.+|<[^>]*>|\$\{?[\w.]+\}?|\{\{.*\}\})$|example|changeme|placeholder|your[_-]|dummy|redacted";
+
+#[derive(Debug)]
+struct Format {
+    id: String,
+    pattern: Regex,
+    description: String,
+}
+
+/// One suspected secret
+#[derive(Debug, Clone, PartialEq)]
+pub struct Finding {
+    pub id: String,
+    pub description: String,
+    /// 1-based line within the scanned text
+    pub line: usize,
+    /// The match, truncated so the secret isn't echoed back
+    pub redacted: String,
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} on line {} ({})", self.description, self.line, self.redacted)
+    }
+}
+
+pub struct Scanner {
+    formats: Vec<Format>,
+    assignment: Regex,
+    placeholder: Regex,
+    allow: Vec<Regex>,
+    entropy_threshold: f64,
+    min_length: usize,
+}
+
+impl Scanner {
+    pub fn new(config: &ScannerConfig) -> Result<Self> {
+        let builtin = KNOWN_FORMATS
+            .iter()
+            .map(|(id, pattern, description)| (id.to_string(), pattern.to_string(), description.to_string()));
+        let custom = config.patterns
+            .iter()
+            .map(|p| (p.id.clone(), p.pattern.clone(), p.description.clone()));
+        let formats = builtin
+            .chain(custom)
+            .map(|(id, pattern, description)| {
+                let pattern = Regex::new(&pattern).with_context(|| format!("Invalid pattern for '{}'", id))?;
+                Ok(Format { id, pattern, description })
+            })
+            .collect::<Result<_>>()?;
+        let allow = config.allow
+            .iter()
+            .map(|pattern| Regex::new(pattern).with_context(|| format!("Invalid allow pattern '{}'", pattern)))
+            .collect::<Result<_>>()?;
+        
+        Ok(Self {
+            formats,
+            assignment: Regex::new(ASSIGNMENT)?,
+            placeholder: Regex::new(PLACEHOLDER)?,
+            allow,
+            entropy_threshold: config.entropy_threshold,
+            min_length: config.min_length,
+        })
+    }
+    
+    /// Suspected secrets in `text`, at most one per line
+    pub fn scan(&self, text: &str) -> Vec<Finding> {
+        text.lines()
+            .enumerate()
+            .filter_map(|(index, line)| self.scan_line(line).map(|(id, description, secret)| Finding {
+                id,
+                description,
+                line: index + 1,
+                redacted: redact(secret),
+            }))
+            .collect()
+    }
+    
+    fn scan_line<'a>(&self, line: &'a str) -> Option<(String, String, &'a str)> {
+        for format in &self.formats {
+            if let Some(found) = format.pattern.find(line) {
+                if !self.is_allowed(found.as_str()) {
+                    return Some((format.id.clone(), format.description.clone(), found.as_str()));
+                }
+            }
+        }
+        
+        for captures in self.assignment.captures_iter(line) {
+            let value = captures.get(1)?.as_str();
+            if value.chars().count() >= self.min_length
+                && shannon_entropy(value) >= self.entropy_threshold
+                && !self.placeholder.is_match(value)
+                && !self.is_allowed(value)
+            {
+                return Some(("high-entropy".to_string(), "high-entropy value assigned to a secret name".to_string(), value));
+            }
+        }
+        None
+    }
+    
+    fn is_allowed(&self, found: &str) -> bool {
+        self.allow.iter().any(|allow| allow.is_match(found))
+    }
+}
+
+/// Shannon entropy in bits per character
+pub fn shannon_entropy(value: &str) -> f64 {
+    let mut counts: HashMap<char, usize> = HashMap::new();
+    for c in value.chars() {
+        *counts.entry(c).or_default() += 1;
+    }
+    let len = value.chars().count() as f64;
+    counts
+        .values()
+        .map(|&count| {
+            let p = count as f64 / len;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+/// Keep only enough of a secret to recognize it
+fn redact(secret: &str) -> String {
+    let prefix: String = secret.chars().take(4).collect();
+    format!("{}…", prefix)
+}
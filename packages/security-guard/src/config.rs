@@ -0,0 +1,192 @@
+//! Rule file loading
+//!
+//! Rules come from `~/.claude/hooks/security-guard/rules.toml`, then the
+//! project's `.claude/security-guard.toml`; `CLAUDE_SECURITY_GUARD_RULES`
+//! replaces the global file with another path.
+//!
+//! The project file is checked in with the code, so it can only tighten
+//! the policy: add rules, or make the privilege action stricter. Its
+//! `builtin`, `disable`, `allow`, and `privilege.allow` are ignored unless
+//! the global file sets `trust_project = true`. A project file that doesn't
+//! parse is ignored with a warning, leaving the global and built-in rules.
+//!
+//! ```toml
+//! # Built-in rules to turn off, by id
+//! disable = ["pipe-to-shell"]
+//! # Commands matching any of these regexes are never blocked
+//! allow = ['^curl -fsSL https://sh\.rustup\.rs']
+//!
+//! [[rules]]
+//! id = "force-push"
+//! pattern = 'git\s+push\s+.*--force'
+//! message = "Force-pushing is not allowed here"
+//!
+//! # Commands run through sudo, doas, runas, or su -c
+//! [privilege]
+//! action = "ask"            # "block" (the default), "ask", or "allow"
+//! allow = ["apt-get", "systemctl"]
+//! ```
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+use crate::rules::RuleSet;
+
+/// Environment variable naming an alternative global rule file
+pub const RULES_ENV: &str = "CLAUDE_SECURITY_GUARD_RULES";
+
+/// A rule defined in a rule file
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct RuleConfig {
+    pub id: String,
+    /// Regex matched against the whole command
+    pub pattern: String,
+    /// Why the command was blocked
+    pub message: String,
+}
+
+/// Contents of one rule file, or several merged
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct RuleFile {
+    /// Include the built-in rules
+    #[serde(default = "default_true")]
+    pub builtin: bool,
+    
+    /// Built-in or earlier rules to turn off, by id
+    #[serde(default)]
+    pub disable: Vec<String>,
+    
+    /// Regexes for commands that are never blocked
+    #[serde(default)]
+    pub allow: Vec<String>,
+    
+    #[serde(default)]
+    pub rules: Vec<RuleConfig>,
+    
+    #[serde(default)]
+    pub privilege: PrivilegeConfig,
+    
+    /// Let the project file loosen the policy too (global file only)
+    #[serde(default)]
+    pub trust_project: bool,
+    
+    /// Problems with the files merged, to pass on to the user
+    #[serde(skip)]
+    pub warnings: Vec<String>,
+}
+
+/// How commands run with elevated privileges are treated
+#[derive(Debug, Clone, Default, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct PrivilegeConfig {
+    /// Unset means block
+    pub action: Option<PrivilegeAction>,
+    /// Programs that may be run elevated anyway, by name
+    #[serde(default)]
+    pub allow: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PrivilegeAction {
+    #[default]
+    Block,
+    Ask,
+    Allow,
+}
+
+impl Default for RuleFile {
+    fn default() -> Self {
+        Self {
+            builtin: true,
+            disable: Vec::new(),
+            allow: Vec::new(),
+            rules: Vec::new(),
+            privilege: PrivilegeConfig::default(),
+            trust_project: false,
+            warnings: Vec::new(),
+        }
+    }
+}
+
+impl RuleFile {
+    /// Load the global rule file, then layer the project's on top
+    pub fn load(project_dir: Option<&Path>) -> Result<Self> {
+        let global = std::env::var_os(RULES_ENV)
+            .map(PathBuf::from)
+            .or_else(|| dirs::home_dir().map(|home| home.join(".claude/hooks/security-guard/rules.toml")));
+        Self::load_from(global.as_deref(), project_dir)
+    }
+    
+    /// [`Self::load`] with the global rule file given. A project file that
+    /// can't be used is skipped with a warning rather than failing the
+    /// hook, which would approve the command.
+    pub fn load_from(global: Option<&Path>, project_dir: Option<&Path>) -> Result<Self> {
+        let mut file = Self::default();
+        if let Some(path) = global.filter(|path| path.is_file()) {
+            file.merge(Self::read(path)?);
+        }
+        if let Some(path) = project_dir.map(|dir| dir.join(".claude").join("security-guard.toml")) {
+            if path.is_file() {
+                match Self::read(&path).and_then(|project| RuleSet::from_file(&project).map(|_| project)) {
+                    Ok(project) => file.merge_project(project),
+                    Err(e) => file.warnings.push(format!("Ignoring the project's rules: {:#}", e)),
+                }
+            }
+        }
+        Ok(file)
+    }
+    
+    pub fn read(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read rule file {}", path.display()))?;
+        toml::from_str(&content)
+            .with_context(|| format!("Failed to parse rule file {}", path.display()))
+    }
+    
+    /// Layer `other` on top; a later file can add rules, exceptions,
+    /// disabled ids, and elevated programs, turn the built-ins off, or
+    /// replace the privilege action
+    pub fn merge(&mut self, other: RuleFile) {
+        self.builtin &= other.builtin;
+        self.disable.extend(other.disable);
+        self.allow.extend(other.allow);
+        self.rules.extend(other.rules);
+        self.privilege.action = other.privilege.action.or(self.privilege.action);
+        self.privilege.allow.extend(other.privilege.allow);
+        self.trust_project |= other.trust_project;
+    }
+    
+    /// Layer a project file on top: everything [`Self::merge`] takes when
+    /// this file trusts the project, otherwise only its rules and a
+    /// stricter privilege action
+    pub fn merge_project(&mut self, other: RuleFile) {
+        if self.trust_project {
+            self.merge(RuleFile { trust_project: false, ..other });
+            return;
+        }
+        self.rules.extend(other.rules);
+        let current = self.privilege.action.unwrap_or_default();
+        if let Some(action) = other.privilege.action.filter(|action| action.stricter_than(current)) {
+            self.privilege.action = Some(action);
+        }
+    }
+}
+
+impl PrivilegeAction {
+    fn stricter_than(self, other: PrivilegeAction) -> bool {
+        let rank = |action| match action {
+            PrivilegeAction::Allow => 0,
+            PrivilegeAction::Ask => 1,
+            PrivilegeAction::Block => 2,
+        };
+        rank(self) > rank(other)
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
@@ -0,0 +1,97 @@
+//! Security Guard PreToolUse Hook
+//!
+//! Blocks obviously destructive or risky Bash commands before they run:
+//! - `rm -rf /` and friends
+//! - `chmod -R 777`
+//! - piping a download straight into a shell
+//! - fork bombs
+//! - writes under /etc
+//!
+//! and stops Claude escalating privileges with `sudo`, `doas`, `runas`, or
+//! `su -c`, or asks first, as configured.
+//!
+//! The built-in rules can be disabled, extended, or given exceptions in a
+//! rule file (see [`config`]).
+//!
+//! The binary is a thin wrapper around [`handle`], which hook-mux also
+//! calls in-process.
+
+use anyhow::Result;
+use claude_hooks_sdk::{HookInput, HookOutput};
+use serde::Deserialize;
+
+mod config;
+mod privilege;
+mod rules;
+#[cfg(test)]
+mod tests;
+
+use config::{PrivilegeAction, PrivilegeConfig, RuleFile};
+use rules::RuleSet;
+
+/// Name used in error messages
+pub const NAME: &str = "Security guard";
+
+/// Tool data for Bash commands
+#[derive(Debug, Deserialize)]
+struct BashToolData {
+    command: String,
+}
+
+/// Handle one hook event, given the raw JSON input
+pub fn handle(input: &str) -> HookOutput {
+    claude_hooks_sdk::handle(NAME, input, hook)
+}
+
+/// Handle one parsed hook event
+pub fn hook(input: HookInput) -> Result<HookOutput> {
+    if input.event.event_type != "PreToolUse" {
+        return Ok(HookOutput::approve());
+    }
+    // Only Bash tool calls carry a command
+    let Some(tool_data) = input.event.tool_data::<BashToolData>() else {
+        return Ok(HookOutput::approve());
+    };
+    
+    let rule_file = RuleFile::load(Some(input.project_dir()))?;
+    let output = check(&tool_data.command, &rule_file)?;
+    if rule_file.warnings.is_empty() {
+        return Ok(output);
+    }
+    Ok(output.with_system_message(format!("{}: {}", NAME, rule_file.warnings.join("\n"))))
+}
+
+/// The verdict on `command` under `rule_file`
+fn check(command: &str, rule_file: &RuleFile) -> Result<HookOutput> {
+    let rules = RuleSet::from_file(rule_file)?;
+    
+    if let Some(violation) = rules.check(command) {
+        return Ok(HookOutput::block(violation.to_string()));
+    }
+    if rules.allows(command) {
+        return Ok(HookOutput::approve());
+    }
+    Ok(check_privilege(command, &rule_file.privilege))
+}
+
+/// Block or ask about the first elevated program the policy doesn't allow
+fn check_privilege(command: &str, policy: &PrivilegeConfig) -> HookOutput {
+    let action = policy.action.unwrap_or_default();
+    if action == PrivilegeAction::Allow {
+        return HookOutput::approve();
+    }
+    let permitted = |program: &Option<String>| program.as_ref().is_some_and(|program| policy.allow.contains(program));
+    let Some(escalation) = privilege::escalations(command).into_iter().find(|e| !permitted(&e.program)) else {
+        return HookOutput::approve();
+    };
+    
+    let message = match &escalation.program {
+        Some(program) => format!("Privilege escalation: `{}` would run {} as another user", escalation.via, program),
+        None => format!("Privilege escalation: `{}` would run commands as another user", escalation.via),
+    };
+    match action {
+        PrivilegeAction::Ask => HookOutput::ask(message),
+        _ => HookOutput::block(format!("Blocked by security-guard (privilege-escalation): {}", message)),
+    }
+}
+
@@ -0,0 +1,5 @@
+//! Security Guard PreToolUse Hook binary; the rules live in the library
+
+fn main() {
+    claude_hooks_sdk::run(security_guard::NAME, security_guard::hook);
+}
@@ -0,0 +1,89 @@
+//! Spotting commands run with elevated privileges
+//!
+//! An agent almost never means to escalate, so `sudo`, `doas`, `runas`,
+//! and `su` are reported along with the program they would run.
+
+use claude_hooks_sdk::shell::simple_commands;
+
+/// Options of `sudo` and `doas` that take a separate value
+const VALUE_OPTIONS: &[&str] = &[
+    "-u", "-g", "-h", "-p", "-C", "-D", "-r", "-t", "-T", "-U",
+    "--user", "--group", "--host", "--prompt", "--close-from", "--chdir", "--role", "--type",
+    "--command-timeout", "--other-user",
+];
+
+/// One elevated command
+#[derive(Debug, Clone, PartialEq)]
+pub struct Escalation {
+    /// `sudo`, `doas`, `runas`, or `su`
+    pub via: String,
+    /// Name of the program run elevated; `None` for an interactive shell
+    pub program: Option<String>,
+}
+
+/// Every elevated command in `command`
+pub fn escalations(command: &str) -> Vec<Escalation> {
+    simple_commands(command).iter().filter_map(|words| escalation(words)).collect()
+}
+
+fn escalation(words: &[String]) -> Option<Escalation> {
+    // Skip `VAR=value` assignments ahead of the command
+    let mut words = words.iter().skip_while(|word| is_assignment(word));
+    let via = program_name(words.next()?);
+    let rest: Vec<&String> = words.collect();
+
+    let program = match via.as_str() {
+        "sudo" | "doas" => {
+            let mut args = rest.into_iter();
+            let mut program = None;
+            while let Some(arg) = args.next() {
+                if arg == "--" {
+                    program = args.next();
+                    break;
+                }
+                if !arg.starts_with('-') {
+                    program = Some(arg);
+                    break;
+                }
+                if VALUE_OPTIONS.contains(&arg.as_str()) {
+                    args.next();
+                }
+            }
+            program.map(|program| program_name(program))
+        }
+        // `su [options] [user] -c <command>`
+        "su" => rest
+            .iter()
+            .enumerate()
+            .find_map(|(i, arg)| match arg.as_str() {
+                "-c" | "--command" => rest.get(i + 1).map(|command| command.as_str()),
+                _ => arg.strip_prefix("--command="),
+            })
+            .and_then(first_program),
+        // `runas /user:Administrator "program args"`
+        "runas" => rest.iter().find(|arg| !arg.starts_with('/')).and_then(|command| first_program(command)),
+        _ => return None,
+    };
+    Some(Escalation { via, program })
+}
+
+/// The program a nested command line runs first
+fn first_program(command: &str) -> Option<String> {
+    let words = simple_commands(command).into_iter().next()?;
+    words.iter().find(|word| !is_assignment(word)).map(|word| program_name(word))
+}
+
+/// `/usr/bin/apt-get` → `apt-get`, `RUNAS.EXE` → `runas`
+fn program_name(path: &str) -> String {
+    let name = path.rsplit(['/', '\\']).next().unwrap_or(path).to_ascii_lowercase();
+    match name.strip_suffix(".exe") {
+        Some(stem) => stem.to_string(),
+        None => name,
+    }
+}
+
+fn is_assignment(word: &str) -> bool {
+    word.split_once('=').is_some_and(|(name, _)| {
+        !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+    })
+}
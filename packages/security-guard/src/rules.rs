@@ -0,0 +1,116 @@
+//! Built-in rules and command checking
+
+use anyhow::{Context, Result};
+use regex::Regex;
+use std::fmt;
+
+use crate::config::RuleFile;
+
+/// `(id, pattern, message)` for the rules every install starts with
+const BUILTIN_RULES: &[(&str, &str, &str)] = &[
+    (
+        "rm-rf-root",
+        r"\brm\s+(?:-\S+\s+)*(?:-[a-zA-Z]*[rR][a-zA-Z]*|--recursive)\s+(?:-\S+\s+)*(?:/|/\*|~/?|\$HOME/?)(?:\s|[;&|]|$)",
+        "Recursively deleting the filesystem root or home directory",
+    ),
+    (
+        "chmod-777-recursive",
+        r"\bchmod\s+(?:[^;&|]*\s)?(?:-[a-zA-Z]*R[a-zA-Z]*|--recursive)\s+(?:[^;&|]*\s)?0?777\b|\bchmod\s+(?:[^;&|]*\s)?0?777\s+(?:[^;&|]*\s)?(?:-[a-zA-Z]*R[a-zA-Z]*|--recursive)\b",
+        "Recursively making files world-writable",
+    ),
+    (
+        "pipe-to-shell",
+        r"\b(?:curl|wget)\b[^;&|]*\|\s*(?:sudo\s+)?(?:ba|z|k|da|fi)?sh\b",
+        "Piping a download straight into a shell runs unreviewed code",
+    ),
+    (
+        "fork-bomb",
+        r":\s*\(\s*\)\s*\{\s*:\s*\|\s*:\s*&\s*\}\s*;\s*:",
+        "Fork bomb",
+    ),
+    (
+        "write-etc",
+        r">>?\s*/etc/|\btee\s+(?:-\S+\s+)*/etc/|\b(?:cp|mv|install|ln)\s+[^;&|]*\s/etc/\S*\s*(?:[;&|]|$)|\bsed\s+(?:[^;&|]*\s)?-i\S*\s+[^;&|]*\s/etc/",
+        "Writing to system configuration under /etc",
+    ),
+    (
+        "disk-overwrite",
+        r"\bmkfs(?:\.\w+)?\s|\bdd\s+[^;&|]*\bof=/dev/(?:sd|hd|nvme|disk|mmcblk)",
+        "Overwriting a disk or partition",
+    ),
+];
+
+#[derive(Debug)]
+struct Rule {
+    id: String,
+    pattern: Regex,
+    message: String,
+}
+
+/// Which rule a command broke
+#[derive(Debug, Clone, PartialEq)]
+pub struct Violation {
+    pub rule: String,
+    pub message: String,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Blocked by security-guard ({}): {}", self.rule, self.message)
+    }
+}
+
+/// Compiled rules and exceptions
+#[derive(Debug)]
+pub struct RuleSet {
+    rules: Vec<Rule>,
+    allow: Vec<Regex>,
+}
+
+impl RuleSet {
+    /// Compile the built-ins (unless turned off) and the file's own rules,
+    /// dropping disabled ids
+    pub fn from_file(file: &RuleFile) -> Result<Self> {
+        let builtin = BUILTIN_RULES
+            .iter()
+            .filter(|_| file.builtin)
+            .map(|(id, pattern, message)| (id.to_string(), pattern.to_string(), message.to_string()));
+        let custom = file.rules.iter().map(|rule| (rule.id.clone(), rule.pattern.clone(), rule.message.clone()));
+        
+        let mut rules = Vec::new();
+        for (id, pattern, message) in builtin.chain(custom) {
+            if file.disable.contains(&id) {
+                continue;
+            }
+            let pattern = Regex::new(&pattern)
+                .with_context(|| format!("Invalid pattern for rule '{}'", id))?;
+            rules.push(Rule { id, pattern, message });
+        }
+        
+        let allow = file.allow
+            .iter()
+            .map(|pattern| Regex::new(pattern).with_context(|| format!("Invalid allow pattern '{}'", pattern)))
+            .collect::<Result<_>>()?;
+        
+        Ok(Self { rules, allow })
+    }
+    
+    /// Whether an allow pattern exempts `command` from every check
+    pub fn allows(&self, command: &str) -> bool {
+        self.allow.iter().any(|allow| allow.is_match(command))
+    }
+    
+    /// The first rule `command` breaks, unless an allow pattern exempts it
+    pub fn check(&self, command: &str) -> Option<Violation> {
+        if self.allows(command) {
+            return None;
+        }
+        self.rules
+            .iter()
+            .find(|rule| rule.pattern.is_match(command))
+            .map(|rule| Violation {
+                rule: rule.id.clone(),
+                message: rule.message.clone(),
+            })
+    }
+}
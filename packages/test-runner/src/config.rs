@@ -0,0 +1,105 @@
+//! Runner configuration
+//!
+//! Read from `~/.claude/hooks/test-runner/config.toml`, then the project's
+//! `.claude/test-runner.toml`.
+//!
+//! ```toml
+//! mode = "run"
+//! max_concurrent = 2
+//! timeout_ms = 60000
+//! disable = ["vitest"]
+//! ```
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+/// What to do with the tests for an edited file
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Mode {
+    /// Tell Claude which command to run
+    #[default]
+    Suggest,
+    /// Run the tests and report the result
+    Run,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunnerConfig {
+    pub mode: Mode,
+    /// Test runs allowed at once across all hook processes
+    pub max_concurrent: usize,
+    /// How long one test run may take
+    pub timeout_ms: u64,
+    /// Most output lines reported from a failing run
+    pub max_output_lines: usize,
+    /// Runners turned off: `cargo`, `vitest`, `pytest`
+    pub disable: Vec<String>,
+}
+
+impl Default for RunnerConfig {
+    fn default() -> Self {
+        Self {
+            mode: Mode::default(),
+            max_concurrent: 1,
+            timeout_ms: 120_000,
+            max_output_lines: 40,
+            disable: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigLayer {
+    mode: Option<Mode>,
+    max_concurrent: Option<usize>,
+    timeout_ms: Option<u64>,
+    max_output_lines: Option<usize>,
+    #[serde(default)]
+    disable: Vec<String>,
+}
+
+impl RunnerConfig {
+    /// Load the global config, then layer the project's on top
+    pub fn load(project_dir: Option<&Path>) -> Result<Self> {
+        let global = Self::hook_dir().map(|dir| dir.join("config.toml"));
+        let project = project_dir.map(|dir| dir.join(".claude").join("test-runner.toml"));
+        
+        let mut config = Self::default();
+        for path in global.into_iter().chain(project) {
+            if path.is_file() {
+                let content = std::fs::read_to_string(&path)
+                    .with_context(|| format!("Failed to read {}", path.display()))?;
+                config.merge_toml(&content)
+                    .with_context(|| format!("Failed to parse {}", path.display()))?;
+            }
+        }
+        Ok(config)
+    }
+    
+    /// Layer one config file's contents on top of this config
+    pub fn merge_toml(&mut self, content: &str) -> Result<()> {
+        let layer: ConfigLayer = toml::from_str(content)?;
+        self.mode = layer.mode.unwrap_or(self.mode);
+        self.max_concurrent = layer.max_concurrent.unwrap_or(self.max_concurrent);
+        self.timeout_ms = layer.timeout_ms.unwrap_or(self.timeout_ms);
+        self.max_output_lines = layer.max_output_lines.unwrap_or(self.max_output_lines);
+        self.disable.extend(layer.disable);
+        Ok(())
+    }
+    
+    pub fn is_enabled(&self, runner: &str) -> bool {
+        !self.disable.iter().any(|disabled| disabled == runner)
+    }
+    
+    /// Where the concurrency slots live
+    pub fn lock_dir(&self) -> PathBuf {
+        Self::hook_dir().unwrap_or_else(std::env::temp_dir).join("locks")
+    }
+    
+    fn hook_dir() -> Option<PathBuf> {
+        dirs::home_dir().map(|home| home.join(".claude").join("hooks").join("test-runner"))
+    }
+}
@@ -0,0 +1,61 @@
+//! Test Runner PostToolUse Hook
+//!
+//! Maps an edited file to the tests that cover it and either suggests the
+//! command to Claude or runs it right away:
+//! - Rust: `cargo test` filtered to the file's module, or `--test` for
+//!   integration tests
+//! - JavaScript/TypeScript: `vitest related`
+//! - Python: `pytest` on the file's test module
+
+use anyhow::Result;
+use claude_hooks_sdk::{HookInput, HookOutput};
+use serde::Deserialize;
+
+mod config;
+mod runner;
+mod targets;
+#[cfg(test)]
+mod tests;
+
+use config::{Mode, RunnerConfig};
+use runner::RunOutcome;
+
+/// Tool data of a file-editing tool
+#[derive(Debug, Default, Deserialize)]
+struct EditToolData {
+    #[serde(default)]
+    file_path: Option<String>,
+}
+
+fn main() {
+    claude_hooks_sdk::run("Test runner", run);
+}
+
+fn run(input: HookInput) -> Result<HookOutput> {
+    if input.event.event_type != "PostToolUse" {
+        return Ok(HookOutput::approve());
+    }
+    let tool_data: EditToolData = input.event.tool_data().unwrap_or_default();
+    let Some(file_path) = tool_data.file_path else {
+        return Ok(HookOutput::approve());
+    };
+    
+    let project_dir = input.project_dir();
+    let config = RunnerConfig::load(Some(project_dir))?;
+    let file = project_dir.join(&file_path);
+    let Some(target) = targets::target_for(&config, project_dir, &file) else {
+        return Ok(HookOutput::approve());
+    };
+    
+    let context = match config.mode {
+        Mode::Suggest => format!("test-runner: run `{}` to check {}", target, file_path),
+        Mode::Run => match runner::run_target(&config, &target, &config.lock_dir())? {
+            RunOutcome::Passed => format!("test-runner: `{}` passed", target),
+            RunOutcome::Failed(output) => format!("test-runner: `{}` failed:\n{}", target, output),
+            RunOutcome::TimedOut => format!("test-runner: `{}` timed out after {} ms", target, config.timeout_ms),
+            // Too many runs in flight; leave it to Claude
+            RunOutcome::Busy => format!("test-runner: run `{}` to check {}", target, file_path),
+        },
+    };
+    Ok(HookOutput::additional_context(context))
+}
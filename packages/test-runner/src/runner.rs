@@ -0,0 +1,99 @@
+//! Running a test target under the concurrency and timeout limits
+//!
+//! Hook processes don't share memory, so concurrency is limited with slot
+//! files: a run claims `slot-N.lock` by creating it exclusively and removes
+//! it when done. A slot older than twice the timeout belongs to a process
+//! that died and is reclaimed.
+
+use anyhow::{Context, Result};
+use std::fs::OpenOptions;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::config::RunnerConfig;
+use crate::targets::TestTarget;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RunOutcome {
+    Passed,
+    /// The last lines of the run's output
+    Failed(String),
+    TimedOut,
+    /// Every slot was taken
+    Busy,
+}
+
+/// A claimed concurrency slot, released on drop
+struct Slot(PathBuf);
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+fn claim_slot(lock_dir: &Path, max_concurrent: usize, stale_after: Duration) -> Result<Option<Slot>> {
+    std::fs::create_dir_all(lock_dir)
+        .with_context(|| format!("Failed to create {}", lock_dir.display()))?;
+    for index in 0..max_concurrent {
+        let path = lock_dir.join(format!("slot-{}.lock", index));
+        let stale = std::fs::metadata(&path)
+            .and_then(|meta| meta.modified())
+            .is_ok_and(|modified| SystemTime::now().duration_since(modified).unwrap_or_default() > stale_after);
+        if stale {
+            let _ = std::fs::remove_file(&path);
+        }
+        if OpenOptions::new().write(true).create_new(true).open(&path).is_ok() {
+            return Ok(Some(Slot(path)));
+        }
+    }
+    Ok(None)
+}
+
+/// Run `target` if a slot is free
+pub fn run_target(config: &RunnerConfig, target: &TestTarget, lock_dir: &Path) -> Result<RunOutcome> {
+    let timeout = Duration::from_millis(config.timeout_ms);
+    let Some(_slot) = claim_slot(lock_dir, config.max_concurrent.max(1), timeout * 2)? else {
+        return Ok(RunOutcome::Busy);
+    };
+    
+    let log = std::env::temp_dir().join(format!("test-runner-{}.log", std::process::id()));
+    let output_file = std::fs::File::create(&log)
+        .with_context(|| format!("Failed to create {}", log.display()))?;
+    let mut child = Command::new(&target.program)
+        .args(&target.args)
+        .current_dir(&target.cwd)
+        .stdin(Stdio::null())
+        .stdout(output_file.try_clone()?)
+        .stderr(output_file)
+        .spawn()
+        .with_context(|| format!("Failed to start {}", target.program))?;
+    
+    let started = Instant::now();
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break Some(status);
+        }
+        if started.elapsed() >= timeout {
+            let _ = child.kill();
+            let _ = child.wait();
+            break None;
+        }
+        std::thread::sleep(Duration::from_millis(20));
+    };
+    
+    let output = std::fs::read_to_string(&log).unwrap_or_default();
+    let _ = std::fs::remove_file(&log);
+    Ok(match status {
+        None => RunOutcome::TimedOut,
+        Some(status) if status.success() => RunOutcome::Passed,
+        Some(_) => RunOutcome::Failed(tail(&output, config.max_output_lines)),
+    })
+}
+
+/// The last `lines` lines of `text`
+fn tail(text: &str, lines: usize) -> String {
+    let all: Vec<&str> = text.lines().collect();
+    all[all.len().saturating_sub(lines)..].join("\n")
+}
@@ -0,0 +1,114 @@
+//! Mapping an edited file to the test command that covers it
+
+use std::fmt;
+use std::path::{Component, Path, PathBuf};
+
+use crate::config::RunnerConfig;
+
+/// A test command and where to run it
+#[derive(Debug, Clone, PartialEq)]
+pub struct TestTarget {
+    pub program: String,
+    pub args: Vec<String>,
+    pub cwd: PathBuf,
+}
+
+impl fmt::Display for TestTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.program)?;
+        for arg in &self.args {
+            if arg.contains(char::is_whitespace) {
+                write!(f, " '{}'", arg)?;
+            } else {
+                write!(f, " {}", arg)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The tests for `file`, if a runner knows how to find them
+pub fn target_for(config: &RunnerConfig, project_dir: &Path, file: &Path) -> Option<TestTarget> {
+    let extension = file.extension()?.to_str()?;
+    match extension {
+        "rs" if config.is_enabled("cargo") => cargo_target(file),
+        "js" | "jsx" | "mjs" | "cjs" | "ts" | "tsx" | "mts" | "cts" if config.is_enabled("vitest") => vitest_target(file),
+        "py" if config.is_enabled("pytest") => pytest_target(project_dir, file),
+        _ => None,
+    }
+}
+
+/// `cargo test --test NAME` for integration tests, otherwise `cargo test`
+/// filtered to the file's module path (`src/config/load.rs` → `config::load`)
+fn cargo_target(file: &Path) -> Option<TestTarget> {
+    let (crate_dir, manifest) = enclosing(file, "Cargo.toml")?;
+    let relative = file.strip_prefix(&crate_dir).ok()?;
+    let mut args = vec!["test".to_string(), "--manifest-path".to_string(), manifest.display().to_string()];
+    
+    let mut components: Vec<String> = relative
+        .components()
+        .filter_map(|component| match component {
+            Component::Normal(name) => name.to_str().map(str::to_string),
+            _ => None,
+        })
+        .collect();
+    match components.first().map(String::as_str) {
+        Some("tests") if components.len() == 2 => {
+            args.extend(["--test".to_string(), file.file_stem()?.to_str()?.to_string()]);
+        }
+        Some("src") => {
+            components.remove(0);
+            if let Some(last) = components.last_mut() {
+                *last = last.trim_end_matches(".rs").to_string();
+            }
+            // Crate roots and `mod.rs` name their parent module
+            if matches!(components.last().map(String::as_str), Some("lib" | "main" | "mod")) {
+                components.pop();
+            }
+            if !components.is_empty() {
+                args.extend(["--".to_string(), components.join("::")]);
+            }
+        }
+        _ => {}
+    }
+    
+    Some(TestTarget { program: "cargo".to_string(), args, cwd: crate_dir })
+}
+
+/// `npx vitest related --run FILE` from the enclosing package
+fn vitest_target(file: &Path) -> Option<TestTarget> {
+    let (package_dir, _) = enclosing(file, "package.json")?;
+    Some(TestTarget {
+        program: "npx".to_string(),
+        args: vec!["vitest".to_string(), "related".to_string(), "--run".to_string(), file.display().to_string()],
+        cwd: package_dir,
+    })
+}
+
+/// `pytest` on the file itself if it is a test module, otherwise on a
+/// `test_<name>.py` beside it or under the project's `tests/`
+fn pytest_target(project_dir: &Path, file: &Path) -> Option<TestTarget> {
+    let stem = file.file_stem()?.to_str()?;
+    let test_file = if stem.starts_with("test_") || stem.ends_with("_test") {
+        file.to_path_buf()
+    } else {
+        let name = format!("test_{}.py", stem);
+        [file.parent()?.join(&name), file.parent()?.join("tests").join(&name), project_dir.join("tests").join(&name)]
+            .into_iter()
+            .find(|candidate| candidate.is_file())?
+    };
+    
+    Some(TestTarget {
+        program: "pytest".to_string(),
+        args: vec![test_file.display().to_string()],
+        cwd: project_dir.to_path_buf(),
+    })
+}
+
+/// The nearest directory above `file` containing `marker`, and the marker
+fn enclosing(file: &Path, marker: &str) -> Option<(PathBuf, PathBuf)> {
+    file.ancestors()
+        .skip(1)
+        .map(|dir| (dir.to_path_buf(), dir.join(marker)))
+        .find(|(_, path)| path.is_file())
+}
@@ -0,0 +1,86 @@
+//! Tests for target mapping and run limits
+
+use std::path::{Path, PathBuf};
+
+use crate::config::RunnerConfig;
+use crate::runner::{run_target, RunOutcome};
+use crate::targets::{target_for, TestTarget};
+
+fn temp_project(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("test-runner-{}-{}", name, std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn touch(path: &Path) {
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(path, "").unwrap();
+}
+
+#[test]
+fn test_cargo_targets() {
+    let dir = temp_project("cargo");
+    touch(&dir.join("Cargo.toml"));
+    let config = RunnerConfig::default();
+    let args = |file: &str| target_for(&config, &dir, &dir.join(file)).unwrap().args[3..].join(" ");
+    
+    assert_eq!(args("src/config/load.rs"), "-- config::load");
+    assert_eq!(args("src/config/mod.rs"), "-- config");
+    assert_eq!(args("src/lib.rs"), "");
+    assert_eq!(args("tests/cli.rs"), "--test cli");
+    
+    let target = target_for(&config, &dir, &dir.join("src/rules.rs")).unwrap();
+    assert_eq!(target.to_string(), format!("cargo test --manifest-path {} -- rules", dir.join("Cargo.toml").display()));
+    
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_vitest_and_pytest_targets() {
+    let dir = temp_project("other");
+    touch(&dir.join("web/package.json"));
+    touch(&dir.join("tests/test_parser.py"));
+    let config = RunnerConfig::default();
+    
+    let vitest = target_for(&config, &dir, &dir.join("web/src/app.ts")).unwrap();
+    assert_eq!(vitest.program, "npx");
+    assert_eq!(vitest.cwd, dir.join("web"));
+    
+    let pytest = target_for(&config, &dir, &dir.join("pkg/parser.py")).unwrap();
+    assert_eq!(pytest.args, vec![dir.join("tests/test_parser.py").display().to_string()]);
+    assert!(target_for(&config, &dir, &dir.join("pkg/untested.py")).is_none());
+    
+    let mut config = RunnerConfig::default();
+    config.merge_toml(r#"disable = ["vitest"]"#).unwrap();
+    assert!(target_for(&config, &dir, &dir.join("web/src/app.ts")).is_none());
+    
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_run_limits() {
+    let dir = temp_project("run");
+    let shell = |script: &str| TestTarget {
+        program: "sh".to_string(),
+        args: vec!["-c".to_string(), script.to_string()],
+        cwd: dir.clone(),
+    };
+    let mut config = RunnerConfig { max_output_lines: 1, ..RunnerConfig::default() };
+    let locks = dir.join("locks");
+    
+    assert_eq!(run_target(&config, &shell("true"), &locks).unwrap(), RunOutcome::Passed);
+    assert_eq!(
+        run_target(&config, &shell("echo first; echo 'assertion failed'; exit 1"), &locks).unwrap(),
+        RunOutcome::Failed("assertion failed".to_string()),
+    );
+    
+    config.timeout_ms = 50;
+    assert_eq!(run_target(&config, &shell("sleep 5"), &locks).unwrap(), RunOutcome::TimedOut);
+    
+    // A held slot makes the next run back off
+    config.timeout_ms = 60_000;
+    touch(&locks.join("slot-0.lock"));
+    assert_eq!(run_target(&config, &shell("true"), &locks).unwrap(), RunOutcome::Busy);
+    
+    std::fs::remove_dir_all(&dir).unwrap();
+}
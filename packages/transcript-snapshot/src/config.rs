@@ -0,0 +1,81 @@
+//! Transcript snapshot configuration
+//!
+//! Read from `~/.claude/hooks/transcript-snapshot/config.toml`, then the
+//! project's `.claude/transcript-snapshot.toml`.
+//!
+//! ```toml
+//! archive_dir = "~/.claude/transcripts"
+//! # Snapshots kept per session; the oldest are removed first
+//! max_snapshots = 20
+//! ```
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SnapshotConfig {
+    pub enabled: bool,
+    /// Snapshots go in a directory per session under this one
+    pub archive_dir: PathBuf,
+    /// Snapshots kept per session; 0 keeps them all
+    pub max_snapshots: usize,
+}
+
+impl Default for SnapshotConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            archive_dir: dirs::home_dir()
+                .unwrap_or_else(|| PathBuf::from("."))
+                .join(".claude")
+                .join("transcripts"),
+            max_snapshots: 20,
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigLayer {
+    enabled: Option<bool>,
+    archive_dir: Option<String>,
+    max_snapshots: Option<usize>,
+}
+
+impl SnapshotConfig {
+    /// Load the global config, then layer the project's on top
+    pub fn load(project_dir: Option<&Path>) -> Result<Self> {
+        let global = dirs::home_dir().map(|home| home.join(".claude/hooks/transcript-snapshot/config.toml"));
+        let project = project_dir.map(|dir| dir.join(".claude").join("transcript-snapshot.toml"));
+        
+        let mut config = Self::default();
+        for path in global.into_iter().chain(project) {
+            if path.is_file() {
+                let content = std::fs::read_to_string(&path)
+                    .with_context(|| format!("Failed to read {}", path.display()))?;
+                config.merge_toml(&content)
+                    .with_context(|| format!("Failed to parse {}", path.display()))?;
+            }
+        }
+        Ok(config)
+    }
+    
+    /// Layer one config file's contents on top of this config
+    pub fn merge_toml(&mut self, content: &str) -> Result<()> {
+        let layer: ConfigLayer = toml::from_str(content)?;
+        self.enabled = layer.enabled.unwrap_or(self.enabled);
+        if let Some(archive_dir) = layer.archive_dir {
+            self.archive_dir = expand_home(&archive_dir);
+        }
+        self.max_snapshots = layer.max_snapshots.unwrap_or(self.max_snapshots);
+        Ok(())
+    }
+}
+
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), dirs::home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest),
+        _ => PathBuf::from(path),
+    }
+}
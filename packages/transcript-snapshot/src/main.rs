@@ -0,0 +1,53 @@
+//! Transcript Snapshot PreCompact Hook
+//!
+//! Before Claude compacts its context, copies the session transcript to a
+//! timestamped archive under `~/.claude/transcripts/<session>/` and writes
+//! the Bash commands run so far next to it, so the session's command
+//! history survives the compaction. It never blocks anything.
+
+use anyhow::{Context, Result};
+use claude_hooks_sdk::{HookInput, HookOutput};
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+mod config;
+mod snapshot;
+#[cfg(test)]
+mod tests;
+
+use config::SnapshotConfig;
+
+fn main() {
+    claude_hooks_sdk::run("Transcript snapshot", run);
+}
+
+fn run(input: HookInput) -> Result<HookOutput> {
+    if input.event.event_type != "PreCompact" {
+        return Ok(HookOutput::approve());
+    }
+    let config = SnapshotConfig::load(Some(input.project_dir()))?;
+    if !config.enabled {
+        return Ok(HookOutput::approve());
+    }
+    
+    // The official schema keeps the path with the session, the envelope
+    // with the event
+    let field = |name: &str| {
+        input.session.context.get(name)
+            .or_else(|| input.event.data.get(name))
+            .and_then(|value| value.as_str())
+    };
+    let transcript = field("transcript_path").map(PathBuf::from).context("No transcript_path in PreCompact event")?;
+    let trigger = field("trigger").unwrap_or("compact");
+    let ts_ms = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_millis() as u64);
+    
+    let snapshot = snapshot::write(&config, &input.session.id, trigger, &transcript, ts_ms)?;
+    Ok(HookOutput::approve().with_system_message(format!(
+        "Transcript saved to {} ({} commands in {})",
+        snapshot.transcript.display(),
+        snapshot.command_count,
+        snapshot.commands.display(),
+    )))
+}
@@ -0,0 +1,143 @@
+//! Transcript archives and the command history pulled out of them
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+use crate::config::SnapshotConfig;
+
+/// One Bash call found in the transcript
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommandEntry {
+    /// As recorded in the transcript, when it was
+    pub timestamp: Option<String>,
+    pub command: String,
+    pub description: Option<String>,
+}
+
+/// The parts of a transcript line that can hold tool calls
+#[derive(Debug, Deserialize)]
+struct TranscriptLine {
+    #[serde(default)]
+    timestamp: Option<String>,
+    #[serde(default)]
+    message: Option<Message>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Message {
+    #[serde(default)]
+    content: serde_json::Value,
+}
+
+/// Every Bash command in a JSONL transcript, in order; lines that don't
+/// parse are skipped
+pub fn commands(transcript: &str) -> Vec<CommandEntry> {
+    transcript
+        .lines()
+        .filter_map(|line| serde_json::from_str::<TranscriptLine>(line).ok())
+        .flat_map(|line| {
+            let timestamp = line.timestamp;
+            let content = line.message.map(|message| message.content).unwrap_or_default();
+            let blocks = match content {
+                serde_json::Value::Array(blocks) => blocks,
+                _ => Vec::new(),
+            };
+            blocks.into_iter().filter_map(move |block| {
+                if block.get("type")?.as_str()? != "tool_use" || block.get("name")?.as_str()? != "Bash" {
+                    return None;
+                }
+                let input = block.get("input")?;
+                Some(CommandEntry {
+                    timestamp: timestamp.clone(),
+                    command: input.get("command")?.as_str()?.to_string(),
+                    description: input.get("description").and_then(|d| d.as_str()).map(str::to_string),
+                })
+            })
+        })
+        .collect()
+}
+
+/// The command history as a shell-style listing, each command preceded by
+/// a comment saying when it ran and why
+pub fn render_commands(entries: &[CommandEntry]) -> String {
+    let mut out = String::new();
+    for entry in entries {
+        let note = [entry.timestamp.as_deref(), entry.description.as_deref()]
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>()
+            .join(" ");
+        if !note.is_empty() {
+            out.push_str(&format!("# {}\n", note));
+        }
+        out.push_str(&entry.command);
+        out.push('\n');
+    }
+    out
+}
+
+/// Where one snapshot was written
+#[derive(Debug, Clone, PartialEq)]
+pub struct Snapshot {
+    pub transcript: PathBuf,
+    pub commands: PathBuf,
+    pub command_count: usize,
+}
+
+/// Archive the transcript at `transcript` for `session_id`, next to the
+/// list of commands it ran
+pub fn write(config: &SnapshotConfig, session_id: &str, trigger: &str, transcript: &Path, ts_ms: u64) -> Result<Snapshot> {
+    let content = std::fs::read_to_string(transcript)
+        .with_context(|| format!("Failed to read {}", transcript.display()))?;
+    let dir = config.archive_dir.join(sanitize(if session_id.is_empty() { "unknown" } else { session_id }));
+    std::fs::create_dir_all(&dir)
+        .with_context(|| format!("Failed to create {}", dir.display()))?;
+    
+    let stem = format!("{}-{}", stamp(ts_ms), sanitize(trigger));
+    let snapshot = Snapshot {
+        transcript: dir.join(format!("{}.jsonl", stem)),
+        commands: dir.join(format!("{}.commands.sh", stem)),
+        command_count: 0,
+    };
+    let entries = commands(&content);
+    std::fs::write(&snapshot.transcript, &content)
+        .with_context(|| format!("Failed to write {}", snapshot.transcript.display()))?;
+    std::fs::write(&snapshot.commands, render_commands(&entries))
+        .with_context(|| format!("Failed to write {}", snapshot.commands.display()))?;
+    
+    prune(&dir, config.max_snapshots)?;
+    Ok(Snapshot { command_count: entries.len(), ..snapshot })
+}
+
+/// Drop the oldest snapshots in `dir` beyond `keep`
+fn prune(dir: &Path, keep: usize) -> Result<()> {
+    if keep == 0 {
+        return Ok(());
+    }
+    // Stamps sort chronologically, so names do too
+    let mut transcripts: Vec<PathBuf> = std::fs::read_dir(dir)
+        .with_context(|| format!("Failed to list {}", dir.display()))?
+        .filter_map(|entry| Some(entry.ok()?.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "jsonl"))
+        .collect();
+    transcripts.sort();
+    let excess = transcripts.len().saturating_sub(keep);
+    for path in &transcripts[..excess] {
+        let _ = std::fs::remove_file(path.with_extension("commands.sh"));
+        std::fs::remove_file(path).with_context(|| format!("Failed to remove {}", path.display()))?;
+    }
+    Ok(())
+}
+
+/// `YYYY-MM-DDTHH-MM-SS.mmmZ`: RFC 3339 without the colons Windows won't
+/// put in a file name
+pub fn stamp(ts_ms: u64) -> String {
+    claude_hooks_sdk::rfc3339(ts_ms).replace(':', "-")
+}
+
+fn sanitize(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect()
+}
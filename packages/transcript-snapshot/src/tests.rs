@@ -0,0 +1,57 @@
+//! Tests for transcript snapshots
+
+use crate::config::SnapshotConfig;
+use crate::snapshot::{self, CommandEntry};
+
+const TRANSCRIPT: &str = r#"{"type":"user","timestamp":"2026-01-02T10:00:00Z","message":{"role":"user","content":"run the tests"}}
+{"type":"assistant","timestamp":"2026-01-02T10:00:05Z","message":{"role":"assistant","content":[{"type":"text","text":"Running them"},{"type":"tool_use","id":"t1","name":"Bash","input":{"command":"cargo test","description":"Run tests"}}]}}
+not json
+{"type":"assistant","timestamp":"2026-01-02T10:01:00Z","message":{"role":"assistant","content":[{"type":"tool_use","id":"t2","name":"Read","input":{"file_path":"src/lib.rs"}},{"type":"tool_use","id":"t3","name":"Bash","input":{"command":"git status"}}]}}
+"#;
+
+#[test]
+fn test_extract_commands() {
+    let entries = snapshot::commands(TRANSCRIPT);
+    assert_eq!(entries, vec![
+        CommandEntry {
+            timestamp: Some("2026-01-02T10:00:05Z".to_string()),
+            command: "cargo test".to_string(),
+            description: Some("Run tests".to_string()),
+        },
+        CommandEntry {
+            timestamp: Some("2026-01-02T10:01:00Z".to_string()),
+            command: "git status".to_string(),
+            description: None,
+        },
+    ]);
+    
+    assert_eq!(
+        snapshot::render_commands(&entries),
+        "# 2026-01-02T10:00:05Z Run tests\ncargo test\n# 2026-01-02T10:01:00Z\ngit status\n",
+    );
+}
+
+#[test]
+fn test_write_and_prune() {
+    let dir = std::env::temp_dir().join(format!("transcript-snapshot-{}", std::process::id()));
+    let transcript = dir.join("session.jsonl");
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(&transcript, TRANSCRIPT).unwrap();
+    
+    let mut config = SnapshotConfig::default();
+    config.merge_toml(&format!("archive_dir = {:?}\nmax_snapshots = 2", dir.join("archive").display().to_string())).unwrap();
+    
+    let first = snapshot::write(&config, "s/1", "auto", &transcript, 1_000).unwrap();
+    assert_eq!(first.command_count, 2);
+    assert_eq!(first.transcript, dir.join("archive/s_1/1970-01-01T00-00-01.000Z-auto.jsonl"));
+    assert_eq!(std::fs::read_to_string(&first.transcript).unwrap(), TRANSCRIPT);
+    assert!(std::fs::read_to_string(&first.commands).unwrap().contains("git status"));
+    
+    // Only the newest two survive
+    snapshot::write(&config, "s/1", "manual", &transcript, 2_000).unwrap();
+    snapshot::write(&config, "s/1", "auto", &transcript, 3_000).unwrap();
+    assert!(!first.transcript.exists() && !first.commands.exists());
+    assert_eq!(std::fs::read_dir(dir.join("archive/s_1")).unwrap().count(), 4);
+    
+    let _ = std::fs::remove_dir_all(&dir);
+}
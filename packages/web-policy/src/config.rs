@@ -0,0 +1,70 @@
+//! Web policy configuration
+//!
+//! Read from `~/.claude/hooks/web-policy/config.toml`, then the project's
+//! `.claude/web-policy.toml`; lists are combined and project mirrors
+//! override global ones.
+//!
+//! ```toml
+//! # Only these domains (and their subdomains) may be fetched or searched
+//! allow = ["docs.rs", "*.rust-lang.org", "github.com"]
+//! # Never these, even when allowed
+//! block = ["pastebin.com"]
+//!
+//! # URL prefixes fetched from a faster mirror or a local cache instead
+//! [mirrors]
+//! "https://docs.python.org/3/" = "http://localhost:8000/python-docs/"
+//! ```
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WebConfig {
+    /// Domains that may be reached; empty allows any not blocked
+    pub allow: Vec<String>,
+    /// Domains that are never reached
+    pub block: Vec<String>,
+    /// URL prefix -> replacement prefix
+    pub mirrors: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigLayer {
+    #[serde(default)]
+    allow: Vec<String>,
+    #[serde(default)]
+    block: Vec<String>,
+    #[serde(default)]
+    mirrors: BTreeMap<String, String>,
+}
+
+impl WebConfig {
+    /// Load the global config, then layer the project's on top
+    pub fn load(project_dir: Option<&Path>) -> Result<Self> {
+        let global = dirs::home_dir().map(|home| home.join(".claude/hooks/web-policy/config.toml"));
+        let project = project_dir.map(|dir| dir.join(".claude").join("web-policy.toml"));
+        
+        let mut config = Self::default();
+        for path in global.into_iter().chain(project) {
+            if path.is_file() {
+                let content = std::fs::read_to_string(&path)
+                    .with_context(|| format!("Failed to read {}", path.display()))?;
+                config.merge_toml(&content)
+                    .with_context(|| format!("Failed to parse {}", path.display()))?;
+            }
+        }
+        Ok(config)
+    }
+    
+    /// Layer one config file's contents on top of this config
+    pub fn merge_toml(&mut self, content: &str) -> Result<()> {
+        let layer: ConfigLayer = toml::from_str(content)?;
+        self.allow.extend(layer.allow);
+        self.block.extend(layer.block);
+        self.mirrors.extend(layer.mirrors);
+        Ok(())
+    }
+}
@@ -0,0 +1,68 @@
+//! Web Policy PreToolUse Hook
+//!
+//! Applies a per-project domain policy to WebFetch and WebSearch: fetches
+//! of blocked or unlisted domains are refused, known-slow documentation
+//! URLs are switched to configured mirrors, and searches are narrowed to
+//! the allowed domains (or told to skip the blocked ones) through the
+//! tool's own `allowed_domains`/`blocked_domains` parameters.
+
+use anyhow::Result;
+use claude_hooks_sdk::{HookInput, HookOutput};
+use serde::Deserialize;
+
+mod config;
+mod policy;
+#[cfg(test)]
+mod tests;
+
+use config::WebConfig;
+use policy::Verdict;
+
+/// The fields of WebFetch and WebSearch tool data this hook looks at
+#[derive(Debug, Default, Deserialize)]
+struct ToolData {
+    #[serde(default)]
+    url: Option<String>,
+    #[serde(default)]
+    query: Option<String>,
+    #[serde(default)]
+    allowed_domains: Vec<String>,
+    #[serde(default)]
+    blocked_domains: Vec<String>,
+}
+
+fn main() {
+    claude_hooks_sdk::run("Web policy", run);
+}
+
+fn run(input: HookInput) -> Result<HookOutput> {
+    if input.event.event_type != "PreToolUse" {
+        return Ok(HookOutput::approve());
+    }
+    let tool_data: ToolData = input.event.tool_data().unwrap_or_default();
+    // Without a tool name, the input's shape tells the two tools apart
+    let tool = input.event.tool_name.as_deref();
+    let is_fetch = tool.map_or(tool_data.url.is_some(), |tool| tool == "WebFetch");
+    let is_search = tool.map_or(tool_data.query.is_some(), |tool| tool == "WebSearch");
+    if !is_fetch && !is_search {
+        return Ok(HookOutput::approve());
+    }
+    
+    let config = WebConfig::load(Some(input.project_dir()))?;
+    let verdict = match (&tool_data.url, is_fetch) {
+        (Some(url), true) => policy::check_fetch(url, &config),
+        _ => policy::check_search(&tool_data.allowed_domains, &tool_data.blocked_domains, &config),
+    };
+    
+    Ok(match verdict {
+        Verdict::Allow => HookOutput::approve(),
+        Verdict::Block(reason) => HookOutput::block(reason),
+        Verdict::Update { fields, message } => {
+            let output = HookOutput::approve().with_context(serde_json::json!({ "updated_input": fields }));
+            match message {
+                Some(message) => output.with_system_message(message),
+                None => output,
+            }
+        }
+    })
+}
@@ -0,0 +1,106 @@
+//! Domain policy for WebFetch URLs and WebSearch queries
+
+use crate::config::WebConfig;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Verdict {
+    Allow,
+    Block(String),
+    /// Proceed with these tool input fields replaced
+    Update {
+        fields: serde_json::Map<String, serde_json::Value>,
+        message: Option<String>,
+    },
+}
+
+/// The lowercased host of an absolute URL, without userinfo or port
+pub fn host(url: &str) -> Option<String> {
+    let (_, rest) = url.split_once("://")?;
+    let authority = rest.split(['/', '?', '#']).next()?;
+    let host = authority.rsplit('@').next()?;
+    // Bracketed IPv6 hosts keep their colons
+    let host = match host.strip_prefix('[') {
+        Some(v6) => v6.split(']').next()?,
+        None => host.split(':').next()?,
+    };
+    (!host.is_empty()).then(|| host.trim_end_matches('.').to_ascii_lowercase())
+}
+
+/// Whether `host` is `pattern` or one of its subdomains; a leading `*.` is
+/// accepted and means the same
+pub fn domain_matches(host: &str, pattern: &str) -> bool {
+    let pattern = pattern.trim().trim_start_matches("*.").to_ascii_lowercase();
+    host == pattern || host.strip_suffix(pattern.as_str()).is_some_and(|prefix| prefix.ends_with('.'))
+}
+
+/// Why `host` may not be reached, if it may not
+fn refusal(host: &str, config: &WebConfig) -> Option<String> {
+    if let Some(pattern) = config.block.iter().find(|pattern| domain_matches(host, pattern)) {
+        return Some(format!("{} is blocked by web-policy (matched `{}`)", host, pattern));
+    }
+    if !config.allow.is_empty() && !config.allow.iter().any(|pattern| domain_matches(host, pattern)) {
+        return Some(format!("{} is not in the web-policy allowlist", host));
+    }
+    None
+}
+
+/// Check a WebFetch URL, switching it to a configured mirror first
+pub fn check_fetch(url: &str, config: &WebConfig) -> Verdict {
+    // The longest matching prefix wins
+    let mirrored = config.mirrors.iter()
+        .filter(|(prefix, _)| url.starts_with(prefix.as_str()))
+        .max_by_key(|(prefix, _)| prefix.len())
+        .map(|(prefix, mirror)| format!("{}{}", mirror, &url[prefix.len()..]));
+    
+    // Mirrors are trusted; it is the original host that has to be allowed
+    let Some(host) = host(url) else {
+        return Verdict::Block(format!("web-policy could not find a host in `{}`", url));
+    };
+    if let Some(reason) = refusal(&host, config) {
+        return Verdict::Block(reason);
+    }
+    
+    match mirrored {
+        Some(mirrored) => Verdict::Update {
+            message: Some(format!("web-policy: fetching {} instead of {}", mirrored, url)),
+            fields: serde_json::Map::from_iter([("url".to_string(), mirrored.into())]),
+        },
+        None => Verdict::Allow,
+    }
+}
+
+/// Narrow a WebSearch to the allowed domains, or exclude the blocked ones.
+/// The search API takes one list or the other, never both.
+pub fn check_search(allowed: &[String], blocked: &[String], config: &WebConfig) -> Verdict {
+    let mut fields = serde_json::Map::new();
+    if !allowed.is_empty() {
+        // Claude asked for specific sites; keep the permitted ones
+        let permitted: Vec<&str> = allowed.iter()
+            .map(String::as_str)
+            .filter(|domain| refusal(&domain.to_ascii_lowercase(), config).is_none())
+            .collect();
+        if permitted.is_empty() {
+            return Verdict::Block(format!("web-policy permits none of the requested domains: {}", allowed.join(", ")));
+        }
+        if permitted.len() == allowed.len() {
+            return Verdict::Allow;
+        }
+        fields.insert("allowed_domains".to_string(), permitted.into());
+    } else if !config.allow.is_empty() {
+        let domains: Vec<&str> = config.allow.iter().map(|pattern| pattern.trim().trim_start_matches("*.")).collect();
+        fields.insert("allowed_domains".to_string(), domains.into());
+    } else {
+        let mut domains = blocked.to_vec();
+        for pattern in &config.block {
+            let domain = pattern.trim().trim_start_matches("*.").to_string();
+            if !domains.contains(&domain) {
+                domains.push(domain);
+            }
+        }
+        if domains.len() == blocked.len() {
+            return Verdict::Allow;
+        }
+        fields.insert("blocked_domains".to_string(), domains.into());
+    }
+    Verdict::Update { fields, message: None }
+}
@@ -0,0 +1,61 @@
+//! Tests for domain matching, mirrors, and search narrowing
+
+use serde_json::json;
+
+use crate::config::WebConfig;
+use crate::policy::{check_fetch, check_search, domain_matches, host, Verdict};
+
+fn config(content: &str) -> WebConfig {
+    let mut config = WebConfig::default();
+    config.merge_toml(content).unwrap();
+    config
+}
+
+#[test]
+fn test_hosts_and_domains() {
+    assert_eq!(host("https://user:pw@Docs.RS:443/serde?x#y").as_deref(), Some("docs.rs"));
+    assert_eq!(host("http://[::1]:8000/").as_deref(), Some("::1"));
+    assert_eq!(host("docs.rs/serde"), None);
+    
+    assert!(domain_matches("doc.rust-lang.org", "*.rust-lang.org"));
+    assert!(domain_matches("rust-lang.org", "rust-lang.org"));
+    assert!(!domain_matches("evilrust-lang.org", "rust-lang.org"));
+}
+
+#[test]
+fn test_fetch_policy_and_mirrors() {
+    let config = config(r#"
+        allow = ["docs.python.org", "github.com"]
+        block = ["gist.github.com"]
+        [mirrors]
+        "https://docs.python.org/" = "http://localhost:8000/"
+        "https://docs.python.org/3/library/" = "http://localhost:8000/lib/"
+    "#);
+    
+    assert_eq!(check_fetch("https://github.com/rust-lang/rust", &config), Verdict::Allow);
+    assert!(matches!(check_fetch("https://gist.github.com/x", &config), Verdict::Block(_)));
+    assert!(matches!(check_fetch("https://example.com/", &config), Verdict::Block(_)));
+    
+    let Verdict::Update { fields, .. } = check_fetch("https://docs.python.org/3/library/os.html", &config) else {
+        panic!("expected a mirror");
+    };
+    assert_eq!(fields["url"], "http://localhost:8000/lib/os.html");
+}
+
+#[test]
+fn test_search_narrowing() {
+    let open = config(r#"block = ["pinterest.com"]"#);
+    let Verdict::Update { fields, .. } = check_search(&[], &["quora.com".to_string()], &open) else {
+        panic!("expected blocked domains");
+    };
+    assert_eq!(fields["blocked_domains"], json!(["quora.com", "pinterest.com"]));
+    
+    let closed = config(r#"allow = ["*.rust-lang.org", "docs.rs"]"#);
+    let Verdict::Update { fields, .. } = check_search(&[], &[], &closed) else {
+        panic!("expected allowed domains");
+    };
+    assert_eq!(fields["allowed_domains"], json!(["rust-lang.org", "docs.rs"]));
+    
+    assert_eq!(check_search(&["docs.rs".to_string()], &[], &closed), Verdict::Allow);
+    assert!(matches!(check_search(&["reddit.com".to_string()], &[], &closed), Verdict::Block(_)));
+}
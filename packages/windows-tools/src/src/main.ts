@@ -86,4 +86,5 @@ WSL Account: ${wslTest.stdout.trim() || "None"}
   }
 }
 
+export * from "./shared-config.js";
 export * from "./types.js";
@@ -0,0 +1,119 @@
+import { execFileSync } from "node:child_process";
+
+// Mirrors `SharedConfig` in packages/command-replacer/src/shared.rs, written by
+// `command-replacer config export --format json` (schema: `config schema`)
+export const SHARED_CONFIG_VERSION = 1;
+
+export interface SharedReplacement {
+  readonly enabled: boolean;
+  readonly tool: string;
+  readonly priority: number;
+}
+
+export interface SharedPolicies {
+  readonly never_replace: readonly string[];
+  readonly only_replace: readonly string[];
+  readonly ask: readonly string[];
+}
+
+export interface SharedConfig {
+  readonly version: number;
+  readonly os: string;
+  readonly tools: Readonly<Record<string, string>>;
+  readonly replacements: Readonly<Record<string, SharedReplacement>>;
+  readonly policies: SharedPolicies;
+}
+
+export class SharedConfigError extends Error {
+  constructor(message: string) {
+    super(message);
+    this.name = "SharedConfigError";
+  }
+}
+
+const isRecord = (value: unknown): value is Record<string, unknown> =>
+  typeof value === "object" && value !== null && !Array.isArray(value);
+
+const isStringArray = (value: unknown): value is string[] =>
+  Array.isArray(value) && value.every((item) => typeof item === "string");
+
+/**
+ * Parse `config export` output, rejecting other versions of the format
+ */
+export function parseSharedConfig(json: string): SharedConfig {
+  const value: unknown = JSON.parse(json);
+  if (!isRecord(value)) {
+    throw new SharedConfigError("shared config is not an object");
+  }
+  if (value.version !== SHARED_CONFIG_VERSION) {
+    throw new SharedConfigError(
+      `shared config version ${String(value.version)} is not ${SHARED_CONFIG_VERSION}`
+    );
+  }
+  const { os, tools, replacements, policies } = value;
+  if (typeof os !== "string" || !isRecord(tools) || !isRecord(replacements) || !isRecord(policies)) {
+    throw new SharedConfigError("shared config is missing os, tools, replacements, or policies");
+  }
+  for (const [tool, path] of Object.entries(tools)) {
+    if (typeof path !== "string") {
+      throw new SharedConfigError(`tools.${tool} is not a path`);
+    }
+  }
+  for (const [command, replacement] of Object.entries(replacements)) {
+    if (
+      !isRecord(replacement) ||
+      typeof replacement.enabled !== "boolean" ||
+      typeof replacement.tool !== "string" ||
+      typeof replacement.priority !== "number"
+    ) {
+      throw new SharedConfigError(`replacements.${command} needs enabled, tool, and priority`);
+    }
+  }
+  for (const policy of ["never_replace", "only_replace", "ask"] as const) {
+    if (!isStringArray(policies[policy])) {
+      throw new SharedConfigError(`policies.${policy} is not a list of patterns`);
+    }
+  }
+  return value as unknown as SharedConfig;
+}
+
+/**
+ * The hook's effective config, or null when command-replacer isn't installed.
+ * COMMAND_REPLACER_BIN overrides the binary looked up on PATH.
+ */
+export function loadSharedConfig(
+  binary: string = process.env.COMMAND_REPLACER_BIN ?? "command-replacer",
+  cwd: string = process.cwd()
+): SharedConfig | null {
+  let output: string;
+  try {
+    output = execFileSync(binary, ["config", "export", "--format", "json", "--project", cwd], {
+      encoding: "utf8",
+      timeout: 5000,
+      windowsHide: true,
+    });
+  } catch {
+    return null;
+  }
+  return parseSharedConfig(output);
+}
+
+/**
+ * The pinned path of `tool`, or the bare name to look up on PATH
+ */
+export function toolPath(config: SharedConfig, tool: string): string {
+  return config.tools[tool] ?? tool;
+}
+
+/**
+ * The tool an enabled replacement rewrites `command` to; commands match in
+ * any case on Windows, as they do in the hook
+ */
+export function replacementTool(config: SharedConfig, command: string): string | undefined {
+  const key =
+    config.os === "windows"
+      ? Object.keys(config.replacements).find((name) => name.toLowerCase() === command.toLowerCase())
+      : command;
+  const replacement = key === undefined ? undefined : config.replacements[key];
+  return replacement?.enabled ? replacement.tool : undefined;
+}
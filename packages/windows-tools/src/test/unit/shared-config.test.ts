@@ -0,0 +1,44 @@
+import { describe, test, expect } from "vitest";
+import {
+  parseSharedConfig,
+  replacementTool,
+  SHARED_CONFIG_VERSION,
+  SharedConfigError,
+  toolPath,
+} from "../../src/shared-config.js";
+
+// Shaped like `command-replacer config export --format json` on Windows
+const exported = {
+  version: SHARED_CONFIG_VERSION,
+  os: "windows",
+  tools: { rg: "C:\\tools\\rg.exe" },
+  replacements: {
+    findstr: { enabled: true, tool: "rg", priority: 10 },
+    ls: { enabled: false, tool: "eza", priority: 8 },
+  },
+  policies: { never_replace: ["make *"], only_replace: [], ask: [] },
+};
+
+describe("Shared config", () => {
+  test("parses config export output", () => {
+    const config = parseSharedConfig(JSON.stringify(exported));
+    expect(config.policies.never_replace).toEqual(["make *"]);
+    expect(toolPath(config, "rg")).toBe("C:\\tools\\rg.exe");
+    expect(toolPath(config, "fd")).toBe("fd");
+  });
+
+  test("matches commands in any case on Windows only", () => {
+    const config = parseSharedConfig(JSON.stringify(exported));
+    expect(replacementTool(config, "FINDSTR")).toBe("rg");
+    expect(replacementTool(config, "ls")).toBeUndefined();
+
+    const unix = parseSharedConfig(JSON.stringify({ ...exported, os: "linux" }));
+    expect(replacementTool(unix, "FINDSTR")).toBeUndefined();
+  });
+
+  test("rejects other versions and malformed entries", () => {
+    expect(() => parseSharedConfig(JSON.stringify({ ...exported, version: 2 }))).toThrow(SharedConfigError);
+    const broken = { ...exported, replacements: { grep: { tool: "rg" } } };
+    expect(() => parseSharedConfig(JSON.stringify(broken))).toThrow(SharedConfigError);
+  });
+});